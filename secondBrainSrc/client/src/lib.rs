@@ -0,0 +1,248 @@
+//! Query a running Second Brain from your own Rust programs.
+//!
+//! The recall service listens on TCP (127.0.0.1:8080 by default) and the
+//! learner serves capture stats over HTTP (127.0.0.1:9090 by default).
+//! `SecondBrainClient` wraps both.
+//!
+//! # Example: a daily standup reporter
+//!
+//! ```no_run
+//! use second_brain_client::SecondBrainClient;
+//!
+//! #[tokio::main]
+//! async fn main() -> second_brain_client::Result<()> {
+//!     let client = SecondBrainClient::default();
+//!
+//!     let yesterday = client.query("What was I working on yesterday?").await?;
+//!     let today = client.query("What have I done today?").await?;
+//!
+//!     println!("## Standup");
+//!     println!("**Yesterday** ({})", yesterday.timeframe_description);
+//!     println!("{}", yesterday.formatted_text);
+//!     println!("**Today** ({})", today.timeframe_description);
+//!     println!("{}", today.formatted_text);
+//!
+//!     let stats = client.get_stats().await?;
+//!     println!("_{} events captured so far_", stats.events_captured);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Prefix that asks the recall service for a JSON `RecallResponse`
+/// instead of plain text.
+pub const JSON_PREFIX: &str = "JSON:";
+
+/// Prefix that asks the recall service for the raw query result, with
+/// every matching summary, event or statistic, as JSON.
+pub const RAW_PREFIX: &str = "RAW:";
+
+/// Prefix that asks the recall service how it would interpret a query
+/// instead of answering it.
+pub const EXPLAIN_PREFIX: &str = "EXPLAIN:";
+
+/// Prefix that asks the recall service to answer several queries, one per
+/// line, in a single request.
+pub const BATCH_PREFIX: &str = "BATCH:";
+
+/// Prefix that asks the recall service to record a waypoint, given as JSON
+/// with a `title` and optional `note` and `category`.
+pub const WAYPOINT_PREFIX: &str = "WAYPOINT:";
+
+/// Prefix that asks the recall service to send the answer, as plain text, a
+/// piece at a time as it's written. Over a framed connection each piece is a
+/// frame and an empty frame ends the answer.
+pub const STREAM_PREFIX: &str = "STREAM:";
+
+/// Longest response the client will read from the recall service.
+pub const MAX_RESPONSE_BYTES: usize = 32 * 1024 * 1024;
+
+const DEFAULT_RECALL_ADDR: &str = "127.0.0.1:8080";
+const DEFAULT_STATS_URL: &str = "http://127.0.0.1:9090";
+
+/// Answer to a recall query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecallResponse {
+    /// The answer as shown to users on the command line.
+    pub formatted_text: String,
+    /// How the query was answered: "summaries", "window_titles",
+    /// "statistics", "tasks", "diff", "correlations", "freeform" or "fuzzy".
+    /// "error", "timeout" and "llm_unavailable" mean it wasn't.
+    pub query_type: String,
+    /// Human-readable time range the answer covers.
+    pub timeframe_description: String,
+    /// App the results were narrowed to, if the query named one.
+    pub app_filter: Option<String>,
+    /// Reminders the thinker queued since the last query, e.g. "You've
+    /// been at the computer for 2 hours without a break."
+    #[serde(default)]
+    pub suggestions: Vec<String>,
+}
+
+/// One query of a batch and the answer to it, as plain text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchAnswer {
+    pub query: String,
+    pub response: String,
+}
+
+/// Live capture statistics reported by the learner.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub events_captured: u64,
+    pub buffer_depth: u64,
+    pub last_event_at: Option<DateTime<Utc>>,
+    pub events_per_min: f64,
+    pub db_errors: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SecondBrainClient {
+    recall_addr: String,
+    stats_url: String,
+    http: reqwest::Client,
+}
+
+impl Default for SecondBrainClient {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECALL_ADDR, DEFAULT_STATS_URL)
+    }
+}
+
+impl SecondBrainClient {
+    /// `recall_addr` is the recall service's `host:port`, `stats_url` the
+    /// base URL of the learner's metrics endpoint.
+    pub fn new(recall_addr: impl Into<String>, stats_url: impl Into<String>) -> Self {
+        Self {
+            recall_addr: recall_addr.into(),
+            stats_url: stats_url.into().trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Ask the recall service a natural language question.
+    pub async fn query(&self, text: &str) -> Result<RecallResponse> {
+        let response = self.send(&format!("{}{}", JSON_PREFIX, text)).await?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Ask a question and get the answer as plain text, a piece at a time.
+    /// Open-ended questions are passed to `on_text` as the LLM writes the
+    /// answer; anything else arrives in one piece.
+    pub async fn query_stream(&self, text: &str, mut on_text: impl FnMut(&str)) -> Result<()> {
+        let mut stream = TcpStream::connect(&self.recall_addr).await?;
+        write_framed(&mut stream, format!("{}{}", STREAM_PREFIX, text).as_bytes()).await?;
+
+        loop {
+            let piece = read_framed(&mut stream, MAX_RESPONSE_BYTES).await?;
+            if piece.is_empty() {
+                return Ok(());
+            }
+            on_text(std::str::from_utf8(&piece)?);
+        }
+    }
+
+    /// Ask a question and get the raw query result rather than formatted
+    /// text. The shape depends on the `type` field of the result. For
+    /// summaries, `total_count` is how many there are in all, which is more
+    /// than `data` holds when only the most recent are returned.
+    pub async fn query_raw(&self, text: &str) -> Result<serde_json::Value> {
+        let response = self.send(&format!("{}{}", RAW_PREFIX, text)).await?;
+        let value: serde_json::Value = serde_json::from_str(&response)?;
+
+        match value.get("error").and_then(|e| e.as_str()) {
+            Some(error) => Err(error.into()),
+            None => Ok(value),
+        }
+    }
+
+    /// Find out how a question would be interpreted: the intent, time
+    /// range, app filter, data source and the SQL that answering it runs.
+    pub async fn explain(&self, text: &str) -> Result<serde_json::Value> {
+        let response = self.send(&format!("{}{}", EXPLAIN_PREFIX, text)).await?;
+        let value: serde_json::Value = serde_json::from_str(&response)?;
+
+        match value.get("error").and_then(|e| e.as_str()) {
+            Some(error) => Err(error.into()),
+            None => Ok(value),
+        }
+    }
+
+    /// Ask several questions at once. Questions about the same period are
+    /// answered from one fetch of its activity. Answers come back in the
+    /// order asked.
+    pub async fn query_batch(&self, queries: &[String]) -> Result<Vec<BatchAnswer>> {
+        let response = self.send(&format!("{}{}", BATCH_PREFIX, queries.join("\n"))).await?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Mark the current moment in the activity timeline, e.g. "finished
+    /// the auth feature". Timeline answers mention it from then on.
+    pub async fn mark_waypoint(&self, title: &str, note: Option<&str>, category: Option<&str>) -> Result<()> {
+        let waypoint = serde_json::json!({ "title": title, "note": note, "category": category });
+        let response = self.send(&format!("{}{}", WAYPOINT_PREFIX, waypoint)).await?;
+        let value: serde_json::Value = serde_json::from_str(&response)?;
+
+        match value.get("error").and_then(|e| e.as_str()) {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Fetch the learner's capture statistics.
+    pub async fn get_stats(&self) -> Result<Stats> {
+        let stats = self
+            .http
+            .get(format!("{}/stats", self.stats_url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(stats)
+    }
+
+    // The recall protocol is one framed request and one framed response
+    // per connection.
+    async fn send(&self, request: &str) -> Result<String> {
+        let mut stream = TcpStream::connect(&self.recall_addr).await?;
+        write_framed(&mut stream, request.as_bytes()).await?;
+
+        let response = read_framed(&mut stream, MAX_RESPONSE_BYTES).await?;
+        Ok(String::from_utf8(response)?)
+    }
+}
+
+/// Write one message of the recall protocol: a 4-byte big-endian length,
+/// then that many bytes. The recall service answers a framed request with a
+/// framed response.
+pub async fn write_framed<W: AsyncWrite + Unpin>(writer: &mut W, frame: &[u8]) -> Result<()> {
+    let len =
+        u32::try_from(frame.len()).map_err(|_| format!("message of {} bytes is too long to send", frame.len()))?;
+
+    writer.write_u32(len).await?;
+    writer.write_all(frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read one message written by `write_framed`, looping until all of it has
+/// arrived. Fails without reading it if it's longer than `max_len`.
+pub async fn read_framed<R: AsyncRead + Unpin>(reader: &mut R, max_len: usize) -> Result<Vec<u8>> {
+    let len = reader.read_u32().await? as usize;
+    if len > max_len {
+        return Err(format!("message of {} bytes is over the {} byte limit", len, max_len).into());
+    }
+
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame).await?;
+    Ok(frame)
+}
@@ -0,0 +1,75 @@
+#!/bin/bash
+
+# Compares the latest Criterion results against a baseline Criterion saved on
+# the same machine, so the comparison doesn't depend on how fast whichever
+# machine recorded it was. Fails if any benchmark's mean time regressed by
+# more than the threshold; benchmarks missing from either run are reported
+# but can't fail the check.
+#
+# Usage:
+#   # On the commit to compare against
+#   cargo bench -p activity-tracker-common --bench event_storage -- --save-baseline base
+#   # On the change
+#   cargo bench -p activity-tracker-common --bench event_storage -- --baseline-lenient base
+#   ./common/benches/check_regressions.sh base
+
+# Color codes for better output
+GREEN='\033[0;32m'
+RED='\033[0;31m'
+YELLOW='\033[1;33m'
+NC='\033[0m' # No Color
+
+THRESHOLD_PERCENT=20
+BASELINE="${1:-base}"
+
+# Starting directory (workspace root)
+cd "$(dirname "$0")/../.."
+
+CRITERION_DIR="target/criterion"
+
+if [ ! -d "$CRITERION_DIR" ]; then
+    echo -e "${RED}No Criterion results found in ${CRITERION_DIR}. Run cargo bench first.${NC}"
+    exit 1
+fi
+
+# "benchmark id -> mean nanoseconds" for every benchmark saved under a name
+means() {
+    find "$CRITERION_DIR" -path "*/$1/benchmark.json" | sort | while read -r bench_file; do
+        dir=$(dirname "$bench_file")
+        id=$(jq -r '.full_id' "$bench_file")
+        mean=$(jq -r '.mean.point_estimate' "$dir/estimates.json")
+        jq -n --arg id "$id" --argjson mean "$mean" '{($id): $mean}'
+    done | jq -s 'add // {}'
+}
+
+current=$(means new)
+baseline=$(means "$BASELINE")
+
+failed=0
+for id in $(echo "$current" | jq -r 'keys[]'); do
+    new=$(echo "$current" | jq -r --arg id "$id" '.[$id]')
+    old=$(echo "$baseline" | jq -r --arg id "$id" '.[$id] // empty')
+
+    if [ -z "$old" ]; then
+        echo -e "${YELLOW}${id}: not in the ${BASELINE} baseline${NC}"
+        continue
+    fi
+
+    change=$(jq -n --argjson new "$new" --argjson old "$old" '(($new - $old) / $old) * 100 | floor')
+    if [ "$change" -gt "$THRESHOLD_PERCENT" ]; then
+        echo -e "${RED}${id}: regressed by ${change}%${NC}"
+        failed=1
+    else
+        echo -e "${GREEN}${id}: ${change}%${NC}"
+    fi
+done
+
+# A benchmark that didn't run this time, e.g. because Docker was unavailable,
+# has nothing to compare
+for id in $(echo "$baseline" | jq -r 'keys[]'); do
+    if [ "$(echo "$current" | jq --arg id "$id" 'has($id)')" != "true" ]; then
+        echo -e "${YELLOW}${id}: not run${NC}"
+    fi
+done
+
+exit $failed
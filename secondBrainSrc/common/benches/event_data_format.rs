@@ -0,0 +1,145 @@
+// Compares JSON and MessagePack encodings of event data. Needs the
+// msgpack-storage feature:
+//
+//     cargo bench -p activity-tracker-common --features msgpack-storage --bench event_data_format
+//
+// Prints the total encoded size of a million typical keystroke payloads in
+// each format, then times encoding and decoding a single payload.
+//
+// The query benchmarks store a million keystrokes in each format, a week of
+// them a month and two months back, and time reading a day back. They need
+// Postgres, from BENCH_DATABASE_URL or a testcontainers container, as the
+// event_storage benchmarks do.
+use activity_tracker_common::{
+    decode_event_data, encode_event_data, EventStore, StorageFormat, UserEvent, UserEventBuilder,
+};
+use chrono::{DateTime, Duration, Utc};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::StreamExt;
+use std::env;
+use tokio::runtime::Runtime;
+
+mod support;
+
+const ROWS: usize = 1_000_000;
+const SEED_BATCH: usize = 1_000;
+const KEYS: [&str; 6] = ["KeyA", "KeyE", "Space", "Backspace", "Return", "KeyS"];
+
+fn sample_data(i: usize) -> String {
+    let modifiers: &[&str] = if i.is_multiple_of(20) { &["Shift"] } else { &[] };
+    serde_json::json!({ "key": KEYS[i % KEYS.len()], "modifiers": modifiers }).to_string()
+}
+
+fn report_sizes() {
+    let total = |format: StorageFormat| -> usize {
+        (0..ROWS)
+            .map(|i| encode_event_data(&sample_data(i), format).unwrap().len())
+            .sum()
+    };
+
+    let json = total(StorageFormat::Json);
+    let msgpack = total(StorageFormat::MsgPack);
+
+    println!(
+        "{} rows: JSON {:.1} MiB, MessagePack {:.1} MiB ({:.0}% smaller)",
+        ROWS,
+        json as f64 / (1024.0 * 1024.0),
+        msgpack as f64 / (1024.0 * 1024.0),
+        (1.0 - msgpack as f64 / json as f64) * 100.0
+    );
+}
+
+fn bench_formats(c: &mut Criterion) {
+    report_sizes();
+
+    let data = sample_data(0);
+
+    let mut encode_group = c.benchmark_group("encode_event_data");
+    let mut decode_group_inputs = Vec::new();
+    for (label, format) in [("json", StorageFormat::Json), ("msgpack", StorageFormat::MsgPack)] {
+        encode_group.bench_with_input(BenchmarkId::from_parameter(label), &format, |b, format| {
+            b.iter(|| encode_event_data(&data, *format).unwrap())
+        });
+        decode_group_inputs.push((label, encode_event_data(&data, format).unwrap()));
+    }
+    encode_group.finish();
+
+    let mut decode_group = c.benchmark_group("decode_event_data");
+    for (label, bytes) in &decode_group_inputs {
+        decode_group.bench_with_input(BenchmarkId::from_parameter(label), bytes, |b, bytes| {
+            b.iter(|| decode_event_data(bytes).unwrap())
+        });
+    }
+    decode_group.finish();
+}
+
+fn sample_event(i: usize, timestamp: DateTime<Utc>) -> UserEvent {
+    let mut event = UserEventBuilder::keystroke(KEYS[i % KEYS.len()])
+        .app("ghostty")
+        .at(timestamp)
+        .build();
+    event.data = sample_data(i);
+    event
+}
+
+fn bench_queries(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let now = Utc::now();
+    let mut group = c.benchmark_group("stream_day_of_1m_rows");
+    // Each iteration reads a seventh of a million rows
+    group.sample_size(10);
+
+    for (label, weeks_back) in [("json", 4), ("msgpack", 8)] {
+        // The client takes its format from the environment when it connects
+        env::set_var("EVENT_STORAGE_FORMAT", label);
+        let Some((client, _container)) = support::event_database(&rt, &format!("stream_day_of_1m_rows/{}", label))
+        else {
+            continue;
+        };
+
+        // A million rows spread evenly over the week, stored once per database
+        let end = now - Duration::weeks(weeks_back);
+        let start = end - Duration::weeks(1);
+        let step = Duration::weeks(1) / ROWS as i32;
+        if rt.block_on(client.count_events_in_timeframe(start, end)).unwrap() < ROWS as u64 {
+            rt.block_on(async {
+                for batch in (0..ROWS).collect::<Vec<_>>().chunks(SEED_BATCH) {
+                    let events: Vec<UserEvent> = batch
+                        .iter()
+                        .map(|&i| sample_event(i, start + step * i as i32))
+                        .collect();
+                    client.store_events_batch(&events).await.unwrap();
+                }
+            });
+        }
+
+        let bytes = rt.block_on(client.event_data_bytes(start, end)).unwrap();
+        println!(
+            "{} rows stored as {}: {:.1} MiB of event data",
+            ROWS,
+            label,
+            bytes as f64 / (1024.0 * 1024.0)
+        );
+
+        let day_start = end - Duration::days(1);
+        let day_rows = rt.block_on(client.count_events_in_timeframe(day_start, end)).unwrap();
+        group.throughput(Throughput::Elements(day_rows));
+        group.bench_function(label, |b| {
+            b.iter(|| {
+                rt.block_on(async {
+                    let mut events = client.stream_events_in_timeframe(day_start, end);
+                    let mut count = 0;
+                    while let Some(event) = events.next().await {
+                        event.unwrap();
+                        count += 1;
+                    }
+                    count
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_formats, bench_queries);
+criterion_main!(benches);
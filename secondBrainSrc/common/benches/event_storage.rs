@@ -0,0 +1,150 @@
+// Benchmarks for the event and summary storage pipeline.
+//
+// The TimescaleClient benchmarks start a throwaway Postgres container through
+// testcontainers, so Docker must be running, or use the empty database named
+// by BENCH_DATABASE_URL. Run with:
+//
+//     cargo bench -p activity-tracker-common --bench event_storage
+//
+// and compare against a baseline saved on the same machine with
+// benches/check_regressions.sh.
+use activity_tracker_common::{
+    ActivitySummary, EventStore, GeneralDbClient, SummaryStore, UserEvent, UserEventBuilder,
+};
+use chrono::{DateTime, Duration, Utc};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use futures::StreamExt;
+use tokio::runtime::Runtime;
+
+mod support;
+
+const APPS: [&str; 4] = ["ghostty", "firefox", "Code", "Slack"];
+const TOPICS: [&str; 8] = [
+    "rust", "tokio", "database", "query", "terminal", "browser", "review", "meeting",
+];
+const SUMMARY_ROWS: usize = 10_000;
+
+fn sample_event(i: usize, timestamp: DateTime<Utc>) -> UserEvent {
+    UserEventBuilder::keystroke("KeyA")
+        .app(APPS[i % APPS.len()])
+        .window(&format!("window {}", i % 50))
+        .at(timestamp)
+        .build()
+}
+
+fn sample_summary(i: usize) -> ActivitySummary {
+    let end_time = Utc::now() - Duration::minutes(5 * i as i64);
+    let topic = TOPICS[i % TOPICS.len()];
+
+    ActivitySummary {
+        start_time: end_time - Duration::minutes(5),
+        end_time,
+        description: format!(
+            "The user was working on {} in {} for a while",
+            topic,
+            APPS[i % APPS.len()]
+        ),
+        events: Vec::new(),
+        tags: vec![topic.to_string(), TOPICS[(i + 3) % TOPICS.len()].to_string()],
+    }
+}
+
+fn bench_timescale(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+
+    let Some((client, _container)) = support::event_database(
+        &rt,
+        "store_event, store_events_batch, get_events_in_timeframe and stream_events_in_timeframe",
+    ) else {
+        return;
+    };
+
+    // The write benchmarks store their events a year back, out of the way of
+    // the week the read benchmarks query
+    let now = Utc::now();
+    let written_at = now - Duration::days(365);
+
+    c.bench_function("store_event/single", |b| {
+        b.iter(|| rt.block_on(client.store_event(sample_event(0, written_at))).unwrap())
+    });
+
+    let mut batch_group = c.benchmark_group("store_events_batch");
+    for size in [10, 100, 1000] {
+        let events: Vec<UserEvent> = (0..size).map(|i| sample_event(i, written_at)).collect();
+        batch_group.bench_with_input(BenchmarkId::from_parameter(size), &events, |b, events| {
+            b.iter(|| rt.block_on(client.store_events_batch(events)).unwrap())
+        });
+    }
+    batch_group.finish();
+
+    // Seed one event per minute for the past week
+    let week_of_events: Vec<UserEvent> = (0..7 * 24 * 60)
+        .map(|i| sample_event(i, now - Duration::minutes(i as i64)))
+        .collect();
+    rt.block_on(client.store_events_batch(&week_of_events)).unwrap();
+
+    let mut range_group = c.benchmark_group("get_events_in_timeframe");
+    for (label, range) in [
+        ("1h", Duration::hours(1)),
+        ("1d", Duration::days(1)),
+        ("1w", Duration::weeks(1)),
+    ] {
+        range_group.bench_function(label, |b| {
+            b.iter(|| rt.block_on(client.get_events_in_timeframe(now - range, now)).unwrap())
+        });
+    }
+    range_group.finish();
+
+    // The same ranges read one event at a time, as the thinker does, so only
+    // one event is in memory at once instead of the whole range
+    let mut stream_group = c.benchmark_group("stream_events_in_timeframe");
+    for (label, range) in [
+        ("1h", Duration::hours(1)),
+        ("1d", Duration::days(1)),
+        ("1w", Duration::weeks(1)),
+    ] {
+        stream_group.bench_function(label, |b| {
+            b.iter(|| {
+                rt.block_on(async {
+                    let mut events = client.stream_events_in_timeframe(now - range, now);
+                    let mut count = 0;
+                    while let Some(event) = events.next().await {
+                        event.unwrap();
+                        count += 1;
+                    }
+                    count
+                })
+            })
+        });
+    }
+    stream_group.finish();
+}
+
+fn bench_summary_search(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let db_url = format!("sqlite:{}?mode=rwc", dir.path().join("bench.db").display());
+    let client = rt.block_on(GeneralDbClient::new(&db_url)).unwrap();
+
+    rt.block_on(async {
+        for i in 0..SUMMARY_ROWS {
+            client.store_summary(&sample_summary(i)).await.unwrap();
+        }
+    });
+
+    let mut group = c.benchmark_group("search_summaries");
+    group.bench_function("single_term", |b| {
+        b.iter(|| rt.block_on(client.search_summaries("rust")).unwrap())
+    });
+    group.bench_function("five_terms", |b| {
+        b.iter(|| {
+            rt.block_on(client.search_summaries("rust tokio database query terminal"))
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_timescale, bench_summary_search);
+criterion_main!(benches);
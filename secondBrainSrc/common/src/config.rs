@@ -0,0 +1,168 @@
+use crate::llm::anthropic::DEFAULT_ANTHROPIC_MODEL;
+use crate::llm::openai::DEFAULT_OPENAI_MODEL;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_CIRCUIT_BREAKER_SECS: u64 = 300;
+pub const DEFAULT_REOPEN_PROBE_INTERVAL_SECS: u64 = 60;
+pub const DEFAULT_OLLAMA_MODEL: &str = "llama3.2:3b";
+const DEFAULT_PERSONA: &str = "fishy";
+
+// config.toml, shared by every service. All sections are optional and
+// unknown keys are rejected, so typos show up instead of being ignored.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub llm: LlmConfig,
+    #[serde(default)]
+    pub persona: PersonaConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<HookConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub goals: Vec<WeeklyGoal>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LlmConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker_duration_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker_reopen_probe_interval_secs: Option<u64>,
+    #[serde(default)]
+    pub backends: Vec<BackendConfig>,
+}
+
+// One [[llm.backends]] entry, of kind "ollama", "anthropic" or "openai"
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BackendConfig {
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+}
+
+fn default_kind() -> String {
+    "ollama".to_string()
+}
+
+impl BackendConfig {
+    // The single backend used when the config lists none, picked by LLM_BACKEND
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let kind = env::var("LLM_BACKEND").unwrap_or_else(|_| default_kind());
+
+        let (model, base_url) = match kind.as_str() {
+            "ollama" => (
+                env::var("OLLAMA_MODEL").unwrap_or_else(|_| DEFAULT_OLLAMA_MODEL.to_string()),
+                env::var("OLLAMA_HOST").ok(),
+            ),
+            "anthropic" => (
+                env::var("ANTHROPIC_MODEL").unwrap_or_else(|_| DEFAULT_ANTHROPIC_MODEL.to_string()),
+                None,
+            ),
+            "openai" => (
+                env::var("OPENAI_MODEL").unwrap_or_else(|_| DEFAULT_OPENAI_MODEL.to_string()),
+                env::var("OPENAI_BASE_URL").ok(),
+            ),
+            other => {
+                return Err(format!("Unknown LLM_BACKEND '{}', expected ollama, anthropic or openai", other).into())
+            }
+        };
+
+        Ok(Self { kind, model, base_url })
+    }
+}
+
+// The [persona] section. `name` selects a built-in persona, any other field
+// overrides that persona's value.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PersonaConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intro_messages: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_data_messages: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt_prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_unavailable_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub busy_message: Option<String>,
+}
+
+// One [[hooks]] entry, run by the Thinker after each stored summary
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HookConfig {
+    Webhook { url: String },
+    Shell { command: String },
+    File { path: String },
+}
+
+// One [[goals]] entry: hours a week the user means to spend in an app
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WeeklyGoal {
+    pub app: String,
+    pub hours: f64,
+}
+
+impl Config {
+    // An empty config when the file doesn't exist yet
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    // Parse errors name the line and column of the problem
+    pub fn from_toml(contents: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    // The config as the services see it: the env var backend when none are
+    // listed, and defaults for every unset setting
+    pub fn resolved(mut self) -> Result<Self, Box<dyn Error>> {
+        if self.llm.backends.is_empty() {
+            self.llm.backends.push(BackendConfig::from_env()?);
+        }
+        self.llm
+            .circuit_breaker_duration_secs
+            .get_or_insert(DEFAULT_CIRCUIT_BREAKER_SECS);
+        self.llm
+            .circuit_breaker_reopen_probe_interval_secs
+            .get_or_insert(DEFAULT_REOPEN_PROBE_INTERVAL_SECS);
+        self.persona.name.get_or_insert_with(|| DEFAULT_PERSONA.to_string());
+
+        Ok(self)
+    }
+}
+
+// SECOND_BRAIN_CONFIG if set, then ./config.toml if there is one, then
+// ~/.config/second-brain/config.toml
+pub fn config_path() -> PathBuf {
+    if let Ok(path) = env::var("SECOND_BRAIN_CONFIG") {
+        return PathBuf::from(path);
+    }
+
+    let local = PathBuf::from("./config.toml");
+    if local.exists() {
+        return local;
+    }
+
+    match env::var("HOME") {
+        Ok(home) => PathBuf::from(home).join(".config/second-brain/config.toml"),
+        Err(_) => local,
+    }
+}
@@ -0,0 +1,70 @@
+use std::env;
+use std::error::Error;
+
+// First byte of an encoded payload, naming the format of the rest
+const JSON_PREFIX: u8 = 0x00;
+#[cfg(feature = "msgpack-storage")]
+const MSGPACK_PREFIX: u8 = 0x01;
+
+// How UserEvent.data is written to the event database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageFormat {
+    #[default]
+    Json,
+    #[cfg(feature = "msgpack-storage")]
+    MsgPack,
+}
+
+impl StorageFormat {
+    // EVENT_STORAGE_FORMAT, `json` or `msgpack`, defaulting to JSON
+    pub fn from_env() -> Self {
+        match env::var("EVENT_STORAGE_FORMAT").ok().as_deref() {
+            #[cfg(feature = "msgpack-storage")]
+            Some("msgpack") => Self::MsgPack,
+            #[cfg(not(feature = "msgpack-storage"))]
+            Some("msgpack") => {
+                eprintln!("⚠️ EVENT_STORAGE_FORMAT=msgpack needs the msgpack-storage feature, using JSON");
+                Self::Json
+            }
+            _ => Self::Json,
+        }
+    }
+}
+
+// Encodes an event's JSON data, prefixed with a byte naming the format
+pub fn encode_event_data(data: &str, format: StorageFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+    match format {
+        StorageFormat::Json => {
+            let mut bytes = Vec::with_capacity(data.len() + 1);
+            bytes.push(JSON_PREFIX);
+            bytes.extend_from_slice(data.as_bytes());
+            Ok(bytes)
+        }
+        #[cfg(feature = "msgpack-storage")]
+        StorageFormat::MsgPack => {
+            let value: serde_json::Value = serde_json::from_str(data)?;
+            let mut bytes = vec![MSGPACK_PREFIX];
+            bytes.extend(rmp_serde::to_vec(&value)?);
+            Ok(bytes)
+        }
+    }
+}
+
+// Decodes a payload written by encode_event_data back to JSON, whatever
+// format it was written in. Object keys come back sorted.
+pub fn decode_event_data(bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+    match bytes.split_first() {
+        Some((&JSON_PREFIX, data)) => Ok(String::from_utf8(data.to_vec())?),
+        #[cfg(feature = "msgpack-storage")]
+        Some((&MSGPACK_PREFIX, data)) => {
+            let value: serde_json::Value = rmp_serde::from_slice(data)?;
+            Ok(value.to_string())
+        }
+        Some((prefix, _)) => Err(format!(
+            "Unknown event data format {:#04x}, it may need the msgpack-storage feature",
+            prefix
+        )
+        .into()),
+        None => Err("Empty event data".into()),
+    }
+}
@@ -1,19 +1,193 @@
-use crate::models::ActivitySummary;
+use crate::models::{ActivitySummary, Suggestion, Task, TimelineBucket, Urgency};
+use crate::privacy::{self, AuditEntry};
+use crate::utils::search::sanitize_query_for_search;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use sqlx::{Pool, Row, Sqlite, SqlitePool};
+use chrono::{DateTime, TimeZone, Utc};
+use futures::{Stream, StreamExt};
+use sqlx::{sqlite::SqliteRow, Pool, Row, Sqlite, SqlitePool, Transaction};
 use std::error::Error;
 use std::path::Path;
 
+// What a summary covers. The queries below only return window summaries,
+// the thinker's descriptions of each few minutes; the others, like daily
+// digests, are read with get_summaries_by_type. The thinker's own daily
+// rollups live in the event database's daily_summaries table instead.
+pub const WINDOW_SUMMARY: &str = "window";
+pub const DAILY_SUMMARY: &str = "daily";
+
+// Summaries folded into a merged one are kept for auditing but left out of
+// every query below
+pub const SUMMARIES_IN_TIMEFRAME_QUERY: &str = r#"
+    SELECT id, start_time, end_time, description, tags, events_json
+    FROM activity_summaries
+    WHERE merged = 0 AND summary_type = 'window' AND (
+        (start_time BETWEEN ? AND ?) OR
+        (end_time BETWEEN ? AND ?) OR
+        (start_time <= ? AND end_time >= ?)
+    )
+    ORDER BY start_time DESC
+"#;
+
+// Window summaries starting in the range, oldest first
+const SUMMARIES_STARTING_IN_QUERY: &str = r#"
+    SELECT id, start_time, end_time, description, tags, events_json
+    FROM activity_summaries
+    WHERE merged = 0 AND summary_type = 'window' AND start_time BETWEEN ? AND ?
+    ORDER BY start_time ASC
+"#;
+
+// Same filter as SUMMARIES_IN_TIMEFRAME_QUERY
+const COUNT_SUMMARIES_IN_TIMEFRAME_QUERY: &str = r#"
+    SELECT COUNT(*)
+    FROM activity_summaries
+    WHERE merged = 0 AND summary_type = 'window' AND (
+        (start_time BETWEEN ? AND ?) OR
+        (end_time BETWEEN ? AND ?) OR
+        (start_time <= ? AND end_time >= ?)
+    )
+"#;
+
+// How many summaries RECENT_SUMMARIES_QUERY picks its page from
+const COUNT_WINDOW_SUMMARIES_QUERY: &str = r#"
+    SELECT COUNT(*) FROM activity_summaries WHERE merged = 0 AND summary_type = 'window'
+"#;
+
+// Summaries whose description or tags match an FTS5 expression, through
+// the summary_search index kept alongside activity_summaries
+pub const SEARCH_SUMMARIES_QUERY: &str = r#"
+    SELECT s.id, s.start_time, s.end_time, s.description, s.tags, s.events_json, COUNT(*) OVER () AS total
+    FROM summary_search
+    JOIN activity_summaries s ON s.id = summary_search.rowid
+    WHERE summary_search MATCH ? AND s.merged = 0
+    ORDER BY s.start_time DESC
+"#;
+
+// How many summaries SEARCH_SUMMARIES_QUERY returns
+const COUNT_SEARCH_SUMMARIES_QUERY: &str = r#"
+    SELECT COUNT(*)
+    FROM summary_search
+    JOIN activity_summaries s ON s.id = summary_search.rowid
+    WHERE summary_search MATCH ? AND s.merged = 0
+"#;
+
+// The total is taken before LIMIT, so it's every summary the page is from
+pub const RECENT_SUMMARIES_QUERY: &str = r#"
+    SELECT id, start_time, end_time, description, tags, events_json, COUNT(*) OVER () AS total
+    FROM activity_summaries
+    WHERE merged = 0 AND summary_type = 'window'
+    ORDER BY start_time DESC
+    LIMIT 10
+"#;
+
+const MERGED_SUMMARIES_IN_TIMEFRAME_QUERY: &str = r#"
+    SELECT id, start_time, end_time, description, tags, events_json
+    FROM activity_summaries
+    WHERE merged = 1 AND summary_type = 'window' AND start_time < ? AND end_time > ?
+    ORDER BY start_time
+"#;
+
+pub const SUMMARIES_BY_TYPE_QUERY: &str = r#"
+    SELECT id, start_time, end_time, description, tags, events_json
+    FROM activity_summaries
+    WHERE summary_type = ? AND start_time < ? AND end_time > ?
+    ORDER BY start_time
+"#;
+
+pub const TASKS_IN_TIMEFRAME_QUERY: &str = r#"
+    SELECT id, title, start_time, end_time, confidence
+    FROM tasks
+    WHERE start_time < ? AND end_time > ?
+    ORDER BY start_time
+"#;
+
+// Events recorded with each summary, counted per app and event type in
+// buckets of ?1 seconds. Buckets start on multiples of the bucket length
+// since the epoch, like TimescaleDB's time_bucket.
+const EVENT_TIMELINE_QUERY: &str = r#"
+    SELECT (CAST(strftime('%s', json_extract(event.value, '$.timestamp')) AS INTEGER) / ?1) * ?1 AS bucket_start,
+           json_extract(event.value, '$.app_context.app_name') AS app_name,
+           json_extract(event.value, '$.event') AS event_type,
+           COUNT(*) AS event_count
+    FROM activity_summaries, json_each(activity_summaries.events_json) AS event
+    WHERE merged = 0 AND summary_type = 'window' AND start_time < ?3 AND end_time > ?2
+      AND CAST(strftime('%s', json_extract(event.value, '$.timestamp')) AS INTEGER) >= ?4
+      AND CAST(strftime('%s', json_extract(event.value, '$.timestamp')) AS INTEGER) < ?5
+    GROUP BY 1, 2, 3
+    ORDER BY 1, 2, 3
+"#;
+
 #[async_trait]
 pub trait SummaryStore {
-    async fn store_summary(&self, summary: &ActivitySummary) -> Result<(), Box<dyn Error>>;
+    // Returns the id of the stored summary
+    async fn store_summary(&self, summary: &ActivitySummary) -> Result<i64, Box<dyn Error>>;
     async fn get_summaries_in_timeframe(
         &self,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<Vec<ActivitySummary>, Box<dyn Error>>;
     async fn search_summaries(&self, query: &str) -> Result<Vec<ActivitySummary>, Box<dyn Error>>;
+    // How many summaries get_summaries_in_timeframe and search_summaries
+    // would return, without loading them
+    async fn count_summaries_in_timeframe(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u64, Box<dyn Error>>;
+    async fn count_summaries_matching(&self, query: &str) -> Result<u64, Box<dyn Error>>;
+    async fn delete_summaries_in_timeframe(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u64, Box<dyn Error>>;
+    async fn get_latest_summary_end_time(&self) -> Result<Option<DateTime<Utc>>, Box<dyn Error>>;
+    // Replaces whichever of the description and tags are given. Errors if
+    // there's no summary with that id.
+    async fn update_summary(
+        &self,
+        id: i64,
+        description: Option<String>,
+        tags: Option<Vec<String>>,
+    ) -> Result<(), Box<dyn Error>>;
+    // Returns whether there was a summary with that id
+    async fn delete_summary(&self, id: i64) -> Result<bool, Box<dyn Error>>;
+    // Stores a summary of another type than WINDOW_SUMMARY, e.g. a daily
+    // digest, replacing any of that type with the same start and end. These
+    // aren't searchable or returned by the methods above. Returns the id.
+    async fn store_summary_with_type(
+        &self,
+        summary: &ActivitySummary,
+        summary_type: &str,
+    ) -> Result<i64, Box<dyn Error>>;
+    // Summaries of the type overlapping the range, oldest first
+    async fn get_summaries_by_type(
+        &self,
+        summary_type: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ActivitySummary>, Box<dyn Error>>;
+}
+
+// Summaries reindexed per transaction when rebuilding the search index
+const REINDEX_BATCH_SIZE: i64 = 100;
+
+// How the summary search index compares with the summaries it should cover
+#[derive(Debug, Default)]
+pub struct SearchIndexReport {
+    // Summaries that should be searchable, i.e. window summaries not merged away
+    pub summaries: u64,
+    pub indexed: u64,
+    // Summaries with no entry, or an entry with a different description
+    pub unindexed: u64,
+    // Entries for summaries that are gone or merged away
+    pub orphaned: u64,
+    // What FTS5's own integrity check found wrong, if anything
+    pub corruption: Option<String>,
+}
+
+impl SearchIndexReport {
+    pub fn is_clean(&self) -> bool {
+        self.unindexed == 0 && self.orphaned == 0 && self.corruption.is_none()
+    }
 }
 
 #[derive(Clone)]
@@ -28,13 +202,13 @@ impl GeneralDbClient {
             let path = connection_string.trim_start_matches("sqlite:");
             if path != ":memory:" && !path.is_empty() {
                 println!("Using SQLite database at path: {}", path);
-                
+
                 let db_path = Path::new(path);
-                
+
                 // Check if the database file already exists
                 if !db_path.exists() {
                     println!("Database file doesn't exist yet, will be created");
-                    
+
                     // Ensure parent directory exists
                     if let Some(parent) = db_path.parent() {
                         if !parent.exists() {
@@ -52,29 +226,29 @@ impl GeneralDbClient {
         }
 
         println!("Connecting to SQLite database: {}", connection_string);
-        
+
         let pool = match SqlitePool::connect(connection_string).await {
             Ok(pool) => pool,
             Err(e) => {
                 eprintln!("SQLite connection error: {}", e);
-                
+
                 if connection_string.starts_with("sqlite:") {
                     let path = connection_string.trim_start_matches("sqlite:");
                     if path != ":memory:" && !path.is_empty() {
                         let db_path = Path::new(path);
-                        
+
                         if !db_path.exists() {
                             eprintln!("Database file doesn't exist: {}", path);
                         } else {
                             eprintln!("Database file exists but couldn't connect. Check permissions.");
-                            
+
                             // Try to get more info about the file
                             match std::fs::metadata(db_path) {
                                 Ok(metadata) => {
                                     eprintln!("File size: {} bytes", metadata.len());
                                     let permissions = metadata.permissions();
                                     eprintln!("File permissions: readonly={}", permissions.readonly());
-                                },
+                                }
                                 Err(e) => {
                                     eprintln!("Couldn't get file metadata: {}", e);
                                 }
@@ -82,19 +256,19 @@ impl GeneralDbClient {
                         }
                     }
                 }
-                
+
                 return Err(e.into());
             }
         };
-        
+
         let client = Self { pool };
-        
+
         // Ensure the required tables and indices exist
         client.ensure_schema().await?;
-        
+
         Ok(client)
     }
-    
+
     async fn ensure_schema(&self) -> Result<(), Box<dyn Error>> {
         // Create tables if they don't exist
         sqlx::query(
@@ -111,81 +285,665 @@ impl GeneralDbClient {
             
             CREATE INDEX IF NOT EXISTS idx_summaries_time_range 
             ON activity_summaries(start_time, end_time);
-            "#
+            
+            CREATE VIRTUAL TABLE IF NOT EXISTS summary_search 
+            USING fts5(description, tags);
+            
+            CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL,
+                start_time TIMESTAMP NOT NULL,
+                end_time TIMESTAMP NOT NULL,
+                confidence REAL NOT NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_tasks_time_range
+            ON tasks(start_time, end_time);
+            
+            CREATE TABLE IF NOT EXISTS proactive_suggestions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                message TEXT NOT NULL,
+                urgency TEXT NOT NULL,
+                created_at TIMESTAMP NOT NULL,
+                shown INTEGER NOT NULL DEFAULT 0
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_suggestions_kind
+            ON proactive_suggestions(kind, created_at);
+            "#,
         )
         .execute(&self.pool)
         .await?;
-        
+
+        // Columns added after the table was first created. SQLite has no
+        // ADD COLUMN IF NOT EXISTS, so check first.
+        let has_merged: i64 =
+            sqlx::query("SELECT COUNT(*) AS count FROM pragma_table_info('activity_summaries') WHERE name = 'merged'")
+                .fetch_one(&self.pool)
+                .await?
+                .try_get("count")?;
+
+        if has_merged == 0 {
+            sqlx::query("ALTER TABLE activity_summaries ADD COLUMN merged INTEGER NOT NULL DEFAULT 0")
+                .execute(&self.pool)
+                .await?;
+        }
+
+        let has_summary_type: i64 = sqlx::query(
+            "SELECT COUNT(*) AS count FROM pragma_table_info('activity_summaries') WHERE name = 'summary_type'",
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .try_get("count")?;
+
+        if has_summary_type == 0 {
+            sqlx::query("ALTER TABLE activity_summaries ADD COLUMN summary_type TEXT NOT NULL DEFAULT 'window'")
+                .execute(&self.pool)
+                .await?;
+        }
+
         Ok(())
     }
-    
-    // Helper to convert between DB representation and ActivitySummary
-    fn parse_summary_from_row(
-        _id: i64,  // We don't use the ID in our ActivitySummary model, but it's useful for debugging
-        start_time: DateTime<Utc>,
-        end_time: DateTime<Utc>,
-        description: String,
-        tags_json: String,
-        events_json: String,
-    ) -> Result<ActivitySummary, Box<dyn Error>> {
-        let tags: Vec<String> = serde_json::from_str(&tags_json)?;
-        let events = serde_json::from_str(&events_json)?;
-        
-        Ok(ActivitySummary {
-            start_time,
-            end_time,
-            description,
-            events,
-            tags,
+
+    fn summary_from_row(row: &SqliteRow) -> Result<ActivitySummary, Box<dyn Error>> {
+        let id: i64 = row.try_get("id")?;
+        let start_time: DateTime<Utc> = row.try_get("start_time")?;
+        let end_time: DateTime<Utc> = row.try_get("end_time")?;
+        let description: String = row.try_get("description")?;
+        let tags_json: String = row.try_get("tags")?;
+        let events_json: String = row.try_get("events_json")?;
+
+        Self::parse_summary_from_row(id, start_time, end_time, description, tags_json, events_json)
+    }
+
+    // search_summaries along with how many summaries match in all, counted in
+    // the same query. Without search terms only the most recent summaries are
+    // returned, but the count is of all of them.
+    pub async fn search_summaries_counted(&self, query: &str) -> Result<(Vec<ActivitySummary>, u64), Box<dyn Error>> {
+        let search = summary_match_expression(query);
+
+        let rows = if search.is_empty() {
+            sqlx::query(RECENT_SUMMARIES_QUERY).fetch_all(&self.pool).await?
+        } else {
+            sqlx::query(SEARCH_SUMMARIES_QUERY)
+                .bind(&search)
+                .fetch_all(&self.pool)
+                .await?
+        };
+
+        let total: i64 = match rows.first() {
+            Some(row) => row.try_get("total")?,
+            None => 0,
+        };
+        let summaries = rows.iter().map(Self::summary_from_row).collect::<Result<_, _>>()?;
+
+        Ok((summaries, total as u64))
+    }
+
+    // Like get_summaries_in_timeframe, but yields summaries one at a time
+    // instead of buffering the whole range in memory
+    pub fn stream_summaries_in_timeframe<'a>(
+        &'a self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> impl Stream<Item = Result<ActivitySummary, Box<dyn Error>>> + 'a {
+        sqlx::query(SUMMARIES_IN_TIMEFRAME_QUERY)
+            .bind(start)
+            .bind(end)
+            .bind(start)
+            .bind(end)
+            .bind(start)
+            .bind(end)
+            .fetch(&self.pool)
+            .map(|row| Self::summary_from_row(&row?))
+    }
+
+    // Summaries starting in the range, oldest first, one at a time
+    pub fn stream_summaries_starting_in<'a>(
+        &'a self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> impl Stream<Item = Result<ActivitySummary, Box<dyn Error>>> + Send + 'a {
+        sqlx::query(SUMMARIES_STARTING_IN_QUERY)
+            .bind(start)
+            .bind(end)
+            .fetch(&self.pool)
+            .map(|row| Self::summary_from_row(&row?))
+    }
+
+    // Summaries overlapping the range along with their row ids, oldest first
+    pub async fn get_summaries_with_ids_in_timeframe(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(i64, ActivitySummary)>, Box<dyn Error>> {
+        let rows = sqlx::query(SUMMARIES_IN_TIMEFRAME_QUERY)
+            .bind(start)
+            .bind(end)
+            .bind(start)
+            .bind(end)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut summaries = rows
+            .iter()
+            .map(|row| Ok((row.try_get("id")?, Self::summary_from_row(row)?)))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        summaries.sort_by_key(|(id, _)| *id);
+
+        Ok(summaries)
+    }
+
+    // Summaries already folded into merged ones, overlapping the range, oldest first
+    pub async fn get_merged_summaries_in_timeframe(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ActivitySummary>, Box<dyn Error>> {
+        let rows = sqlx::query(MERGED_SUMMARIES_IN_TIMEFRAME_QUERY)
+            .bind(end)
+            .bind(start)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(Self::summary_from_row).collect()
+    }
+
+    // Stores a summary covering several others and flags those others as
+    // merged, in one transaction. Returns the new summary's id.
+    pub async fn store_merged_summary(
+        &self,
+        merged: &ActivitySummary,
+        child_ids: &[i64],
+    ) -> Result<i64, Box<dyn Error>> {
+        let mut tx = self.pool.begin().await?;
+
+        for id in child_ids {
+            sqlx::query("UPDATE activity_summaries SET merged = 1 WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query("DELETE FROM summary_search WHERE rowid = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let id = Self::insert_summary(&mut tx, merged, WINDOW_SUMMARY).await?;
+
+        tx.commit().await?;
+
+        audit_summary_stored(merged);
+
+        Ok(id)
+    }
+
+    // Stores a summary in place of others, deleting them in the same
+    // transaction, so they're only gone once the new one is stored. Returns
+    // the new summary's id.
+    pub async fn replace_summaries(
+        &self,
+        replaced_ids: &[i64],
+        summary: &ActivitySummary,
+    ) -> Result<i64, Box<dyn Error>> {
+        let mut tx = self.pool.begin().await?;
+
+        for id in replaced_ids {
+            sqlx::query("DELETE FROM summary_search WHERE rowid = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            sqlx::query("DELETE FROM activity_summaries WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let id = Self::insert_summary(&mut tx, summary, WINDOW_SUMMARY).await?;
+
+        tx.commit().await?;
+
+        audit_summary_stored(summary);
+
+        Ok(id)
+    }
+
+    pub async fn delete_summaries_by_id(&self, ids: &[i64]) -> Result<u64, Box<dyn Error>> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let mut deleted = 0;
+
+        for id in ids {
+            sqlx::query("DELETE FROM summary_search WHERE rowid = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            deleted += sqlx::query("DELETE FROM activity_summaries WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+
+        Ok(deleted)
+    }
+
+    // Tasks only store their time range; their summaries are looked up from it
+    pub async fn store_task(&self, task: &Task) -> Result<i64, Box<dyn Error>> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO tasks (title, start_time, end_time, confidence)
+            VALUES (?, ?, ?, ?)
+            RETURNING id
+            "#,
+        )
+        .bind(&task.title)
+        .bind(task.start_time)
+        .bind(task.end_time)
+        .bind(task.confidence)
+        .fetch_one(&self.pool)
+        .await?
+        .get::<i64, _>("id");
+
+        Ok(id)
+    }
+
+    // Tasks overlapping the range, oldest first, with their summaries
+    pub async fn get_tasks_in_timeframe(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Task>, Box<dyn Error>> {
+        let rows = sqlx::query(TASKS_IN_TIMEFRAME_QUERY)
+            .bind(end)
+            .bind(start)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut tasks = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let start_time: DateTime<Utc> = row.try_get("start_time")?;
+            let end_time: DateTime<Utc> = row.try_get("end_time")?;
+
+            let mut summaries: Vec<ActivitySummary> = self
+                .get_summaries_in_timeframe(start_time, end_time)
+                .await?
+                .into_iter()
+                .filter(|s| s.start_time >= start_time && s.end_time <= end_time)
+                .collect();
+            summaries.sort_by_key(|s| s.start_time);
+
+            tasks.push(Task {
+                id: row.try_get("id")?,
+                title: row.try_get("title")?,
+                summaries,
+                start_time,
+                end_time,
+                confidence: row.try_get("confidence")?,
+            });
+        }
+
+        Ok(tasks)
+    }
+
+    // The events stored with summaries, counted per app in `bucket_minutes`
+    // slices of the range, oldest first and busiest app first within a slice
+    pub async fn get_event_timeline(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket_minutes: u32,
+    ) -> Result<Vec<TimelineBucket>, Box<dyn Error>> {
+        if bucket_minutes == 0 {
+            return Err("bucket_minutes must be at least 1".into());
+        }
+
+        let rows = sqlx::query(EVENT_TIMELINE_QUERY)
+            .bind(bucket_minutes as i64 * 60)
+            .bind(start)
+            .bind(end)
+            .bind(start.timestamp())
+            .bind(end.timestamp())
+            .fetch_all(&self.pool)
+            .await?;
+
+        // SQLite has no mode(), so the dominant type is picked here from the
+        // per-type counts. Ties go to the first type alphabetically, as they
+        // do in Postgres.
+        let mut buckets: Vec<(TimelineBucket, u64)> = Vec::new();
+
+        for row in rows {
+            let bucket_start: i64 = row.try_get("bucket_start")?;
+            let app_name: String = row.try_get("app_name")?;
+            let event_type: String = row.try_get("event_type")?;
+            let count: i64 = row.try_get("event_count")?;
+            let count = count.max(0) as u64;
+            let Some(bucket_start) = Utc.timestamp_opt(bucket_start, 0).single() else {
+                continue;
+            };
+
+            match buckets.last_mut() {
+                Some((bucket, dominant_count))
+                    if bucket.bucket_start == bucket_start && bucket.app_name == app_name =>
+                {
+                    bucket.event_count += count;
+                    if count > *dominant_count {
+                        bucket.dominant_event_type = event_type;
+                        *dominant_count = count;
+                    }
+                }
+                _ => buckets.push((
+                    TimelineBucket {
+                        bucket_start,
+                        app_name,
+                        event_count: count,
+                        dominant_event_type: event_type,
+                    },
+                    count,
+                )),
+            }
+        }
+
+        let mut buckets: Vec<TimelineBucket> = buckets.into_iter().map(|(bucket, _)| bucket).collect();
+        buckets.sort_by(|a, b| {
+            a.bucket_start
+                .cmp(&b.bucket_start)
+                .then_with(|| b.event_count.cmp(&a.event_count))
+                .then_with(|| a.app_name.cmp(&b.app_name))
+        });
+
+        Ok(buckets)
+    }
+
+    pub async fn get_latest_task_end_time(&self) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+        let row = sqlx::query("SELECT end_time FROM tasks ORDER BY end_time DESC LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| row.get("end_time")))
+    }
+
+    // `shown` is true for suggestions the user has already seen, like those
+    // sent as notifications, so they aren't shown again with the next answer
+    pub async fn store_suggestion(&self, suggestion: &Suggestion, shown: bool) -> Result<i64, Box<dyn Error>> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO proactive_suggestions (kind, message, urgency, created_at, shown)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&suggestion.kind)
+        .bind(&suggestion.message)
+        .bind(suggestion.urgency.as_str())
+        .bind(suggestion.created_at)
+        .bind(shown)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    // When a suggestion of this kind was last raised, if ever
+    pub async fn get_latest_suggestion_time(&self, kind: &str) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+        let row =
+            sqlx::query("SELECT created_at FROM proactive_suggestions WHERE kind = ? ORDER BY created_at DESC LIMIT 1")
+                .bind(kind)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(row.map(|row| row.get("created_at")))
+    }
+
+    // Suggestions not shown yet, oldest first, marked as shown
+    pub async fn take_unshown_suggestions(&self) -> Result<Vec<Suggestion>, Box<dyn Error>> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, kind, message, urgency, created_at
+            FROM proactive_suggestions
+            WHERE shown = 0
+            ORDER BY created_at ASC
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for row in &rows {
+            sqlx::query("UPDATE proactive_suggestions SET shown = 1 WHERE id = ?")
+                .bind(row.get::<i64, _>("id"))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        rows.iter().map(suggestion_from_row).collect()
+    }
+
+    // The most recent suggestions, newest first, whether shown or not
+    pub async fn get_recent_suggestions(&self, limit: i64) -> Result<Vec<Suggestion>, Box<dyn Error>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT kind, message, urgency, created_at
+            FROM proactive_suggestions
+            ORDER BY created_at DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(suggestion_from_row).collect()
+    }
+
+    // Runs SQLite's own consistency check, failing with its report if it finds problems
+    pub async fn integrity_check(&self) -> Result<(), Box<dyn Error>> {
+        let rows = sqlx::query("PRAGMA integrity_check").fetch_all(&self.pool).await?;
+
+        let report: Vec<String> = rows
+            .iter()
+            .map(|row| row.try_get::<String, _>(0))
+            .collect::<Result<_, _>>()?;
+
+        if report != ["ok"] {
+            return Err(format!("SQLite integrity check failed: {}", report.join("; ")).into());
+        }
+
+        Ok(())
+    }
+
+    // Compares the search index with the summaries, and has FTS5 check the
+    // index's own structure. Changes nothing.
+    pub async fn check_search_index(&self) -> Result<SearchIndexReport, Box<dyn Error>> {
+        let count = |sql: &'static str| async move {
+            sqlx::query_scalar::<_, i64>(sql)
+                .fetch_one(&self.pool)
+                .await
+                .map(|count| count.max(0) as u64)
+        };
+
+        let summaries =
+            count("SELECT COUNT(*) FROM activity_summaries WHERE merged = 0 AND summary_type = 'window'").await?;
+        let indexed = count("SELECT COUNT(*) FROM summary_search").await?;
+        let unindexed = count(
+            r#"
+            SELECT COUNT(*)
+            FROM activity_summaries s
+            LEFT JOIN summary_search f ON f.rowid = s.id
+            WHERE s.merged = 0 AND s.summary_type = 'window' AND (f.rowid IS NULL OR f.description IS NOT s.description)
+            "#,
+        )
+        .await?;
+        let orphaned = count(
+            r#"
+            SELECT COUNT(*)
+            FROM summary_search f
+            WHERE NOT EXISTS (SELECT 1 FROM activity_summaries s WHERE s.id = f.rowid AND s.merged = 0 AND s.summary_type = 'window')
+            "#,
+        )
+        .await?;
+
+        // Fails with SQLITE_CORRUPT_VTAB when the index doesn't match its own content
+        let corruption = sqlx::query("INSERT INTO summary_search(summary_search) VALUES('integrity-check')")
+            .execute(&self.pool)
+            .await
+            .err()
+            .map(|e| e.to_string());
+
+        Ok(SearchIndexReport {
+            summaries,
+            indexed,
+            unindexed,
+            orphaned,
+            corruption,
         })
     }
-}
 
-#[async_trait]
-impl SummaryStore for GeneralDbClient {
-    async fn store_summary(&self, summary: &ActivitySummary) -> Result<(), Box<dyn Error>> {
-        // Convert summary to DB representation
+    // Empties the search index and indexes every window summary that isn't merged
+    // away again, REINDEX_BATCH_SIZE per transaction. `progress` is called
+    // after each batch with the summaries done so far and the total.
+    pub async fn rebuild_search_index(&self, mut progress: impl FnMut(u64, u64)) -> Result<u64, Box<dyn Error>> {
+        let total: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM activity_summaries WHERE merged = 0 AND summary_type = 'window'")
+                .fetch_one(&self.pool)
+                .await?;
+
+        sqlx::query("DELETE FROM summary_search").execute(&self.pool).await?;
+
+        let mut reindexed = 0;
+        let mut last_id = 0;
+        loop {
+            let rows = sqlx::query(
+                "SELECT id, description, tags FROM activity_summaries WHERE merged = 0 AND summary_type = 'window' AND id > ? ORDER BY id LIMIT ?",
+            )
+            .bind(last_id)
+            .bind(REINDEX_BATCH_SIZE)
+            .fetch_all(&self.pool)
+            .await?;
+            if rows.is_empty() {
+                break;
+            }
+
+            let mut tx = self.pool.begin().await?;
+            for row in &rows {
+                let id: i64 = row.try_get("id")?;
+                // Tags that don't parse are left out rather than failing the rebuild
+                let tags: Vec<String> = serde_json::from_str(row.try_get("tags")?).unwrap_or_default();
+
+                sqlx::query("INSERT INTO summary_search (rowid, description, tags) VALUES (?, ?, ?)")
+                    .bind(id)
+                    .bind(row.try_get::<String, _>("description")?)
+                    .bind(tags.join(" "))
+                    .execute(&mut *tx)
+                    .await?;
+                last_id = id;
+            }
+            tx.commit().await?;
+
+            reindexed += rows.len() as u64;
+            progress(reindexed, total.max(0) as u64);
+        }
+
+        Ok(reindexed)
+    }
+
+    // Adds a summary within a transaction, with a search index entry if
+    // it's a window summary
+    async fn insert_summary(
+        tx: &mut Transaction<'_, Sqlite>,
+        summary: &ActivitySummary,
+        summary_type: &str,
+    ) -> Result<i64, Box<dyn Error>> {
         let tags_json = serde_json::to_string(&summary.tags)?;
         let events_json = serde_json::to_string(&summary.events)?;
-        
-        // Start a transaction
-        let mut tx = self.pool.begin().await?;
-        
-        // Insert into main table
+
         let summary_id = sqlx::query(
             r#"
             INSERT INTO activity_summaries
-                (start_time, end_time, description, tags, events_json)
-            VALUES (?, ?, ?, ?, ?)
+                (start_time, end_time, description, tags, events_json, summary_type)
+            VALUES (?, ?, ?, ?, ?, ?)
             RETURNING id
-            "#
+            "#,
         )
         .bind(summary.start_time)
         .bind(summary.end_time)
         .bind(&summary.description)
         .bind(&tags_json)
         .bind(&events_json)
-        .fetch_one(&mut *tx)
+        .bind(summary_type)
+        .fetch_one(&mut **tx)
         .await?
         .get::<i64, _>("id");
-        
+
+        if summary_type != WINDOW_SUMMARY {
+            return Ok(summary_id);
+        }
+
         // Insert into search index
         sqlx::query(
             r#"
             INSERT INTO summary_search 
                 (rowid, description, tags)
             VALUES (?, ?, ?)
-            "#
+            "#,
         )
         .bind(summary_id)
         .bind(&summary.description)
-        .bind(&summary.tags.join(" "))
-        .execute(&mut *tx)
+        .bind(summary.tags.join(" "))
+        .execute(&mut **tx)
         .await?;
-        
+
+        Ok(summary_id)
+    }
+
+    // Helper to convert between DB representation and ActivitySummary
+    fn parse_summary_from_row(
+        _id: i64, // We don't use the ID in our ActivitySummary model, but it's useful for debugging
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        description: String,
+        tags_json: String,
+        events_json: String,
+    ) -> Result<ActivitySummary, Box<dyn Error>> {
+        let tags: Vec<String> = serde_json::from_str(&tags_json)?;
+        let events = serde_json::from_str(&events_json)?;
+
+        Ok(ActivitySummary {
+            start_time,
+            end_time,
+            description,
+            events,
+            tags,
+        })
+    }
+}
+
+#[async_trait]
+impl SummaryStore for GeneralDbClient {
+    async fn store_summary(&self, summary: &ActivitySummary) -> Result<i64, Box<dyn Error>> {
+        let mut tx = self.pool.begin().await?;
+        let summary_id = Self::insert_summary(&mut tx, summary, WINDOW_SUMMARY).await?;
         tx.commit().await?;
-        
-        Ok(())
+
+        audit_summary_stored(summary);
+
+        Ok(summary_id)
     }
 
     async fn get_summaries_in_timeframe(
@@ -193,127 +951,255 @@ impl SummaryStore for GeneralDbClient {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<Vec<ActivitySummary>, Box<dyn Error>> {
-        let rows = sqlx::query(
+        let rows = sqlx::query(SUMMARIES_IN_TIMEFRAME_QUERY)
+            .bind(start)
+            .bind(end)
+            .bind(start)
+            .bind(end)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(Self::summary_from_row).collect()
+    }
+
+    async fn search_summaries(&self, query: &str) -> Result<Vec<ActivitySummary>, Box<dyn Error>> {
+        Ok(self.search_summaries_counted(query).await?.0)
+    }
+
+    async fn delete_summaries_in_timeframe(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u64, Box<dyn Error>> {
+        let mut tx = self.pool.begin().await?;
+
+        // Remove the search index entries first so they don't outlive their summaries
+        sqlx::query(
             r#"
-            SELECT id, start_time, end_time, description, tags, events_json
-            FROM activity_summaries
-            WHERE 
-                (start_time BETWEEN ? AND ?) OR
-                (end_time BETWEEN ? AND ?) OR
-                (start_time <= ? AND end_time >= ?)
-            ORDER BY start_time DESC
-            "#
+            DELETE FROM summary_search
+            WHERE rowid IN (
+                SELECT id FROM activity_summaries
+                WHERE summary_type = 'window' AND start_time >= ? AND end_time <= ?
+            )
+            "#,
         )
         .bind(start)
         .bind(end)
+        .execute(&mut *tx)
+        .await?;
+
+        // Only summaries that lie entirely inside the range are deleted
+        let result = sqlx::query(
+            r#"
+            DELETE FROM activity_summaries
+            WHERE summary_type = 'window' AND start_time >= ? AND end_time <= ?
+            "#,
+        )
         .bind(start)
         .bind(end)
-        .bind(start)
-        .bind(end)
-        .fetch_all(&self.pool)
+        .execute(&mut *tx)
         .await?;
-        
-        let mut summaries = Vec::with_capacity(rows.len());
-        
-        for row in rows {
-            let id: i64 = row.get("id");
-            let start_time: DateTime<Utc> = row.get("start_time");
-            let end_time: DateTime<Utc> = row.get("end_time");
-            let description: String = row.get("description");
-            let tags_json: String = row.get("tags");
-            let events_json: String = row.get("events_json");
-            
-            let summary = Self::parse_summary_from_row(
-                id, start_time, end_time, description, tags_json, events_json
-            )?;
-            
-            summaries.push(summary);
-        }
-        
-        Ok(summaries)
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
     }
 
-    async fn search_summaries(&self, query: &str) -> Result<Vec<ActivitySummary>, Box<dyn Error>> {
-        // Simple approach: search for each word with LIKE
-        let search_terms = query.split_whitespace()
-            .filter(|term| !term.is_empty())
-            .map(|term| format!("%{}%", term))
-            .collect::<Vec<_>>();
-        
-        // If no search terms, return recent summaries
-        if search_terms.is_empty() {
-            let rows = sqlx::query(
-                r#"
-                SELECT id, start_time, end_time, description, tags, events_json
-                FROM activity_summaries
-                ORDER BY start_time DESC
-                LIMIT 10
-                "#
-            )
-            .fetch_all(&self.pool)
-            .await?;
-            
-            let mut summaries = Vec::with_capacity(rows.len());
-            for row in rows {
-                let id: i64 = row.get("id");
-                let start_time: DateTime<Utc> = row.get("start_time");
-                let end_time: DateTime<Utc> = row.get("end_time");
-                let description: String = row.get("description");
-                let tags_json: String = row.get("tags");
-                let events_json: String = row.get("events_json");
-                
-                let summary = Self::parse_summary_from_row(
-                    id, start_time, end_time, description, tags_json, events_json
-                )?;
-                
-                summaries.push(summary);
-            }
-            return Ok(summaries);
-        }
-        
-        // Build a query that searches both description and tags using LIKE
-        let mut combined_query = String::from(
+    async fn get_latest_summary_end_time(&self) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+        let row = sqlx::query(
             r#"
-            SELECT id, start_time, end_time, description, tags, events_json
+            SELECT end_time
             FROM activity_summaries
-            WHERE 
-            "#
-        );
-        
-        for (i, term) in search_terms.iter().enumerate() {
-            if i > 0 {
-                combined_query.push_str(" OR ");
-            }
-            combined_query.push_str(&format!("description LIKE '{}'", term));
-            combined_query.push_str(&format!(" OR tags LIKE '{}'", term));
+            WHERE summary_type = 'window'
+            ORDER BY end_time DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| row.get("end_time")))
+    }
+
+    async fn update_summary(
+        &self,
+        id: i64,
+        description: Option<String>,
+        tags: Option<Vec<String>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let tags_json = tags.as_ref().map(serde_json::to_string).transpose()?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE activity_summaries
+            SET description = COALESCE(?, description), tags = COALESCE(?, tags)
+            WHERE id = ?
+            "#,
+        )
+        .bind(&description)
+        .bind(&tags_json)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if updated == 0 {
+            return Err(format!("No summary with id {}", id).into());
         }
-        
-        combined_query.push_str(" ORDER BY start_time DESC");
-        
-        let rows = sqlx::query(&combined_query)
-            .fetch_all(&self.pool)
+
+        let row = sqlx::query("SELECT description, tags, merged, summary_type FROM activity_summaries WHERE id = ?")
+            .bind(id)
+            .fetch_one(&mut *tx)
             .await?;
-        
-        let mut summaries = Vec::with_capacity(rows.len());
-        
-        for row in rows {
-            let id: i64 = row.get("id");
-            let start_time: DateTime<Utc> = row.get("start_time");
-            let end_time: DateTime<Utc> = row.get("end_time");
-            let description: String = row.get("description");
-            let tags_json: String = row.get("tags");
-            let events_json: String = row.get("events_json");
-            
-            let summary = match Self::parse_summary_from_row(
-                id, start_time, end_time, description, tags_json, events_json
-            ) {
-                Ok(s) => s,
-                Err(e) => return Err(e),
-            };
-            
-            summaries.push(summary);
+        let tags: Vec<String> = serde_json::from_str(row.get("tags"))?;
+        let merged: i64 = row.get("merged");
+        let summary_type: String = row.get("summary_type");
+
+        // Merged-away summaries and those of other types have no search
+        // entry, so they don't get one back
+        sqlx::query("DELETE FROM summary_search WHERE rowid = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        if merged == 0 && summary_type == WINDOW_SUMMARY {
+            sqlx::query("INSERT INTO summary_search (rowid, description, tags) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(row.get::<String, _>("description"))
+                .bind(tags.join(" "))
+                .execute(&mut *tx)
+                .await?;
         }
-        
-        Ok(summaries)
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete_summary(&self, id: i64) -> Result<bool, Box<dyn Error>> {
+        Ok(self.delete_summaries_by_id(&[id]).await? > 0)
+    }
+
+    async fn store_summary_with_type(
+        &self,
+        summary: &ActivitySummary,
+        summary_type: &str,
+    ) -> Result<i64, Box<dyn Error>> {
+        if summary_type == WINDOW_SUMMARY {
+            return self.store_summary(summary).await;
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM activity_summaries WHERE summary_type = ? AND start_time = ? AND end_time = ?")
+            .bind(summary_type)
+            .bind(summary.start_time)
+            .bind(summary.end_time)
+            .execute(&mut *tx)
+            .await?;
+
+        let summary_id = Self::insert_summary(&mut tx, summary, summary_type).await?;
+        tx.commit().await?;
+
+        audit_summary_stored(summary);
+
+        Ok(summary_id)
+    }
+
+    async fn get_summaries_by_type(
+        &self,
+        summary_type: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ActivitySummary>, Box<dyn Error>> {
+        let rows = sqlx::query(SUMMARIES_BY_TYPE_QUERY)
+            .bind(summary_type)
+            .bind(end)
+            .bind(start)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(Self::summary_from_row).collect()
+    }
+
+    async fn count_summaries_in_timeframe(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u64, Box<dyn Error>> {
+        let count: i64 = sqlx::query_scalar(COUNT_SUMMARIES_IN_TIMEFRAME_QUERY)
+            .bind(start)
+            .bind(end)
+            .bind(start)
+            .bind(end)
+            .bind(start)
+            .bind(end)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count as u64)
+    }
+
+    // Counts with the same terms search_summaries uses. With none, every
+    // summary the recent ones are picked from is counted.
+    async fn count_summaries_matching(&self, query: &str) -> Result<u64, Box<dyn Error>> {
+        let search = summary_match_expression(query);
+
+        let count: i64 = if search.is_empty() {
+            sqlx::query_scalar(COUNT_WINDOW_SUMMARIES_QUERY)
+                .fetch_one(&self.pool)
+                .await?
+        } else {
+            sqlx::query_scalar(COUNT_SEARCH_SUMMARIES_QUERY)
+                .bind(&search)
+                .fetch_one(&self.pool)
+                .await?
+        };
+
+        Ok(count as u64)
     }
 }
+
+fn suggestion_from_row(row: &SqliteRow) -> Result<Suggestion, Box<dyn Error>> {
+    let urgency: String = row.try_get("urgency")?;
+
+    Ok(Suggestion {
+        kind: row.try_get("kind")?,
+        message: row.try_get("message")?,
+        urgency: Urgency::parse(&urgency).ok_or_else(|| format!("Unknown urgency '{}'", urgency))?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn audit_summary_stored(summary: &ActivitySummary) {
+    privacy::record(AuditEntry::SummaryStored {
+        time_range: (summary.start_time, summary.end_time),
+        description_length: summary.description.len(),
+        tags: summary.tags.clone(),
+    });
+}
+
+// The FTS5 expression search_summaries binds for a query: any of its terms,
+// each matching words that start with it. Terms are quoted so nothing in
+// them is read as FTS5 syntax. Empty when the query has no terms.
+fn summary_match_expression(query: &str) -> String {
+    sanitize_query_for_search(query)
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+// The SQL search_summaries runs for a query, with a placeholder for the
+// match expression
+pub fn summary_search_sql(query: &str) -> String {
+    if summary_match_expression(query).is_empty() {
+        return RECENT_SUMMARIES_QUERY.to_string();
+    }
+
+    SEARCH_SUMMARIES_QUERY.to_string()
+}
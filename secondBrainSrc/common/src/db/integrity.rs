@@ -0,0 +1,167 @@
+use super::{decode_event_data, TimescaleClient};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::Row;
+use std::error::Error;
+
+const SCAN_BATCH_SIZE: i64 = 1000;
+// Nothing was recorded before this
+const EARLIEST_VALID_DATE: NaiveDate = match NaiveDate::from_ymd_opt(2020, 1, 1) {
+    Some(date) => date,
+    None => panic!("2020-01-01 is a valid date"),
+};
+// Clocks drift, so events a little in the future are allowed
+const CLOCK_SKEW_HOURS: i64 = 1;
+
+// Rows with every column the same but the id
+const DUPLICATE_EVENTS_QUERY: &str = r#"
+    SELECT COALESCE(SUM(copies - 1), 0)::BIGINT
+    FROM (
+        SELECT COUNT(*) AS copies
+        FROM user_events
+        GROUP BY timestamp, event_type, event_data, app_name, window_title, url, event_payload
+        HAVING COUNT(*) > 1
+    ) duplicated
+"#;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IntegrityReport {
+    pub total_events: u64,
+    // event_data, or the decoded event_payload, that isn't JSON
+    pub malformed_json: u64,
+    // Missing or blank app names
+    pub null_app_names: u64,
+    // Timestamps before 2020 or more than an hour ahead
+    pub future_timestamps: u64,
+    // Copies beyond the first of identical events
+    pub duplicate_events: u64,
+    // Daily rollups with a negative event count or a date outside the valid range
+    pub corrupt_summaries: u64,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.malformed_json == 0
+            && self.null_app_names == 0
+            && self.future_timestamps == 0
+            && self.duplicate_events == 0
+            && self.corrupt_summaries == 0
+    }
+}
+
+// What a scan of every event turned up, with the ids of rows to delete
+#[derive(Default)]
+struct EventScan {
+    total: u64,
+    malformed_ids: Vec<i32>,
+    null_app_ids: Vec<i32>,
+    out_of_range: u64,
+}
+
+// Reads every event, SCAN_BATCH_SIZE at a time, and counts the ones that
+// can't be right. Nothing is changed.
+pub async fn check_integrity(client: &TimescaleClient) -> Result<IntegrityReport, Box<dyn Error>> {
+    let scan = scan_events(client).await?;
+
+    let duplicate_events: i64 = sqlx::query_scalar(DUPLICATE_EVENTS_QUERY)
+        .fetch_one(&client.pool)
+        .await?;
+
+    let corrupt_summaries: i64 = if client.table_exists("daily_summaries").await? {
+        sqlx::query_scalar(
+            "SELECT COUNT(*) FROM daily_summaries WHERE event_count < 0 OR date < $1 OR date > CURRENT_DATE + 1",
+        )
+        .bind(EARLIEST_VALID_DATE)
+        .fetch_one(&client.pool)
+        .await?
+    } else {
+        0
+    };
+
+    Ok(IntegrityReport {
+        total_events: scan.total,
+        malformed_json: scan.malformed_ids.len() as u64,
+        null_app_names: scan.null_app_ids.len() as u64,
+        future_timestamps: scan.out_of_range,
+        duplicate_events: duplicate_events as u64,
+        corrupt_summaries: corrupt_summaries as u64,
+    })
+}
+
+// Deletes events whose data isn't JSON or that have no app name, returning
+// how many were deleted. Duplicates and odd timestamps are left for the
+// user to look at.
+pub async fn delete_corrupt_events(client: &TimescaleClient) -> Result<u64, Box<dyn Error>> {
+    let scan = scan_events(client).await?;
+    let ids: Vec<i32> = scan.malformed_ids.into_iter().chain(scan.null_app_ids).collect();
+
+    let mut deleted = 0;
+    for chunk in ids.chunks(SCAN_BATCH_SIZE as usize) {
+        deleted += sqlx::query("DELETE FROM user_events WHERE id = ANY($1)")
+            .bind(chunk)
+            .execute(&client.pool)
+            .await?
+            .rows_affected();
+    }
+
+    Ok(deleted)
+}
+
+async fn scan_events(client: &TimescaleClient) -> Result<EventScan, Box<dyn Error>> {
+    let earliest = EARLIEST_VALID_DATE.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let latest = Utc::now() + Duration::hours(CLOCK_SKEW_HOURS);
+
+    let mut scan = EventScan::default();
+    let mut last_id = 0;
+
+    loop {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, timestamp, event_data, event_payload, app_name
+            FROM user_events
+            WHERE id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(last_id)
+        .bind(SCAN_BATCH_SIZE)
+        .fetch_all(&client.pool)
+        .await?;
+
+        let Some(last_row) = rows.last() else {
+            break;
+        };
+        last_id = last_row.try_get("id")?;
+
+        for row in &rows {
+            let id: i32 = row.try_get("id")?;
+            let timestamp: Option<DateTime<Utc>> = row.try_get("timestamp")?;
+            let event_data: Option<String> = row.try_get("event_data")?;
+            let event_payload: Option<Vec<u8>> = row.try_get("event_payload")?;
+            let app_name: Option<String> = row.try_get("app_name")?;
+
+            scan.total += 1;
+
+            // Payloads in a format this build can't read are given the
+            // benefit of the doubt rather than counted as corrupt
+            let data = match event_payload {
+                Some(payload) => decode_event_data(&payload).ok(),
+                None => Some(event_data.unwrap_or_default()),
+            };
+            if data.is_some_and(|data| serde_json::from_str::<serde_json::Value>(&data).is_err()) {
+                scan.malformed_ids.push(id);
+            }
+
+            if app_name.is_none_or(|name| name.trim().is_empty()) {
+                scan.null_app_ids.push(id);
+            }
+
+            if timestamp.is_none_or(|t| t < earliest || t > latest) {
+                scan.out_of_range += 1;
+            }
+        }
+    }
+
+    Ok(scan)
+}
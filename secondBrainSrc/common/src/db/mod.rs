@@ -1,157 +1,1104 @@
-use crate::models::{AppContext, UserEvent};
+use crate::models::{
+    AppContext, AppCorrelation, DailySummary, EventType, FocusSession, TimelineBucket, UserEvent, WaypointEvent,
+    WindowTitleHit,
+};
+use crate::privacy::{self, AuditEntry};
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use sqlx::{Pool, Postgres, Row};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use sqlx::{postgres::PgRow, Pool, Postgres, QueryBuilder, Row};
 use std::error::Error;
+use std::pin::Pin;
 
+mod event_data;
 mod general_db;
+mod integrity;
+pub use event_data::*;
 pub use general_db::*;
+pub use integrity::*;
+
+const BATCH_INSERT_CHUNK_SIZE: usize = 1000;
+
+const DAILY_ROLLUP_JOB_NAME: &str = "second-brain-daily-rollup";
+// Five past midnight, in the database server's time zone
+const DAILY_ROLLUP_SCHEDULE: &str = "5 0 * * *";
+
+// Fills in yesterday's event count and top apps for the pg_cron job. The
+// description and highlight need the summaries and the LLM, so they're left
+// for the thinker, which overwrites the whole row when it rolls the day up.
+const DAILY_ROLLUP_JOB: &str = r#"
+    INSERT INTO daily_summaries (date, event_count, top_apps)
+    SELECT CURRENT_DATE - 1, SUM(events)::BIGINT, (ARRAY_AGG(app_name ORDER BY events DESC))[1:3]
+    FROM (
+        SELECT app_name, COUNT(*) AS events
+        FROM user_events
+        WHERE timestamp >= CURRENT_DATE - 1 AND timestamp < CURRENT_DATE
+        GROUP BY app_name
+    ) apps
+    HAVING SUM(events) > 0
+    ON CONFLICT (date) DO UPDATE
+    SET event_count = EXCLUDED.event_count, top_apps = EXCLUDED.top_apps, rolled_up_at = NOW()
+"#;
+
+// The SQL behind the read queries recall makes, public so recall can show
+// how a question was answered
+
+pub const EVENTS_IN_TIMEFRAME_QUERY: &str = r#"
+    SELECT timestamp, event_type as "event_type!", event_data as "event_data!", event_payload,
+          app_name as "app_name!", window_title as "window_title!", url,
+          screen_index, display_name, raw_window_title
+    FROM user_events
+    WHERE timestamp >= $1 AND timestamp <= $2
+    ORDER BY timestamp ASC
+"#;
+
+// Events in apps whose name contains $3, case-insensitively. $3 is a LIKE
+// pattern, so the name inside it is escaped with like_pattern_escape.
+pub const EVENTS_BY_APP_QUERY: &str = r#"
+    SELECT timestamp, event_type as "event_type!", event_data as "event_data!", event_payload,
+          app_name as "app_name!", window_title as "window_title!", url,
+          screen_index, display_name, raw_window_title
+    FROM user_events
+    WHERE timestamp >= $1 AND timestamp <= $2 AND app_name ILIKE $3 ESCAPE '\'
+    ORDER BY timestamp ASC
+"#;
+
+// Events per app in $1-minute buckets, without reading any event data
+pub const EVENT_TIMELINE_QUERY: &str = r#"
+    SELECT time_bucket(make_interval(mins => $1), timestamp) AS bucket_start,
+           app_name,
+           COUNT(*)::BIGINT AS event_count,
+           MODE() WITHIN GROUP (ORDER BY event_type) AS dominant_event_type
+    FROM user_events
+    WHERE timestamp >= $2 AND timestamp < $3
+    GROUP BY 1, 2
+    ORDER BY 1, 3 DESC, 2
+"#;
+
+// Postgres may check the conditions in any order, so the cast is guarded
+// for rows whose data is in event_payload and event_data is empty
+pub const APP_DWELL_TIMES_QUERY: &str = r#"
+    SELECT data->>'from_app' AS app_name,
+           SUM((data->>'dwell_time_ms')::bigint)::bigint AS dwell_time_ms
+    FROM (
+        SELECT CASE WHEN event_data <> '' THEN event_data::jsonb END AS data
+        FROM user_events
+        WHERE event_type = 'app_switch'
+          AND timestamp >= $1 AND timestamp < $2
+    ) switches
+    WHERE data->>'dwell_time_ms' IS NOT NULL
+    GROUP BY 1
+    ORDER BY 2 DESC
+"#;
+
+pub const WAYPOINTS_QUERY: &str = r#"
+    SELECT timestamp, event_type as "event_type!", event_data as "event_data!", event_payload,
+          app_name as "app_name!", window_title as "window_title!", url,
+          screen_index, display_name, raw_window_title
+    FROM user_events
+    WHERE event_type = 'waypoint'
+      AND timestamp >= $1 AND timestamp <= $2
+    ORDER BY timestamp ASC
+"#;
+
+pub const TOP_APP_NAMES_QUERY: &str = r#"
+    SELECT app_name
+    FROM user_events
+    WHERE timestamp >= $1 AND timestamp <= $2
+    GROUP BY app_name
+    ORDER BY COUNT(*) DESC
+    LIMIT $3
+"#;
+
+// $1 is an app name, or NULL for every pair
+pub const APP_CORRELATIONS_QUERY: &str = r#"
+    SELECT app_a, app_b, co_occurrence_count, co_occurrence_rate
+    FROM app_correlations
+    WHERE $1::text IS NULL OR app_a = $1 OR app_b = $1
+    ORDER BY co_occurrence_rate DESC, co_occurrence_count DESC
+    LIMIT $2
+"#;
+
+pub const DAILY_SUMMARY_QUERY: &str = r#"
+    SELECT date, description, event_count, top_apps, highlight
+    FROM daily_summaries
+    WHERE date >= $1 AND date <= $2
+    ORDER BY date ASC
+"#;
+
+pub const WINDOW_TITLE_SEARCH_QUERY: &str = r#"
+    SELECT DISTINCT ON (window_title) timestamp, app_name, window_title, url
+    FROM user_events
+    WHERE window_title ILIKE $1 AND timestamp >= $2 AND timestamp <= $3
+    ORDER BY window_title, timestamp DESC
+"#;
+
+// Events in time order, read from the database as they're consumed
+pub type EventStream<'a> = Pin<Box<dyn Stream<Item = Result<UserEvent, Box<dyn Error>>> + Send + 'a>>;
 
 #[async_trait]
 pub trait EventStore {
     async fn store_event(&self, event: UserEvent) -> Result<(), Box<dyn Error>>;
+    async fn store_events_batch(&self, events: &[UserEvent]) -> Result<(), Box<dyn Error>>;
     async fn get_events_in_timeframe(
         &self,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<Vec<UserEvent>, Box<dyn Error>>;
+    // Like get_events_in_timeframe, but yields events one at a time instead of
+    // buffering the whole range in memory
+    fn stream_events_in_timeframe<'a>(&'a self, start: DateTime<Utc>, end: DateTime<Utc>) -> EventStream<'a>;
+    // How many events get_events_in_timeframe would return, without loading them
+    async fn count_events_in_timeframe(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<u64, Box<dyn Error>>;
+    // Events in the timeframe from apps whose name contains app_name, ignoring
+    // case. Stores that can't filter by app load the timeframe and filter it.
+    async fn get_events_by_app_name(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        app_name: &str,
+    ) -> Result<Vec<UserEvent>, Box<dyn Error>> {
+        let app_name = app_name.to_lowercase();
+        let mut events = self.get_events_in_timeframe(start, end).await?;
+        events.retain(|event| event.app_context.app_name.to_lowercase().contains(&app_name));
+        Ok(events)
+    }
 }
 
+// Every event, oldest first. Columns added since user_events was first
+// created are read through to_jsonb, so tables without them can still be read.
+const ALL_EVENTS_QUERY: &str = r#"
+    SELECT timestamp, event_type as "event_type!", event_data as "event_data!",
+          decode(substr(to_jsonb(e)->>'event_payload', 3), 'hex') AS event_payload,
+          app_name as "app_name!", window_title as "window_title!", url,
+          (to_jsonb(e)->>'screen_index')::SMALLINT AS screen_index,
+          to_jsonb(e)->>'display_name' AS display_name,
+          to_jsonb(e)->>'raw_window_title' AS raw_window_title
+    FROM user_events e
+    ORDER BY timestamp ASC
+"#;
+
+// The events table as first created, before columns were added to it
+const CREATE_USER_EVENTS: &str = r#"
+    CREATE TABLE IF NOT EXISTS user_events (
+        id SERIAL PRIMARY KEY,
+        timestamp TIMESTAMPTZ NOT NULL,
+        event_type TEXT NOT NULL,
+        event_data TEXT NOT NULL,
+        app_name TEXT NOT NULL,
+        window_title TEXT NOT NULL,
+        url TEXT
+    )
+"#;
+
+const CREATE_USER_EVENTS_TIMESTAMP_INDEX: &str = r#"
+    CREATE INDEX IF NOT EXISTS user_events_timestamp_idx ON user_events (timestamp)
+"#;
+
+// Which monitor the window was on
+const ADD_USER_EVENTS_DISPLAY: &str = r#"
+    ALTER TABLE user_events
+        ADD COLUMN IF NOT EXISTS screen_index SMALLINT,
+        ADD COLUMN IF NOT EXISTS display_name TEXT
+"#;
+
+// Event data stored in a binary format, with event_data left empty
+const ADD_USER_EVENTS_PAYLOAD: &str = r#"
+    ALTER TABLE user_events ADD COLUMN IF NOT EXISTS event_payload BYTEA
+"#;
+
+const ADD_USER_EVENTS_RAW_WINDOW_TITLE: &str = r#"
+    ALTER TABLE user_events ADD COLUMN IF NOT EXISTS raw_window_title TEXT
+"#;
+
+// Last timestamp exchanged with each sync peer, per direction
+const CREATE_SYNC_STATE: &str = r#"
+    CREATE TABLE IF NOT EXISTS sync_state (
+        peer TEXT NOT NULL,
+        direction TEXT NOT NULL,
+        last_synced TIMESTAMPTZ NOT NULL,
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (peer, direction)
+    )
+"#;
+
+const CREATE_WINDOW_FOCUS_SESSIONS: &str = r#"
+    CREATE TABLE IF NOT EXISTS window_focus_sessions (
+        id SERIAL PRIMARY KEY,
+        app_name TEXT NOT NULL,
+        window_title TEXT NOT NULL,
+        start_time TIMESTAMPTZ NOT NULL,
+        end_time TIMESTAMPTZ NOT NULL
+    )
+"#;
+
+const CREATE_WINDOW_FOCUS_SESSIONS_TIME_INDEX: &str = r#"
+    CREATE INDEX IF NOT EXISTS window_focus_sessions_time_idx
+    ON window_focus_sessions (start_time, end_time)
+"#;
+
+// Recomputed from the focus sessions once a week by the thinker
+const CREATE_APP_CORRELATIONS: &str = r#"
+    CREATE TABLE IF NOT EXISTS app_correlations (
+        app_a TEXT NOT NULL,
+        app_b TEXT NOT NULL,
+        co_occurrence_count BIGINT NOT NULL,
+        co_occurrence_rate REAL NOT NULL,
+        computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+        PRIMARY KEY (app_a, app_b)
+    )
+"#;
+
+// One row per local day, rolled up from its summaries by the thinker
+const CREATE_DAILY_SUMMARIES: &str = r#"
+    CREATE TABLE IF NOT EXISTS daily_summaries (
+        date DATE PRIMARY KEY,
+        description TEXT NOT NULL DEFAULT '',
+        event_count BIGINT NOT NULL,
+        top_apps TEXT[] NOT NULL,
+        highlight TEXT,
+        rolled_up_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+    )
+"#;
+
+// Numbered schema changes, applied in order by run_migrations and recorded
+// in schema_migrations. Databases set up before a migration existed may
+// already have what it creates, so each one checks first.
+const MIGRATIONS: [(i32, &str); 10] = [
+    (1, CREATE_USER_EVENTS),
+    (2, CREATE_USER_EVENTS_TIMESTAMP_INDEX),
+    (3, ADD_USER_EVENTS_DISPLAY),
+    (4, ADD_USER_EVENTS_PAYLOAD),
+    (5, ADD_USER_EVENTS_RAW_WINDOW_TITLE),
+    (6, CREATE_SYNC_STATE),
+    (7, CREATE_WINDOW_FOCUS_SESSIONS),
+    (8, CREATE_WINDOW_FOCUS_SESSIONS_TIME_INDEX),
+    (9, CREATE_APP_CORRELATIONS),
+    (10, CREATE_DAILY_SUMMARIES),
+];
+
+// How often the background task checks the event database is reachable
+const HEALTH_CHECK_INTERVAL_SECS: u64 = 30;
+
+#[derive(Clone)]
 pub struct TimescaleClient {
     pool: Pool<Postgres>,
+    storage_format: StorageFormat,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStats {
+    pub pool_size: u32,
+    pub idle_connections: usize,
+    pub active_connections: u32,
 }
 
 impl TimescaleClient {
     pub async fn new(connection_string: &str) -> Result<Self, Box<dyn Error>> {
+        let client = Self::connect(connection_string).await?;
+
+        // Events already stored are kept; an existing table counts as the
+        // first migration and is only altered by later ones
+        let applied = client.run_migrations().await?;
+        if !applied.is_empty() {
+            println!("Applied event database migrations {:?}", applied);
+        }
+
+        client.spawn_health_check();
+
+        Ok(client)
+    }
+
+    // Connects without touching the schema, for maintenance that needs the
+    // tables as they were left
+    pub async fn connect(connection_string: &str) -> Result<Self, Box<dyn Error>> {
         println!("Connecting to database: {}", connection_string);
         let pool = sqlx::postgres::PgPoolOptions::new()
-            .max_connections(5)
+            .max_connections(10)
+            // Keep a couple of warm connections ready
+            .min_connections(2)
             .connect(connection_string)
             .await?;
-        
-        let client = Self { pool };
-        
-        // First, check if we need to drop existing tables
-        // This is temporary for development - remove in production
-        if let Ok(tables) = sqlx::query("SELECT to_regclass('user_events') as exists")
-            .fetch_one(&client.pool)
-            .await
-        {
-            // If table exists but is wrong, drop it
-            let exists: Option<String> = tables.try_get("exists").unwrap_or(None);
-            if exists.is_some() {
-                println!("Dropping existing user_events table to recreate it correctly");
-                sqlx::query("DROP TABLE user_events CASCADE;")
-                    .execute(&client.pool)
-                    .await?;
-            }
+
+        Ok(Self {
+            pool,
+            storage_format: StorageFormat::from_env(),
+        })
+    }
+
+    pub async fn table_exists(&self, table: &str) -> Result<bool, Box<dyn Error>> {
+        let exists: Option<String> = sqlx::query_scalar("SELECT to_regclass($1)::TEXT")
+            .bind(table)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(exists.is_some())
+    }
+
+    pub async fn drop_events_table(&self) -> Result<(), Box<dyn Error>> {
+        sqlx::query("DROP TABLE IF EXISTS user_events CASCADE")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // Versions recorded in schema_migrations, oldest first. Empty if the
+    // migration runner has never run.
+    pub async fn applied_migrations(&self) -> Result<Vec<i32>, Box<dyn Error>> {
+        if !self.table_exists("schema_migrations").await? {
+            return Ok(Vec::new());
         }
-        
-        // Ensure the required tables exist
-        client.ensure_tables_exist().await?;
-        
-        Ok(client)
+
+        let versions = sqlx::query_scalar("SELECT version FROM schema_migrations ORDER BY version")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(versions)
     }
-    
-    // Create the necessary tables if they don't exist
-    async fn ensure_tables_exist(&self) -> Result<(), Box<dyn Error>> {
-        // Create the user_events table if it doesn't exist
+
+    // Applies each migration not yet in schema_migrations in its own
+    // transaction. Returns the versions applied.
+    pub async fn run_migrations(&self) -> Result<Vec<i32>, Box<dyn Error>> {
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS user_events (
-                id SERIAL PRIMARY KEY,
-                timestamp TIMESTAMPTZ NOT NULL,
-                event_type TEXT NOT NULL,
-                event_data TEXT NOT NULL,
-                app_name TEXT NOT NULL,
-                window_title TEXT NOT NULL,
-                url TEXT
+            CREATE TABLE IF NOT EXISTS schema_migrations (
+                version INTEGER PRIMARY KEY,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
             )
-            "#
+            "#,
         )
         .execute(&self.pool)
         .await?;
-        
-        // Create an index on timestamp separately
+
+        let already_applied = self.applied_migrations().await?;
+        let mut applied = Vec::new();
+
+        for (version, sql) in MIGRATIONS {
+            if already_applied.contains(&version) {
+                continue;
+            }
+
+            let mut tx = self.pool.begin().await?;
+            sqlx::query(sql).execute(&mut *tx).await?;
+            sqlx::query("INSERT INTO schema_migrations (version) VALUES ($1)")
+                .bind(version)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            applied.push(version);
+        }
+
+        // Schema changes are when rows are most likely to have been mangled
+        if !applied.is_empty() {
+            let report = check_integrity(self).await?;
+            if report.is_clean() {
+                println!("✅ Integrity check passed: {} events", report.total_events);
+            } else {
+                eprintln!(
+                    "⚠️ Integrity check found problems, run `second-brain integrity-check`: {:?}",
+                    report
+                );
+            }
+        }
+
+        Ok(applied)
+    }
+
+    pub async fn count_all_events(&self) -> Result<u64, Box<dyn Error>> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_events")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count as u64)
+    }
+
+    // Bytes the data of events in the timeframe takes up on disk, in either
+    // column, for comparing storage formats
+    pub async fn event_data_bytes(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<u64, Box<dyn Error>> {
+        let bytes: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(pg_column_size(event_data) + COALESCE(pg_column_size(event_payload), 0)), 0)::BIGINT
+             FROM user_events WHERE timestamp >= $1 AND timestamp <= $2",
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(bytes as u64)
+    }
+
+    // Every event, oldest first, including from tables created before the
+    // columns added since
+    pub fn stream_all_events(&self) -> impl Stream<Item = Result<UserEvent, Box<dyn Error>>> + '_ {
+        sqlx::query(ALL_EVENTS_QUERY)
+            .fetch(&self.pool)
+            .map(|row| Self::event_from_row(&row?))
+    }
+
+    // Periodically runs SELECT 1 so dead connections (server restart, idle
+    // timeout) are noticed. sqlx drops broken connections and opens new ones
+    // on the next acquire, so this only needs to report.
+    fn spawn_health_check(&self) {
+        let pool = self.pool.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(HEALTH_CHECK_INTERVAL_SECS));
+            let mut healthy = true;
+
+            loop {
+                interval.tick().await;
+
+                if pool.is_closed() {
+                    break;
+                }
+
+                match sqlx::query("SELECT 1").execute(&pool).await {
+                    Ok(_) if !healthy => {
+                        println!("✅ Event database connection restored");
+                        healthy = true;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        eprintln!("❌ Event database health check failed: {}", e);
+                        healthy = false;
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn pool_stats(&self) -> PoolStats {
+        let pool_size = self.pool.size();
+        let idle_connections = self.pool.num_idle();
+
+        PoolStats {
+            pool_size,
+            idle_connections,
+            active_connections: pool_size.saturating_sub(idle_connections as u32),
+        }
+    }
+
+    pub async fn get_sync_cursor(&self, peer: &str, direction: &str) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+        let row = sqlx::query("SELECT last_synced FROM sync_state WHERE peer = $1 AND direction = $2")
+            .bind(peer)
+            .bind(direction)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.map(|row| row.get("last_synced")))
+    }
+
+    pub async fn set_sync_cursor(
+        &self,
+        peer: &str,
+        direction: &str,
+        last_synced: DateTime<Utc>,
+    ) -> Result<(), Box<dyn Error>> {
         sqlx::query(
             r#"
-            CREATE INDEX IF NOT EXISTS user_events_timestamp_idx ON user_events (timestamp)
-            "#
+            INSERT INTO sync_state (peer, direction, last_synced)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (peer, direction)
+            DO UPDATE SET last_synced = EXCLUDED.last_synced, updated_at = NOW()
+            "#,
         )
+        .bind(peer)
+        .bind(direction)
+        .bind(last_synced)
         .execute(&self.pool)
         .await?;
-        
+
         Ok(())
     }
+
+    // Every (peer, direction, last_synced, updated_at), most recently synced first
+    pub async fn get_sync_states(&self) -> Result<Vec<(String, String, DateTime<Utc>, DateTime<Utc>)>, Box<dyn Error>> {
+        let rows =
+            sqlx::query("SELECT peer, direction, last_synced, updated_at FROM sync_state ORDER BY updated_at DESC")
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut states = Vec::with_capacity(rows.len());
+        for row in rows {
+            states.push((
+                row.try_get("peer")?,
+                row.try_get("direction")?,
+                row.try_get("last_synced")?,
+                row.try_get("updated_at")?,
+            ));
+        }
+
+        Ok(states)
+    }
+
+    // Stores events from another device, skipping any already present. Events
+    // are the same when their timestamp, app and data all match. Returns the
+    // number of events inserted.
+    pub async fn import_events(&self, events: &[UserEvent]) -> Result<u64, Box<dyn Error>> {
+        let mut tx = self.pool.begin().await?;
+        let mut imported = Vec::new();
+
+        for event in events {
+            let (data, payload) = self.encode_columns(event);
+            let inserted = sqlx::query(
+                r#"
+                INSERT INTO user_events (timestamp, event_type, event_data, app_name, window_title, url, screen_index, display_name, event_payload, raw_window_title)
+                SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM user_events
+                    WHERE timestamp = $1 AND app_name = $4 AND event_data = $3
+                      AND event_payload IS NOT DISTINCT FROM $9
+                )
+                "#
+            )
+            .bind(event.timestamp)
+            .bind(&event.event)
+            .bind(data)
+            .bind(&event.app_context.app_name)
+            .bind(&event.app_context.window_title)
+            .bind(&event.app_context.url)
+            .bind(event.app_context.screen_index.map(i16::from))
+            .bind(&event.app_context.display_name)
+            .bind(payload)
+            .bind(&event.app_context.raw_window_title)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+            if inserted > 0 {
+                imported.push(event);
+            }
+        }
+
+        tx.commit().await?;
+
+        imported.iter().for_each(|event| audit_event_stored(event));
+
+        Ok(imported.len() as u64)
+    }
+
+    // Stores a newly opened session, returning its id so it can be extended
+    pub async fn store_focus_session(&self, session: &FocusSession) -> Result<i32, Box<dyn Error>> {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO window_focus_sessions (app_name, window_title, start_time, end_time)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(&session.app_name)
+        .bind(&session.window_title)
+        .bind(session.start_time)
+        .bind(session.end_time)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.try_get("id")?)
+    }
+
+    pub async fn update_focus_session_end(&self, id: i32, end_time: DateTime<Utc>) -> Result<(), Box<dyn Error>> {
+        sqlx::query("UPDATE window_focus_sessions SET end_time = $1 WHERE id = $2")
+            .bind(end_time)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // Sessions overlapping the range, oldest first
+    pub async fn get_focus_sessions(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<FocusSession>, Box<dyn Error>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT app_name, window_title, start_time, end_time
+            FROM window_focus_sessions
+            WHERE start_time < $2 AND end_time > $1
+            ORDER BY start_time
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut sessions = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            sessions.push(FocusSession {
+                app_name: row.try_get("app_name")?,
+                window_title: row.try_get("window_title")?,
+                start_time: row.try_get("start_time")?,
+                end_time: row.try_get("end_time")?,
+            });
+        }
+
+        Ok(sessions)
+    }
+
+    // Event counts per app in `bucket_minutes`-long slices of the range,
+    // oldest first and busiest app first within a slice
+    pub async fn get_event_timeline(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket_minutes: u32,
+    ) -> Result<Vec<TimelineBucket>, Box<dyn Error>> {
+        if bucket_minutes == 0 {
+            return Err("bucket_minutes must be at least 1".into());
+        }
+
+        let rows = sqlx::query(EVENT_TIMELINE_QUERY)
+            .bind(bucket_minutes.min(i32::MAX as u32) as i32)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut buckets = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let event_count: i64 = row.try_get("event_count")?;
+            buckets.push(TimelineBucket {
+                bucket_start: row.try_get("bucket_start")?,
+                app_name: row.try_get("app_name")?,
+                event_count: event_count.max(0) as u64,
+                dominant_event_type: row.try_get("dominant_event_type")?,
+            });
+        }
+
+        Ok(buckets)
+    }
+
+    // Replaces every stored correlation with a freshly computed set
+    pub async fn replace_app_correlations(&self, correlations: &[AppCorrelation]) -> Result<(), Box<dyn Error>> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM app_correlations").execute(&mut *tx).await?;
+
+        // 4 bind parameters per row
+        for chunk in correlations.chunks(BATCH_INSERT_CHUNK_SIZE) {
+            let mut builder = QueryBuilder::<Postgres>::new(
+                "INSERT INTO app_correlations (app_a, app_b, co_occurrence_count, co_occurrence_rate) ",
+            );
+            builder.push_values(chunk, |mut row, correlation| {
+                row.push_bind(&correlation.app_a)
+                    .push_bind(&correlation.app_b)
+                    .push_bind(correlation.co_occurrence_count as i64)
+                    .push_bind(correlation.co_occurrence_rate);
+            });
+            builder.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Stored correlations involving the app, or all of them, strongest first
+    pub async fn get_app_correlations(
+        &self,
+        app_name: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<AppCorrelation>, Box<dyn Error>> {
+        let rows = sqlx::query(APP_CORRELATIONS_QUERY)
+            .bind(app_name)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut correlations = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let co_occurrence_count: i64 = row.try_get("co_occurrence_count")?;
+            correlations.push(AppCorrelation {
+                app_a: row.try_get("app_a")?,
+                app_b: row.try_get("app_b")?,
+                co_occurrence_count: co_occurrence_count.max(0) as u64,
+                co_occurrence_rate: row.try_get("co_occurrence_rate")?,
+            });
+        }
+
+        Ok(correlations)
+    }
+
+    // When the stored correlations were computed, if they ever have been
+    pub async fn get_app_correlations_computed_at(&self) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+        let row = sqlx::query("SELECT MAX(computed_at) AS computed_at FROM app_correlations")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(row.try_get("computed_at")?)
+    }
+
+    // Total time spent in each app before switching away, from app_switch
+    // events in the range, longest first
+    pub async fn get_app_dwell_times(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(String, u64)>, Box<dyn Error>> {
+        let rows = sqlx::query(APP_DWELL_TIMES_QUERY)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut dwell_times = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let app_name: String = row.try_get("app_name")?;
+            let dwell_time_ms: i64 = row.try_get("dwell_time_ms")?;
+            dwell_times.push((app_name, dwell_time_ms.max(0) as u64));
+        }
+
+        Ok(dwell_times)
+    }
+
+    // Moments the user marked in the range, oldest first. Waypoints whose
+    // data can't be read are skipped.
+    pub async fn get_waypoints(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, WaypointEvent)>, Box<dyn Error>> {
+        let rows = sqlx::query(WAYPOINTS_QUERY)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut waypoints = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let event = Self::event_from_row(&row)?;
+            if let Ok(waypoint) = serde_json::from_str::<WaypointEvent>(&event.data) {
+                waypoints.push((event.timestamp, waypoint));
+            }
+        }
+
+        Ok(waypoints)
+    }
+
+    // Apps with events in the range, most events first
+    pub async fn get_top_app_names(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let rows = sqlx::query(TOP_APP_NAMES_QUERY)
+            .bind(start)
+            .bind(end)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut app_names = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            app_names.push(row.try_get("app_name")?);
+        }
+
+        Ok(app_names)
+    }
+
+    // Stores the day's rollup, replacing any earlier one
+    pub async fn store_daily_summary(&self, summary: &DailySummary) -> Result<(), Box<dyn Error>> {
+        sqlx::query(
+            r#"
+            INSERT INTO daily_summaries (date, description, event_count, top_apps, highlight)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (date) DO UPDATE
+            SET description = EXCLUDED.description, event_count = EXCLUDED.event_count,
+                top_apps = EXCLUDED.top_apps, highlight = EXCLUDED.highlight, rolled_up_at = NOW()
+            "#,
+        )
+        .bind(summary.date)
+        .bind(&summary.description)
+        .bind(summary.event_count as i64)
+        .bind(&summary.top_apps)
+        .bind(&summary.highlight)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_daily_summary(&self, date: NaiveDate) -> Result<Option<DailySummary>, Box<dyn Error>> {
+        Ok(self.get_daily_summaries(date, date).await?.pop())
+    }
+
+    // Rollups for the days from `start` to `end` inclusive, earliest first.
+    // Days with no activity, or not rolled up yet, are missing.
+    pub async fn get_daily_summaries(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<DailySummary>, Box<dyn Error>> {
+        let rows = sqlx::query(DAILY_SUMMARY_QUERY)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut summaries = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let event_count: i64 = row.try_get("event_count")?;
+            summaries.push(DailySummary {
+                date: row.try_get("date")?,
+                description: row.try_get("description")?,
+                event_count: event_count.max(0) as u64,
+                top_apps: row.try_get("top_apps")?,
+                highlight: row.try_get("highlight")?,
+            });
+        }
+
+        Ok(summaries)
+    }
+
+    // Schedules the SQL half of the daily rollup with pg_cron, so event counts
+    // and top apps are filled in even on days the thinker wasn't running.
+    // Returns false when pg_cron isn't installed in this database; the thinker
+    // rolls the days up either way.
+    pub async fn create_daily_rollup_policy(&self) -> Result<bool, Box<dyn Error>> {
+        let installed = sqlx::query("SELECT 1 FROM pg_extension WHERE extname = 'pg_cron'")
+            .fetch_optional(&self.pool)
+            .await?
+            .is_some();
+        if !installed {
+            return Ok(false);
+        }
+
+        // Scheduling under an existing name replaces that job
+        sqlx::query("SELECT cron.schedule($1, $2, $3)")
+            .bind(DAILY_ROLLUP_JOB_NAME)
+            .bind(DAILY_ROLLUP_SCHEDULE)
+            .bind(DAILY_ROLLUP_JOB)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(true)
+    }
+
+    // Cheap round trip to check the connection is still usable
+    pub async fn ping(&self) -> Result<(), Box<dyn Error>> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    // Find distinct window titles matching a pattern, most recent sighting of each
+    pub async fn search_window_titles(
+        &self,
+        pattern: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<WindowTitleHit>, Box<dyn Error>> {
+        let rows = sqlx::query(WINDOW_TITLE_SEARCH_QUERY)
+            .bind(format!("%{}%", pattern))
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut hits = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            hits.push(WindowTitleHit {
+                timestamp: row.try_get("timestamp")?,
+                app_name: row.try_get("app_name")?,
+                window_title: row.try_get("window_title")?,
+                url: row.try_get("url").ok(),
+            });
+        }
+
+        // Present the most recent titles first
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.timestamp));
+
+        Ok(hits)
+    }
+
+    // Shared by get_events_in_timeframe and stream_events_in_timeframe
+    fn event_from_row(row: &PgRow) -> Result<UserEvent, Box<dyn Error>> {
+        let timestamp: DateTime<Utc> = row.try_get("timestamp")?;
+        let event_type: String = row.try_get("event_type!")?;
+        let event_data: String = row.try_get("event_data!")?;
+        let event_payload: Option<Vec<u8>> = row.try_get("event_payload")?;
+        let app_name: String = row.try_get("app_name!")?;
+        let window_title: String = row.try_get("window_title!")?;
+        let url: Option<String> = row.try_get("url").ok();
+        let screen_index: Option<i16> = row.try_get("screen_index").ok();
+        let display_name: Option<String> = row.try_get("display_name").ok();
+        let raw_window_title: Option<String> = row.try_get("raw_window_title").ok();
+
+        Ok(UserEvent {
+            timestamp,
+            event: event_type,
+            data: match event_payload {
+                Some(payload) => decode_event_data(&payload)?,
+                None => event_data,
+            },
+            app_context: AppContext {
+                app_name,
+                window_title,
+                url,
+                screen_index: screen_index.and_then(|i| u8::try_from(i).ok()),
+                display_name,
+                raw_window_title,
+            },
+        })
+    }
+
+    // The (event_data, event_payload) columns for an event. JSON stays in the
+    // text column, where SQL can read it, as do app switches whatever the
+    // format since dwell times are summed in SQL. Data that isn't valid JSON
+    // is kept as text too.
+    fn encode_columns<'a>(&self, event: &'a UserEvent) -> (&'a str, Option<Vec<u8>>) {
+        if self.storage_format == StorageFormat::Json || event.event == EventType::AppSwitch.as_str() {
+            return (&event.data, None);
+        }
+
+        match encode_event_data(&event.data, self.storage_format) {
+            Ok(payload) => ("", Some(payload)),
+            Err(_) => (&event.data, None),
+        }
+    }
+}
+
+// Text to match literally inside a LIKE pattern with ESCAPE '\', so "my_app"
+// doesn't also match "myXapp"
+fn like_pattern_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+// Records that an event was stored, without any of its data
+fn audit_event_stored(event: &UserEvent) {
+    privacy::record(AuditEntry::EventStored {
+        timestamp: event.timestamp,
+        app_name: event.app_context.app_name.clone(),
+        event_type: event.event.clone(),
+    });
 }
 
 #[async_trait]
 impl EventStore for TimescaleClient {
     async fn store_event(&self, event: UserEvent) -> Result<(), Box<dyn Error>> {
-        // First, check if the events table exists, create it if it doesn't
-        self.ensure_tables_exist().await?;
-        
         // Insert the event into the database
+        let (data, payload) = self.encode_columns(&event);
         sqlx::query(
             r#"
-            INSERT INTO user_events (timestamp, event_type, event_data, app_name, window_title, url)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO user_events (timestamp, event_type, event_data, app_name, window_title, url, screen_index, display_name, event_payload, raw_window_title)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             "#
         )
         .bind(event.timestamp)
         .bind(&event.event)
-        .bind(&event.data)
+        .bind(data)
         .bind(&event.app_context.app_name)
         .bind(&event.app_context.window_title)
         .bind(&event.app_context.url)
+        .bind(event.app_context.screen_index.map(i16::from))
+        .bind(&event.app_context.display_name)
+        .bind(payload)
+        .bind(&event.app_context.raw_window_title)
         .execute(&self.pool)
         .await?;
-        
+
+        audit_event_stored(&event);
+
+        Ok(())
+    }
+
+    async fn store_events_batch(&self, events: &[UserEvent]) -> Result<(), Box<dyn Error>> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        // Postgres caps a statement at 65535 bind parameters, 10 per event
+        for chunk in events.chunks(BATCH_INSERT_CHUNK_SIZE) {
+            let mut builder = QueryBuilder::<Postgres>::new(
+                "INSERT INTO user_events (timestamp, event_type, event_data, app_name, window_title, url, screen_index, display_name, event_payload, raw_window_title) "
+            );
+
+            let rows = chunk.iter().map(|event| (event, self.encode_columns(event)));
+            builder.push_values(rows, |mut row, (event, (data, payload))| {
+                row.push_bind(event.timestamp)
+                    .push_bind(&event.event)
+                    .push_bind(data)
+                    .push_bind(&event.app_context.app_name)
+                    .push_bind(&event.app_context.window_title)
+                    .push_bind(&event.app_context.url)
+                    .push_bind(event.app_context.screen_index.map(i16::from))
+                    .push_bind(&event.app_context.display_name)
+                    .push_bind(payload)
+                    .push_bind(&event.app_context.raw_window_title);
+            });
+
+            builder.build().execute(&self.pool).await?;
+        }
+
+        events.iter().for_each(audit_event_stored);
+
         Ok(())
     }
+
     async fn get_events_in_timeframe(
         &self,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<Vec<UserEvent>, Box<dyn Error>> {
         // Query events within the timeframe using regular query to avoid compile-time checks
-        let rows = sqlx::query(
-            r#"
-            SELECT timestamp, event_type as "event_type!", event_data as "event_data!", 
-                  app_name as "app_name!", window_title as "window_title!", url
-            FROM user_events
-            WHERE timestamp >= $1 AND timestamp <= $2
-            ORDER BY timestamp ASC
-            "#
-        )
-        .bind(start)
-        .bind(end)
-        .fetch_all(&self.pool)
-        .await?;
+        let rows = sqlx::query(EVENTS_IN_TIMEFRAME_QUERY)
+            .bind(start)
+            .bind(end)
+            .fetch_all(&self.pool)
+            .await?;
 
         // Convert rows to UserEvent objects
-        let mut events = Vec::with_capacity(rows.len());
-        
-        for row in rows {
-            let timestamp: DateTime<Utc> = row.try_get("timestamp")?;
-            let event_type: String = row.try_get("event_type!")?;
-            let event_data: String = row.try_get("event_data!")?;
-            let app_name: String = row.try_get("app_name!")?;
-            let window_title: String = row.try_get("window_title!")?;
-            let url: Option<String> = row.try_get("url").ok();
-            
-            events.push(UserEvent {
-                timestamp,
-                event: event_type,
-                data: event_data,
-                app_context: AppContext {
-                    app_name,
-                    window_title,
-                    url,
-                },
-            });
-        }
+        rows.iter().map(Self::event_from_row).collect()
+    }
 
-        Ok(events)
+    fn stream_events_in_timeframe<'a>(&'a self, start: DateTime<Utc>, end: DateTime<Utc>) -> EventStream<'a> {
+        Box::pin(
+            sqlx::query(EVENTS_IN_TIMEFRAME_QUERY)
+                .bind(start)
+                .bind(end)
+                .fetch(&self.pool)
+                .map(|row| Self::event_from_row(&row?)),
+        )
+    }
+
+    async fn count_events_in_timeframe(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<u64, Box<dyn Error>> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM user_events WHERE timestamp >= $1 AND timestamp <= $2")
+                .bind(start)
+                .bind(end)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(count as u64)
+    }
+
+    async fn get_events_by_app_name(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        app_name: &str,
+    ) -> Result<Vec<UserEvent>, Box<dyn Error>> {
+        let rows = sqlx::query(EVENTS_BY_APP_QUERY)
+            .bind(start)
+            .bind(end)
+            .bind(format!("%{}%", like_pattern_escape(app_name)))
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(Self::event_from_row).collect()
     }
 }
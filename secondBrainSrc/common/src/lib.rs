@@ -1,8 +1,12 @@
 // common/src/lib.rs
+pub mod config;
 pub mod db;
 pub mod llm;
+pub mod memory;
 pub mod models;
-// pub mod utils;
+pub mod privacy;
+pub mod utils;
+pub mod window_sanitizer;
 
 // Re-export commonly used items
 pub use db::*;
@@ -0,0 +1,320 @@
+use crate::llm::{tags_from_reply, ContextWindowManager, LlmClient};
+use async_trait::async_trait;
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+pub const DEFAULT_ANTHROPIC_MODEL: &str = "claude-3-haiku-20240307";
+pub const DEFAULT_ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+// Longest we'll honour a rate limit's retry-after before giving up
+const MAX_RETRY_AFTER_SECS: u64 = 60;
+const HEALTH_CHECK_TIMEOUT_SECS: u64 = 5;
+
+const TAGS_SYSTEM_PROMPT: &str = "You extract 3-5 key tags or topics from descriptions of computer activity. \
+Respond with only a JSON array of short lowercase strings, for example [\"rust\", \"code review\"], and nothing else.";
+
+pub struct AnthropicClient {
+    client: Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    context_window: ContextWindowManager,
+}
+
+#[derive(Serialize, Debug)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    // Required by the Messages API, unlike Ollama's num_predict
+    max_tokens: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a str>,
+    messages: Vec<Message<'a>>,
+}
+
+#[derive(Serialize, Debug)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContentBlock {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorDetail {
+    message: String,
+}
+
+impl AnthropicClient {
+    // Reads ANTHROPIC_API_KEY and ANTHROPIC_MODEL from the environment
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let model = std::env::var("ANTHROPIC_MODEL").unwrap_or_else(|_| DEFAULT_ANTHROPIC_MODEL.to_string());
+        Self::with_base_url(&model, DEFAULT_ANTHROPIC_BASE_URL)
+    }
+
+    // Builds a client for the given model, taking the API key from ANTHROPIC_API_KEY
+    pub fn with_base_url(model: &str, base_url: &str) -> Result<Self, Box<dyn Error>> {
+        let api_key = std::env::var("ANTHROPIC_API_KEY")
+            .map_err(|_| "ANTHROPIC_API_KEY must be set to use the Anthropic backend")?;
+        Self::new(model, base_url, &api_key)
+    }
+
+    pub fn new(model: &str, base_url: &str, api_key: &str) -> Result<Self, Box<dyn Error>> {
+        let client = Client::builder().timeout(Duration::from_secs(180)).build()?;
+
+        Ok(Self {
+            client,
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            context_window: ContextWindowManager::from_env(),
+        })
+    }
+
+    // Sends a single-turn conversation and returns the first text block of the reply
+    async fn send_message(&self, system: Option<&str>, prompt: &str) -> Result<String, Box<dyn Error>> {
+        let request = MessagesRequest {
+            model: &self.model,
+            max_tokens: self.context_window.reserved_for_response,
+            system,
+            messages: vec![Message {
+                role: "user",
+                content: prompt,
+            }],
+        };
+
+        let mut response = self.post_messages(&request).await?;
+
+        // Rate limited: wait as long as we're told to, then try once more
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let wait = retry_after(&response);
+            eprintln!("⚠️ Anthropic rate limit hit, retrying in {}s", wait.as_secs());
+            tokio::time::sleep(wait).await;
+            response = self.post_messages(&request).await?;
+        }
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<ErrorResponse>(&body)
+                .map(|e| e.error.message)
+                .unwrap_or(body);
+            return Err(format!("Anthropic API returned {}: {}", status, message).into());
+        }
+
+        let reply: MessagesResponse = response.json().await?;
+        let text = reply
+            .content
+            .into_iter()
+            .next()
+            .ok_or("Anthropic API returned no content")?
+            .text;
+
+        Ok(text.trim().to_string())
+    }
+
+    async fn post_messages(&self, request: &MessagesRequest<'_>) -> Result<Response, Box<dyn Error>> {
+        let url = format!("{}/v1/messages", self.base_url);
+
+        Ok(self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(request)
+            .send()
+            .await?)
+    }
+
+    // Looks the model up, which checks the API is reachable, the key is
+    // valid and the model exists without spending any tokens
+    async fn check_health(&self) -> Result<(), Box<dyn Error>> {
+        let url = format!("{}/v1/models/{}", self.base_url, self.model);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .timeout(Duration::from_secs(HEALTH_CHECK_TIMEOUT_SECS))
+            .send()
+            .await
+            .map_err(|e| format!("Anthropic API is unreachable at {}: {}", self.base_url, e))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND => Err(format!("Anthropic model '{}' not found", self.model).into()),
+            status => Err(format!("Anthropic API returned {}", status).into()),
+        }
+    }
+}
+
+// The retry-after header in seconds, capped at MAX_RETRY_AFTER_SECS.
+// Defaults to a second when the header is missing or unreadable.
+fn retry_after(response: &Response) -> Duration {
+    let secs = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(1);
+
+    Duration::from_secs(secs.min(MAX_RETRY_AFTER_SECS))
+}
+
+#[async_trait]
+impl LlmClient for AnthropicClient {
+    async fn generate_text(&self, prompt: &str) -> Result<String, Box<dyn Error>> {
+        self.send_message(None, prompt).await
+    }
+
+    async fn extract_tags(&self, text: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let prompt = self.context_window.fit_prompt("", text, "");
+        let reply = self.send_message(Some(TAGS_SYSTEM_PROMPT), &prompt).await?;
+
+        Ok(tags_from_reply(&reply))
+    }
+
+    async fn health_check(&self) -> Result<(), Box<dyn Error>> {
+        self.check_health().await
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> AnthropicClient {
+        AnthropicClient::new(DEFAULT_ANTHROPIC_MODEL, &server.uri(), "test-key").unwrap()
+    }
+
+    fn reply(text: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({ "content": [{ "type": "text", "text": text }] }))
+    }
+
+    fn error(status: u16, message: &str) -> ResponseTemplate {
+        ResponseTemplate::new(status).set_body_json(json!({ "error": { "type": "error", "message": message } }))
+    }
+
+    #[tokio::test]
+    async fn a_reply_is_returned_as_text() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-api-key", "test-key"))
+            .and(header("anthropic-version", ANTHROPIC_VERSION))
+            .and(body_partial_json(json!({
+                "model": DEFAULT_ANTHROPIC_MODEL,
+                "messages": [{ "role": "user", "content": "Summarize this" }]
+            })))
+            .respond_with(reply("  Wrote Rust in the editor\n"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let text = client(&server).generate_text("Summarize this").await.unwrap();
+        assert_eq!(text, "Wrote Rust in the editor");
+    }
+
+    #[tokio::test]
+    async fn a_rate_limited_request_is_retried_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(error(429, "Rate limited").insert_header("retry-after", "0"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(reply("Read the news"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(
+            client(&server).generate_text("Summarize this").await.unwrap(),
+            "Read the news"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_second_rate_limit_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(error(429, "Rate limited").insert_header("retry-after", "0"))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let e = client(&server)
+            .generate_text("Summarize this")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("429"), "{}", e);
+        assert!(e.contains("Rate limited"), "{}", e);
+    }
+
+    #[tokio::test]
+    async fn a_server_error_is_reported_with_its_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(error(529, "Overloaded"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let e = client(&server)
+            .generate_text("Summarize this")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("529"), "{}", e);
+        assert!(e.contains("Overloaded"), "{}", e);
+    }
+
+    #[tokio::test]
+    async fn a_server_error_without_json_is_reported_as_is() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .respond_with(ResponseTemplate::new(502).set_body_string("Bad Gateway"))
+            .mount(&server)
+            .await;
+
+        let e = client(&server)
+            .generate_text("Summarize this")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("502"), "{}", e);
+        assert!(e.contains("Bad Gateway"), "{}", e);
+    }
+}
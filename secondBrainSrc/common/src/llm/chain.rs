@@ -0,0 +1,220 @@
+use crate::config::{BackendConfig, Config, DEFAULT_CIRCUIT_BREAKER_SECS, DEFAULT_REOPEN_PROBE_INTERVAL_SECS};
+use crate::llm::anthropic::DEFAULT_ANTHROPIC_BASE_URL;
+use crate::llm::openai::DEFAULT_OPENAI_BASE_URL;
+use crate::llm::{AnthropicClient, LlmClient, OllamaClient, OpenAiClient, TextStream};
+use async_trait::async_trait;
+use std::error::Error;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_OLLAMA_HOST: &str = "http://localhost:11434";
+
+type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Box<dyn Error>>> + Send + 'a>>;
+
+#[derive(Default)]
+struct BackendHealth {
+    // When the backend last failed while healthy; None means healthy
+    unhealthy_since: Option<Instant>,
+    // When the backend was last tried, so probes are spaced out
+    last_attempt: Option<Instant>,
+}
+
+struct Backend {
+    name: String,
+    client: Box<dyn LlmClient + Send + Sync>,
+    health: Mutex<BackendHealth>,
+}
+
+// Tries each backend in priority order and returns the first success.
+// A failing backend is skipped for `circuit_breaker_duration`, after which
+// one call every `reopen_probe_interval` is let through until it recovers.
+pub struct LlmChain {
+    backends: Vec<Backend>,
+    circuit_breaker_duration: Duration,
+    reopen_probe_interval: Duration,
+}
+
+impl LlmChain {
+    pub fn new(circuit_breaker_duration: Duration, reopen_probe_interval: Duration) -> Self {
+        Self {
+            backends: Vec::new(),
+            circuit_breaker_duration,
+            reopen_probe_interval,
+        }
+    }
+
+    pub fn add_backend(&mut self, name: &str, client: Box<dyn LlmClient + Send + Sync>) {
+        self.backends.push(Backend {
+            name: name.to_string(),
+            client,
+            health: Mutex::new(BackendHealth::default()),
+        });
+    }
+
+    // Builds the chain from [[llm.backends]] in the config file. Without any
+    // entries the chain holds a single backend of the LLM_BACKEND kind,
+    // configured from OLLAMA_HOST and OLLAMA_MODEL or ANTHROPIC_MODEL.
+    pub async fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let config = Config::load(path)?.llm;
+
+        let mut chain = Self::new(
+            Duration::from_secs(
+                config
+                    .circuit_breaker_duration_secs
+                    .unwrap_or(DEFAULT_CIRCUIT_BREAKER_SECS),
+            ),
+            Duration::from_secs(
+                config
+                    .circuit_breaker_reopen_probe_interval_secs
+                    .unwrap_or(DEFAULT_REOPEN_PROBE_INTERVAL_SECS),
+            ),
+        );
+
+        let backends = if config.backends.is_empty() {
+            vec![BackendConfig::from_env()?]
+        } else {
+            config.backends
+        };
+
+        for backend in backends {
+            let default_base_url = match backend.kind.as_str() {
+                "anthropic" => DEFAULT_ANTHROPIC_BASE_URL,
+                "openai" => DEFAULT_OPENAI_BASE_URL,
+                _ => DEFAULT_OLLAMA_HOST,
+            };
+            let base_url = backend.base_url.as_deref().unwrap_or(default_base_url);
+            let name = format!("{} {} ({})", backend.kind, backend.model, base_url);
+
+            let client: Box<dyn LlmClient + Send + Sync> = match backend.kind.as_str() {
+                "ollama" => Box::new(OllamaClient::with_base_url(&backend.model, base_url)?),
+                "anthropic" => Box::new(AnthropicClient::with_base_url(&backend.model, base_url)?),
+                "openai" => Box::new(OpenAiClient::with_base_url(&backend.model, base_url)?),
+                other => return Err(format!("Unknown LLM backend kind '{}'", other).into()),
+            };
+
+            // Backends that are down at startup stay in the chain so they can recover
+            let available = client.health_check().await;
+            chain.add_backend(&name, client);
+
+            if let Err(e) = available {
+                eprintln!("⚠️ LLM backend {} unavailable: {}", name, e);
+                chain.mark_failed(chain.backends.len() - 1);
+            }
+        }
+
+        Ok(chain)
+    }
+
+    // Claims the backend for a call if it's healthy or due for a probe
+    fn try_claim(&self, index: usize) -> bool {
+        let mut health = self.backends[index].health.lock().unwrap();
+        let now = Instant::now();
+
+        let Some(unhealthy_since) = health.unhealthy_since else {
+            return true;
+        };
+
+        let breaker_elapsed = now.duration_since(unhealthy_since) >= self.circuit_breaker_duration;
+        let probe_due = health
+            .last_attempt
+            .is_none_or(|last| now.duration_since(last) >= self.reopen_probe_interval);
+
+        if breaker_elapsed && probe_due {
+            // Recording the attempt up front keeps concurrent calls from
+            // probing the same backend at once
+            health.last_attempt = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn mark_failed(&self, index: usize) {
+        let mut health = self.backends[index].health.lock().unwrap();
+        let now = Instant::now();
+
+        health.unhealthy_since.get_or_insert(now);
+        health.last_attempt = Some(now);
+    }
+
+    fn mark_healthy(&self, index: usize) {
+        let mut health = self.backends[index].health.lock().unwrap();
+
+        if health.unhealthy_since.take().is_some() {
+            println!("✅ LLM backend {} recovered", self.backends[index].name);
+        }
+        health.last_attempt = Some(Instant::now());
+    }
+
+    async fn call<'a, T>(
+        &'a self,
+        request: impl Fn(&'a (dyn LlmClient + Send + Sync)) -> BackendFuture<'a, T> + Send,
+    ) -> Result<T, Box<dyn Error>> {
+        let mut last_error = None;
+
+        for (index, backend) in self.backends.iter().enumerate() {
+            if !self.try_claim(index) {
+                continue;
+            }
+
+            match request(backend.client.as_ref()).await {
+                Ok(value) => {
+                    self.mark_healthy(index);
+                    return Ok(value);
+                }
+                Err(e) => {
+                    eprintln!("⚠️ LLM backend {} failed, failing over: {}", backend.name, e);
+                    self.mark_failed(index);
+                    last_error = Some(e.to_string());
+                }
+            }
+        }
+
+        Err(match last_error {
+            Some(e) => format!("All LLM backends failed, last error: {}", e).into(),
+            None => "No healthy LLM backend available".into(),
+        })
+    }
+}
+
+#[async_trait]
+impl LlmClient for LlmChain {
+    async fn generate_text(&self, prompt: &str) -> Result<String, Box<dyn Error>> {
+        self.call(|client| client.generate_text(prompt)).await
+    }
+
+    // Fails over until a backend starts answering. A stream that breaks
+    // partway isn't picked up by the next backend.
+    async fn generate_text_stream(&self, prompt: &str) -> Result<TextStream, Box<dyn Error>> {
+        self.call(|client| client.generate_text_stream(prompt)).await
+    }
+
+    async fn extract_tags(&self, text: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        self.call(|client| client.extract_tags(text)).await
+    }
+
+    // Healthy while any backend is, so failover can still answer
+    async fn health_check(&self) -> Result<(), Box<dyn Error>> {
+        let mut errors = Vec::new();
+
+        for backend in &self.backends {
+            match backend.client.health_check().await {
+                Ok(()) => return Ok(()),
+                Err(e) => errors.push(format!("{}: {}", backend.name, e)),
+            }
+        }
+
+        Err(match errors.is_empty() {
+            true => "No LLM backends configured".into(),
+            false => errors.join("; ").into(),
+        })
+    }
+
+    // Any backend may end up answering once the ones before it fail
+    fn is_remote(&self) -> bool {
+        self.backends.iter().any(|backend| backend.client.is_remote())
+    }
+}
@@ -0,0 +1,97 @@
+use std::env;
+
+// Default context size for llama3.2:3b
+pub const DEFAULT_MODEL_MAX_TOKENS: usize = 4096;
+// Matches the num_predict limit we send to Ollama
+pub const DEFAULT_RESERVED_FOR_RESPONSE: usize = 1024;
+
+// Keeps prompts inside the model's context window by trimming the data section
+#[derive(Debug, Clone)]
+pub struct ContextWindowManager {
+    pub max_tokens: usize,
+    pub reserved_for_response: usize,
+}
+
+impl ContextWindowManager {
+    pub fn new(max_tokens: usize, reserved_for_response: usize) -> Self {
+        Self {
+            max_tokens,
+            reserved_for_response,
+        }
+    }
+
+    // Reads MODEL_MAX_TOKENS from the environment, falling back to the llama3.2:3b default
+    pub fn from_env() -> Self {
+        let max_tokens = env::var("MODEL_MAX_TOKENS")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_MODEL_MAX_TOKENS);
+
+        Self::new(max_tokens, DEFAULT_RESERVED_FOR_RESPONSE)
+    }
+
+    fn prompt_budget(&self) -> usize {
+        self.max_tokens.saturating_sub(self.reserved_for_response)
+    }
+
+    // Builds a prompt from the three sections, keeping system and query intact and
+    // dropping the oldest lines of data until the whole prompt fits the budget
+    pub fn fit_prompt(&self, system: &str, data: &str, query: &str) -> String {
+        let full_prompt = assemble_prompt(system, data, query);
+        let original_tokens = estimate_tokens(&full_prompt);
+        let budget = self.prompt_budget();
+
+        if original_tokens <= budget {
+            return full_prompt;
+        }
+
+        // Binary search for the largest number of trailing (most recent) lines that fit
+        let lines: Vec<&str> = data.lines().collect();
+        let mut low = 0;
+        let mut high = lines.len();
+
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            let candidate = assemble_prompt(system, &lines[lines.len() - mid..].join("\n"), query);
+
+            if estimate_tokens(&candidate) <= budget {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+
+        let truncated_prompt = assemble_prompt(system, &lines[lines.len() - low..].join("\n"), query);
+
+        eprintln!(
+            "⚠️ Prompt truncated to fit context window: ~{} tokens -> ~{} tokens (kept {}/{} data lines, budget {})",
+            original_tokens,
+            estimate_tokens(&truncated_prompt),
+            low,
+            lines.len(),
+            budget
+        );
+
+        truncated_prompt
+    }
+}
+
+impl Default for ContextWindowManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_MODEL_MAX_TOKENS, DEFAULT_RESERVED_FOR_RESPONSE)
+    }
+}
+
+// Rough token estimate (~4 characters per token for English text)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn assemble_prompt(system: &str, data: &str, query: &str) -> String {
+    [system, data, query]
+        .iter()
+        .map(|section| section.trim())
+        .filter(|section| !section.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
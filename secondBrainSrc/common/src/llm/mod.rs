@@ -1,15 +1,79 @@
 use async_trait::async_trait;
+use futures::stream::{self, Stream};
 use std::error::Error;
+use std::pin::Pin;
+
+// Pieces of generated text, in the order they were generated
+pub type TextStream = Pin<Box<dyn Stream<Item = Result<String, Box<dyn Error>>> + Send>>;
 
 #[async_trait]
 pub trait LlmClient {
     async fn generate_text(&self, prompt: &str) -> Result<String, Box<dyn Error>>;
+
+    // Like generate_text, but yields the text as it's generated. Backends
+    // that can't stream yield it all at once.
+    async fn generate_text_stream(&self, prompt: &str) -> Result<TextStream, Box<dyn Error>> {
+        let text = self.generate_text(prompt).await?;
+        Ok(Box::pin(stream::once(async move { Ok(text) })))
+    }
+
     async fn extract_tags(&self, text: &str) -> Result<Vec<String>, Box<dyn Error>>;
+
+    // Cheap check that the backend is reachable and ready, for health monitoring
+    async fn health_check(&self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    // Whether prompts leave this machine
+    fn is_remote(&self) -> bool {
+        false
+    }
+}
+
+// The JSON in an LLM reply. Models often wrap it in a ```json fence despite
+// being asked not to, so that's stripped when the reply doesn't parse as is.
+pub fn try_extract_json(text: &str) -> Option<serde_json::Value> {
+    let text = text.trim();
+    if let Ok(value) = serde_json::from_str(text) {
+        return Some(value);
+    }
+
+    let fenced = text.strip_prefix("```json").or_else(|| text.strip_prefix("```"))?;
+    let inner = fenced.trim_end().strip_suffix("```").unwrap_or(fenced);
+    let value = serde_json::from_str(inner.trim()).ok()?;
+    eprintln!("⚠️ LLM reply wasn't bare JSON, used the JSON inside its code fence");
+    Some(value)
+}
+
+// Tags from a reply asked to be a JSON array of strings, or one tag per
+// line if the model ignored the format
+pub(crate) fn tags_from_reply(reply: &str) -> Vec<String> {
+    let tags = match try_extract_json(reply).and_then(|value| serde_json::from_value::<Vec<String>>(value).ok()) {
+        Some(tags) => tags,
+        None => {
+            eprintln!("⚠️ LLM reply wasn't a JSON list of tags, reading one tag per line");
+            reply.lines().map(|line| line.to_string()).collect()
+        }
+    };
+
+    tags.into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
 }
 
+pub(crate) mod anthropic;
+mod chain;
+mod context_window;
 mod ollama;
-pub use ollama::OllamaClient;
+pub(crate) mod openai;
+pub use anthropic::AnthropicClient;
+pub use chain::LlmChain;
+pub use context_window::{estimate_tokens, ContextWindowManager};
+pub use ollama::{OllamaClient, DEFAULT_CONNECT_RETRIES, DEFAULT_CONNECT_RETRY_DELAY};
+pub use openai::OpenAiClient;
 
-pub async fn create_default_client() -> Result<impl LlmClient, Box<dyn Error>> {
-    ollama::OllamaClient::new("llama3.2:3b").await
+// Backends come from [[llm.backends]] in the config file
+pub async fn create_default_client() -> Result<LlmChain, Box<dyn Error>> {
+    LlmChain::load(&crate::config::config_path()).await
 }
@@ -1,14 +1,19 @@
-use crate::llm::LlmClient;
+use crate::llm::{ContextWindowManager, LlmClient, TextStream};
 use async_trait::async_trait;
-use reqwest::Client;
+use futures::stream;
+use reqwest::{Client, Response};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::time::Duration;
 
+pub const DEFAULT_CONNECT_RETRIES: u32 = 5;
+pub const DEFAULT_CONNECT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 pub struct OllamaClient {
     client: Client,
     model: String,
     base_url: String,
+    context_window: ContextWindowManager,
 }
 
 #[derive(Serialize, Debug)]
@@ -34,6 +39,20 @@ struct GenerateOptions {
 }
 
 #[derive(Deserialize, Debug)]
+struct TagsResponse {
+    models: Vec<ModelTag>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ModelTag {
+    name: String,
+}
+
+// Health checks shouldn't wait as long as generation does
+const HEALTH_CHECK_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
 struct GenerateResponse {
     model: String,
     created_at: String,
@@ -41,24 +60,64 @@ struct GenerateResponse {
     done: bool,
 }
 
+// One line of a streamed generation
+#[derive(Deserialize, Debug)]
+struct GenerateChunk {
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    done: bool,
+    // Sent in place of the text when generation fails partway
+    error: Option<String>,
+}
+
 impl OllamaClient {
-    pub async fn new(model: &str) -> Result<Self, Box<dyn Error>> {
+    // Checks the server has the model. Ollama is often still starting at
+    // boot, so an unreachable server is retried up to max_retries times,
+    // waiting retry_delay and then twice as long after each attempt.
+    pub async fn new(model: &str, max_retries: u32, retry_delay: Duration) -> Result<Self, Box<dyn Error>> {
+        let base_url = std::env::var("OLLAMA_HOST").unwrap_or_else(|_| "http://localhost:11434".to_string());
+        let ollama = Self::with_base_url(model, &base_url)?;
+
+        let mut delay = retry_delay;
+        let mut attempts = 1;
+        loop {
+            match ollama.check_model().await {
+                Ok(()) => return Ok(ollama),
+                Err(e) if !is_unreachable(e.as_ref()) => return Err(e),
+                Err(e) if attempts > max_retries => {
+                    return Err(format!(
+                        "Could not reach Ollama at {} after {} attempts – is `ollama serve` running? ({})",
+                        ollama.base_url, attempts, e
+                    )
+                    .into())
+                }
+                Err(_) => {}
+            }
+
+            eprintln!(
+                "⚠️ Ollama is unreachable at {}, retrying in {:?}",
+                ollama.base_url, delay
+            );
+            tokio::time::sleep(delay).await;
+            delay = delay.saturating_mul(2);
+            attempts += 1;
+        }
+    }
+
+    // Builds a client without checking that the server has the model
+    pub fn with_base_url(model: &str, base_url: &str) -> Result<Self, Box<dyn Error>> {
         let client = Client::builder().timeout(Duration::from_secs(180)).build()?;
 
-        let base_url =
-            std::env::var("OLLAMA_HOST").unwrap_or_else(|_| "http://localhost:11434".to_string());
-        let ollama = Self {
+        Ok(Self {
             client,
             model: model.to_string(),
-            base_url,
-        };
-
-        ollama.check_model().await?;
-
-        Ok(ollama)
+            base_url: base_url.trim_end_matches('/').to_string(),
+            context_window: ContextWindowManager::from_env(),
+        })
     }
 
-    async fn check_model(&self) -> Result<(), Box<dyn Error>> {
+    pub async fn check_model(&self) -> Result<(), Box<dyn Error>> {
         let url = format!("{}/api/show", self.base_url);
 
         let response = self
@@ -78,30 +137,109 @@ impl OllamaClient {
 
         Ok(())
     }
-}
 
-#[async_trait]
-impl LlmClient for OllamaClient {
-    async fn generate_text(&self, prompt: &str) -> Result<String, Box<dyn Error>> {
-        let url = format!("{}/api/generate", self.base_url);
-        // println!("{}/api/generate", self.base_url);
+    // Lists the installed models to check both that Ollama is up and that
+    // the configured model has been pulled
+    async fn check_health(&self) -> Result<(), Box<dyn Error>> {
+        let url = format!("{}/api/tags", self.base_url);
+
+        let tags: TagsResponse = self
+            .client
+            .get(&url)
+            .timeout(Duration::from_secs(HEALTH_CHECK_TIMEOUT_SECS))
+            .send()
+            .await
+            .map_err(|e| format!("Ollama is unreachable at {}: {}", self.base_url, e))?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Ollama lists untagged models as "name:latest"
+        let installed = tags
+            .models
+            .iter()
+            .any(|m| m.name == self.model || m.name == format!("{}:latest", self.model));
+
+        if !installed {
+            return Err(format!("Model '{}' is not installed in Ollama", self.model).into());
+        }
 
-        let request = GenerateRequest {
+        Ok(())
+    }
+
+    fn generate_request(&self, prompt: &str, stream: bool) -> GenerateRequest {
+        GenerateRequest {
             model: self.model.clone(),
             prompt: prompt.to_string(),
-            stream: Some(false),
+            stream: Some(stream),
             options: Some(GenerateOptions {
                 temperature: Some(0.7),
                 top_p: Some(0.9),
-                num_predict: Some(1024),
+                num_predict: Some(self.context_window.reserved_for_response as i32),
                 ..Default::default()
             }),
-        };
+        }
+    }
+}
+
+// Whether a request failed because the server couldn't be reached, rather
+// than because of what it answered
+fn is_unreachable(e: &(dyn Error + 'static)) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout())
+}
+
+// A streamed generation's body is one GenerateChunk per line. Yields the
+// text of each until one says it's done.
+fn generated_pieces(response: Response) -> TextStream {
+    Box::pin(stream::unfold(Some((response, Vec::new())), |state| async move {
+        let (mut response, mut buffer) = state?;
+
+        loop {
+            if let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                if line.trim_ascii().is_empty() {
+                    continue;
+                }
+
+                let chunk = match serde_json::from_slice::<GenerateChunk>(&line) {
+                    Ok(chunk) => chunk,
+                    Err(e) => return Some((Err(e.into()), None)),
+                };
+                if let Some(error) = chunk.error {
+                    return Some((Err(error.into()), None));
+                }
+                if chunk.response.is_empty() {
+                    if chunk.done {
+                        return None;
+                    }
+                    continue;
+                }
+
+                let next = (!chunk.done).then_some((response, buffer));
+                return Some((Ok(chunk.response), next));
+            }
+
+            match response.chunk().await {
+                Ok(Some(bytes)) => buffer.extend_from_slice(&bytes),
+                // The last line may not end in a newline
+                Ok(None) if !buffer.is_empty() => buffer.push(b'\n'),
+                Ok(None) => return None,
+                Err(e) => return Some((Err(e.into()), None)),
+            }
+        }
+    }))
+}
+
+#[async_trait]
+impl LlmClient for OllamaClient {
+    async fn generate_text(&self, prompt: &str) -> Result<String, Box<dyn Error>> {
+        let url = format!("{}/api/generate", self.base_url);
 
         let response = self
             .client
             .post(&url)
-            .json(&request)
+            .json(&self.generate_request(prompt, false))
             .send()
             .await?
             .json::<GenerateResponse>()
@@ -110,10 +248,26 @@ impl LlmClient for OllamaClient {
         Ok(response.response.trim().to_string())
     }
 
+    // Ollama sends the answer as it's generated, a line of JSON at a time
+    async fn generate_text_stream(&self, prompt: &str) -> Result<TextStream, Box<dyn Error>> {
+        let url = format!("{}/api/generate", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&self.generate_request(prompt, true))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(generated_pieces(response))
+    }
+
     async fn extract_tags(&self, text: &str) -> Result<Vec<String>, Box<dyn Error>> {
-        let prompt = format!(
-            "Extract 3-5 key tags or topics from this activity description. Return each tag on a new line, without numbering or bullet points:\n\n{}",
-            text
+        let prompt = self.context_window.fit_prompt(
+            "Extract 3-5 key tags or topics from this activity description. Return each tag on a new line, without numbering or bullet points:",
+            text,
+            "",
         );
 
         let tags_text = self.generate_text(&prompt).await?;
@@ -126,4 +280,8 @@ impl LlmClient for OllamaClient {
 
         Ok(tags)
     }
+
+    async fn health_check(&self) -> Result<(), Box<dyn Error>> {
+        self.check_health().await
+    }
 }
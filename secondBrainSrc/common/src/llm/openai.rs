@@ -0,0 +1,307 @@
+use crate::llm::{tags_from_reply, ContextWindowManager, LlmClient};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+pub const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com";
+
+const HEALTH_CHECK_TIMEOUT_SECS: u64 = 5;
+
+const TAGS_SYSTEM_PROMPT: &str = "You extract 3-5 key tags or topics from descriptions of computer activity. \
+Respond with only a JSON array of short lowercase strings, for example [\"rust\", \"code review\"], and nothing else.";
+
+// Talks to OpenAI's API or any server that speaks its chat completions
+// format, like LM Studio, vLLM or LocalAI
+pub struct OpenAiClient {
+    client: Client,
+    // Local servers usually don't ask for one
+    api_key: Option<String>,
+    model: String,
+    base_url: String,
+    context_window: ContextWindowManager,
+}
+
+#[derive(Serialize, Debug)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    max_tokens: usize,
+    messages: Vec<Message<'a>>,
+}
+
+#[derive(Serialize, Debug)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Choice {
+    message: ReplyMessage,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReplyMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorDetail {
+    message: String,
+}
+
+impl OpenAiClient {
+    pub fn new(model: &str, base_url: &str, api_key: Option<String>) -> Result<Self, Box<dyn Error>> {
+        let client = Client::builder().timeout(Duration::from_secs(180)).build()?;
+
+        Ok(Self {
+            client,
+            api_key,
+            model: model.to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            context_window: ContextWindowManager::from_env(),
+        })
+    }
+
+    // Reads OPENAI_API_KEY, OPENAI_MODEL and OPENAI_BASE_URL from the environment
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        let model = std::env::var("OPENAI_MODEL").unwrap_or_else(|_| DEFAULT_OPENAI_MODEL.to_string());
+        let base_url = std::env::var("OPENAI_BASE_URL").unwrap_or_else(|_| DEFAULT_OPENAI_BASE_URL.to_string());
+        Self::with_base_url(&model, &base_url)
+    }
+
+    // Builds a client for the given model, taking the API key from
+    // OPENAI_API_KEY. The key is only required by OpenAI itself.
+    pub fn with_base_url(model: &str, base_url: &str) -> Result<Self, Box<dyn Error>> {
+        let api_key = std::env::var("OPENAI_API_KEY").ok().filter(|key| !key.is_empty());
+        if api_key.is_none() && base_url.trim_end_matches('/') == DEFAULT_OPENAI_BASE_URL {
+            return Err("OPENAI_API_KEY must be set to use the OpenAI API".into());
+        }
+
+        Self::new(model, base_url, api_key)
+    }
+
+    // Sends a single-turn conversation and returns the first choice's reply
+    async fn chat(&self, system: Option<&str>, prompt: &str) -> Result<String, Box<dyn Error>> {
+        let mut messages = Vec::new();
+        if let Some(system) = system {
+            messages.push(Message {
+                role: "system",
+                content: system,
+            });
+        }
+        messages.push(Message {
+            role: "user",
+            content: prompt,
+        });
+
+        let request = ChatCompletionRequest {
+            model: &self.model,
+            max_tokens: self.context_window.reserved_for_response,
+            messages,
+        };
+
+        let url = format!("{}/v1/chat/completions", self.base_url);
+        let response = self.authorized(self.client.post(&url)).json(&request).send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<ErrorResponse>(&body)
+                .map(|e| e.error.message)
+                .unwrap_or(body);
+            return Err(format!("OpenAI API returned {}: {}", status, message).into());
+        }
+
+        let reply: ChatCompletionResponse = response.json().await?;
+        let text = reply
+            .choices
+            .into_iter()
+            .next()
+            .ok_or("OpenAI API returned no choices")?
+            .message
+            .content
+            .unwrap_or_default();
+
+        Ok(text.trim().to_string())
+    }
+
+    fn authorized(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    // Looks the model up, which checks the server is reachable, the key is
+    // valid and the model exists without spending any tokens
+    async fn check_health(&self) -> Result<(), Box<dyn Error>> {
+        let url = format!("{}/v1/models/{}", self.base_url, self.model);
+
+        let response = self
+            .authorized(self.client.get(&url))
+            .timeout(Duration::from_secs(HEALTH_CHECK_TIMEOUT_SECS))
+            .send()
+            .await
+            .map_err(|e| format!("OpenAI API is unreachable at {}: {}", self.base_url, e))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND => Err(format!("OpenAI model '{}' not found", self.model).into()),
+            status => Err(format!("OpenAI API returned {}", status).into()),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAiClient {
+    async fn generate_text(&self, prompt: &str) -> Result<String, Box<dyn Error>> {
+        self.chat(None, prompt).await
+    }
+
+    async fn extract_tags(&self, text: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let prompt = self.context_window.fit_prompt("", text, "");
+        let reply = self.chat(Some(TAGS_SYSTEM_PROMPT), &prompt).await?;
+
+        Ok(tags_from_reply(&reply))
+    }
+
+    async fn health_check(&self) -> Result<(), Box<dyn Error>> {
+        self.check_health().await
+    }
+
+    // A local LM Studio or vLLM server keeps prompts on this machine
+    fn is_remote(&self) -> bool {
+        let host = Url::parse(&self.base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()));
+
+        !matches!(host.as_deref(), Some("localhost" | "127.0.0.1" | "[::1]"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> OpenAiClient {
+        OpenAiClient::new(DEFAULT_OPENAI_MODEL, &server.uri(), Some("test-key".to_string())).unwrap()
+    }
+
+    fn error(status: u16, message: &str) -> ResponseTemplate {
+        ResponseTemplate::new(status).set_body_json(json!({ "error": { "message": message } }))
+    }
+
+    #[tokio::test]
+    async fn the_first_choice_is_returned_as_text() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer test-key"))
+            .and(body_partial_json(json!({
+                "model": DEFAULT_OPENAI_MODEL,
+                "messages": [{ "role": "user", "content": "Summarize this" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [
+                    { "message": { "role": "assistant", "content": "  Wrote Rust in the editor\n" } },
+                    { "message": { "role": "assistant", "content": "Something else" } }
+                ]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let text = client(&server).generate_text("Summarize this").await.unwrap();
+        assert_eq!(text, "Wrote Rust in the editor");
+    }
+
+    #[tokio::test]
+    async fn a_rate_limit_is_reported_with_its_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(error(429, "Rate limit reached for requests"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let e = client(&server)
+            .generate_text("Summarize this")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("429"), "{}", e);
+        assert!(e.contains("Rate limit reached for requests"), "{}", e);
+    }
+
+    #[tokio::test]
+    async fn a_server_error_is_reported_with_its_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(error(500, "The server had an error"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let e = client(&server)
+            .generate_text("Summarize this")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("500"), "{}", e);
+        assert!(e.contains("The server had an error"), "{}", e);
+    }
+
+    #[tokio::test]
+    async fn a_server_error_without_json_is_reported_as_is() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("Service Unavailable"))
+            .mount(&server)
+            .await;
+
+        let e = client(&server)
+            .generate_text("Summarize this")
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("503"), "{}", e);
+        assert!(e.contains("Service Unavailable"), "{}", e);
+    }
+
+    #[tokio::test]
+    async fn local_servers_need_no_api_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{ "message": { "content": "Read the news" } }]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAiClient::new("local-model", &server.uri(), None).unwrap();
+        assert_eq!(client.generate_text("Summarize this").await.unwrap(), "Read the news");
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0].headers.get("authorization").is_none());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::models::UserEvent;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+
+pub const DEFAULT_CAPACITY: usize = 10_000;
+pub const DEFAULT_TTL_SECS: u64 = 900;
+const DEFAULT_SOCKET_PATH: &str = "/tmp/second-brain-stm.sock";
+// Ten thousand events with long window titles fit with plenty to spare
+const MAX_FRAME_BYTES: usize = 32 * 1024 * 1024;
+
+// The learner's most recent events, kept in RAM so recall can answer "what
+// was I just doing" before the thinker has summarized them. The oldest
+// events are dropped when it's full, and every event is forgotten once it's
+// older than the TTL.
+pub struct ShortTermMemory {
+    events: VecDeque<UserEvent>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+pub type SharedShortTermMemory = Arc<Mutex<ShortTermMemory>>;
+
+impl ShortTermMemory {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+            ttl,
+        }
+    }
+
+    // TTL from SHORT_TERM_MEMORY_TTL_SECS
+    pub fn from_env() -> Self {
+        let ttl_secs = env::var("SHORT_TERM_MEMORY_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+
+        Self::new(
+            DEFAULT_CAPACITY,
+            Duration::seconds(ttl_secs.min(i64::MAX as u64) as i64),
+        )
+    }
+
+    pub fn push(&mut self, event: UserEvent) {
+        self.forget_expired(Utc::now());
+
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    // Remembered events at or after `since`, oldest first
+    pub fn events_since(&mut self, since: DateTime<Utc>) -> Vec<UserEvent> {
+        let now = Utc::now();
+        self.forget_expired(now);
+
+        let since = since.max(now - self.ttl);
+        let mut events: Vec<UserEvent> = self
+            .events
+            .iter()
+            .filter(|event| event.timestamp >= since)
+            .cloned()
+            .collect();
+        events.sort_by_key(|event| event.timestamp);
+        events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // Events arrive roughly in order, so expired ones are at the front
+    fn forget_expired(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - self.ttl;
+        while self.events.front().is_some_and(|event| event.timestamp < cutoff) {
+            self.events.pop_front();
+        }
+    }
+}
+
+// Sent by recall, answered with a JSON array of events
+#[derive(Debug, Serialize, Deserialize)]
+struct RecentEventsRequest {
+    since: DateTime<Utc>,
+}
+
+pub fn socket_path() -> PathBuf {
+    env::var("SHORT_TERM_MEMORY_SOCKET")
+        .unwrap_or_else(|_| DEFAULT_SOCKET_PATH.to_string())
+        .into()
+}
+
+// Answers requests for recent events on a UNIX socket. Each message either
+// way is a 4-byte big-endian length followed by that much JSON.
+pub async fn serve(memory: SharedShortTermMemory, socket_path: PathBuf) {
+    // A socket left behind by a previous run would make bind fail
+    let _ = std::fs::remove_file(&socket_path);
+    let listener = match UnixListener::bind(&socket_path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("❌ Could not listen for recall on {}: {}", socket_path.display(), e);
+            return;
+        }
+    };
+
+    println!("🧠 Recent events shared with recall on {}", socket_path.display());
+
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("❌ Short-term memory socket error: {}", e);
+                continue;
+            }
+        };
+
+        let memory = memory.clone();
+        tokio::spawn(async move {
+            if let Err(e) = answer_request(&mut stream, &memory).await {
+                eprintln!("❌ Short-term memory request failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn answer_request(stream: &mut UnixStream, memory: &SharedShortTermMemory) -> Result<(), Box<dyn Error>> {
+    let request: RecentEventsRequest = serde_json::from_slice(&read_frame(stream).await?)?;
+    let events = memory
+        .lock()
+        .map_err(|_| "short-term memory lock poisoned")?
+        .events_since(request.since);
+
+    write_frame(stream, &serde_json::to_vec(&events)?).await
+}
+
+// Asks the learner for the events it has seen since `since`, oldest first.
+// Fails if the learner isn't running.
+pub async fn fetch_recent_events(socket_path: &Path, since: DateTime<Utc>) -> Result<Vec<UserEvent>, Box<dyn Error>> {
+    let mut stream = UnixStream::connect(socket_path).await?;
+    write_frame(&mut stream, &serde_json::to_vec(&RecentEventsRequest { since })?).await?;
+
+    Ok(serde_json::from_slice(&read_frame(&mut stream).await?)?)
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Vec<u8>, Box<dyn Error>> {
+    let len = reader.read_u32().await? as usize;
+    if len > MAX_FRAME_BYTES {
+        return Err(format!("message of {} bytes is over the {} byte limit", len, MAX_FRAME_BYTES).into());
+    }
+
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame).await?;
+    Ok(frame)
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &[u8]) -> Result<(), Box<dyn Error>> {
+    if frame.len() > MAX_FRAME_BYTES {
+        return Err(format!(
+            "message of {} bytes is over the {} byte limit",
+            frame.len(),
+            MAX_FRAME_BYTES
+        )
+        .into());
+    }
+
+    writer.write_u32(frame.len() as u32).await?;
+    writer.write_all(frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -6,6 +6,28 @@ pub struct AppContext {
     pub app_name: String,
     pub window_title: String,
     pub url: Option<String>,
+    // Monitor the window was on, numbered left to right starting at 0
+    #[serde(default)]
+    pub screen_index: Option<u8>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    // The title as captured, when window_title holds a sanitized version of it
+    #[serde(default)]
+    pub raw_window_title: Option<String>,
+}
+
+impl AppContext {
+    // Used when the active window can't be read
+    pub fn unknown() -> Self {
+        Self {
+            app_name: "unknown".to_string(),
+            window_title: "unknown".to_string(),
+            url: None,
+            screen_index: None,
+            display_name: None,
+            raw_window_title: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,16 +38,253 @@ pub struct UserEvent {
     pub app_context: AppContext,
 }
 
+impl UserEvent {
+    pub fn keystroke(key: &str, app: &str, window: &str) -> Self {
+        UserEventBuilder::keystroke(key).app(app).window(window).build()
+    }
+
+    // Switched from one app to another just now, with no dwell time
+    pub fn app_switch(from: &str, to: &str) -> Self {
+        let switch = AppSwitchEvent {
+            from_app: from.to_string(),
+            to_app: to.to_string(),
+            dwell_time_ms: None,
+        };
+
+        UserEventBuilder::new(EventType::AppSwitch)
+            .data(serde_json::json!(switch))
+            .app(to)
+            .build()
+    }
+
+    // Clicked just now at screen coordinates (x, y)
+    pub fn mouse_click(x: f64, y: f64, button: &str, app: &str) -> Self {
+        let click = MouseClickEvent {
+            x,
+            y,
+            button: button.to_string(),
+        };
+
+        UserEventBuilder::new(EventType::MouseClick)
+            .data(serde_json::json!(click))
+            .app(app)
+            .build()
+    }
+
+    // idle_start and idle_end mark the user being away, not something they did
+    pub fn is_idle_marker(&self) -> bool {
+        self.event == EventType::IdleStart.as_str() || self.event == EventType::IdleEnd.as_str()
+    }
+
+    // Marked just now from the command line
+    pub fn waypoint(waypoint: &WaypointEvent) -> Self {
+        UserEventBuilder::new(EventType::Waypoint)
+            .data(serde_json::json!(waypoint))
+            .app(WAYPOINT_APP_NAME)
+            .window(&waypoint.title)
+            .build()
+    }
+}
+
+// Builds a UserEvent a field at a time. Anything not set is now, in an
+// unknown app, with an empty payload.
+#[derive(Debug, Clone)]
+pub struct UserEventBuilder {
+    timestamp: DateTime<Utc>,
+    event: EventType,
+    data: serde_json::Value,
+    app_context: AppContext,
+}
+
+impl UserEventBuilder {
+    pub fn new(event: EventType) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            event,
+            data: serde_json::json!({}),
+            app_context: AppContext::unknown(),
+        }
+    }
+
+    // A key press with no modifiers held
+    pub fn keystroke(key: &str) -> Self {
+        Self::new(EventType::Keystroke).data(serde_json::json!({ "key": key, "modifiers": [] }))
+    }
+
+    pub fn modifiers(mut self, modifiers: &[&str]) -> Self {
+        self.data["modifiers"] = serde_json::json!(modifiers);
+        self
+    }
+
+    pub fn data(mut self, data: serde_json::Value) -> Self {
+        self.data = data;
+        self
+    }
+
+    pub fn app(mut self, app_name: &str) -> Self {
+        self.app_context.app_name = app_name.to_string();
+        self
+    }
+
+    pub fn window(mut self, window_title: &str) -> Self {
+        self.app_context.window_title = window_title.to_string();
+        self
+    }
+
+    pub fn url(mut self, url: &str) -> Self {
+        self.app_context.url = Some(url.to_string());
+        self
+    }
+
+    pub fn context(mut self, app_context: AppContext) -> Self {
+        self.app_context = app_context;
+        self
+    }
+
+    pub fn at(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn build(self) -> UserEvent {
+        UserEvent {
+            timestamp: self.timestamp,
+            event: self.event.as_str().to_string(),
+            data: self.data.to_string(),
+            app_context: self.app_context,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EventType {
     Keystroke,
-    // MouseClick,
-    // AppSwitch
+    ScreenCapture,
+    GitCommit,
+    GitCheckout,
+    AppSwitch,
+    Clipboard,
+    NetworkAccess,
+    Waypoint,
+    MouseClick,
+    IdleStart,
+    IdleEnd,
+}
+
+impl EventType {
+    // As stored in UserEvent::event
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventType::Keystroke => "keystroke",
+            EventType::ScreenCapture => "screen_capture",
+            EventType::GitCommit => "git_commit",
+            EventType::GitCheckout => "git_checkout",
+            EventType::AppSwitch => "app_switch",
+            EventType::Clipboard => "clipboard",
+            EventType::NetworkAccess => "network_access",
+            EventType::Waypoint => "waypoint",
+            EventType::MouseClick => "mouse_click",
+            EventType::IdleStart => "idle_start",
+            EventType::IdleEnd => "idle_end",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EventData {
-    Keystroke { key: String, modifiers: Vec<String> },
+    Keystroke {
+        key: String,
+        modifiers: Vec<String>,
+    },
+    ScreenCapture {
+        ocr_text: String,
+    },
+    GitCommit {
+        repo: String,
+        branch: String,
+        message: String,
+        files_changed: u32,
+    },
+    GitCheckout {
+        repo: String,
+        from: String,
+        to: String,
+    },
+}
+
+// Payload of an "app_switch" event. The event's app context is the app
+// switched to; the dwell time belongs to the app that was left.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSwitchEvent {
+    pub from_app: String,
+    pub to_app: String,
+    // Missing from events recorded before dwell times were tracked
+    #[serde(default)]
+    pub dwell_time_ms: Option<u64>,
+}
+
+// Payload of a "mouse_click" event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MouseClickEvent {
+    // Screen coordinates of the pointer, in pixels
+    pub x: f64,
+    pub y: f64,
+    // "Left", "Right", "Middle" or "Unknown(n)"
+    pub button: String,
+}
+
+// Payload of "idle_start" and "idle_end" events. idle_start is recorded once
+// nothing has been typed or clicked for the idle timeout, idle_end with the
+// next keystroke or click.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleEvent {
+    // The last keystroke or click before the user went away, or when the
+    // learner started if there wasn't one
+    pub idle_since: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardAction {
+    Copy,
+    Cut,
+    Paste,
+}
+
+// Payload of a "clipboard" event. Only the size of what was copied or
+// pasted is kept, plus an opt-in preview that passed the privacy filter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardEvent {
+    pub action: ClipboardAction,
+    // In characters
+    pub content_length: usize,
+    #[serde(default)]
+    pub content_preview: Option<String>,
+    pub app_name: String,
+}
+
+// Payload of a "network_access" event, recorded once the domain stops
+// being accessed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkAccessEvent {
+    pub domain: String,
+    // How long connections to the domain stayed open, to the poll interval
+    pub duration_ms: u64,
+}
+
+// Recorded as the app of waypoint events, which happen outside any window
+pub const WAYPOINT_APP_NAME: &str = "second-brain";
+
+// Payload of a "waypoint" event, a moment the user marked by hand, e.g.
+// "finished the auth feature"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaypointEvent {
+    pub title: String,
+    #[serde(default)]
+    pub note: Option<String>,
+    // e.g. "feature" or "debugging"
+    #[serde(default)]
+    pub category: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,3 +295,256 @@ pub struct ActivitySummary {
     pub events: Vec<UserEvent>,
     pub tags: Vec<String>,
 }
+
+// A run of consecutive summaries about the same piece of work
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: i64,
+    pub title: String,
+    pub summaries: Vec<ActivitySummary>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    // 0.0 to 1.0, how consistently the summaries describe the same work
+    pub confidence: f32,
+}
+
+// A stretch of time spent in one window, ended by switching away or going idle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusSession {
+    pub app_name: String,
+    pub window_title: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+impl FocusSession {
+    // Seconds of this session that fall inside the range
+    pub fn seconds_within(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> i64 {
+        (self.end_time.min(end) - self.start_time.max(start))
+            .num_seconds()
+            .max(0)
+    }
+}
+
+// How many events one app had in a fixed-length slice of time, for drawing
+// timelines without loading the events themselves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub app_name: String,
+    pub event_count: u64,
+    // The app's most common event type in the bucket
+    pub dominant_event_type: String,
+}
+
+// Two apps used in the same hours. app_a sorts before app_b.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppCorrelation {
+    pub app_a: String,
+    pub app_b: String,
+    // Hours in which both apps were used
+    pub co_occurrence_count: u64,
+    // co_occurrence_count over the hours either app was used, 0.0 to 1.0
+    pub co_occurrence_rate: f32,
+}
+
+// One local day rolled up from its summaries, written by the thinker after
+// midnight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub description: String,
+    pub event_count: u64,
+    // Most events first, at most three
+    pub top_apps: Vec<String>,
+    // The most interesting thing done that day, if the LLM wrote one
+    pub highlight: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    // Shown alongside the next recall answer
+    Low,
+    Medium,
+    // Sent as a desktop notification straight away
+    High,
+}
+
+impl Urgency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Urgency::Low => "low",
+            Urgency::Medium => "medium",
+            Urgency::High => "high",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "low" => Some(Urgency::Low),
+            "medium" => Some(Urgency::Medium),
+            "high" => Some(Urgency::High),
+            _ => None,
+        }
+    }
+}
+
+// A reminder the thinker raises from recent activity, e.g. after hours in one app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suggestion {
+    // The rule and what it was about, e.g. "prolonged_use:Firefox", so the
+    // same reminder isn't raised again straight away
+    pub kind: String,
+    pub message: String,
+    pub urgency: Urgency,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowTitleHit {
+    pub timestamp: DateTime<Utc>,
+    pub app_name: String,
+    pub window_title: String,
+    pub url: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde::de::DeserializeOwned;
+
+    // Serializes, reads the JSON back and checks nothing changed on the way
+    fn round_trip<T: Serialize + DeserializeOwned>(payload: &T) -> T {
+        let json = serde_json::to_string(payload).unwrap();
+        let decoded: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(payload).unwrap()
+        );
+        decoded
+    }
+
+    #[test]
+    fn keystroke_data_round_trips() {
+        let data = round_trip(&EventData::Keystroke {
+            key: "KeyA".to_string(),
+            modifiers: vec!["Shift".to_string()],
+        });
+        assert!(matches!(data, EventData::Keystroke { key, modifiers } if key == "KeyA" && modifiers == ["Shift"]));
+    }
+
+    #[test]
+    fn screen_capture_data_round_trips() {
+        let data = round_trip(&EventData::ScreenCapture {
+            ocr_text: "fn main() {\n    \"quoted\"\n}".to_string(),
+        });
+        assert!(matches!(data, EventData::ScreenCapture { ocr_text } if ocr_text.contains("\"quoted\"")));
+    }
+
+    #[test]
+    fn git_commit_data_round_trips() {
+        let data = round_trip(&EventData::GitCommit {
+            repo: "second-brain".to_string(),
+            branch: "main".to_string(),
+            message: "Fix the parser".to_string(),
+            files_changed: 3,
+        });
+        assert!(matches!(data, EventData::GitCommit { files_changed: 3, .. }));
+    }
+
+    #[test]
+    fn git_checkout_data_round_trips() {
+        let data = round_trip(&EventData::GitCheckout {
+            repo: "second-brain".to_string(),
+            from: "main".to_string(),
+            to: "feature/export".to_string(),
+        });
+        assert!(matches!(data, EventData::GitCheckout { to, .. } if to == "feature/export"));
+    }
+
+    #[test]
+    fn app_switch_event_round_trips() {
+        let event = round_trip(&AppSwitchEvent {
+            from_app: "Code".to_string(),
+            to_app: "Firefox".to_string(),
+            dwell_time_ms: Some(90_000),
+        });
+        assert_eq!(event.dwell_time_ms, Some(90_000));
+
+        // Recorded before dwell times were tracked
+        let old: AppSwitchEvent = serde_json::from_str(r#"{"from_app":"Code","to_app":"Firefox"}"#).unwrap();
+        assert_eq!(old.dwell_time_ms, None);
+    }
+
+    #[test]
+    fn mouse_click_event_round_trips() {
+        let click = UserEvent::mouse_click(1920.5, 0.0, "Left", "Firefox");
+        assert_eq!(click.event, EventType::MouseClick.as_str());
+        assert_eq!(click.app_context.app_name, "Firefox");
+
+        let payload: MouseClickEvent = serde_json::from_str(&click.data).unwrap();
+        let event = round_trip(&payload);
+        assert_eq!((event.x, event.y, event.button.as_str()), (1920.5, 0.0, "Left"));
+    }
+
+    #[test]
+    fn idle_event_round_trips() {
+        let idle_since = Utc.with_ymd_and_hms(2025, 4, 1, 12, 30, 0).unwrap();
+        assert_eq!(round_trip(&IdleEvent { idle_since }).idle_since, idle_since);
+    }
+
+    #[test]
+    fn clipboard_event_round_trips() {
+        let event = round_trip(&ClipboardEvent {
+            action: ClipboardAction::Paste,
+            content_length: 42,
+            content_preview: Some("let x = 1;".to_string()),
+            app_name: "Code".to_string(),
+        });
+        assert_eq!(event.action, ClipboardAction::Paste);
+        assert_eq!(serde_json::to_value(ClipboardAction::Cut).unwrap(), "cut");
+    }
+
+    #[test]
+    fn network_access_event_round_trips() {
+        let event = round_trip(&NetworkAccessEvent {
+            domain: "github.com".to_string(),
+            duration_ms: 12_000,
+        });
+        assert_eq!((event.domain.as_str(), event.duration_ms), ("github.com", 12_000));
+    }
+
+    #[test]
+    fn waypoint_event_round_trips() {
+        let event = round_trip(&WaypointEvent {
+            title: "Finished the auth feature".to_string(),
+            note: None,
+            category: Some("feature".to_string()),
+        });
+        assert_eq!(event.category.as_deref(), Some("feature"));
+
+        let bare: WaypointEvent = serde_json::from_str(r#"{"title":"Lunch"}"#).unwrap();
+        assert_eq!((bare.note, bare.category), (None, None));
+    }
+
+    #[test]
+    fn event_types_round_trip() {
+        for event_type in [
+            EventType::Keystroke,
+            EventType::ScreenCapture,
+            EventType::GitCommit,
+            EventType::GitCheckout,
+            EventType::AppSwitch,
+            EventType::Clipboard,
+            EventType::NetworkAccess,
+            EventType::Waypoint,
+            EventType::MouseClick,
+            EventType::IdleStart,
+            EventType::IdleEnd,
+        ] {
+            assert_eq!(round_trip(&event_type).as_str(), event_type.as_str());
+        }
+    }
+}
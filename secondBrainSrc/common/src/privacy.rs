@@ -0,0 +1,519 @@
+use crate::models::{ClipboardEvent, EventType, NetworkAccessEvent, UserEvent};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// What was captured, stored or queried, and which model builds answered.
+// Entries describe the shape of the
+// data only: never keystrokes, descriptions or query text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditEntry {
+    EventStored {
+        timestamp: DateTime<Utc>,
+        app_name: String,
+        event_type: String,
+    },
+    SummaryStored {
+        time_range: (DateTime<Utc>, DateTime<Utc>),
+        description_length: usize,
+        tags: Vec<String>,
+    },
+    QueryProcessed {
+        query_text_hash: String,
+        timeframe: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        result_type: String,
+    },
+    // `second-brain update` pulled a new build of an Ollama model
+    ModelUpdated {
+        model: String,
+        old_digest: Option<String>,
+        new_digest: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub logged_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub entry: AuditEntry,
+}
+
+// Append-only JSONL log of everything the second brain learns or is asked
+#[derive(Clone)]
+pub struct PrivacyAuditLog {
+    file: Arc<Mutex<File>>,
+}
+
+impl PrivacyAuditLog {
+    pub fn open() -> Result<Self, Box<dyn Error>> {
+        let path = audit_log_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    // Writes on a blocking thread when called from async code so the
+    // capture and query paths never wait on the disk
+    pub fn record(&self, entry: AuditEntry) {
+        let record = AuditRecord {
+            logged_at: Utc::now(),
+            entry,
+        };
+        let file = self.file.clone();
+
+        let write = move || {
+            let Ok(line) = serde_json::to_string(&record) else {
+                return;
+            };
+            if let Ok(mut file) = file.lock() {
+                if let Err(e) = writeln!(file, "{}", line) {
+                    eprintln!("⚠️ Failed to write audit log entry: {}", e);
+                }
+            }
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(write);
+            }
+            Err(_) => write(),
+        }
+    }
+}
+
+// The shared audit log, or None if it couldn't be opened
+pub fn audit_log() -> Option<&'static PrivacyAuditLog> {
+    static AUDIT_LOG: OnceLock<Option<PrivacyAuditLog>> = OnceLock::new();
+    AUDIT_LOG
+        .get_or_init(|| match PrivacyAuditLog::open() {
+            Ok(log) => Some(log),
+            Err(e) => {
+                eprintln!("⚠️ Privacy audit log disabled: {}", e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+pub fn record(entry: AuditEntry) {
+    if let Some(log) = audit_log() {
+        log.record(entry);
+    }
+}
+
+// Queries are identified by hash so the log never holds what was asked
+pub fn hash_query(query: &str) -> String {
+    Sha256::digest(query.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// SECOND_BRAIN_AUDIT_LOG overrides ~/.local/share/second-brain/audit.log
+pub fn audit_log_path() -> Result<PathBuf, Box<dyn Error>> {
+    if let Ok(path) = env::var("SECOND_BRAIN_AUDIT_LOG") {
+        return Ok(PathBuf::from(path));
+    }
+
+    let home = env::var("HOME")?;
+    Ok(PathBuf::from(home).join(".local/share/second-brain/audit.log"))
+}
+
+// The last `count` entries, oldest first. Unparseable lines are skipped.
+pub fn read_tail(count: usize) -> Result<Vec<AuditRecord>, Box<dyn Error>> {
+    let file = match File::open(audit_log_path()?) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let records: Vec<AuditRecord> = BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect();
+
+    let skip = records.len().saturating_sub(count);
+    Ok(records.into_iter().skip(skip).collect())
+}
+
+pub fn clear() -> Result<(), Box<dyn Error>> {
+    let path = audit_log_path()?;
+    if path.exists() {
+        File::create(path)?;
+    }
+    Ok(())
+}
+
+// Password managers, whose keystrokes never go to the LLM
+const PASSWORD_APPS: [&str; 7] = [
+    "1password",
+    "bitwarden",
+    "keepass",
+    "lastpass",
+    "dashlane",
+    "keychain access",
+    "proton pass",
+];
+
+// URL fragments of pages whose address and title are kept from the LLM
+const SENSITIVE_URL_PATTERNS: [&str; 10] = [
+    "login", "signin", "sign-in", "password", "account", "bank", "checkout", "billing", "health", "/auth",
+];
+
+// Clipboard contents that look like credentials never get a preview
+const SECRET_MARKERS: [&str; 6] = ["password", "passwd", "secret", "token", "api_key", "-----begin"];
+
+// Unbroken strings at least this long with letters and digits look like keys
+const MIN_TOKEN_CHARS: usize = 20;
+
+pub const CLIPBOARD_PREVIEW_CHARS: usize = 50;
+
+const REDACTED_KEYSTROKE: &str = r#"{"key":"[REDACTED]","modifiers":[]}"#;
+const PRIVATE: &str = "[PRIVATE]";
+
+// What UserEvent::redact removes before events are described to the LLM
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    // Keystrokes typed into password managers
+    pub redact_passwords: bool,
+    // URLs and titles of login, banking and similar pages
+    pub redact_urls: bool,
+    // Case-insensitive fragments of window titles to hide
+    pub redact_window_titles: Vec<String>,
+}
+
+impl RedactionPolicy {
+    // REDACT_BEFORE_LLM turns redaction on or off, defaulting to on when
+    // prompts go to a remote LLM. REDACT_WINDOW_TITLES adds comma-separated
+    // title fragments to hide. None means events are sent as recorded.
+    pub fn from_env(remote_llm: bool) -> Option<Self> {
+        let enabled = env::var("REDACT_BEFORE_LLM")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(remote_llm);
+        if !enabled {
+            return None;
+        }
+
+        let redact_window_titles = env::var("REDACT_WINDOW_TITLES")
+            .map(|titles| {
+                titles
+                    .split(',')
+                    .map(|title| title.trim().to_lowercase())
+                    .filter(|title| !title.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            redact_passwords: true,
+            redact_urls: true,
+            redact_window_titles,
+        })
+    }
+}
+
+pub fn is_password_app(app_name: &str) -> bool {
+    let app = app_name.to_lowercase();
+    PASSWORD_APPS.iter().any(|password_app| app.contains(password_app))
+}
+
+// The start of the clipboard contents, or None when they came from a password
+// manager or look like a password, key or token
+pub fn clipboard_preview(content: &str, app_name: &str) -> Option<String> {
+    if is_password_app(app_name) {
+        return None;
+    }
+
+    let lower = content.to_lowercase();
+    if SECRET_MARKERS.iter().any(|marker| lower.contains(marker)) {
+        return None;
+    }
+
+    let looks_like_token = |word: &str| {
+        word.chars().count() >= MIN_TOKEN_CHARS
+            && word.chars().any(|c| c.is_ascii_digit())
+            && word.chars().any(|c| c.is_alphabetic())
+    };
+    if content.split_whitespace().any(looks_like_token) {
+        return None;
+    }
+
+    let preview: String = content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(CLIPBOARD_PREVIEW_CHARS)
+        .collect();
+
+    (!preview.is_empty()).then_some(preview)
+}
+
+impl UserEvent {
+    // Replaces sensitive content in place, returning whether anything was removed
+    pub fn redact(&mut self, policy: &RedactionPolicy) -> bool {
+        let mut redacted = false;
+
+        if policy.redact_passwords
+            && self.event == EventType::Keystroke.as_str()
+            && is_password_app(&self.app_context.app_name)
+        {
+            self.data = REDACTED_KEYSTROKE.to_string();
+            redacted = true;
+        }
+
+        let sensitive_url = policy.redact_urls
+            && self.app_context.url.as_deref().is_some_and(|url| {
+                let url = url.to_lowercase();
+                SENSITIVE_URL_PATTERNS.iter().any(|pattern| url.contains(pattern))
+            });
+        let title = self.app_context.window_title.to_lowercase();
+        let sensitive_title = policy
+            .redact_window_titles
+            .iter()
+            .any(|fragment| title.contains(&fragment.to_lowercase()));
+
+        if sensitive_url || sensitive_title {
+            self.app_context.window_title = PRIVATE.to_string();
+            if self.app_context.url.is_some() {
+                self.app_context.url = Some(PRIVATE.to_string());
+            }
+
+            // What was copied from a private page is as private as the page
+            if self.event == EventType::Clipboard.as_str() {
+                if let Ok(mut clipboard) = serde_json::from_str::<ClipboardEvent>(&self.data) {
+                    clipboard.content_preview = None;
+                    self.data = serde_json::to_string(&clipboard).unwrap_or_default();
+                }
+            }
+            redacted = true;
+        }
+
+        // The domain of a network access is its URL
+        if policy.redact_urls && self.event == EventType::NetworkAccess.as_str() {
+            if let Ok(mut access) = serde_json::from_str::<NetworkAccessEvent>(&self.data) {
+                let domain = access.domain.to_lowercase();
+                if SENSITIVE_URL_PATTERNS.iter().any(|pattern| domain.contains(pattern)) {
+                    access.domain = PRIVATE.to_string();
+                    self.data = serde_json::to_string(&access).unwrap_or_default();
+                    redacted = true;
+                }
+            }
+        }
+
+        redacted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ClipboardAction, UserEventBuilder};
+    use std::sync::Mutex;
+
+    // from_env reads process-wide variables, so those tests take turns
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn policy() -> RedactionPolicy {
+        RedactionPolicy {
+            redact_passwords: true,
+            redact_urls: true,
+            redact_window_titles: vec!["payroll".to_string()],
+        }
+    }
+
+    fn from_env(redact_before_llm: Option<&str>, titles: Option<&str>, remote_llm: bool) -> Option<RedactionPolicy> {
+        let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        match redact_before_llm {
+            Some(value) => env::set_var("REDACT_BEFORE_LLM", value),
+            None => env::remove_var("REDACT_BEFORE_LLM"),
+        }
+        match titles {
+            Some(value) => env::set_var("REDACT_WINDOW_TITLES", value),
+            None => env::remove_var("REDACT_WINDOW_TITLES"),
+        }
+        RedactionPolicy::from_env(remote_llm)
+    }
+
+    fn browsing(url: &str, title: &str) -> UserEvent {
+        UserEventBuilder::keystroke("a")
+            .app("Firefox")
+            .window(title)
+            .url(url)
+            .build()
+    }
+
+    #[test]
+    fn password_managers_are_recognized_in_any_case() {
+        for app in [
+            "1Password 8",
+            "Bitwarden",
+            "KeePassXC",
+            "LastPass",
+            "Dashlane",
+            "Keychain Access",
+            "Proton Pass",
+        ] {
+            assert!(is_password_app(app), "{}", app);
+        }
+        for app in ["Firefox", "Code", "Passport Photo Booth", "Keynote"] {
+            assert!(!is_password_app(app), "{}", app);
+        }
+    }
+
+    #[test]
+    fn keystrokes_in_password_managers_are_replaced() {
+        let mut event = UserEventBuilder::keystroke("h")
+            .app("Bitwarden")
+            .window("Vault")
+            .build();
+
+        assert!(event.redact(&policy()));
+        assert_eq!(event.data, REDACTED_KEYSTROKE);
+        assert_eq!(event.app_context.window_title, "Vault");
+    }
+
+    #[test]
+    fn keystrokes_elsewhere_are_kept() {
+        let mut event = UserEventBuilder::keystroke("h").app("Code").window("main.rs").build();
+        let data = event.data.clone();
+
+        assert!(!event.redact(&policy()));
+        assert_eq!(event.data, data);
+    }
+
+    #[test]
+    fn password_keystrokes_are_kept_when_the_policy_allows_them() {
+        let mut event = UserEventBuilder::keystroke("h")
+            .app("1Password")
+            .window("Vault")
+            .build();
+        let data = event.data.clone();
+        let policy = RedactionPolicy {
+            redact_passwords: false,
+            ..policy()
+        };
+
+        assert!(!event.redact(&policy));
+        assert_eq!(event.data, data);
+    }
+
+    #[test]
+    fn sensitive_urls_hide_the_url_and_title() {
+        for url in [
+            "https://accounts.example.com/signin",
+            "https://www.MyBank.com/overview",
+            "https://shop.example.com/checkout?cart=1",
+            "https://example.com/auth/callback",
+        ] {
+            let mut event = browsing(url, "Welcome back, Sam");
+
+            assert!(event.redact(&policy()), "{}", url);
+            assert_eq!(event.app_context.window_title, PRIVATE);
+            assert_eq!(event.app_context.url.as_deref(), Some(PRIVATE));
+        }
+    }
+
+    #[test]
+    fn other_urls_are_kept() {
+        let mut event = browsing("https://docs.rs/tokio", "tokio - Rust");
+
+        assert!(!event.redact(&policy()));
+        assert_eq!(event.app_context.url.as_deref(), Some("https://docs.rs/tokio"));
+        assert_eq!(event.app_context.window_title, "tokio - Rust");
+    }
+
+    #[test]
+    fn listed_window_titles_are_hidden_in_any_case() {
+        let mut event = UserEventBuilder::keystroke("a")
+            .app("Excel")
+            .window("PAYROLL 2025.xlsx")
+            .build();
+
+        assert!(event.redact(&policy()));
+        assert_eq!(event.app_context.window_title, PRIVATE);
+        assert!(event.app_context.url.is_none());
+    }
+
+    #[test]
+    fn clipboard_previews_from_private_pages_are_dropped() {
+        let clipboard = ClipboardEvent {
+            action: ClipboardAction::Copy,
+            content_length: 12,
+            content_preview: Some("4111 1111 11".to_string()),
+            app_name: "Firefox".to_string(),
+        };
+        let mut event = UserEventBuilder::new(EventType::Clipboard)
+            .data(serde_json::json!(clipboard))
+            .app("Firefox")
+            .window("Billing")
+            .url("https://example.com/billing")
+            .build();
+
+        assert!(event.redact(&policy()));
+        let clipboard: ClipboardEvent = serde_json::from_str(&event.data).unwrap();
+        assert!(clipboard.content_preview.is_none());
+        assert_eq!(clipboard.content_length, 12);
+    }
+
+    #[test]
+    fn sensitive_network_domains_are_hidden() {
+        let access = |domain: &str| {
+            UserEventBuilder::new(EventType::NetworkAccess)
+                .data(serde_json::json!(NetworkAccessEvent {
+                    domain: domain.to_string(),
+                    duration_ms: 1000,
+                }))
+                .app("Firefox")
+                .window("New Tab")
+                .build()
+        };
+
+        let mut bank = access("online.bank.example");
+        assert!(bank.redact(&policy()));
+        let data: NetworkAccessEvent = serde_json::from_str(&bank.data).unwrap();
+        assert_eq!(data.domain, PRIVATE);
+
+        let mut docs = access("docs.rs");
+        assert!(!docs.redact(&policy()));
+        let data: NetworkAccessEvent = serde_json::from_str(&docs.data).unwrap();
+        assert_eq!(data.domain, "docs.rs");
+    }
+
+    #[test]
+    fn redaction_defaults_on_only_for_remote_llms() {
+        assert!(from_env(None, None, true).is_some());
+        assert!(from_env(None, None, false).is_none());
+    }
+
+    #[test]
+    fn redact_before_llm_overrides_the_default() {
+        assert!(from_env(Some("false"), None, true).is_none());
+        assert!(from_env(Some("true"), None, false).is_some());
+        // Values that aren't booleans fall back to the default
+        assert!(from_env(Some("yes"), None, false).is_none());
+    }
+
+    #[test]
+    fn window_titles_to_hide_come_from_the_environment() {
+        let policy = from_env(None, Some(" Payroll, ,Medical Records "), true).unwrap();
+
+        assert!(policy.redact_passwords && policy.redact_urls);
+        assert_eq!(policy.redact_window_titles, ["payroll", "medical records"]);
+    }
+}
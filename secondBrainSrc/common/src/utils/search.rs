@@ -0,0 +1,27 @@
+// Characters with a meaning in SQL, LIKE patterns or FTS5 queries. Search
+// terms are always bound as parameters, but inside a pattern or match
+// expression they would still act as syntax, so none of these are passed
+// through.
+pub const SQL_METACHARACTERS: [char; 6] = ['\'', '"', ';', '%', '_', '\\'];
+
+// Longest query searched, so a huge request can't become thousands of search terms
+pub const MAX_SEARCH_QUERY_CHARS: usize = 256;
+
+// Turns a query from the socket into whitespace-separated search terms:
+// metacharacters and control characters become spaces, runs of whitespace
+// collapse to one and the result is cut to MAX_SEARCH_QUERY_CHARS
+pub fn sanitize_query_for_search(query: &str) -> String {
+    let cleaned: String = query
+        .chars()
+        .take(MAX_SEARCH_QUERY_CHARS)
+        .map(|c| {
+            if c.is_control() || SQL_METACHARACTERS.contains(&c) {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
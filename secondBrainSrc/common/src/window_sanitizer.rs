@@ -0,0 +1,163 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::error::Error;
+use std::sync::OnceLock;
+
+// Browsers, their title suffixes and the per-site patterns
+const WINDOW_PATTERNS: &str = include_str!("window_patterns.toml");
+
+// A window title split into what it's about
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanitizedTitle {
+    // The site the page is on, e.g. "GitHub", if a pattern recognized it
+    pub site: Option<String>,
+    // e.g. "rust-lang/rust Pull Request #12: Fix the parser"
+    pub page_title: String,
+    // Roughly where the page lives, e.g. "github.com/rust-lang/rust"
+    pub url_hint: Option<String>,
+}
+
+impl SanitizedTitle {
+    // What's stored as the window title, e.g. "GitHub: rust-lang/rust Pull requests"
+    pub fn window_title(&self) -> String {
+        match &self.site {
+            Some(site) => format!("{}: {}", site, self.page_title),
+            None => self.page_title.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PatternsFile {
+    browsers: Vec<String>,
+    browser_suffixes: Vec<String>,
+    patterns: Vec<PatternEntry>,
+}
+
+#[derive(Deserialize)]
+struct PatternEntry {
+    site: String,
+    regex: String,
+    page_title: String,
+    url_hint: Option<String>,
+}
+
+struct TitlePattern {
+    site: String,
+    regex: Regex,
+    page_title: String,
+    url_hint: Option<String>,
+}
+
+// Rewrites browser window titles, which carry the browser's name and site
+// boilerplate, into the part that says what the page is
+pub struct WindowTitleSanitizer {
+    browsers: Vec<String>,
+    browser_suffixes: Vec<String>,
+    patterns: Vec<TitlePattern>,
+}
+
+impl Default for WindowTitleSanitizer {
+    fn default() -> Self {
+        Self::from_toml(WINDOW_PATTERNS).expect("embedded window patterns should be valid")
+    }
+}
+
+impl WindowTitleSanitizer {
+    fn from_toml(contents: &str) -> Result<Self, Box<dyn Error>> {
+        let file: PatternsFile = toml::from_str(contents)?;
+
+        let patterns = file
+            .patterns
+            .into_iter()
+            .map(|entry| {
+                Ok(TitlePattern {
+                    site: entry.site,
+                    regex: Regex::new(&entry.regex)?,
+                    page_title: entry.page_title,
+                    url_hint: entry.url_hint,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+        Ok(Self {
+            browsers: file
+                .browsers
+                .into_iter()
+                .map(|browser| browser.to_lowercase())
+                .collect(),
+            browser_suffixes: file.browser_suffixes,
+            patterns,
+        })
+    }
+
+    pub fn sanitize(&self, title: &str, app_name: &str) -> SanitizedTitle {
+        let unchanged = SanitizedTitle {
+            site: None,
+            page_title: title.to_string(),
+            url_hint: None,
+        };
+
+        // Whole words only, so "Archive Utility" isn't taken for Arc
+        let app_name = app_name.to_lowercase();
+        let is_browser = app_name
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| self.browsers.iter().any(|browser| browser == word));
+        if !is_browser {
+            return unchanged;
+        }
+
+        let title = self
+            .browser_suffixes
+            .iter()
+            .find_map(|suffix| title.strip_suffix(suffix.as_str()))
+            .unwrap_or(title)
+            .trim();
+
+        for pattern in &self.patterns {
+            let Some(captures) = pattern.regex.captures(title) else {
+                continue;
+            };
+
+            let expand = |template: &str| {
+                let mut expanded = String::new();
+                captures.expand(template, &mut expanded);
+                expanded.trim().to_string()
+            };
+
+            let page_title = expand(&pattern.page_title);
+            if page_title.is_empty() {
+                continue;
+            }
+
+            return SanitizedTitle {
+                site: Some(pattern.site.clone()),
+                page_title,
+                url_hint: pattern.url_hint.as_deref().map(expand),
+            };
+        }
+
+        SanitizedTitle {
+            page_title: title.to_string(),
+            ..unchanged
+        }
+    }
+}
+
+pub fn sanitize_title(title: &str, app_name: &str) -> SanitizedTitle {
+    static SANITIZER: OnceLock<WindowTitleSanitizer> = OnceLock::new();
+    SANITIZER
+        .get_or_init(WindowTitleSanitizer::default)
+        .sanitize(title, app_name)
+}
+
+// The (window_title, raw_window_title) to record for a window: the sanitized
+// title, and the captured one if sanitizing changed it
+pub fn recorded_titles(title: String, app_name: &str) -> (String, Option<String>) {
+    let sanitized = sanitize_title(&title, app_name).window_title();
+    if sanitized == title {
+        (title, None)
+    } else {
+        (sanitized, Some(title))
+    }
+}
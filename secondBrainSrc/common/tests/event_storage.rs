@@ -0,0 +1,198 @@
+// Events written to a real Postgres database come back as they went in
+mod support;
+
+use activity_tracker_common::{
+    EventStore, EventType, FocusSession, UserEvent, UserEventBuilder, WaypointEvent, WAYPOINT_APP_NAME,
+};
+use chrono::{DateTime, Duration, Utc};
+use futures::StreamExt;
+use serde_json::Value;
+use support::{event_database, unused_hour};
+
+fn keystroke(key: &str, app: &str, at: DateTime<Utc>) -> UserEvent {
+    UserEventBuilder::keystroke(key)
+        .app(app)
+        .window(&format!("{} window", app))
+        .at(at)
+        .build()
+}
+
+// The fields that make an event, with the payload parsed so its formatting doesn't matter
+fn fields(event: &UserEvent) -> (DateTime<Utc>, String, Value, String, String, Option<String>) {
+    (
+        event.timestamp,
+        event.event.clone(),
+        serde_json::from_str(&event.data).unwrap(),
+        event.app_context.app_name.clone(),
+        event.app_context.window_title.clone(),
+        event.app_context.url.clone(),
+    )
+}
+
+#[tokio::test]
+async fn stored_events_are_read_back_oldest_first() {
+    let Some((db, _container)) = event_database("stored_events_are_read_back_oldest_first").await else {
+        return;
+    };
+    let (start, end) = unused_hour();
+    let events = vec![
+        keystroke("b", "Code", start + Duration::minutes(20)),
+        UserEventBuilder::keystroke("c")
+            .app("Firefox")
+            .window("Docs")
+            .url("https://docs.rs")
+            .at(start + Duration::minutes(40))
+            .build(),
+        keystroke("a", "Code", start + Duration::minutes(5)),
+    ];
+
+    db.store_events_batch(&events).await.unwrap();
+
+    let mut expected: Vec<_> = events.iter().map(fields).collect();
+    expected.sort_by_key(|event| event.0);
+    let stored: Vec<_> = db
+        .get_events_in_timeframe(start, end)
+        .await
+        .unwrap()
+        .iter()
+        .map(fields)
+        .collect();
+    assert_eq!(stored, expected);
+
+    let streamed: Vec<_> = db
+        .stream_events_in_timeframe(start, end)
+        .map(|event| fields(&event.unwrap()))
+        .collect()
+        .await;
+    assert_eq!(streamed, expected);
+    assert_eq!(db.count_events_in_timeframe(start, end).await.unwrap(), 3);
+}
+
+#[tokio::test]
+async fn only_events_inside_the_timeframe_are_returned() {
+    let Some((db, _container)) = event_database("only_events_inside_the_timeframe_are_returned").await else {
+        return;
+    };
+    let (start, end) = unused_hour();
+    for minutes in [-1, 0, 30, 60, 61] {
+        db.store_event(keystroke("x", "Code", start + Duration::minutes(minutes)))
+            .await
+            .unwrap();
+    }
+
+    let inner = (start + Duration::minutes(1), end - Duration::minutes(1));
+    let times: Vec<_> = db
+        .get_events_in_timeframe(inner.0, inner.1)
+        .await
+        .unwrap()
+        .iter()
+        .map(|event| event.timestamp)
+        .collect();
+    assert_eq!(times, [start + Duration::minutes(30)]);
+    assert_eq!(db.count_events_in_timeframe(inner.0, inner.1).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn events_are_found_by_app_name_in_any_case() {
+    let Some((db, _container)) = event_database("events_are_found_by_app_name_in_any_case").await else {
+        return;
+    };
+    let (start, end) = unused_hour();
+    db.store_event(keystroke("a", "Visual Studio Code", start))
+        .await
+        .unwrap();
+    db.store_event(keystroke("b", "Firefox", start + Duration::minutes(1)))
+        .await
+        .unwrap();
+
+    let events = db.get_events_by_app_name(start, end, "CODE").await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].app_context.app_name, "Visual Studio Code");
+}
+
+#[tokio::test]
+async fn focus_sessions_are_read_back() {
+    let Some((db, _container)) = event_database("focus_sessions_are_read_back").await else {
+        return;
+    };
+    let (start, end) = unused_hour();
+    let session = FocusSession {
+        app_name: "Code".to_string(),
+        window_title: "main.rs".to_string(),
+        start_time: start + Duration::minutes(10),
+        end_time: start + Duration::minutes(25),
+    };
+
+    db.store_focus_session(&session).await.unwrap();
+
+    let sessions = db.get_focus_sessions(start, end).await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].app_name, "Code");
+    assert_eq!(sessions[0].window_title, "main.rs");
+    assert_eq!(
+        (sessions[0].start_time, sessions[0].end_time),
+        (session.start_time, session.end_time)
+    );
+}
+
+#[tokio::test]
+async fn waypoints_are_read_back_with_their_notes() {
+    let Some((db, _container)) = event_database("waypoints_are_read_back_with_their_notes").await else {
+        return;
+    };
+    let (start, end) = unused_hour();
+    let waypoint = WaypointEvent {
+        title: "Shipped the exporter".to_string(),
+        note: Some("Took two tries".to_string()),
+        category: Some("feature".to_string()),
+    };
+    let event = UserEventBuilder::new(EventType::Waypoint)
+        .data(serde_json::json!(waypoint))
+        .app(WAYPOINT_APP_NAME)
+        .window(&waypoint.title)
+        .at(start + Duration::minutes(15))
+        .build();
+
+    db.store_event(event).await.unwrap();
+    db.store_event(keystroke("a", "Code", start + Duration::minutes(16)))
+        .await
+        .unwrap();
+
+    let waypoints = db.get_waypoints(start, end).await.unwrap();
+    assert_eq!(waypoints.len(), 1);
+    let (at, stored) = &waypoints[0];
+    assert_eq!(*at, start + Duration::minutes(15));
+    assert_eq!(stored.title, waypoint.title);
+    assert_eq!(stored.note, waypoint.note);
+    assert_eq!(stored.category, waypoint.category);
+}
+
+#[tokio::test]
+async fn app_names_are_matched_literally() {
+    let Some((db, _container)) = event_database("app_names_are_matched_literally").await else {
+        return;
+    };
+    let (start, end) = unused_hour();
+    for (minutes, app) in [
+        (0, "my_app"),
+        (1, "myXapp"),
+        (2, "100% Focus"),
+        (3, "1000 Focus"),
+        (4, r"C:\Tools"),
+    ] {
+        db.store_event(keystroke("a", app, start + Duration::minutes(minutes)))
+            .await
+            .unwrap();
+    }
+
+    for (pattern, expected) in [("my_app", "my_app"), ("0%", "100% Focus"), (r":\t", r"C:\Tools")] {
+        let apps: Vec<_> = db
+            .get_events_by_app_name(start, end, pattern)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|event| event.app_context.app_name)
+            .collect();
+        assert_eq!(apps, [expected], "{}", pattern);
+    }
+}
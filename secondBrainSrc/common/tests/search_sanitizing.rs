@@ -0,0 +1,92 @@
+// Search queries come straight from the recall socket, so whatever quotes,
+// semicolons and wildcards they hold must neither break the SQL nor turn
+// into a pattern that matches every summary
+mod support;
+
+use activity_tracker_common::utils::search::{sanitize_query_for_search, SQL_METACHARACTERS};
+use activity_tracker_common::{GeneralDbClient, SummaryStore};
+use chrono::Duration;
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+use support::{at, summary, summary_database};
+use tokio::runtime::Runtime;
+
+const DESCRIPTIONS: [&str; 4] = [
+    "Edited the parser in Code",
+    "Reviewed a pull request in Firefox",
+    "Answered messages in Slack",
+    "Ran the test suite in the terminal",
+];
+
+// Metacharacters and spaces, in any order
+fn noise() -> impl Strategy<Value = String> {
+    let mut chars: Vec<char> = SQL_METACHARACTERS.to_vec();
+    chars.push(' ');
+    prop::collection::vec(prop::sample::select(chars), 0..12).prop_map(|chars| chars.into_iter().collect())
+}
+
+fn seeded_store(rt: &Runtime, dir: &tempfile::TempDir) -> GeneralDbClient {
+    rt.block_on(async {
+        let db = summary_database(dir).await;
+        for (i, description) in DESCRIPTIONS.iter().enumerate() {
+            let start_time = at(9, 0) + Duration::minutes(5 * i as i64);
+            db.store_summary(&summary(start_time, 5, description, &[]))
+                .await
+                .unwrap();
+        }
+        db
+    })
+}
+
+proptest! {
+    #[test]
+    fn sanitized_queries_hold_no_metacharacters(query in ".*") {
+        let sanitized = sanitize_query_for_search(&query);
+
+        prop_assert!(!sanitized.contains(&SQL_METACHARACTERS[..]));
+        prop_assert!(!sanitized.contains(char::is_control));
+    }
+}
+
+#[test]
+fn metacharacters_around_a_missing_word_match_nothing() {
+    let rt = Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let db = seeded_store(&rt, &dir);
+
+    TestRunner::new(Config::with_cases(200))
+        .run(&(noise(), noise()), |(before, after)| {
+            let query = format!("{}zebra{}", before, after);
+            let found = rt
+                .block_on(db.search_summaries(&query))
+                .map_err(|e| TestCaseError::fail(format!("{:?} failed: {}", query, e)))?;
+            let counted = rt
+                .block_on(db.count_summaries_matching(&query))
+                .map_err(|e| TestCaseError::fail(format!("{:?} failed to count: {}", query, e)))?;
+
+            prop_assert!(found.is_empty(), "{:?} matched {:?}", query, found);
+            prop_assert_eq!(counted, 0);
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn metacharacters_around_a_word_match_only_that_word() {
+    let rt = Runtime::new().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let db = seeded_store(&rt, &dir);
+
+    TestRunner::new(Config::with_cases(200))
+        .run(&(noise(), noise()), |(before, after)| {
+            let query = format!("{}parser{}", before, after);
+            let found = rt
+                .block_on(db.search_summaries(&query))
+                .map_err(|e| TestCaseError::fail(format!("{:?} failed: {}", query, e)))?;
+
+            prop_assert_eq!(found.len(), 1, "{:?} matched {:?}", query, found);
+            prop_assert_eq!(&found[0].description, DESCRIPTIONS[0]);
+            Ok(())
+        })
+        .unwrap();
+}
@@ -0,0 +1,112 @@
+// Summaries written to SQLite come back as they went in, also after reopening the file
+mod support;
+
+use activity_tracker_common::SummaryStore;
+use support::{at, fields, summary, summary_database};
+
+#[tokio::test]
+async fn stored_summaries_are_read_back_after_reopening() {
+    let dir = tempfile::tempdir().unwrap();
+    let summaries = [
+        summary(
+            at(9, 30),
+            5,
+            "Reviewed the parser, with notes; and \"quotes\"",
+            &["review"],
+        ),
+        summary(at(9, 0), 5, "Wrote the exporter ünïcode", &["rust", "ics"]),
+        summary(at(10, 0), 5, "Answered email", &[]),
+    ];
+    {
+        let db = summary_database(&dir).await;
+        for summary in &summaries {
+            db.store_summary(summary).await.unwrap();
+        }
+    }
+
+    let db = summary_database(&dir).await;
+    let stored = db.get_summaries_in_timeframe(at(9, 0), at(9, 59)).await.unwrap();
+
+    let mut stored: Vec<_> = stored.iter().map(fields).collect();
+    stored.sort();
+    assert_eq!(stored, [fields(&summaries[1]), fields(&summaries[0])]);
+    assert_eq!(db.count_summaries_in_timeframe(at(9, 0), at(9, 59)).await.unwrap(), 2);
+    assert_eq!(db.get_latest_summary_end_time().await.unwrap(), Some(at(10, 5)));
+}
+
+#[tokio::test]
+async fn stored_summaries_are_found_by_search() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = summary_database(&dir).await;
+    db.store_summary(&summary(
+        at(9, 0),
+        5,
+        "Debugged the websocket reconnect",
+        &["networking"],
+    ))
+    .await
+    .unwrap();
+    db.store_summary(&summary(at(9, 5), 5, "Planned the sprint", &["meetings"]))
+        .await
+        .unwrap();
+
+    let found = db.search_summaries("websocket").await.unwrap();
+
+    assert_eq!(
+        found.iter().map(fields).collect::<Vec<_>>(),
+        [fields(&summary(
+            at(9, 0),
+            5,
+            "Debugged the websocket reconnect",
+            &["networking"]
+        ))]
+    );
+    assert_eq!(db.count_summaries_matching("websocket").await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn searches_without_terms_count_every_summary_not_just_the_recent_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = summary_database(&dir).await;
+    for minute in 0..12 {
+        db.store_summary(&summary(at(9, minute * 5), 5, "Wrote tests", &[]))
+            .await
+            .unwrap();
+    }
+
+    let (recent, total) = db.search_summaries_counted("").await.unwrap();
+
+    // Only a page of the most recent comes back, but all of them are counted
+    assert_eq!(recent.len(), 10);
+    assert_eq!(recent[0].start_time, at(9, 55));
+    assert_eq!(total, 12);
+    assert_eq!(db.count_summaries_matching("").await.unwrap(), 12);
+    assert_eq!(db.search_summaries_counted("tests").await.unwrap().1, 12);
+}
+
+#[tokio::test]
+async fn updated_and_deleted_summaries_are_read_back_that_way() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = summary_database(&dir).await;
+    let kept = db
+        .store_summary(&summary(at(9, 0), 5, "Wrote tests", &["rust"]))
+        .await
+        .unwrap();
+    let deleted = db
+        .store_summary(&summary(at(9, 5), 5, "Read the news", &[]))
+        .await
+        .unwrap();
+
+    db.update_summary(kept, Some("Wrote storage tests".to_string()), None)
+        .await
+        .unwrap();
+    assert!(db.delete_summary(deleted).await.unwrap());
+    assert!(!db.delete_summary(deleted).await.unwrap());
+
+    let stored = db.get_summaries_in_timeframe(at(9, 0), at(10, 0)).await.unwrap();
+    assert_eq!(
+        stored.iter().map(fields).collect::<Vec<_>>(),
+        [fields(&summary(at(9, 0), 5, "Wrote storage tests", &["rust"]))]
+    );
+    assert!(db.search_summaries("news").await.unwrap().is_empty());
+}
@@ -0,0 +1,33 @@
+// Summaries are streamed newest first without loading the range up front
+mod support;
+
+use activity_tracker_common::{GeneralDbClient, SummaryStore};
+use chrono::Duration;
+use futures::StreamExt;
+use support::{at, summary};
+
+const SUMMARIES: i64 = 10_000;
+
+#[tokio::test]
+async fn ten_thousand_summaries_stream_newest_first() {
+    let db = GeneralDbClient::new("sqlite::memory:").await.unwrap();
+    let first = at(0, 0);
+
+    // Stored out of order, so the order comes from the query
+    for i in (0..SUMMARIES).map(|i| i * 7919 % SUMMARIES) {
+        let window = summary(first + Duration::minutes(5 * i), 5, &format!("Window {}", i), &[]);
+        db.store_summary(&window).await.unwrap();
+    }
+
+    let end = first + Duration::minutes(5 * SUMMARIES);
+    let mut stream = Box::pin(db.stream_summaries_in_timeframe(first, end));
+    let mut expected = SUMMARIES;
+    while let Some(summary) = stream.next().await {
+        expected -= 1;
+        let summary = summary.unwrap();
+        assert_eq!(summary.description, format!("Window {}", expected));
+        assert_eq!(summary.start_time, first + Duration::minutes(5 * expected));
+    }
+
+    assert_eq!(expected, 0);
+}
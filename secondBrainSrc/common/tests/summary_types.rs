@@ -0,0 +1,74 @@
+// Summaries of other types than window summaries, like daily digests, are
+// kept out of the usual queries and replace each other per span
+mod support;
+
+use activity_tracker_common::{SummaryStore, DAILY_SUMMARY};
+use support::{at, summary, summary_database};
+
+#[tokio::test]
+async fn daily_digests_are_kept_apart_from_window_summaries() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = summary_database(&dir).await;
+    let day = summary(at(0, 0), 24 * 60, "Worked on the query engine all day", &["rust"]);
+    let (start, end) = (day.start_time, day.end_time);
+
+    db.store_summary(&summary(at(9, 0), 60, "Wrote rust code in the editor", &["rust"]))
+        .await
+        .unwrap();
+    db.store_summary_with_type(&day, DAILY_SUMMARY).await.unwrap();
+
+    let windows = db.get_summaries_in_timeframe(start, end).await.unwrap();
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].description, "Wrote rust code in the editor");
+    assert_eq!(db.count_summaries_in_timeframe(start, end).await.unwrap(), 1);
+    assert_eq!(db.search_summaries("rust").await.unwrap().len(), 1);
+    assert!(db.check_search_index().await.unwrap().is_clean());
+
+    let digests = db.get_summaries_by_type(DAILY_SUMMARY, start, end).await.unwrap();
+    assert_eq!(digests.len(), 1);
+    assert_eq!(digests[0].description, "Worked on the query engine all day");
+}
+
+#[tokio::test]
+async fn a_digest_replaces_the_one_for_the_same_day() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = summary_database(&dir).await;
+    let first = summary(at(0, 0), 24 * 60, "First rollup", &["rust"]);
+    let second = summary(at(0, 0), 24 * 60, "Second rollup", &["rust"]);
+
+    db.store_summary_with_type(&first, DAILY_SUMMARY).await.unwrap();
+    db.store_summary_with_type(&second, DAILY_SUMMARY).await.unwrap();
+
+    let digests = db
+        .get_summaries_by_type(DAILY_SUMMARY, first.start_time, first.end_time)
+        .await
+        .unwrap();
+    assert_eq!(digests.len(), 1);
+    assert_eq!(digests[0].description, "Second rollup");
+}
+
+#[tokio::test]
+async fn replacing_windows_leaves_digests_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = summary_database(&dir).await;
+    let day = summary(at(0, 0), 24 * 60, "A day of work", &["rust"]);
+
+    db.store_summary(&summary(at(9, 0), 60, "Reviewed a pull request", &["rust"]))
+        .await
+        .unwrap();
+    db.store_summary_with_type(&day, DAILY_SUMMARY).await.unwrap();
+
+    assert_eq!(
+        db.delete_summaries_in_timeframe(day.start_time, day.end_time)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        db.get_summaries_by_type(DAILY_SUMMARY, day.start_time, day.end_time)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
@@ -0,0 +1,29 @@
+// Summaries for tests to store or export. Has no dependencies beyond the
+// common crate and chrono, so recall's tests include it by path too.
+use activity_tracker_common::ActivitySummary;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+// A time on the day the test summaries are from
+pub fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 4, 1, hour, minute, 0).unwrap()
+}
+
+pub fn summary(start: DateTime<Utc>, minutes: i64, description: &str, tags: &[&str]) -> ActivitySummary {
+    ActivitySummary {
+        start_time: start,
+        end_time: start + Duration::minutes(minutes),
+        description: description.to_string(),
+        events: Vec::new(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+    }
+}
+
+// The fields that make a summary, as ActivitySummary has no PartialEq
+pub fn fields(summary: &ActivitySummary) -> (DateTime<Utc>, DateTime<Utc>, String, Vec<String>) {
+    (
+        summary.start_time,
+        summary.end_time,
+        summary.description.clone(),
+        summary.tags.clone(),
+    )
+}
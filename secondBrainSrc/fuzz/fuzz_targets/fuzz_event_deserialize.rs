@@ -0,0 +1,11 @@
+#![no_main]
+
+use activity_tracker_common::UserEvent;
+use libfuzzer_sys::fuzz_target;
+
+// Events arrive as JSON from sync peers; bad input must be an error, not a panic
+fuzz_target!(|data: &[u8]| {
+    if let Ok(json) = std::str::from_utf8(data) {
+        let _ = serde_json::from_str::<UserEvent>(json);
+    }
+});
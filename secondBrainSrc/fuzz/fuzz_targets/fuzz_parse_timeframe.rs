@@ -0,0 +1,17 @@
+#![no_main]
+
+use activity_tracker_recall::timeframe::parse_timeframe;
+use chrono::{Duration, Utc};
+use libfuzzer_sys::fuzz_target;
+
+// Queries arrive as raw bytes from the recall socket
+fuzz_target!(|data: &[u8]| {
+    let query = String::from_utf8_lossy(data);
+    let now = Utc::now();
+
+    if let Some((start, end)) = parse_timeframe(&query) {
+        assert!(start <= end, "{:?} parsed to a range ending before it starts", query);
+        assert!(start >= now - Duration::days(366), "{:?} starts over a year ago", query);
+        assert!(end <= now + Duration::days(366), "{:?} ends over a year from now", query);
+    }
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use activity_tracker_common::utils::search::{
+    sanitize_query_for_search, MAX_SEARCH_QUERY_CHARS, SQL_METACHARACTERS,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let query = String::from_utf8_lossy(data);
+    let sanitized = sanitize_query_for_search(&query);
+
+    assert!(std::str::from_utf8(sanitized.as_bytes()).is_ok());
+    assert!(!sanitized.contains(&SQL_METACHARACTERS[..]), "{:?} kept a metacharacter", sanitized);
+    assert!(!sanitized.chars().any(char::is_control), "{:?} kept a control character", sanitized);
+    assert!(sanitized.chars().count() <= MAX_SEARCH_QUERY_CHARS);
+});
@@ -0,0 +1,149 @@
+use crate::display::get_display_for_window;
+use crate::focus_tracker::IDLE_TIMEOUT_MINUTES;
+use active_win_pos_rs as active_win;
+use activity_tracker_common::{window_sanitizer, AppContext, AppSwitchEvent, EventType, UserEvent};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+// Saved state older than this is from a previous day's work, not a restart
+const MAX_STATE_AGE_HOURS: i64 = 24;
+
+// The app in focus, saved so a restart doesn't lose how long it's been used
+#[derive(Serialize, Deserialize)]
+struct SessionState {
+    current_app: String,
+    session_start: DateTime<Utc>,
+    last_event: DateTime<Utc>,
+}
+
+// Emits an app_switch event whenever the focused app changes, carrying how
+// long the user stayed in the app they left
+#[derive(Default)]
+pub struct AppSwitchTracker {
+    current: Option<(String, DateTime<Utc>)>,
+}
+
+impl AppSwitchTracker {
+    // Carries on with the app that was in focus before the learner last
+    // stopped, unless the user has been away longer than the idle timeout
+    pub fn restore() -> Self {
+        let Some(state) = load_state() else {
+            return Self::default();
+        };
+
+        let gap = Utc::now() - state.last_event;
+        if gap > Duration::hours(MAX_STATE_AGE_HOURS) {
+            return Self::default();
+        }
+
+        if gap > Duration::minutes(IDLE_TIMEOUT_MINUTES) {
+            println!(
+                "⏹️ {} session ended at {}, starting fresh",
+                state.current_app,
+                state.last_event.format("%H:%M:%S")
+            );
+            return Self::default();
+        }
+
+        println!(
+            "⏯️ Continuing the {} session from before the restart",
+            state.current_app
+        );
+        Self {
+            current: Some((state.current_app, state.session_start)),
+        }
+    }
+
+    // Called every poll tick
+    pub fn poll(&mut self) -> Option<UserEvent> {
+        let window = active_win::get_active_window().ok()?;
+        let last_switch_time = Utc::now();
+
+        let Some((from_app, entered_at)) = self.current.take() else {
+            // Nothing to measure until the first switch we see
+            self.current = Some((window.app_name, last_switch_time));
+            self.save_state_or_warn(last_switch_time);
+            return None;
+        };
+
+        if from_app == window.app_name {
+            self.current = Some((from_app, entered_at));
+            return None;
+        }
+
+        let dwell_time_ms = (last_switch_time - entered_at).num_milliseconds().max(0) as u64;
+        self.current = Some((window.app_name.clone(), last_switch_time));
+        self.save_state_or_warn(last_switch_time);
+
+        let display = get_display_for_window(
+            (window.position.x + window.position.width / 2.0) as i32,
+            (window.position.y + window.position.height / 2.0) as i32,
+        );
+        let (window_title, raw_window_title) = window_sanitizer::recorded_titles(window.title, &window.app_name);
+        let switch = AppSwitchEvent {
+            from_app,
+            to_app: window.app_name.clone(),
+            dwell_time_ms: Some(dwell_time_ms),
+        };
+
+        Some(UserEvent {
+            timestamp: last_switch_time,
+            event: EventType::AppSwitch.as_str().to_string(),
+            data: serde_json::to_string(&switch).ok()?,
+            app_context: AppContext {
+                app_name: window.app_name,
+                window_title,
+                url: None,
+                screen_index: display.as_ref().map(|(i, _)| *i),
+                display_name: display.map(|(_, name)| name),
+                raw_window_title,
+            },
+        })
+    }
+
+    // Writes the current session to session_state.json, replacing the old
+    // file in one rename so a crash mid-write can't leave it half written
+    pub fn save_state(&self, last_event: DateTime<Utc>) -> Result<(), Box<dyn Error>> {
+        let Some((current_app, session_start)) = &self.current else {
+            return Ok(());
+        };
+
+        let state = SessionState {
+            current_app: current_app.clone(),
+            session_start: *session_start,
+            last_event,
+        };
+
+        let path = state_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let partial = path.with_extension("json.tmp");
+        fs::write(&partial, serde_json::to_string(&state)?)?;
+        fs::rename(&partial, &path)?;
+
+        Ok(())
+    }
+
+    fn save_state_or_warn(&self, last_event: DateTime<Utc>) {
+        if let Err(e) = self.save_state(last_event) {
+            eprintln!("❌ Failed to save session state: {}", e);
+        }
+    }
+}
+
+// ~/.local/share/second-brain/session_state.json
+fn state_path() -> Result<PathBuf, Box<dyn Error>> {
+    let home = env::var("HOME")?;
+    Ok(PathBuf::from(home).join(".local/share/second-brain/session_state.json"))
+}
+
+fn load_state() -> Option<SessionState> {
+    let contents = fs::read_to_string(state_path().ok()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
@@ -0,0 +1,220 @@
+use activity_tracker_common::{privacy, AppContext, ClipboardAction, ClipboardEvent, EventType, UserEvent};
+use chrono::Utc;
+use rdev::Key;
+use std::env;
+use std::process::Command;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+// Time for the focused app to put a copy or cut on the clipboard
+const CLIPBOARD_SETTLE: Duration = Duration::from_millis(150);
+
+// Shortcuts waiting for the clipboard to be read. Any more, as when Ctrl+V
+// is held down, are dropped.
+const PENDING_READS: usize = 4;
+
+// Reads the clipboard for copy, cut and paste shortcuts on one long-lived
+// thread, so the listener never waits on the command that reads it
+pub struct ClipboardWorker {
+    requests: SyncSender<(ClipboardAction, AppContext)>,
+}
+
+impl ClipboardWorker {
+    // `captured` is called on the worker thread with each clipboard event
+    pub fn spawn(captured: impl Fn(ClipboardEvent, AppContext) + Send + 'static) -> Self {
+        Self::with_reader(capture, captured)
+    }
+
+    fn with_reader(
+        read: impl Fn(ClipboardAction, &str) -> Option<ClipboardEvent> + Send + 'static,
+        captured: impl Fn(ClipboardEvent, AppContext) + Send + 'static,
+    ) -> Self {
+        let (requests, pending) = sync_channel::<(ClipboardAction, AppContext)>(PENDING_READS);
+
+        thread::spawn(move || {
+            for (action, app_context) in pending {
+                if let Some(clipboard) = read(action, &app_context.app_name) {
+                    captured(clipboard, app_context);
+                }
+            }
+        });
+
+        Self { requests }
+    }
+
+    // Queues a read of the clipboard, returning false if too many are
+    // already waiting and this one was dropped
+    pub fn request(&self, action: ClipboardAction, app_context: AppContext) -> bool {
+        self.requests.try_send((action, app_context)).is_ok()
+    }
+}
+
+// The clipboard action a key pressed with Ctrl or Cmd performs
+pub fn action_for_key(key: Key) -> Option<ClipboardAction> {
+    match key {
+        Key::KeyC => Some(ClipboardAction::Copy),
+        Key::KeyX => Some(ClipboardAction::Cut),
+        Key::KeyV => Some(ClipboardAction::Paste),
+        _ => None,
+    }
+}
+
+// Measures what was copied or pasted. Blocks while a copy or cut settles,
+// so call it off the listener thread.
+pub fn capture(action: ClipboardAction, app_name: &str) -> Option<ClipboardEvent> {
+    if action != ClipboardAction::Paste {
+        thread::sleep(CLIPBOARD_SETTLE);
+    }
+
+    // The contents are only measured here and never stored
+    let content = read_clipboard()?;
+    let content_preview = if previews_enabled() {
+        privacy::clipboard_preview(&content, app_name)
+    } else {
+        None
+    };
+
+    Some(ClipboardEvent {
+        action,
+        content_length: content.chars().count(),
+        content_preview,
+        app_name: app_name.to_string(),
+    })
+}
+
+pub fn clipboard_event(clipboard: &ClipboardEvent, app_context: AppContext) -> Option<UserEvent> {
+    Some(UserEvent {
+        timestamp: Utc::now(),
+        event: EventType::Clipboard.as_str().to_string(),
+        data: serde_json::to_string(clipboard).ok()?,
+        app_context,
+    })
+}
+
+// Text on the clipboard, or None if it's empty, not text or no tool is installed
+fn read_clipboard() -> Option<String> {
+    let output = if cfg!(target_os = "macos") {
+        Command::new("pbpaste").output()
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        Command::new("wl-paste").args(["--no-newline"]).output()
+    } else {
+        Command::new("xclip").args(["-selection", "clipboard", "-o"]).output()
+    }
+    .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout).ok()
+}
+
+// Previews hold copied text, so they're opt-in
+fn previews_enabled() -> bool {
+    env::var("CLIPBOARD_PREVIEW")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::sync::{Arc, Mutex};
+
+    fn context() -> AppContext {
+        AppContext {
+            app_name: "Code".to_string(),
+            window_title: "main.rs".to_string(),
+            url: None,
+            screen_index: None,
+            display_name: None,
+            raw_window_title: None,
+        }
+    }
+
+    fn clipboard(action: ClipboardAction) -> ClipboardEvent {
+        ClipboardEvent {
+            action,
+            content_length: 3,
+            content_preview: None,
+            app_name: "Code".to_string(),
+        }
+    }
+
+    // A worker whose reads wait for `release`, reporting the thread each read ran on
+    fn blocked_worker() -> (
+        ClipboardWorker,
+        Receiver<()>,
+        SyncSender<()>,
+        Receiver<thread::ThreadId>,
+    ) {
+        let (started, reading) = channel();
+        let (release, released) = sync_channel::<()>(0);
+        let released = Mutex::new(released);
+        let (read_on, threads) = channel();
+
+        let worker = ClipboardWorker::with_reader(
+            move |action, _| {
+                started.send(()).unwrap();
+                released.lock().unwrap().recv().unwrap();
+                read_on.send(thread::current().id()).unwrap();
+                Some(clipboard(action))
+            },
+            |_, _| {},
+        );
+
+        (worker, reading, release, threads)
+    }
+
+    #[test]
+    fn held_shortcuts_beyond_the_queue_are_dropped() {
+        let (worker, reading, release, threads) = blocked_worker();
+
+        assert!(worker.request(ClipboardAction::Paste, context()));
+        reading.recv().unwrap();
+        let queued = (0..50)
+            .filter(|_| worker.request(ClipboardAction::Paste, context()))
+            .count();
+        assert_eq!(queued, PENDING_READS);
+
+        for _ in 0..=PENDING_READS {
+            release.send(()).unwrap();
+        }
+        drop(worker);
+        let threads: Vec<_> = threads.iter().collect();
+        assert_eq!(threads.len(), PENDING_READS + 1);
+        assert!(threads.iter().all(|thread| *thread == threads[0]));
+        assert_ne!(threads[0], thread::current().id());
+    }
+
+    #[test]
+    fn captured_clipboard_events_reach_the_callback_in_order() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (done, finished) = channel();
+        let worker = {
+            let seen = seen.clone();
+            ClipboardWorker::with_reader(
+                |action, _| Some(clipboard(action)),
+                move |captured, app_context| {
+                    seen.lock().unwrap().push((captured.action, app_context.app_name));
+                    done.send(()).unwrap();
+                },
+            )
+        };
+
+        for action in [ClipboardAction::Copy, ClipboardAction::Paste] {
+            assert!(worker.request(action, context()));
+            finished.recv().unwrap();
+        }
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (ClipboardAction::Copy, "Code".to_string()),
+                (ClipboardAction::Paste, "Code".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use active_win_pos_rs as active_win;
+use activity_tracker_common::{window_sanitizer, AppContext};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+// Monitors rarely change, so only re-query the layout this often
+const MONITOR_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+// Left to right, kept up to date by watch_monitors
+static MONITORS: Mutex<Vec<Monitor>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone)]
+struct Monitor {
+    name: String,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Monitor {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+// Reads the monitor layout now, then again every MONITOR_REFRESH_INTERVAL on
+// a thread of its own. Reading it runs xrandr on Linux, which is too slow to
+// do from the keyboard listener.
+pub fn watch_monitors() {
+    refresh_monitors();
+
+    thread::spawn(|| loop {
+        thread::sleep(MONITOR_REFRESH_INTERVAL);
+        refresh_monitors();
+    });
+}
+
+fn refresh_monitors() {
+    let mut monitors = get_monitors();
+    monitors.sort_by_key(|m| (m.x, m.y));
+
+    if let Ok(mut cache) = MONITORS.lock() {
+        *cache = monitors;
+    }
+}
+
+// Returns the index (left to right, starting at 0) and name of the monitor
+// containing the given point. None until watch_monitors has run.
+pub fn get_display_for_window(x: i32, y: i32) -> Option<(u8, String)> {
+    let monitors = MONITORS.lock().ok()?;
+    monitors
+        .iter()
+        .enumerate()
+        .find(|(_, m)| m.contains(x, y))
+        .and_then(|(i, m)| Some((u8::try_from(i).ok()?, m.name.clone())))
+}
+
+#[cfg(target_os = "macos")]
+fn get_monitors() -> Vec<Monitor> {
+    use core_graphics::display::CGDisplay;
+
+    CGDisplay::active_displays()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|id| {
+            let display = CGDisplay::new(id);
+            let bounds = display.bounds();
+            Monitor {
+                name: if display.is_main() {
+                    "Main Display".to_string()
+                } else {
+                    format!("Display {}", id)
+                },
+                x: bounds.origin.x as i32,
+                y: bounds.origin.y as i32,
+                width: bounds.size.width as i32,
+                height: bounds.size.height as i32,
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn get_monitors() -> Vec<Monitor> {
+    // Output looks like: " 1: +HDMI-1 2560/597x1440/336+1920+0  HDMI-1"
+    let output = match std::process::Command::new("xrandr").arg("--listmonitors").output() {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .skip(1)
+        .filter_map(parse_xrandr_monitor)
+        .collect()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn parse_xrandr_monitor(line: &str) -> Option<Monitor> {
+    let mut parts = line.split_whitespace();
+    let _index = parts.next()?;
+    let _flags = parts.next()?;
+    let geometry = parts.next()?;
+    let name = parts.next()?.to_string();
+
+    // geometry is WIDTH/mmxHEIGHT/mm+X+Y
+    let (size, offset) = geometry.split_once('+')?;
+    let (x, y) = offset.split_once('+')?;
+    let (width, height) = size.split_once('x')?;
+    let width = width.split('/').next()?;
+    let height = height.split('/').next()?;
+
+    Some(Monitor {
+        name,
+        x: x.parse().ok()?,
+        y: y.parse().ok()?,
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+    })
+}
+
+// The focused window, for events that don't come from a window themselves
+pub fn active_app_context() -> AppContext {
+    match active_win::get_active_window() {
+        Ok(window) => {
+            let display = get_display_for_window(
+                (window.position.x + window.position.width / 2.0) as i32,
+                (window.position.y + window.position.height / 2.0) as i32,
+            );
+
+            let (window_title, raw_window_title) = window_sanitizer::recorded_titles(window.title, &window.app_name);
+
+            AppContext {
+                app_name: window.app_name,
+                window_title,
+                url: None,
+                screen_index: display.as_ref().map(|(i, _)| *i),
+                display_name: display.map(|(_, name)| name),
+                raw_window_title,
+            }
+        }
+        Err(_) => AppContext::unknown(),
+    }
+}
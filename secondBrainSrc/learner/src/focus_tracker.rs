@@ -0,0 +1,89 @@
+use active_win_pos_rs as active_win;
+use activity_tracker_common::{db::TimescaleClient, FocusSession};
+use chrono::{DateTime, Duration, Utc};
+use std::error::Error;
+
+// With no events for this long the user is considered away
+pub const IDLE_TIMEOUT_MINUTES: i64 = 5;
+
+// Records how long each window stays focused. The open session is stored as
+// soon as it starts and its end time is pushed forward on every checkpoint,
+// so analysis sees it before the user switches away.
+pub struct FocusTracker {
+    client: TimescaleClient,
+    current: Option<(i32, FocusSession)>,
+}
+
+impl FocusTracker {
+    pub fn new(client: TimescaleClient) -> Self {
+        Self { client, current: None }
+    }
+
+    // Called every poll tick with the time of the most recent captured event
+    pub async fn update(&mut self, last_event_at: Option<DateTime<Utc>>) -> Result<(), Box<dyn Error>> {
+        let now = Utc::now();
+
+        let active = last_event_at.filter(|last| now - *last <= Duration::minutes(IDLE_TIMEOUT_MINUTES));
+        let Some(last_event_at) = active else {
+            // Idle: the session ended with the last thing the user did
+            if let Some((_, session)) = &self.current {
+                let end = last_event_at.unwrap_or(now).max(session.start_time);
+                self.close(end).await?;
+            }
+            return Ok(());
+        };
+
+        let window = active_win::get_active_window()
+            .ok()
+            .map(|window| (window.app_name, window.title));
+
+        // Resuming after an idle stretch starts from the event that woke us
+        let start_time = if self.current.is_none() {
+            last_event_at.min(now)
+        } else {
+            now
+        };
+
+        if let Some((_, session)) = &mut self.current {
+            let unchanged = window
+                .as_ref()
+                .is_some_and(|(app, title)| *app == session.app_name && *title == session.window_title);
+
+            if unchanged {
+                session.end_time = now;
+                return Ok(());
+            }
+
+            self.close(now).await?;
+        }
+
+        if let Some((app_name, window_title)) = window {
+            let session = FocusSession {
+                app_name,
+                window_title,
+                start_time,
+                end_time: now,
+            };
+
+            let id = self.client.store_focus_session(&session).await?;
+            self.current = Some((id, session));
+        }
+
+        Ok(())
+    }
+
+    // Saves the open session's end time so far
+    pub async fn checkpoint(&self) -> Result<(), Box<dyn Error>> {
+        if let Some((id, session)) = &self.current {
+            self.client.update_focus_session_end(*id, session.end_time).await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self, end_time: DateTime<Utc>) -> Result<(), Box<dyn Error>> {
+        if let Some((id, _)) = self.current.take() {
+            self.client.update_focus_session_end(id, end_time).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use activity_tracker_common::{AppContext, EventType, UserEvent};
+use chrono::Utc;
+use std::collections::VecDeque;
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncReadExt;
+use tokio::net::UnixListener;
+
+const MAX_BUFFER_SIZE: usize = 1000;
+const DEFAULT_SOCKET_PATH: &str = "/tmp/second-brain-git.sock";
+
+// Marks hooks we wrote so we never overwrite someone else's
+const HOOK_MARKER: &str = "# Installed by second-brain";
+
+// Hooks send one message per connection: the event kind on the first line,
+// then one field per line
+const POST_COMMIT_HOOK: &str = r#"#!/bin/sh
+# Installed by second-brain
+{
+    echo commit
+    basename "$(git rev-parse --show-toplevel)"
+    git rev-parse --abbrev-ref HEAD
+    git diff-tree --no-commit-id --name-only -r HEAD | wc -l | tr -d ' '
+    git log -1 --pretty=%s
+} | nc -U "__SOCKET__" >/dev/null 2>&1 &
+
+# core.hooksPath replaces the repository's own hooks, so run them too
+local_hook="$(git rev-parse --git-dir)/hooks/post-commit"
+[ -x "$local_hook" ] && exec "$local_hook" "$@"
+exit 0
+"#;
+
+const POST_CHECKOUT_HOOK: &str = r#"#!/bin/sh
+# Installed by second-brain
+# $3 is 1 for branch checkouts and 0 for file checkouts
+if [ "$3" = "1" ]; then
+    {
+        echo checkout
+        basename "$(git rev-parse --show-toplevel)"
+        git name-rev --name-only --refs='refs/heads/*' "$1" 2>/dev/null || echo "$1"
+        git rev-parse --abbrev-ref HEAD
+    } | nc -U "__SOCKET__" >/dev/null 2>&1 &
+fi
+
+local_hook="$(git rev-parse --git-dir)/hooks/post-checkout"
+[ -x "$local_hook" ] && exec "$local_hook" "$@"
+exit 0
+"#;
+
+pub struct GitTracker {
+    event_buffer: Arc<Mutex<VecDeque<UserEvent>>>,
+}
+
+impl GitTracker {
+    // Installs the hooks and starts listening for events on the socket
+    pub async fn start() -> Result<Self, Box<dyn Error>> {
+        let socket_path = env::var("GIT_TRACKER_SOCKET").unwrap_or_else(|_| DEFAULT_SOCKET_PATH.to_string());
+
+        install_hooks(&socket_path)?;
+
+        // A socket left behind by a previous run would make bind fail
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path)?;
+
+        let event_buffer = Arc::new(Mutex::new(VecDeque::with_capacity(MAX_BUFFER_SIZE)));
+        let buffer_clone = event_buffer.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let mut stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        eprintln!("❌ Git tracker socket error: {}", e);
+                        continue;
+                    }
+                };
+
+                let mut message = String::new();
+                if stream.read_to_string(&mut message).await.is_err() {
+                    continue;
+                }
+
+                if let Some(event) = parse_hook_message(&message) {
+                    let mut buffer = buffer_clone.lock().unwrap();
+                    buffer.push_back(event);
+
+                    if buffer.len() > MAX_BUFFER_SIZE {
+                        buffer.pop_front();
+                    }
+                }
+            }
+        });
+
+        Ok(Self { event_buffer })
+    }
+
+    pub fn poll(&self) -> Option<UserEvent> {
+        let mut buffer = self.event_buffer.lock().unwrap();
+        buffer.pop_front()
+    }
+}
+
+// Git tracking changes the global core.hooksPath, so it's opt-in
+pub fn is_enabled() -> bool {
+    env::var("ENABLE_GIT_TRACKING")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
+
+// Writes our hooks into core.hooksPath, pointing it at ~/.git-hooks if unset
+fn install_hooks(socket_path: &str) -> Result<(), Box<dyn Error>> {
+    let configured = Command::new("git")
+        .args(["config", "--global", "core.hooksPath"])
+        .output()?;
+    let configured = String::from_utf8_lossy(&configured.stdout).trim().to_string();
+
+    let hooks_dir = if configured.is_empty() {
+        let home = env::var("HOME")?;
+        let hooks_dir = Path::new(&home).join(".git-hooks");
+
+        Command::new("git")
+            .args(["config", "--global", "core.hooksPath"])
+            .arg(&hooks_dir)
+            .status()?;
+        println!("🪝 Set git core.hooksPath to {}", hooks_dir.display());
+
+        hooks_dir
+    } else {
+        expand_home(&configured)
+    };
+
+    std::fs::create_dir_all(&hooks_dir)?;
+
+    for (name, script) in [("post-commit", POST_COMMIT_HOOK), ("post-checkout", POST_CHECKOUT_HOOK)] {
+        let path = hooks_dir.join(name);
+
+        if let Ok(existing) = std::fs::read_to_string(&path) {
+            if !existing.contains(HOOK_MARKER) {
+                eprintln!(
+                    "⚠️ {} already exists and wasn't installed by second-brain, leaving it alone",
+                    path.display()
+                );
+                continue;
+            }
+        }
+
+        std::fs::write(&path, script.replace("__SOCKET__", socket_path))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn parse_hook_message(message: &str) -> Option<UserEvent> {
+    let mut lines = message.lines().map(str::trim);
+    let kind = lines.next()?;
+    let repo = lines.next()?.to_string();
+
+    let (event, data) = match kind {
+        "commit" => {
+            let branch = lines.next()?;
+            let files_changed: u32 = lines.next()?.parse().unwrap_or(0);
+            let message = lines.next().unwrap_or_default();
+
+            (
+                EventType::GitCommit.as_str(),
+                serde_json::json!({
+                    "repo": repo,
+                    "branch": branch,
+                    "message": message,
+                    "files_changed": files_changed,
+                }),
+            )
+        }
+        "checkout" => {
+            let from = lines.next()?;
+            let to = lines.next()?;
+
+            (
+                EventType::GitCheckout.as_str(),
+                serde_json::json!({ "repo": repo, "from": from, "to": to }),
+            )
+        }
+        _ => return None,
+    };
+
+    Some(UserEvent {
+        timestamp: Utc::now(),
+        event: event.to_string(),
+        data: data.to_string(),
+        app_context: AppContext {
+            app_name: "git".to_string(),
+            window_title: repo,
+            url: None,
+            screen_index: None,
+            display_name: None,
+            raw_window_title: None,
+        },
+    })
+}
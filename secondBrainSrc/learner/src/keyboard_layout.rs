@@ -0,0 +1,170 @@
+use rdev::Key;
+use std::env;
+use std::process::Command;
+use std::str::FromStr;
+
+// Physical key positions, row by row, as they're laid out on a US keyboard.
+// rdev reports these positions regardless of the active layout.
+const KEY_ROWS: [&[Key]; 4] = [
+    &[
+        Key::BackQuote,
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+        Key::Num0,
+        Key::Minus,
+        Key::Equal,
+    ],
+    &[
+        Key::KeyQ,
+        Key::KeyW,
+        Key::KeyE,
+        Key::KeyR,
+        Key::KeyT,
+        Key::KeyY,
+        Key::KeyU,
+        Key::KeyI,
+        Key::KeyO,
+        Key::KeyP,
+        Key::LeftBracket,
+        Key::RightBracket,
+        Key::BackSlash,
+    ],
+    &[
+        Key::KeyA,
+        Key::KeyS,
+        Key::KeyD,
+        Key::KeyF,
+        Key::KeyG,
+        Key::KeyH,
+        Key::KeyJ,
+        Key::KeyK,
+        Key::KeyL,
+        Key::SemiColon,
+        Key::Quote,
+    ],
+    &[
+        Key::KeyZ,
+        Key::KeyX,
+        Key::KeyC,
+        Key::KeyV,
+        Key::KeyB,
+        Key::KeyN,
+        Key::KeyM,
+        Key::Comma,
+        Key::Dot,
+        Key::Slash,
+    ],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardLayout {
+    Qwerty,
+    Azerty,
+    Dvorak,
+    Colemak,
+}
+
+impl KeyboardLayout {
+    // Unshifted characters produced by each position in KEY_ROWS
+    fn rows(&self) -> [&'static str; 4] {
+        match self {
+            KeyboardLayout::Qwerty => ["`1234567890-=", "qwertyuiop[]\\", "asdfghjkl;'", "zxcvbnm,./"],
+            KeyboardLayout::Azerty => ["²&é\"'(-è_çà)=", "azertyuiop^$*", "qsdfghjklmù", "wxcvbn,;:!"],
+            KeyboardLayout::Dvorak => ["`1234567890[]", "',.pyfgcrl/=\\", "aoeuidhtns-", ";qjkxbmwvz"],
+            KeyboardLayout::Colemak => ["`1234567890-=", "qwfpgjluy;[]\\", "arstdhneio'", "zxcvbkm,./"],
+        }
+    }
+
+    // KEYBOARD_LAYOUT overrides detection, which falls back to QWERTY
+    pub fn from_env_or_detect() -> Self {
+        if let Ok(value) = env::var("KEYBOARD_LAYOUT") {
+            match value.parse() {
+                Ok(layout) => return layout,
+                Err(e) => eprintln!("⚠️ {}, detecting layout instead", e),
+            }
+        }
+
+        Self::detect().unwrap_or(KeyboardLayout::Qwerty)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn detect() -> Option<Self> {
+        // e.g. "com.apple.keylayout.French"
+        let output = Command::new("defaults")
+            .args(["read", "-g", "AppleCurrentKeyboardLayoutInputSourceID"])
+            .output()
+            .ok()?;
+        let source = String::from_utf8_lossy(&output.stdout).to_lowercase();
+
+        Some(if source.contains("dvorak") {
+            KeyboardLayout::Dvorak
+        } else if source.contains("colemak") {
+            KeyboardLayout::Colemak
+        } else if source.contains("french") || source.contains("belgian") {
+            KeyboardLayout::Azerty
+        } else {
+            KeyboardLayout::Qwerty
+        })
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn detect() -> Option<Self> {
+        // Output contains lines like "layout:     fr" and "variant:    dvorak"
+        let output = Command::new("setxkbmap").arg("-query").output().ok()?;
+        let query = String::from_utf8_lossy(&output.stdout).to_lowercase();
+
+        let field = |name: &str| {
+            query
+                .lines()
+                .find_map(|line| line.strip_prefix(name))
+                .and_then(|rest| rest.trim_start_matches(':').split(',').next())
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        let layout = field("layout");
+        let variant = field("variant");
+
+        Some(if layout.contains("dvorak") || variant.contains("dvorak") {
+            KeyboardLayout::Dvorak
+        } else if layout.contains("colemak") || variant.contains("colemak") {
+            KeyboardLayout::Colemak
+        } else if layout == "fr" || layout == "be" {
+            KeyboardLayout::Azerty
+        } else {
+            KeyboardLayout::Qwerty
+        })
+    }
+}
+
+impl FromStr for KeyboardLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "qwerty" => Ok(KeyboardLayout::Qwerty),
+            "azerty" => Ok(KeyboardLayout::Azerty),
+            "dvorak" => Ok(KeyboardLayout::Dvorak),
+            "colemak" => Ok(KeyboardLayout::Colemak),
+            _ => Err(format!("Unknown keyboard layout '{}'", s)),
+        }
+    }
+}
+
+// The character a key produces on the given layout, ignoring modifiers
+pub fn normalize_key(key: Key, layout: KeyboardLayout) -> Option<char> {
+    if key == Key::Space {
+        return Some(' ');
+    }
+
+    KEY_ROWS.iter().zip(layout.rows()).find_map(|(keys, chars)| {
+        let column = keys.iter().position(|k| *k == key)?;
+        chars.chars().nth(column)
+    })
+}
@@ -1,49 +1,241 @@
+use crate::clipboard;
+use crate::display::get_display_for_window;
+use crate::keyboard_layout::{normalize_key, KeyboardLayout};
 use active_win_pos_rs as active_win;
-use activity_tracker_common::{AppContext, UserEvent};
-use chrono::Utc;
+use activity_tracker_common::{
+    window_sanitizer, AppContext, EventType, IdleEvent, MouseClickEvent, UserEvent, UserEventBuilder,
+};
+use chrono::{DateTime, Utc};
 use rdev::{listen, EventType as RdevEventType, Key};
 use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 const MAX_BUFFER_SIZE: usize = 1000;
+pub const DEFAULT_MAX_EVENTS_PER_SECOND: u32 = 500;
+// Rate limits are enforced over windows this long
+const RATE_WINDOW: Duration = Duration::from_millis(100);
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
+
+// Counters updated by the listener thread
+#[derive(Default)]
+pub struct KeyloggerMetrics {
+    pub events_captured: AtomicU64,
+    pub events_dropped: AtomicU64,
+    // Percentage of the buffer in use
+    pub buffer_utilization: AtomicU32,
+    // Unix time of the last captured event in milliseconds, 0 before the first
+    pub last_event_at: AtomicU64,
+    pub clipboard_events: AtomicU64,
+    // Characters copied, cut or pasted
+    pub clipboard_chars: AtomicU64,
+}
+
+impl KeyloggerMetrics {
+    fn set_buffer_len(&self, len: usize) {
+        let utilization = (len * 100 / MAX_BUFFER_SIZE) as u32;
+        self.buffer_utilization.store(utilization, Ordering::Relaxed);
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let last_event_at = match self.last_event_at.load(Ordering::Relaxed) {
+            0 => None,
+            millis => chrono::DateTime::from_timestamp_millis(millis as i64),
+        };
+
+        serde_json::json!({
+            "events_captured": self.events_captured.load(Ordering::Relaxed),
+            "events_dropped": self.events_dropped.load(Ordering::Relaxed),
+            "buffer_utilization": self.buffer_utilization.load(Ordering::Relaxed),
+            "last_event_at": last_event_at,
+            "clipboard_events": self.clipboard_events.load(Ordering::Relaxed),
+            "clipboard_chars": self.clipboard_chars.load(Ordering::Relaxed),
+        })
+    }
+}
+
+// Adds an event to the buffer. When it's full the new event is dropped,
+// so what was already captured stays in order.
+fn push_event(buffer: &Mutex<VecDeque<UserEvent>>, metrics: &KeyloggerMetrics, event: UserEvent) {
+    let mut buffer = buffer.lock().unwrap();
+    if buffer.len() >= MAX_BUFFER_SIZE {
+        metrics.events_dropped.fetch_add(1, Ordering::Relaxed);
+    } else {
+        metrics
+            .last_event_at
+            .store(event.timestamp.timestamp_millis().max(0) as u64, Ordering::Relaxed);
+        buffer.push_back(event);
+        metrics.events_captured.fetch_add(1, Ordering::Relaxed);
+    }
+    metrics.set_buffer_len(buffer.len());
+}
+
+// When the user last typed or clicked, and since when they've been away
+struct IdleState {
+    last_input: DateTime<Utc>,
+    idle_since: Option<DateTime<Utc>>,
+}
+
+// Records a keystroke or click, first ending the idle stretch it interrupts
+fn note_input(
+    idle: &Mutex<IdleState>,
+    buffer: &Mutex<VecDeque<UserEvent>>,
+    metrics: &KeyloggerMetrics,
+    app_context: &AppContext,
+) {
+    let now = Utc::now();
+    let mut idle = idle.lock().unwrap();
+    idle.last_input = now;
+
+    if let Some(idle_since) = idle.idle_since.take() {
+        let event = UserEventBuilder::new(EventType::IdleEnd)
+            .data(serde_json::json!(IdleEvent { idle_since }))
+            .context(app_context.clone())
+            .at(now)
+            .build();
+        push_event(buffer, metrics, event);
+    }
+}
+
+// Drops keystrokes beyond a per-window budget, e.g. when a paste is typed out
+struct RateLimiter {
+    max_events_per_second: u32,
+    window_start: Instant,
+    window_events: u32,
+    window_dropped: u64,
+}
+
+impl RateLimiter {
+    fn new(max_events_per_second: u32) -> Self {
+        Self {
+            max_events_per_second,
+            window_start: Instant::now(),
+            window_events: 0,
+            window_dropped: 0,
+        }
+    }
+
+    // Whether another event fits in the current window
+    fn allow(&mut self) -> bool {
+        if self.window_start.elapsed() >= RATE_WINDOW {
+            if self.window_dropped > 0 {
+                eprintln!(
+                    "⚠️ Rate limited: {} events/s exceeded, dropped {} events",
+                    self.max_events_per_second, self.window_dropped
+                );
+            }
+            self.window_start = Instant::now();
+            self.window_events = 0;
+            self.window_dropped = 0;
+        }
+
+        let budget = (self.max_events_per_second / 10).max(1);
+        if self.window_events >= budget {
+            self.window_dropped += 1;
+            return false;
+        }
+
+        self.window_events += 1;
+        true
+    }
+}
+
+// The focused window, or an unknown one when it can't be read
+fn active_app_context() -> AppContext {
+    let Ok(window) = active_win::get_active_window() else {
+        return AppContext::unknown();
+    };
+
+    // Extract URL from title for common browsers (simple heuristic)
+    let browser_url = if window.app_name.to_lowercase().contains("zen") {
+        println!("{:?}", window.app_name);
+        // Try to extract URL from title (very basic)
+        get_url_from_title(&window.title)
+    } else {
+        None
+    };
+
+    // Use the window's center to decide which monitor it's on
+    let display = get_display_for_window(
+        (window.position.x + window.position.width / 2.0) as i32,
+        (window.position.y + window.position.height / 2.0) as i32,
+    );
+
+    let (window_title, raw_window_title) = window_sanitizer::recorded_titles(window.title, &window.app_name);
+
+    AppContext {
+        app_name: window.app_name,
+        window_title,
+        url: browser_url,
+        screen_index: display.as_ref().map(|(i, _)| *i),
+        display_name: display.map(|(_, name)| name),
+        raw_window_title,
+    }
+}
+
+fn get_url_from_title(title: &str) -> Option<String> {
+    if let Some(i) = title.find(" - ") {
+        let potential_url = title.split_at(i).0.trim();
+        if potential_url.starts_with("http") || potential_url.contains("www.") || potential_url.contains(".com") {
+            return Some(potential_url.to_string());
+        }
+    }
+    None
+}
 
 pub struct Keylogger {
     event_buffer: Arc<Mutex<VecDeque<UserEvent>>>,
+    metrics: Arc<KeyloggerMetrics>,
+    idle: Arc<Mutex<IdleState>>,
+    idle_timeout: chrono::Duration,
     _rx: Option<mpsc::Receiver<()>>,
 }
 
 impl Keylogger {
-    pub fn new() -> Self {
+    pub fn new(layout: KeyboardLayout, max_events_per_second: u32, idle_timeout: Duration) -> Self {
         let event_buffer = Arc::new(Mutex::new(VecDeque::with_capacity(MAX_BUFFER_SIZE)));
         let buffer_clone = event_buffer.clone();
+        let metrics = Arc::new(KeyloggerMetrics::default());
+        let metrics_clone = metrics.clone();
+        // Counted from startup until the first keystroke or click
+        let idle = Arc::new(Mutex::new(IdleState {
+            last_input: Utc::now(),
+            idle_since: None,
+        }));
+        let idle_clone = idle.clone();
 
         // Setup MPSC channel to allow for clean shutdown if needed
         let (_tx, rx) = mpsc::channel(1);
 
+        let clipboard_worker = {
+            let (buffer, metrics) = (event_buffer.clone(), metrics.clone());
+            clipboard::ClipboardWorker::spawn(move |captured, app_context| {
+                metrics.clipboard_events.fetch_add(1, Ordering::Relaxed);
+                metrics
+                    .clipboard_chars
+                    .fetch_add(captured.content_length as u64, Ordering::Relaxed);
+
+                if let Some(event) = clipboard::clipboard_event(&captured, app_context) {
+                    push_event(&buffer, &metrics, event);
+                }
+            })
+        };
+
         thread::spawn(move || {
             // Track modifier key states
             let mut shift_pressed = false;
             let mut ctrl_pressed = false;
             let mut alt_pressed = false;
             let mut meta_pressed = false;
+            let mut rate_limiter = RateLimiter::new(max_events_per_second);
+            // Button presses don't say where the pointer is, so it's
+            // remembered from the last move
+            let mut pointer = (0.0, 0.0);
 
-            // Helper function to extract URLs
-            fn get_url_from_title(title: &str) -> Option<String> {
-                if let Some(i) = title.find(" - ") {
-                    let potential_url = title.split_at(i).0.trim();
-                    if potential_url.starts_with("http")
-                        || potential_url.contains("www.")
-                        || potential_url.contains(".com")
-                    {
-                        return Some(potential_url.to_string());
-                    }
-                }
-                None
-            }
-
-            // Callback that processes each keyboard event
+            // Callback that processes each keyboard and mouse event
             if let Err(error) = listen(move |event| {
                 match event.event_type {
                     RdevEventType::KeyPress(key) => {
@@ -53,47 +245,14 @@ impl Keylogger {
                             Key::ControlLeft | Key::ControlRight => ctrl_pressed = true,
                             Key::Alt | Key::AltGr => alt_pressed = true,
                             Key::MetaLeft | Key::MetaRight => meta_pressed = true,
+                            _ if !rate_limiter.allow() => {
+                                metrics_clone.events_dropped.fetch_add(1, Ordering::Relaxed);
+                            }
                             _ => {
                                 let key_str = format!("{:?}", key);
 
-                                // Get current active window info
-                                let app_context = match active_win::get_active_window() {
-                                    Ok(window) => {
-                                        // Extract URL from title for common browsers (simple heuristic)
-                                        let browser_url =
-                                            if window.app_name.to_lowercase().contains("zen") {
-                                                println!("{:?}", window.app_name);
-                                                // Try to extract URL from title (very basic)
-                                                if let Some(i) = window.title.find(" - ") {
-                                                    let potential_url =
-                                                        window.title.split_at(i).0.trim();
-                                                    if potential_url.starts_with("http")
-                                                        || potential_url.contains("www.")
-                                                        || potential_url.contains(".com")
-                                                    {
-                                                        Some(potential_url.to_string())
-                                                    } else {
-                                                        None
-                                                    }
-                                                } else {
-                                                    None
-                                                }
-                                            } else {
-                                                None
-                                            };
-
-                                        AppContext {
-                                            app_name: window.app_name,
-                                            window_title: window.title,
-                                            url: browser_url,
-                                        }
-                                    }
-                                    Err(_) => AppContext {
-                                        app_name: "unknown".to_string(),
-                                        window_title: "unknown".to_string(),
-                                        url: None,
-                                    },
-                                };
+                                let app_context = active_app_context();
+                                note_input(&idle_clone, &buffer_clone, &metrics_clone, &app_context);
 
                                 // Build modifiers list
                                 let mut modifiers = Vec::new();
@@ -111,28 +270,36 @@ impl Keylogger {
                                 }
 
                                 // Create key data as JSON
+                                // "key" is kept alongside "raw_key" for readers of older events
                                 let key_data = serde_json::json!({
                                     "key": key_str,
+                                    "raw_key": key_str,
+                                    "logical_char": normalize_key(key, layout),
                                     "modifiers": modifiers
                                 })
                                 .to_string();
 
+                                // Ctrl+C and Cmd+C style shortcuts also record what was copied
+                                // or pasted. Reading the clipboard runs a command, so it
+                                // happens on the clipboard worker to keep the listener responsive.
+                                let clipboard_action = (ctrl_pressed || meta_pressed)
+                                    .then(|| clipboard::action_for_key(key))
+                                    .flatten();
+                                if let Some(action) = clipboard_action {
+                                    if !clipboard_worker.request(action, app_context.clone()) {
+                                        metrics_clone.events_dropped.fetch_add(1, Ordering::Relaxed);
+                                    }
+                                }
+
                                 // Create the user event
                                 let event = UserEvent {
                                     timestamp: Utc::now(),
-                                    event: "keystroke".to_string(),
+                                    event: EventType::Keystroke.as_str().to_string(),
                                     data: key_data,
                                     app_context,
                                 };
 
-                                // Add to buffer
-                                let mut buffer = buffer_clone.lock().unwrap();
-                                buffer.push_back(event);
-
-                                // If buffer is full, remove oldest event
-                                if buffer.len() > MAX_BUFFER_SIZE {
-                                    buffer.pop_front();
-                                }
+                                push_event(&buffer_clone, &metrics_clone, event);
                             }
                         }
                     }
@@ -146,6 +313,26 @@ impl Keylogger {
                             _ => {}
                         }
                     }
+                    RdevEventType::MouseMove { x, y } => pointer = (x, y),
+                    RdevEventType::ButtonPress(_) if !rate_limiter.allow() => {
+                        metrics_clone.events_dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    RdevEventType::ButtonPress(button) => {
+                        let click = MouseClickEvent {
+                            x: pointer.0,
+                            y: pointer.1,
+                            button: format!("{:?}", button),
+                        };
+                        let app_context = active_app_context();
+                        note_input(&idle_clone, &buffer_clone, &metrics_clone, &app_context);
+
+                        let event = UserEventBuilder::new(EventType::MouseClick)
+                            .data(serde_json::json!(click))
+                            .context(app_context)
+                            .build();
+
+                        push_event(&buffer_clone, &metrics_clone, event);
+                    }
                     _ => {} // Ignore other event types
                 }
             }) {
@@ -155,12 +342,145 @@ impl Keylogger {
 
         Keylogger {
             event_buffer,
+            metrics,
+            idle,
+            idle_timeout: chrono::Duration::from_std(idle_timeout).unwrap_or(chrono::Duration::MAX),
             _rx: Some(rx),
         }
     }
 
-    pub fn poll(&self) -> Option<UserEvent> {
-        let mut buffer = self.event_buffer.lock().unwrap();
-        buffer.pop_front()
+    // An idle_start event once nothing has been typed or clicked for the idle
+    // timeout. Only one is returned per idle stretch; the next keystroke or
+    // click ends it with an idle_end event.
+    pub fn poll_idle(&self) -> Option<UserEvent> {
+        let now = Utc::now();
+        let mut idle = self.idle.lock().unwrap();
+        if idle.idle_since.is_some() || now - idle.last_input < self.idle_timeout {
+            return None;
+        }
+
+        let idle_since = idle.last_input;
+        idle.idle_since = Some(idle_since);
+
+        Some(
+            UserEventBuilder::new(EventType::IdleStart)
+                .data(serde_json::json!(IdleEvent { idle_since }))
+                .context(active_app_context())
+                .at(now)
+                .build(),
+        )
+    }
+
+    // Point-in-time copy of the counters
+    pub fn metrics(&self) -> KeyloggerMetrics {
+        let load64 = |counter: &AtomicU64| AtomicU64::new(counter.load(Ordering::Relaxed));
+
+        KeyloggerMetrics {
+            events_captured: load64(&self.metrics.events_captured),
+            events_dropped: load64(&self.metrics.events_dropped),
+            buffer_utilization: AtomicU32::new(self.metrics.buffer_utilization.load(Ordering::Relaxed)),
+            last_event_at: load64(&self.metrics.last_event_at),
+            clipboard_events: load64(&self.metrics.clipboard_events),
+            clipboard_chars: load64(&self.metrics.clipboard_chars),
+        }
+    }
+
+    pub fn buffer_depth(&self) -> usize {
+        self.event_buffer.lock().map(|buffer| buffer.len()).unwrap_or(0)
+    }
+
+    // Takes every buffered event, oldest first
+    pub fn drain_all(&self) -> Vec<UserEvent> {
+        drain_events(&self.event_buffer, &self.metrics)
+    }
+}
+
+// The lock is held once for the whole drain, so the capture thread can't
+// slip events in part way through
+fn drain_events(buffer: &Mutex<VecDeque<UserEvent>>, metrics: &KeyloggerMetrics) -> Vec<UserEvent> {
+    let mut buffer = buffer.lock().unwrap();
+    let events = buffer.drain(..).collect();
+    metrics.set_buffer_len(0);
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+
+    const PRODUCERS: usize = 4;
+    const EVENTS_PER_PRODUCER: i64 = 5_000;
+
+    #[test]
+    fn concurrent_pushes_and_drains_lose_no_events() {
+        let buffer = Arc::new(Mutex::new(VecDeque::with_capacity(MAX_BUFFER_SIZE)));
+        let metrics = Arc::new(KeyloggerMetrics::default());
+        // Producers and the drainer all start at once
+        let start = Arc::new(Barrier::new(PRODUCERS + 1));
+        let epoch = DateTime::<Utc>::from_timestamp(0, 0).unwrap();
+
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|producer| {
+                let (buffer, metrics, start) = (buffer.clone(), metrics.clone(), start.clone());
+                thread::spawn(move || {
+                    start.wait();
+                    for i in 0..EVENTS_PER_PRODUCER {
+                        let event = UserEventBuilder::keystroke("a")
+                            .app(&format!("producer-{}", producer))
+                            .at(epoch + chrono::Duration::milliseconds(i))
+                            .build();
+                        push_event(&buffer, &metrics, event);
+                    }
+                })
+            })
+            .collect();
+
+        start.wait();
+        let mut drained = Vec::new();
+        while producers.iter().any(|producer| !producer.is_finished()) {
+            drained.extend(drain_events(&buffer, &metrics));
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        drained.extend(drain_events(&buffer, &metrics));
+
+        // Events only go missing by being dropped from a full buffer, and are counted
+        let captured = metrics.events_captured.load(Ordering::Relaxed);
+        let dropped = metrics.events_dropped.load(Ordering::Relaxed);
+        assert_eq!(drained.len() as u64, captured);
+        assert_eq!(captured + dropped, PRODUCERS as u64 * EVENTS_PER_PRODUCER as u64);
+        assert_eq!(metrics.buffer_utilization.load(Ordering::Relaxed), 0);
+
+        // Each producer's events come out in the order they went in
+        for producer in 0..PRODUCERS {
+            let app = format!("producer-{}", producer);
+            let times: Vec<_> = drained
+                .iter()
+                .filter(|event| event.app_context.app_name == app)
+                .map(|event| event.timestamp)
+                .collect();
+            assert!(times.windows(2).all(|pair| pair[0] < pair[1]), "{} out of order", app);
+        }
+    }
+
+    #[test]
+    fn draining_takes_everything_in_order() {
+        let buffer = Mutex::new(VecDeque::new());
+        let metrics = KeyloggerMetrics::default();
+        for key in ["a", "b", "c"] {
+            push_event(&buffer, &metrics, UserEventBuilder::keystroke(key).build());
+        }
+
+        let keys: Vec<String> = drain_events(&buffer, &metrics)
+            .iter()
+            .map(|event| {
+                let data: serde_json::Value = serde_json::from_str(&event.data).unwrap();
+                data["key"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(keys, ["a", "b", "c"]);
+        assert!(drain_events(&buffer, &metrics).is_empty());
     }
 }
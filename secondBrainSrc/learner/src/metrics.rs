@@ -0,0 +1,118 @@
+use crate::keylogger::Keylogger;
+use activity_tracker_common::db::TimescaleClient;
+use chrono::{DateTime, Utc};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// Counters shared between the capture loop and the metrics endpoint
+pub struct LearnerStats {
+    pub events_captured: AtomicUsize,
+    pub buffer_depth: AtomicUsize,
+    pub db_errors: AtomicUsize,
+    pub last_event_at: Mutex<Option<DateTime<Utc>>>,
+    started_at: Instant,
+}
+
+impl LearnerStats {
+    pub fn new() -> Self {
+        Self {
+            events_captured: AtomicUsize::new(0),
+            buffer_depth: AtomicUsize::new(0),
+            db_errors: AtomicUsize::new(0),
+            last_event_at: Mutex::new(None),
+            started_at: Instant::now(),
+        }
+    }
+
+    pub fn record_event(&self, timestamp: DateTime<Utc>) {
+        self.events_captured.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut last) = self.last_event_at.lock() {
+            *last = Some(timestamp);
+        }
+    }
+
+    pub fn events_per_min(&self) -> f64 {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.events_captured.load(Ordering::Relaxed) as f64 / elapsed * 60.0
+        } else {
+            0.0
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "events_captured": self.events_captured.load(Ordering::Relaxed),
+            "buffer_depth": self.buffer_depth.load(Ordering::Relaxed),
+            "last_event_at": self.last_event_at.lock().ok().and_then(|last| *last),
+            "events_per_min": (self.events_per_min() * 10.0).round() / 10.0,
+            "db_errors": self.db_errors.load(Ordering::Relaxed),
+        })
+    }
+}
+
+// Serves GET /stats and GET /health on localhost until the process exits
+pub async fn serve(port: u16, stats: Arc<LearnerStats>, keylogger: Arc<Keylogger>, client: TimescaleClient) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+
+    let make_service = make_service_fn(move |_| {
+        let stats = stats.clone();
+        let keylogger = keylogger.clone();
+        let client = client.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle_request(request, stats.clone(), keylogger.clone(), client.clone())
+            }))
+        }
+    });
+
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            eprintln!("❌ Could not start metrics endpoint on {}: {}", addr, e);
+            return;
+        }
+    };
+
+    println!("📡 Metrics available at http://{}/stats", addr);
+
+    if let Err(e) = server.await {
+        eprintln!("❌ Metrics endpoint stopped: {}", e);
+    }
+}
+
+async fn handle_request(
+    request: Request<Body>,
+    stats: Arc<LearnerStats>,
+    keylogger: Arc<Keylogger>,
+    client: TimescaleClient,
+) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/stats") => {
+            let mut json = stats.to_json();
+            json["keylogger"] = keylogger.metrics().to_json();
+            json["pool"] = serde_json::json!(client.pool_stats());
+
+            Response::builder()
+                .header("Content-Type", "application/json")
+                .body(Body::from(json.to_string()))
+        }
+        (&Method::GET, "/health") => {
+            let status = if client.ping().await.is_ok() {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            };
+            Response::builder().status(status).body(Body::empty())
+        }
+        _ => Response::builder().status(StatusCode::NOT_FOUND).body(Body::empty()),
+    };
+
+    Ok(response.unwrap_or_else(|_| Response::new(Body::empty())))
+}
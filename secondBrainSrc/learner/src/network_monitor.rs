@@ -0,0 +1,234 @@
+use crate::display::active_app_context;
+use activity_tracker_common::{EventType, NetworkAccessEvent, UserEvent};
+use chrono::Utc;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::error::Error;
+use std::io::SeekFrom;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::process::Command;
+
+// Only connections to web servers are worth naming
+const WEB_PORTS: [u16; 2] = [80, 443];
+
+// /proc/net/tcp state of an open connection
+const TCP_ESTABLISHED: &str = "01";
+
+const PROC_NET_TCP: [&str; 2] = ["/proc/net/tcp", "/proc/net/tcp6"];
+const MITMPROXY_DNS_LOG: &str = ".mitmproxy/dns.log";
+const SYSTEM_LOG: &str = "/var/log/system.log";
+
+// Emits a network_access event for each domain once it's no longer being
+// accessed, with how long it was seen for
+#[derive(Default)]
+pub struct NetworkMonitor {
+    // Domains seen on the last poll, and when each was first seen
+    active: HashMap<String, Instant>,
+    // Reverse DNS answers, misses included, so each address is looked up once
+    hostnames: HashMap<IpAddr, Option<String>>,
+    // How much of each log has been read
+    log_offsets: HashMap<PathBuf, u64>,
+}
+
+impl NetworkMonitor {
+    pub async fn poll(&mut self) -> Result<Vec<UserEvent>, Box<dyn Error>> {
+        let domains: HashSet<String> = self.poll_dns_queries().await?.into_iter().collect();
+        let now = Instant::now();
+
+        let ended: Vec<String> = self
+            .active
+            .keys()
+            .filter(|domain| !domains.contains(*domain))
+            .cloned()
+            .collect();
+
+        let mut events = Vec::new();
+        for domain in ended {
+            let Some(first_seen) = self.active.remove(&domain) else {
+                continue;
+            };
+            let access = NetworkAccessEvent {
+                domain,
+                duration_ms: now.duration_since(first_seen).as_millis() as u64,
+            };
+
+            events.push(UserEvent {
+                timestamp: Utc::now(),
+                event: EventType::NetworkAccess.as_str().to_string(),
+                data: serde_json::to_string(&access)?,
+                app_context: active_app_context(),
+            });
+        }
+
+        for domain in domains {
+            self.active.entry(domain).or_insert(now);
+        }
+
+        Ok(events)
+    }
+
+    // Domains being accessed right now. On Linux these are the reverse DNS
+    // names of open web connections, which for CDNs name the CDN rather
+    // than the site. On macOS they're the lookups logged since the last poll.
+    pub async fn poll_dns_queries(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        if cfg!(target_os = "linux") {
+            self.open_connection_domains().await
+        } else if cfg!(target_os = "macos") {
+            let mitmproxy_log = env::var("HOME")
+                .map(|home| PathBuf::from(home).join(MITMPROXY_DNS_LOG))
+                .ok()
+                .filter(|path| path.exists());
+
+            match mitmproxy_log {
+                Some(path) => self.logged_domains(&path, None).await,
+                None => self.logged_domains(Path::new(SYSTEM_LOG), Some("mDNSResponder")).await,
+            }
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn open_connection_domains(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut peers = HashSet::new();
+        for table in PROC_NET_TCP {
+            // tcp6 is missing when IPv6 is disabled
+            if let Ok(contents) = tokio::fs::read_to_string(table).await {
+                peers.extend(established_web_peers(&contents));
+            }
+        }
+
+        let mut domains = Vec::new();
+        for ip in peers {
+            let hostname = match self.hostnames.get(&ip) {
+                Some(hostname) => hostname.clone(),
+                None => {
+                    let hostname = reverse_lookup(ip).await;
+                    self.hostnames.insert(ip, hostname.clone());
+                    hostname
+                }
+            };
+            domains.extend(hostname);
+        }
+
+        Ok(domains)
+    }
+
+    // Domains on lines appended to the log since the last poll. The first
+    // poll only notes where the log ends, so history isn't replayed.
+    async fn logged_domains(&mut self, path: &Path, line_filter: Option<&str>) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let len = file.metadata().await?.len();
+
+        let Some(offset) = self.log_offsets.insert(path.to_path_buf(), len) else {
+            return Ok(Vec::new());
+        };
+        // A shorter log has been rotated, so read it from the start
+        let offset = if offset > len { 0 } else { offset };
+
+        file.seek(SeekFrom::Start(offset)).await?;
+        let mut appended = Vec::new();
+        file.take(len - offset).read_to_end(&mut appended).await?;
+
+        let mut domains: Vec<String> = String::from_utf8_lossy(&appended)
+            .lines()
+            .filter(|line| line_filter.is_none_or(|filter| line.contains(filter)))
+            .flat_map(domains_in)
+            .collect();
+        domains.sort();
+        domains.dedup();
+
+        Ok(domains)
+    }
+}
+
+// Remote addresses of established connections to web ports, from the
+// contents of /proc/net/tcp or /proc/net/tcp6
+fn established_web_peers(table: &str) -> Vec<IpAddr> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(3) != Some(&TCP_ESTABLISHED) {
+                return None;
+            }
+
+            let (address, port) = fields.get(2)?.split_once(':')?;
+            if !WEB_PORTS.contains(&u16::from_str_radix(port, 16).ok()?) {
+                return None;
+            }
+
+            let ip = parse_proc_address(address)?;
+            (!ip.is_loopback() && !ip.is_unspecified()).then_some(ip)
+        })
+        .collect()
+}
+
+// Addresses are written as 32-bit words in host byte order
+fn parse_proc_address(hex: &str) -> Option<IpAddr> {
+    let words = (0..hex.len() / 8)
+        .map(|i| u32::from_str_radix(hex.get(i * 8..i * 8 + 8)?, 16).ok())
+        .collect::<Option<Vec<u32>>>()?;
+
+    let ip = match words[..] {
+        [word] => IpAddr::V4(Ipv4Addr::from(word.to_ne_bytes())),
+        [a, b, c, d] => {
+            let mut octets = [0u8; 16];
+            for (chunk, word) in octets.chunks_mut(4).zip([a, b, c, d]) {
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+
+    // IPv4 connections show up in tcp6 as mapped addresses
+    Some(ip.to_canonical())
+}
+
+// Asks the system resolver, so /etc/hosts and nscd caching apply
+async fn reverse_lookup(ip: IpAddr) -> Option<String> {
+    let output = Command::new("getent")
+        .args(["hosts", &ip.to_string()])
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    // "142.250.180.14  lhr25s34-in-f14.1e100.net"
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .map(|hostname| hostname.trim_end_matches('.').to_lowercase())
+}
+
+// Words in a log line that look like domain names
+fn domains_in(line: &str) -> Vec<String> {
+    line.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'))
+        .map(|word| word.trim_matches('.').to_lowercase())
+        .filter(|word| {
+            let labels: Vec<&str> = word.split('.').collect();
+            let tld = labels.last().copied().unwrap_or_default();
+
+            labels.len() >= 2
+                && labels.iter().all(|label| !label.is_empty())
+                && tld.len() >= 2
+                && tld.chars().all(|c| c.is_ascii_alphabetic())
+                // Bundle identifiers, not hosts
+                && !word.starts_with("com.apple.")
+        })
+        .collect()
+}
+
+// Network monitoring is opt-in because it records every site visited
+pub fn is_enabled() -> bool {
+    env::var("ENABLE_NETWORK_MONITOR")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
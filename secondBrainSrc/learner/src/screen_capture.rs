@@ -0,0 +1,64 @@
+use crate::display::active_app_context;
+use activity_tracker_common::{EventType, UserEvent};
+use chrono::Utc;
+use std::env;
+use std::error::Error;
+use tokio::process::Command;
+
+// Take a screenshot and run it through tesseract, returning the recognized text
+pub async fn capture_screen_text() -> Result<String, Box<dyn Error>> {
+    // The screenshot goes in a file only this user can read, with a random
+    // name in a directory only this user can open, so no one else can read
+    // it or plant a symlink where it's written. Both are deleted when dropped.
+    let dir = tempfile::Builder::new().prefix("second-brain-capture").tempdir()?;
+    let file = tempfile::Builder::new().suffix(".png").tempfile_in(dir.path())?;
+    let capture_path = file.path().to_string_lossy().into_owned();
+
+    let capture = if cfg!(target_os = "macos") {
+        // -x disables the shutter sound
+        Command::new("screencapture")
+            .args(["-x", &capture_path])
+            .output()
+            .await?
+    } else {
+        // -o overwrites the empty file instead of adding a suffix
+        Command::new("scrot").args(["-o", &capture_path]).output().await?
+    };
+
+    if !capture.status.success() {
+        return Err(format!(
+            "Screen capture failed: {}",
+            String::from_utf8_lossy(&capture.stderr).trim()
+        )
+        .into());
+    }
+
+    // "stdout" makes tesseract print the text instead of writing a file
+    let ocr = Command::new("tesseract")
+        .args([capture_path.as_str(), "stdout"])
+        .output()
+        .await?;
+
+    if !ocr.status.success() {
+        return Err(format!("OCR failed: {}", String::from_utf8_lossy(&ocr.stderr).trim()).into());
+    }
+
+    Ok(String::from_utf8_lossy(&ocr.stdout).trim().to_string())
+}
+
+// Builds a screen_capture event tagged with the window that was on screen
+pub fn screen_capture_event(ocr_text: String) -> UserEvent {
+    UserEvent {
+        timestamp: Utc::now(),
+        event: EventType::ScreenCapture.as_str().to_string(),
+        data: serde_json::json!({ "ocr_text": ocr_text }).to_string(),
+        app_context: active_app_context(),
+    }
+}
+
+// Screen capture is opt-in because it records everything visible on screen
+pub fn is_enabled() -> bool {
+    env::var("ENABLE_SCREEN_CAPTURE")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
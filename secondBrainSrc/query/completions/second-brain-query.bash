@@ -0,0 +1,22 @@
+# bash completion for second-brain-query
+# Install: source this file, or copy it to /etc/bash_completion.d/
+_second_brain_query() {
+    local cur prev
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+
+    case "${prev}" in
+        --format)
+            COMPREPLY=($(compgen -W "plain json markdown" -- "${cur}"))
+            return 0
+            ;;
+        --query|-q|--timeout|--server)
+            COMPREPLY=()
+            return 0
+            ;;
+    esac
+
+    COMPREPLY=($(compgen -W "-q --query --timeout --format --server -h --help" -- "${cur}"))
+}
+
+complete -F _second_brain_query -o bashdefault -o default second-brain-query
@@ -0,0 +1,7 @@
+# fish completion for second-brain-query
+# Install: copy this file to ~/.config/fish/completions/
+complete -c second-brain-query -s q -l query -d 'The question, read from stdin when omitted' -r
+complete -c second-brain-query -l timeout -d 'Seconds to wait for the recall service before giving up' -r
+complete -c second-brain-query -l format -d 'Output format' -r -f -a 'plain json markdown'
+complete -c second-brain-query -l server -d 'host:port of the recall service' -r
+complete -c second-brain-query -s h -l help -d 'Print help'
@@ -0,0 +1,121 @@
+use clap::{Parser, ValueEnum};
+use second_brain_client::{RecallResponse, JSON_PREFIX, MAX_RESPONSE_BYTES};
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Ask the running recall service a question and print the answer
+///
+/// Reads the question from stdin when --query isn't given, e.g.
+/// `echo "what did I do today" | second-brain-query`
+#[derive(Parser, Debug)]
+#[command(name = "second-brain-query")]
+struct Cli {
+    /// The question, e.g. "what did I work on yesterday?"
+    #[arg(long, short)]
+    query: Option<String>,
+
+    /// Seconds to wait for the recall service before giving up
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+
+    /// `plain` prints the answer as the recall service words it, `json` the
+    /// full response and `markdown` the answer under a heading
+    #[arg(long, value_enum, default_value_t = Format::Plain)]
+    format: Format,
+
+    /// host:port of the recall service
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    server: String,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    Plain,
+    Json,
+    Markdown,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+
+    let query = match cli.query {
+        Some(query) => query,
+        None => {
+            let mut query = String::new();
+            io::stdin().read_to_string(&mut query)?;
+            query
+        }
+    };
+    let query = query.trim();
+    if query.is_empty() {
+        return Err("No question given, pass --query or pipe one in on stdin".into());
+    }
+
+    let timeout = Duration::from_secs(cli.timeout);
+
+    let output = match cli.format {
+        Format::Plain => send(&cli.server, query, timeout)?,
+        Format::Json => send(&cli.server, &format!("{}{}", JSON_PREFIX, query), timeout)?,
+        Format::Markdown => {
+            let response = send(&cli.server, &format!("{}{}", JSON_PREFIX, query), timeout)?;
+            markdown(&serde_json::from_str(&response)?)
+        }
+    };
+
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", output.trim_end())?;
+
+    Ok(())
+}
+
+// One request per connection, framed as second_brain_client::write_framed
+// does it: a 4-byte big-endian length, then the bytes
+fn send(server: &str, request: &str, timeout: Duration) -> Result<String, Box<dyn Error>> {
+    let addr = server
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("Could not resolve {}", server))?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)
+        .map_err(|e| format!("Could not reach the recall service at {}: {}", server, e))?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    stream.write_all(&(request.len() as u32).to_be_bytes())?;
+    stream.write_all(request.as_bytes())?;
+
+    let no_answer = |e: io::Error| match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+            format!("No answer from {} within {}s", server, timeout.as_secs())
+        }
+        _ => e.to_string(),
+    };
+
+    let mut len = [0; 4];
+    stream.read_exact(&mut len).map_err(no_answer)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_RESPONSE_BYTES {
+        return Err(format!("Answer of {} bytes is over the {} byte limit", len, MAX_RESPONSE_BYTES).into());
+    }
+
+    let mut response = vec![0; len];
+    stream.read_exact(&mut response).map_err(no_answer)?;
+
+    Ok(String::from_utf8(response)?)
+}
+
+fn markdown(response: &RecallResponse) -> String {
+    let mut heading = response.timeframe_description.clone();
+    if let Some(app) = &response.app_filter {
+        heading.push_str(&format!(" in {}", app));
+    }
+
+    let mut text = format!("## {}\n\n{}", heading, response.formatted_text.trim());
+    for suggestion in &response.suggestions {
+        text.push_str(&format!("\n\n> 💡 {}", suggestion));
+    }
+
+    text
+}
@@ -0,0 +1,114 @@
+use edit_distance::edit_distance;
+use std::collections::HashMap;
+use std::error::Error;
+
+// Canonical app names and their aliases, e.g. "vscode" = ["vs code", "code"]
+const APP_ALIASES: &str = include_str!("app_aliases.toml");
+
+// Matches scoring this or lower are treated as no match
+const MIN_CONFIDENCE: f32 = 0.7;
+// An alias resolved to an app that has been seen is surer than one that hasn't
+const SEEN_ALIAS_CONFIDENCE: f32 = 0.95;
+const ALIAS_CONFIDENCE: f32 = 0.9;
+
+// Resolves what the user called an app ("vs code", "Visual Studio Code",
+// "firefx") to the name it was recorded under
+#[derive(Debug, Clone)]
+pub struct SimilarityMatcher {
+    aliases: HashMap<String, Vec<String>>,
+}
+
+impl Default for SimilarityMatcher {
+    fn default() -> Self {
+        Self::from_toml(APP_ALIASES).expect("embedded app aliases should be valid TOML")
+    }
+}
+
+impl SimilarityMatcher {
+    fn from_toml(contents: &str) -> Result<Self, Box<dyn Error>> {
+        let aliases: HashMap<String, Vec<String>> = toml::from_str(contents)?;
+
+        // Lowercased once here so lookups don't have to
+        let aliases = aliases
+            .into_iter()
+            .map(|(canonical, aliases)| {
+                (
+                    canonical.to_lowercase(),
+                    aliases.into_iter().map(|alias| alias.to_lowercase()).collect(),
+                )
+            })
+            .collect();
+
+        Ok(Self { aliases })
+    }
+
+    // The best match for the fragment among known_apps and the alias table,
+    // with a confidence from 0 to 1. Tries an exact match, then aliases, then
+    // edit distance. Known app names are returned as recorded; aliases of
+    // apps not in known_apps resolve to the canonical name.
+    pub fn match_app(&self, query_fragment: &str, known_apps: &[String]) -> Option<(String, f32)> {
+        let fragment = query_fragment.trim().to_lowercase();
+        if fragment.is_empty() {
+            return None;
+        }
+
+        if let Some(app) = known_apps.iter().find(|app| app.to_lowercase() == fragment) {
+            return Some((app.clone(), 1.0));
+        }
+
+        if let Some(canonical) = self.canonical_name(&fragment) {
+            return Some(match self.find_known(canonical, known_apps) {
+                Some(app) => (app.clone(), SEEN_ALIAS_CONFIDENCE),
+                None => (canonical.to_string(), ALIAS_CONFIDENCE),
+            });
+        }
+
+        // Misspellings: compare against every known app and every name in the alias table
+        let known = known_apps.iter().map(|app| (app.to_lowercase(), app.clone()));
+        let aliased = self.aliases.iter().flat_map(|(canonical, aliases)| {
+            let resolved = self
+                .find_known(canonical, known_apps)
+                .cloned()
+                .unwrap_or_else(|| canonical.clone());
+
+            std::iter::once(canonical)
+                .chain(aliases)
+                .map(move |name| (name.clone(), resolved.clone()))
+        });
+
+        known
+            .chain(aliased)
+            .map(|(name, resolved)| (resolved, similarity(&fragment, &name)))
+            .filter(|(_, score)| *score > MIN_CONFIDENCE)
+            // Break ties by name so the result doesn't depend on hash order
+            .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+    }
+
+    // The canonical name a lowercase name is, or is an alias of
+    fn canonical_name(&self, name: &str) -> Option<&str> {
+        self.aliases
+            .iter()
+            .find(|(canonical, aliases)| *canonical == name || aliases.iter().any(|alias| alias == name))
+            .map(|(canonical, _)| canonical.as_str())
+    }
+
+    // The known app recorded under a canonical name or one of its aliases
+    fn find_known<'a>(&self, canonical: &str, known_apps: &'a [String]) -> Option<&'a String> {
+        let aliases = self.aliases.get(canonical)?;
+
+        known_apps.iter().find(|app| {
+            let app = app.to_lowercase();
+            app == canonical || aliases.contains(&app)
+        })
+    }
+}
+
+// 1 for identical strings, falling towards 0 as more edits are needed
+fn similarity(a: &str, b: &str) -> f32 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    1.0 - edit_distance(a, b) as f32 / longest as f32
+}
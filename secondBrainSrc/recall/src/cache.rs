@@ -0,0 +1,242 @@
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use std::env;
+use std::error::Error;
+use std::future::Future;
+use std::path::PathBuf;
+
+// Answers about windows that include the present can change as new
+// summaries arrive, so they're only reused for this long
+const LIVE_ENTRY_TTL_MINUTES: i64 = 10;
+
+pub type Timeframe = (DateTime<Utc>, DateTime<Utc>);
+
+#[derive(Debug, Clone)]
+pub struct CacheStats {
+    pub entries: i64,
+    pub hits: i64,
+    pub misses: i64,
+    pub disk_bytes: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+// LLM responses stored on disk, keyed by the question and the time window it
+// covers. The past doesn't change, so answers about it are kept forever.
+#[derive(Clone)]
+pub struct DiskCache {
+    pool: SqlitePool,
+    path: PathBuf,
+}
+
+impl DiskCache {
+    pub async fn open() -> Result<Self, Box<dyn Error>> {
+        let path = cache_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let pool = SqlitePool::connect(&format!("sqlite:{}?mode=rwc", path.display())).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS cache_entries (
+                query_hash TEXT PRIMARY KEY,
+                timeframe_start TIMESTAMP,
+                timeframe_end TIMESTAMP,
+                response TEXT NOT NULL,
+                created_at TIMESTAMP NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        // Running totals so the hit rate survives restarts
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS cache_counters (
+                name TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool, path })
+    }
+
+    // Returns the stored response, or generates, stores and returns a new one.
+    // Cache errors are logged and never stop the response from being generated.
+    pub async fn get_or_generate<F>(&self, query: &str, timeframe: Option<Timeframe>, generate: F) -> Option<String>
+    where
+        F: Future<Output = Option<String>>,
+    {
+        let key = cache_key(query, timeframe);
+
+        // Box<dyn Error> isn't Send, so drop it before any further awaits
+        match self.lookup(&key).await.map_err(|e| e.to_string()) {
+            Ok(Some(response)) => {
+                self.count("hits").await;
+                return Some(response);
+            }
+            Ok(None) => self.count("misses").await,
+            Err(e) => eprintln!("⚠️ LLM cache lookup failed: {}", e),
+        }
+
+        let response = generate.await?;
+
+        if let Err(e) = self.store(&key, timeframe, &response).await {
+            eprintln!("⚠️ Failed to cache LLM response: {}", e);
+        }
+
+        Some(response)
+    }
+
+    async fn lookup(&self, key: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let row = sqlx::query("SELECT response, timeframe_end, created_at FROM cache_entries WHERE query_hash = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let timeframe_end: Option<DateTime<Utc>> = row.try_get("timeframe_end")?;
+        let created_at: DateTime<Utc> = row.try_get("created_at")?;
+
+        if is_live(timeframe_end, created_at) && Utc::now() - created_at > Duration::minutes(LIVE_ENTRY_TTL_MINUTES) {
+            return Ok(None);
+        }
+
+        Ok(Some(row.try_get("response")?))
+    }
+
+    async fn store(&self, key: &str, timeframe: Option<Timeframe>, response: &str) -> Result<(), Box<dyn Error>> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO cache_entries (query_hash, timeframe_start, timeframe_end, response, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(key)
+        .bind(timeframe.map(|t| t.0))
+        .bind(timeframe.map(|t| t.1))
+        .bind(response)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn count(&self, counter: &str) {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO cache_counters (name, value) VALUES (?, 1)
+            ON CONFLICT(name) DO UPDATE SET value = value + 1
+            "#,
+        )
+        .bind(counter)
+        .execute(&self.pool)
+        .await;
+
+        if let Err(e) = result {
+            eprintln!("⚠️ Failed to update LLM cache {}: {}", counter, e);
+        }
+    }
+
+    // Removes every entry and resets the counters, returning how many entries were removed
+    pub async fn clear(&self) -> Result<u64, Box<dyn Error>> {
+        let removed = sqlx::query("DELETE FROM cache_entries")
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        sqlx::query("DELETE FROM cache_counters").execute(&self.pool).await?;
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+
+        Ok(removed)
+    }
+
+    pub async fn stats(&self) -> Result<CacheStats, Box<dyn Error>> {
+        let entries: i64 = sqlx::query("SELECT COUNT(*) AS count FROM cache_entries")
+            .fetch_one(&self.pool)
+            .await?
+            .try_get("count")?;
+
+        let counter = |name: &'static str| async move {
+            sqlx::query("SELECT value FROM cache_counters WHERE name = ?")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await
+                .map(|row| row.and_then(|row| row.try_get::<i64, _>("value").ok()).unwrap_or(0))
+        };
+
+        Ok(CacheStats {
+            entries,
+            hits: counter("hits").await?,
+            misses: counter("misses").await?,
+            disk_bytes: std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0),
+        })
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+// SECOND_BRAIN_LLM_CACHE, defaulting to ~/.cache/second-brain/llm_cache.db
+pub fn cache_path() -> Result<PathBuf, Box<dyn Error>> {
+    if let Ok(path) = env::var("SECOND_BRAIN_LLM_CACHE") {
+        return Ok(PathBuf::from(path));
+    }
+
+    let home = env::var("HOME")?;
+    Ok(PathBuf::from(home).join(".cache/second-brain/llm_cache.db"))
+}
+
+// SHA256 of the normalized query followed by a hash of the timeframe, so the
+// same question about a different window is a different entry. Relative
+// windows like "yesterday" move with the clock, so times are bucketed to the
+// live TTL for them to hit at all.
+fn cache_key(query: &str, timeframe: Option<Timeframe>) -> String {
+    let normalized = query
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(['?', '.', '!'])
+        .to_string();
+
+    let timeframe = match timeframe {
+        Some((start, end)) => {
+            let bucket = LIVE_ENTRY_TTL_MINUTES * 60;
+            format!("{}..{}", start.timestamp() / bucket, end.timestamp() / bucket)
+        }
+        None => "all time".to_string(),
+    };
+    let timeframe_hash = format!("{:x}", Sha256::digest(timeframe.as_bytes()));
+
+    format!(
+        "{:x}",
+        Sha256::digest(format!("{}{}", normalized, timeframe_hash).as_bytes())
+    )
+}
+
+// Windows ending within the TTL of when the answer was generated may still
+// be getting new summaries; "all time" always does
+fn is_live(timeframe_end: Option<DateTime<Utc>>, created_at: DateTime<Utc>) -> bool {
+    timeframe_end.is_none_or(|end| end > created_at - Duration::minutes(LIVE_ENTRY_TTL_MINUTES))
+}
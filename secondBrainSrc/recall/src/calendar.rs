@@ -0,0 +1,373 @@
+use crate::query_engine::Timeframe;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use edit_distance::edit_distance;
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+
+// "before the standup" is the half hour before it starts, "after" the half hour after it ends
+const PROXIMITY_MINUTES: i64 = 30;
+// Recurring events are searched back this far for their latest occurrence
+const RECURRENCE_LOOKBACK_DAYS: i64 = 366;
+// Share of an event's name that has to appear in the query
+const MIN_MATCH_SCORE: f32 = 0.5;
+// Word similarity needed to forgive typos like "stnadup"
+const MIN_WORD_SIMILARITY: f32 = 0.8;
+
+// Only queries phrased around an event are looked up, so a question about
+// "firefox" doesn't pick up a meeting named Firefox
+const EVENT_TRIGGERS: [&str; 6] = [" during ", " before ", " after ", " meeting", " at the ", " in the "];
+
+// Words too common in event names to identify one
+const GENERIC_WORDS: [&str; 8] = ["meeting", "call", "sync", "the", "and", "with", "for", "weekly"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// The parts of an RRULE used to find an event's latest occurrence. COUNT
+// and the BY* rules other than BYDAY are ignored.
+#[derive(Debug, Clone)]
+struct Recurrence {
+    frequency: Frequency,
+    interval: i64,
+    until: Option<DateTime<Utc>>,
+    weekdays: Vec<Weekday>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CalendarEvent {
+    pub summary: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    recurrence: Option<Recurrence>,
+}
+
+impl CalendarEvent {
+    // The latest occurrence that has started by `now`, if any
+    fn latest_occurrence(&self, now: DateTime<Utc>) -> Option<Timeframe> {
+        let Some(rule) = &self.recurrence else {
+            return (self.start <= now).then_some((self.start, self.end));
+        };
+
+        let first = self.start.with_timezone(&Local);
+        let length = self.end - self.start;
+        let today = now.with_timezone(&Local).date_naive();
+
+        (0..=RECURRENCE_LOOKBACK_DAYS)
+            .map(|days_ago| today - Duration::days(days_ago))
+            .take_while(|day| *day >= first.date_naive())
+            .filter(|day| rule.occurs_on(*day, first.date_naive()))
+            .filter_map(|day| Local.from_local_datetime(&day.and_time(first.time())).earliest())
+            .map(|start| start.with_timezone(&Utc))
+            .find(|start| *start <= now && rule.until.is_none_or(|until| *start <= until))
+            .map(|start| (start, start + length))
+    }
+}
+
+impl Recurrence {
+    fn parse(value: &str) -> Option<Self> {
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut until = None;
+        let mut weekdays = Vec::new();
+
+        for part in value.split(';') {
+            let (key, value) = part.split_once('=')?;
+            match key {
+                "FREQ" => {
+                    frequency = match value {
+                        "DAILY" => Some(Frequency::Daily),
+                        "WEEKLY" => Some(Frequency::Weekly),
+                        "MONTHLY" => Some(Frequency::Monthly),
+                        "YEARLY" => Some(Frequency::Yearly),
+                        _ => None,
+                    }
+                }
+                "INTERVAL" => interval = value.parse::<i64>().ok().filter(|i| *i > 0).unwrap_or(1),
+                "UNTIL" => until = parse_date_time(value, false),
+                // Weekdays may carry an ordinal, as in "1MO"
+                "BYDAY" => {
+                    weekdays = value
+                        .split(',')
+                        .filter_map(|day| parse_weekday(day.trim_start_matches(|c: char| !c.is_alphabetic())))
+                        .collect()
+                }
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            frequency: frequency?,
+            interval,
+            until,
+            weekdays,
+        })
+    }
+
+    fn occurs_on(&self, day: NaiveDate, first: NaiveDate) -> bool {
+        match self.frequency {
+            Frequency::Daily => (day - first).num_days() % self.interval == 0,
+            Frequency::Weekly => {
+                let on_weekday = match self.weekdays.is_empty() {
+                    true => day.weekday() == first.weekday(),
+                    false => self.weekdays.contains(&day.weekday()),
+                };
+                let weeks = (week_start(day) - week_start(first)).num_days() / 7;
+                on_weekday && weeks % self.interval == 0
+            }
+            Frequency::Monthly => {
+                let months = (day.year() - first.year()) as i64 * 12 + day.month() as i64 - first.month() as i64;
+                day.day() == first.day() && months % self.interval == 0
+            }
+            Frequency::Yearly => {
+                day.month() == first.month()
+                    && day.day() == first.day()
+                    && (day.year() - first.year()) as i64 % self.interval == 0
+            }
+        }
+    }
+}
+
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(day.weekday().num_days_from_monday() as i64)
+}
+
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    Some(match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+// SECOND_BRAIN_CALENDAR, defaulting to ~/.config/second-brain/calendar.ics
+pub fn calendar_path() -> Result<PathBuf, Box<dyn Error>> {
+    if let Ok(path) = env::var("SECOND_BRAIN_CALENDAR") {
+        return Ok(PathBuf::from(path));
+    }
+
+    let home = env::var("HOME")?;
+    Ok(PathBuf::from(home).join(".config/second-brain/calendar.ics"))
+}
+
+// Events from the calendar file, or none if there isn't one
+pub fn load_calendar() -> Vec<CalendarEvent> {
+    let Ok(path) = calendar_path() else {
+        return Vec::new();
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => {
+            let events = parse_ics(&contents);
+            println!("📅 Loaded {} calendar events from {}", events.len(), path.display());
+            events
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+// Downloads the calendar from CALENDAR_ICS_URL over the local copy
+pub async fn refresh_calendar(url: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let contents = reqwest::get(url).await?.error_for_status()?.text().await?;
+    if !contents.contains("BEGIN:VCALENDAR") {
+        return Err(format!("{} didn't return an iCalendar file", url).into());
+    }
+
+    let path = calendar_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, contents)?;
+
+    Ok(path)
+}
+
+// Reads the VEVENTs of an iCalendar file. Times with a TZID are taken as
+// local time; events without a usable DTSTART are skipped.
+pub fn parse_ics(contents: &str) -> Vec<CalendarEvent> {
+    // Long lines are folded onto following lines that start with a space or tab
+    let unfolded = contents.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+
+    let mut events = Vec::new();
+    let mut current: Option<Vec<(String, String)>> = None;
+
+    for line in unfolded.lines() {
+        match line.trim_end() {
+            "BEGIN:VEVENT" => current = Some(Vec::new()),
+            "END:VEVENT" => {
+                if let Some(event) = current.take().and_then(|properties| event_from_properties(&properties)) {
+                    events.push(event);
+                }
+            }
+            line => {
+                if let (Some(properties), Some(property)) = (current.as_mut(), split_property(line)) {
+                    properties.push(property);
+                }
+            }
+        }
+    }
+
+    events
+}
+
+// "DTSTART;TZID=Europe/London:20240101T090000" -> ("DTSTART;TZID=Europe/London", "20240101T090000")
+fn split_property(line: &str) -> Option<(String, String)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+
+    Some((line[..colon].to_string(), line[colon + 1..].to_string()))
+}
+
+fn event_from_properties(properties: &[(String, String)]) -> Option<CalendarEvent> {
+    let find = |name: &str| properties.iter().find(|(key, _)| key.split(';').next() == Some(name));
+
+    let summary = find("SUMMARY").map(|(_, value)| unescape(value)).unwrap_or_default();
+    let (start_key, start_value) = find("DTSTART")?;
+    let all_day = start_key.contains("VALUE=DATE") && !start_key.contains("VALUE=DATE-TIME");
+    let start = parse_date_time(start_value, all_day)?;
+
+    let end = find("DTEND")
+        .and_then(|(key, value)| parse_date_time(value, key.contains("VALUE=DATE") && !key.contains("VALUE=DATE-TIME")))
+        .filter(|end| *end >= start)
+        .unwrap_or_else(|| start + if all_day { Duration::days(1) } else { Duration::hours(1) });
+
+    Some(CalendarEvent {
+        summary,
+        start,
+        end,
+        recurrence: find("RRULE").and_then(|(_, value)| Recurrence::parse(value)),
+    })
+}
+
+// 20240101T090000Z is UTC, 20240101T090000 local time and 20240101 local midnight
+fn parse_date_time(value: &str, all_day: bool) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .ok()
+            .map(|time| time.and_utc());
+    }
+
+    let local = if all_day || value.len() == 8 {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()?
+            .and_time(NaiveTime::MIN)
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?
+    };
+
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+// The period of the calendar event a query refers to, e.g. "during the
+// standup", "before the design review" or "after the 1:1 with Sam". Event
+// names are matched word by word, forgiving typos, and recurring events
+// resolve to their latest occurrence.
+pub fn find_calendar_event(query: &str, events: &[CalendarEvent]) -> Option<Timeframe> {
+    let query = query.to_lowercase();
+    let padded = format!(" {} ", query);
+    if !EVENT_TRIGGERS.iter().any(|trigger| padded.contains(trigger)) {
+        return None;
+    }
+
+    let query_words = words(&query);
+    let now = Utc::now();
+
+    let (start, end) = events
+        .iter()
+        .filter_map(|event| {
+            let score = match_score(&query_words, &event.summary);
+            if score < MIN_MATCH_SCORE {
+                return None;
+            }
+            event.latest_occurrence(now).map(|occurrence| (score, occurrence))
+        })
+        // Best match first, then the most recent
+        .max_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1 .0.cmp(&b.1 .0)))
+        .map(|(_, occurrence)| occurrence)?;
+
+    let proximity = Duration::minutes(PROXIMITY_MINUTES);
+    Some(if padded.contains(" before ") {
+        (start - proximity, start)
+    } else if padded.contains(" after ") {
+        (end, end + proximity)
+    } else {
+        (start, end)
+    })
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+// Share of the event name's distinctive words found in the query
+fn match_score(query_words: &[String], summary: &str) -> f32 {
+    let all_words = words(summary);
+    let distinctive: Vec<&String> = all_words
+        .iter()
+        .filter(|word| !GENERIC_WORDS.contains(&word.as_str()))
+        .collect();
+    // Names made only of generic words ("Weekly sync") have to match on those
+    let summary_words = if distinctive.is_empty() {
+        all_words.iter().collect()
+    } else {
+        distinctive
+    };
+
+    if summary_words.is_empty() {
+        return 0.0;
+    }
+
+    let matched = summary_words
+        .iter()
+        .filter(|word| {
+            query_words
+                .iter()
+                .any(|query_word| similarity(word, query_word) >= MIN_WORD_SIMILARITY)
+        })
+        .count();
+
+    matched as f32 / summary_words.len() as f32
+}
+
+fn similarity(a: &str, b: &str) -> f32 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    1.0 - edit_distance(a, b) as f32 / longest as f32
+}
@@ -0,0 +1,121 @@
+use activity_tracker_common::ActivitySummary;
+use chrono::{DateTime, Duration, Utc};
+
+const PRODID: &str = "-//SecondBrain//ActivityTracker//EN";
+
+// Summaries starting within this long of the previous one ending share an event
+const MERGE_GAP_MINUTES: i64 = 5;
+// Longest event title, in characters, taken from the start of the description
+const SUMMARY_CHARS: usize = 80;
+// RFC 5545 lines are folded at 75 octets
+const MAX_LINE_OCTETS: usize = 75;
+
+// A calendar event covering one or more summaries that ran into each other
+struct ActivityBlock {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    descriptions: Vec<String>,
+    tags: Vec<String>,
+}
+
+// Writes the summaries as an iCalendar file for Apple or Google Calendar.
+// Summaries that overlap or are within MERGE_GAP_MINUTES of each other
+// become one event, so a busy afternoon isn't dozens of five-minute blocks.
+pub fn export_summaries_to_ics(summaries: &[ActivitySummary]) -> String {
+    let stamp = format_date_time(Utc::now());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for block in merge_blocks(summaries) {
+        let description = block.descriptions.join("\n");
+        let title: String = description.chars().take(SUMMARY_CHARS).collect();
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:{}-{}@second-brain",
+            block.start.timestamp(),
+            block.end.timestamp()
+        ));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART:{}", format_date_time(block.start)));
+        lines.push(format!("DTEND:{}", format_date_time(block.end)));
+        lines.push(format!("SUMMARY:{}", escape(title.trim())));
+        lines.push(format!("DESCRIPTION:{}", escape(&description)));
+        if !block.tags.is_empty() {
+            let tags: Vec<String> = block.tags.iter().map(|tag| escape(tag)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect::<Vec<_>>().join("\r\n") + "\r\n"
+}
+
+fn merge_blocks(summaries: &[ActivitySummary]) -> Vec<ActivityBlock> {
+    let mut sorted: Vec<&ActivitySummary> = summaries.iter().collect();
+    sorted.sort_by_key(|s| s.start_time);
+
+    let mut blocks: Vec<ActivityBlock> = Vec::new();
+    for summary in sorted {
+        if let Some(block) = blocks.last_mut() {
+            if summary.start_time <= block.end + Duration::minutes(MERGE_GAP_MINUTES) {
+                block.end = block.end.max(summary.end_time);
+                block.descriptions.push(summary.description.clone());
+                for tag in &summary.tags {
+                    if !block.tags.contains(tag) {
+                        block.tags.push(tag.clone());
+                    }
+                }
+                continue;
+            }
+        }
+
+        blocks.push(ActivityBlock {
+            start: summary.start_time,
+            end: summary.end_time,
+            descriptions: vec![summary.description.clone()],
+            tags: summary.tags.clone(),
+        });
+    }
+
+    blocks
+}
+
+// 20240131T093000Z
+fn format_date_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// Backslashes, separators and newlines in text values are escaped
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\r', "")
+        .replace('\n', "\\n")
+}
+
+// Splits a line into MAX_LINE_OCTETS pieces, each after the first starting
+// with a space, without cutting a character in two
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut octets = 0;
+
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+
+    folded
+}
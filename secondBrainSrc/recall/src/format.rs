@@ -0,0 +1,368 @@
+use crate::persona::Persona;
+use crate::query_engine::{AppStats, Timeframe};
+use activity_tracker_common::{ActivitySummary, AppCorrelation, Task, WindowTitleHit};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+// Side-by-side markdown table of two periods, largest change first
+pub fn format_diff(
+    period_a: Timeframe,
+    period_b: Timeframe,
+    stats_a: &AppStats,
+    stats_b: &AppStats,
+    delta: &HashMap<String, f64>,
+) -> String {
+    let label = |period: Timeframe| {
+        format!(
+            "{} – {}",
+            period.0.format("%b %d %H:%M"),
+            period.1.format("%b %d %H:%M")
+        )
+    };
+
+    let mut result = format!("| App | {} | {} | Change |\n", label(period_a), label(period_b));
+    result.push_str("|-----|-----|-----|--------|\n");
+
+    let mut apps: Vec<(&String, &f64)> = delta.iter().filter(|(app, _)| *app != "total").collect();
+    apps.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+
+    for (app, change) in apps {
+        result.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            app,
+            format_minutes(stats_a.minutes.get(app).copied().unwrap_or(0.0)),
+            format_minutes(stats_b.minutes.get(app).copied().unwrap_or(0.0)),
+            format_change(*change)
+        ));
+    }
+
+    result.push_str(&format!(
+        "| **Total** | {} | {} | {} |\n",
+        format_minutes(stats_a.total_minutes()),
+        format_minutes(stats_b.total_minutes()),
+        format_change(delta.get("total").copied().unwrap_or(0.0))
+    ));
+
+    result
+}
+
+fn format_change(minutes: f64) -> String {
+    if minutes < 0.0 {
+        format!("-{}", format_minutes(-minutes))
+    } else {
+        format!("+{}", format_minutes(minutes))
+    }
+}
+
+// Numbered list of tasks with their time span, oldest first
+pub fn format_tasks(tasks: &[Task], timeframe: Timeframe, persona: &Persona) -> String {
+    if tasks.is_empty() {
+        return persona.random_no_data();
+    }
+
+    let mut result = String::new();
+    let intro = persona.random_intro();
+    if !intro.is_empty() {
+        result.push_str(&intro);
+        result.push('\n');
+    }
+
+    result.push_str(&format!(
+        "{} task{} between {} and {}:\n",
+        tasks.len(),
+        if tasks.len() == 1 { "" } else { "s" },
+        timeframe.0.format("%Y-%m-%d %H:%M"),
+        timeframe.1.format("%Y-%m-%d %H:%M")
+    ));
+
+    for (i, task) in tasks.iter().enumerate() {
+        result.push_str(&format!(
+            "{}. {} ({} – {}, {} summar{}, {:.0}% confidence)\n",
+            i + 1,
+            task.title,
+            task.start_time.format("%b %d %H:%M"),
+            task.end_time.format("%H:%M"),
+            task.summaries.len(),
+            if task.summaries.len() == 1 { "y" } else { "ies" },
+            task.confidence * 100.0
+        ));
+    }
+
+    result
+}
+
+// Apps used in the same hours as `app`, strongest first
+pub fn format_correlations(app: &str, correlations: &[AppCorrelation], persona: &Persona) -> String {
+    if correlations.is_empty() {
+        return persona.random_no_data();
+    }
+
+    let mut result = String::new();
+    let intro = persona.random_intro();
+    if !intro.is_empty() {
+        result.push_str(&intro);
+        result.push('\n');
+    }
+
+    result.push_str(&format!("Apps you usually use with {}:\n", app));
+
+    for (i, correlation) in correlations.iter().enumerate() {
+        let other = if correlation.app_a == app {
+            &correlation.app_b
+        } else {
+            &correlation.app_a
+        };
+        result.push_str(&format!(
+            "{}. {} ({:.0}% of the hours you use either, {} hour{} together)\n",
+            i + 1,
+            other,
+            correlation.co_occurrence_rate * 100.0,
+            correlation.co_occurrence_count,
+            if correlation.co_occurrence_count == 1 { "" } else { "s" }
+        ));
+    }
+
+    result
+}
+
+// Renders aggregate stats as a markdown table, most active app first
+pub fn format_statistics(
+    stats: &HashMap<String, serde_json::Value>,
+    timeframe: (DateTime<Utc>, DateTime<Utc>),
+    query: &str,
+    persona: &Persona,
+) -> String {
+    let total_events = stats.get("total_events").and_then(|v| v.as_u64()).unwrap_or(0);
+    if total_events == 0 {
+        return persona.random_no_data();
+    }
+
+    let mut result = String::new();
+    let intro = persona.random_intro();
+    if !intro.is_empty() {
+        result.push_str(&intro);
+        result.push('\n');
+    }
+
+    result.push_str(&format!(
+        "Statistics for \"{}\" ({} to {})\n\n",
+        query.trim(),
+        timeframe.0.format("%Y-%m-%d %H:%M"),
+        timeframe.1.format("%Y-%m-%d %H:%M")
+    ));
+
+    let mut apps: Vec<(&String, u64, f64)> = stats
+        .get("apps")
+        .and_then(|v| v.as_object())
+        .map(|apps| {
+            apps.iter()
+                .map(|(app, v)| {
+                    (
+                        app,
+                        v.get("events").and_then(|e| e.as_u64()).unwrap_or(0),
+                        v.get("active_minutes").and_then(|m| m.as_f64()).unwrap_or(0.0),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    apps.sort_by(|a, b| b.2.total_cmp(&a.2).then(b.1.cmp(&a.1)));
+
+    result.push_str("| App | Events | Active time |\n");
+    result.push_str("|-----|--------|-------------|\n");
+    for (app, events, minutes) in apps {
+        result.push_str(&format!("| {} | {} | {} |\n", app, events, format_minutes(minutes)));
+    }
+
+    let active_hours = stats.get("active_hours").and_then(|v| v.as_f64()).unwrap_or(0.0);
+    result.push_str(&format!(
+        "\nTotal: {} events, {} active\n",
+        total_events,
+        format_minutes(active_hours * 60.0)
+    ));
+
+    if let Some(app) = stats.get("most_used_app").and_then(|v| v.as_str()) {
+        result.push_str(&format!("Most used app: {}\n", app));
+    }
+
+    result
+}
+
+fn format_minutes(minutes: f64) -> String {
+    let minutes = minutes.round() as i64;
+    if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+pub fn format_window_titles(hits: Vec<WindowTitleHit>, persona: &Persona) -> String {
+    if hits.is_empty() {
+        return persona.random_no_data();
+    }
+
+    let mut result = String::new();
+    let intro = persona.random_intro();
+    if !intro.is_empty() {
+        result.push_str(&intro);
+        result.push('\n');
+    }
+
+    for hit in hits {
+        result.push_str(&format!(
+            "• {} — {} ({})\n",
+            hit.timestamp.format("%H:%M"),
+            hit.window_title,
+            hit.app_name
+        ));
+    }
+
+    result
+}
+
+// "Showing 1–10 of 157 results", when the summaries shown are only the
+// first of more
+pub fn format_showing(shown: usize, total_count: Option<u64>) -> Option<String> {
+    let total = total_count.filter(|total| *total > shown as u64)?;
+    Some(format!("Showing 1–{} of {} results", shown, total))
+}
+
+pub fn format_summaries(summaries: Vec<ActivitySummary>, query: &str, persona: &Persona) -> String {
+    if summaries.is_empty() {
+        return persona.random_no_data();
+    }
+
+    let mut result = String::new();
+    let intro = persona.random_intro();
+    if !intro.is_empty() {
+        result.push_str(&intro);
+        result.push('\n');
+    }
+
+    // Identify query type
+    let query_lower = query.to_lowercase();
+    let is_key_query =
+        query_lower.contains("key") && (query_lower.contains("most") || query_lower.contains("frequent"));
+    let is_app_query = (query_lower.contains("app") || query_lower.contains("application"))
+        && (query_lower.contains("most") || query_lower.contains("frequent"));
+
+    for s in summaries {
+        // Format the time
+        let time_str = format!("{} to {}", s.start_time.format("%H:%M"), s.end_time.format("%H:%M"));
+
+        // Extract the most used keys and apps only when specifically asked about them
+        if is_key_query || is_app_query {
+            // Extract the most used keys
+            let mut key_counts = std::collections::HashMap::new();
+            for event in &s.events {
+                if let Ok(data) = serde_json::from_str::<serde_json::Value>(&event.data) {
+                    if let Some(key) = data.get("key").and_then(|k| k.as_str()) {
+                        *key_counts.entry(key.to_string()).or_insert(0) += 1;
+                    }
+                }
+            }
+
+            let mut key_vec: Vec<_> = key_counts.into_iter().collect();
+            key_vec.sort_by_key(|b| std::cmp::Reverse(b.1));
+
+            // Extract the most used apps
+            let mut app_counts = std::collections::HashMap::new();
+            for event in &s.events {
+                let app_name = if event.app_context.app_name.contains("ghostty") {
+                    "Terminal".to_string()
+                } else if event.app_context.app_name.contains("firefox") {
+                    "Firefox".to_string()
+                } else {
+                    event.app_context.app_name.clone()
+                };
+
+                *app_counts.entry(app_name).or_insert(0) += 1;
+            }
+
+            let mut app_vec: Vec<_> = app_counts.into_iter().collect();
+            app_vec.sort_by_key(|b| std::cmp::Reverse(b.1));
+
+            // Generate appropriate response based on query type
+            if is_key_query {
+                // Format keys response
+                let mut response = String::new();
+                for (i, (key, count)) in key_vec.iter().take(10).enumerate() {
+                    response.push_str(&format!("{}. {} ({} times)\n", i + 1, key, count));
+                }
+
+                result.push_str(&format!(
+                    "• {}: Most frequently used keys:\n{} ({} events)\n",
+                    time_str,
+                    response,
+                    s.events.len()
+                ));
+            } else if is_app_query {
+                // Format apps response
+                let mut response = String::new();
+                for (i, (app, count)) in app_vec.iter().take(5).enumerate() {
+                    response.push_str(&format!("{}. {} ({} events)\n", i + 1, app, count));
+                }
+
+                result.push_str(&format!(
+                    "• {}: Most frequently used applications:\n{} ({} events)\n",
+                    time_str,
+                    response,
+                    s.events.len()
+                ));
+            }
+        } else {
+            // For regular activity queries, use the description from the summary
+            // Get apps for context without showing detailed stats
+            let mut app_counts = std::collections::HashMap::new();
+            for event in &s.events {
+                let app_name = if event.app_context.app_name.contains("ghostty") {
+                    "Terminal".to_string()
+                } else if event.app_context.app_name.contains("firefox") {
+                    "Firefox".to_string()
+                } else {
+                    event.app_context.app_name.clone()
+                };
+
+                *app_counts.entry(app_name).or_insert(0) += 1;
+            }
+
+            let mut app_vec: Vec<_> = app_counts.into_iter().collect();
+            app_vec.sort_by_key(|b| std::cmp::Reverse(b.1));
+
+            let apps: Vec<String> = app_vec.iter().take(2).map(|(name, _)| name.clone()).collect();
+
+            let apps_str = if !apps.is_empty() {
+                format!(" in {}", apps.join(" and "))
+            } else {
+                String::new()
+            };
+
+            // Clean up the description to remove any code-related content
+            let description = s
+                .description
+                .lines()
+                .take(3)
+                .filter(|line| {
+                    !line.contains("```")
+                        && !line.contains("script")
+                        && !line.contains("parse")
+                        && !line.contains("code")
+                        && !line.contains("example")
+                        && !line.contains("analyze this data")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            result.push_str(&format!(
+                "• {}: {}{} ({} events)\n",
+                time_str,
+                description,
+                apps_str,
+                s.events.len()
+            ));
+        }
+    }
+
+    result
+}
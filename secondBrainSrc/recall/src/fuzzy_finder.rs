@@ -1,6 +1,6 @@
 use activity_tracker_common::{
+    db::{GeneralDbClient, SummaryStore},
     ActivitySummary,
-    db::{GeneralDbClient, SummaryStore}
 };
 use std::error::Error;
 
@@ -0,0 +1,176 @@
+use activity_tracker_common::{
+    db::{GeneralDbClient, TimescaleClient},
+    llm::{LlmChain, LlmClient},
+};
+use chrono::{DateTime, Utc};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use tokio::time::{interval, Duration};
+
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentHealth {
+    pub healthy: bool,
+    pub error: Option<String>,
+}
+
+impl ComponentHealth {
+    fn from_result(result: Result<(), Box<dyn Error>>) -> Self {
+        match result {
+            Ok(()) => Self {
+                healthy: true,
+                error: None,
+            },
+            Err(e) => Self {
+                healthy: false,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthStatus {
+    pub postgres: ComponentHealth,
+    pub sqlite: ComponentHealth,
+    pub llm: ComponentHealth,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl HealthStatus {
+    pub fn is_healthy(&self) -> bool {
+        self.postgres.healthy && self.sqlite.healthy && self.llm.healthy
+    }
+}
+
+pub type SharedHealth = Arc<RwLock<HealthStatus>>;
+
+// Checks every backend recall depends on, so queries can fail fast instead of
+// waiting on one that's down
+pub struct HealthChecker {
+    events_db: TimescaleClient,
+    summary_db: GeneralDbClient,
+    llm_client: Option<Arc<LlmChain>>,
+}
+
+impl HealthChecker {
+    pub fn new(events_db: TimescaleClient, summary_db: GeneralDbClient, llm_client: Option<Arc<LlmChain>>) -> Self {
+        Self {
+            events_db,
+            summary_db,
+            llm_client,
+        }
+    }
+
+    pub async fn check(&self) -> HealthStatus {
+        // Box<dyn Error> isn't Send, so each result is converted before the next await
+        let postgres = ComponentHealth::from_result(self.events_db.ping().await);
+        let sqlite = ComponentHealth::from_result(self.summary_db.integrity_check().await);
+        let llm = match &self.llm_client {
+            Some(client) => ComponentHealth::from_result(client.health_check().await),
+            None => ComponentHealth {
+                healthy: false,
+                error: Some("No LLM backend is configured".to_string()),
+            },
+        };
+
+        HealthStatus {
+            postgres,
+            sqlite,
+            llm,
+            checked_at: Utc::now(),
+        }
+    }
+
+    // Rechecks every 30 seconds, logging whenever a component changes state
+    pub async fn run(self, status: SharedHealth) {
+        let mut interval = interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            let current = self.check().await;
+            if let Ok(mut status) = status.write() {
+                log_changes(&status, &current);
+                *status = current;
+            }
+        }
+    }
+}
+
+fn log_changes(previous: &HealthStatus, current: &HealthStatus) {
+    let components = [
+        ("PostgreSQL", &previous.postgres, &current.postgres),
+        ("SQLite", &previous.sqlite, &current.sqlite),
+        ("LLM", &previous.llm, &current.llm),
+    ];
+
+    for (name, before, after) in components {
+        match (before.healthy, after.healthy) {
+            (true, false) => eprintln!(
+                "⚠️ {} is unhealthy: {}",
+                name,
+                after.error.as_deref().unwrap_or("unknown error")
+            ),
+            (false, true) => println!("✅ {} recovered", name),
+            _ => {}
+        }
+    }
+}
+
+// Serves GET /health on localhost until the process exits
+pub async fn serve(port: u16, status: SharedHealth) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+
+    let make_service = make_service_fn(move |_| {
+        let status = status.clone();
+
+        async move { Ok::<_, Infallible>(service_fn(move |request| handle_request(request, status.clone()))) }
+    });
+
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            eprintln!("❌ Could not start health endpoint on {}: {}", addr, e);
+            return;
+        }
+    };
+
+    println!("📡 Health available at http://{}/health", addr);
+
+    if let Err(e) = server.await {
+        eprintln!("❌ Health endpoint stopped: {}", e);
+    }
+}
+
+async fn handle_request(request: Request<Body>, status: SharedHealth) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/health") => {
+            let status = status.read().map(|status| status.clone()).ok();
+            let healthy = status.as_ref().is_some_and(|status| status.is_healthy());
+
+            let mut json = serde_json::json!({ "healthy": healthy });
+            if let Some(status) = status {
+                json["components"] = serde_json::json!(status);
+            }
+
+            Response::builder()
+                .status(if healthy {
+                    StatusCode::OK
+                } else {
+                    StatusCode::SERVICE_UNAVAILABLE
+                })
+                .header("Content-Type", "application/json")
+                .body(Body::from(json.to_string()))
+        }
+        _ => Response::builder().status(StatusCode::NOT_FOUND).body(Body::empty()),
+    };
+
+    Ok(response.unwrap_or_else(|_| Response::new(Body::empty())))
+}
@@ -0,0 +1,295 @@
+use activity_tracker_recall::timeframe::{self, HourRange};
+use serde::{Deserialize, Serialize};
+
+// Phrases that mark a query as having a deterministic, aggregate answer
+pub(crate) const AGGREGATE_TRIGGERS: [&str; 8] = [
+    "how many",
+    "how much time",
+    "total",
+    "count",
+    "hours",
+    "most used app",
+    "most-used app",
+    "how long",
+];
+
+// Phrases that ask to compare two periods. Matched against the query padded with spaces.
+pub(crate) const DIFF_TRIGGERS: [&str; 7] = [
+    " compared to ",
+    " compare to ",
+    " compared with ",
+    " vs ",
+    " vs. ",
+    " versus ",
+    " difference between ",
+];
+
+// Phrases asking which apps were used, answered from the same aggregates as stats
+const LIST_APPS_TRIGGERS: [&str; 9] = [
+    "what apps",
+    "which apps",
+    "what applications",
+    "which applications",
+    "list apps",
+    "list my apps",
+    "list of apps",
+    "apps did i use",
+    "apps have i used",
+];
+
+// Openers of questions that need reasoning over the summaries, not a lookup
+const FREEFORM_TRIGGERS: [&str; 12] = [
+    "why",
+    "how should",
+    "what should",
+    "should i",
+    "explain",
+    "summarize",
+    "summarise",
+    "describe",
+    "tell me about",
+    "how productive",
+    "how focused",
+    "advice",
+];
+
+// Words naming a period that parse_time_query understands
+const PERIOD_WORDS: [&str; 11] = [
+    "today",
+    "yesterday",
+    "this week",
+    "last week",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+// Words that carry no search meaning in a question about past activity
+const QUESTION_WORDS: [&str; 102] = [
+    "what",
+    "when",
+    "where",
+    "which",
+    "who",
+    "how",
+    "show",
+    "me",
+    "tell",
+    "list",
+    "all",
+    "did",
+    "do",
+    "does",
+    "i",
+    "was",
+    "were",
+    "am",
+    "pm",
+    "have",
+    "has",
+    "had",
+    "been",
+    "doing",
+    "working",
+    "work",
+    "worked",
+    "on",
+    "done",
+    "up",
+    "my",
+    "the",
+    "a",
+    "an",
+    "of",
+    "for",
+    "with",
+    "at",
+    "in",
+    "to",
+    "from",
+    "and",
+    "until",
+    "any",
+    "is",
+    "it",
+    "that",
+    "this",
+    "last",
+    "week",
+    "today",
+    "yesterday",
+    "find",
+    "search",
+    "look",
+    "between",
+    "time",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+    "just",
+    "now",
+    "moment",
+    "minute",
+    "minutes",
+    "few",
+    "ago",
+    "hour",
+    "hours",
+    "day",
+    "days",
+    "weeks",
+    "month",
+    "months",
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+    "jan",
+    "feb",
+    "mar",
+    "apr",
+    "jun",
+    "jul",
+    "aug",
+    "sep",
+    "sept",
+    "oct",
+    "nov",
+    "dec",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QueryIntent {
+    // "what apps did I use today"
+    ListApps,
+    // "how much time did I spend in Slack"
+    GetStats,
+    // "when did I work on the parser", with the meaningful words
+    SearchActivity(Vec<String>),
+    // "what did I do yesterday", with the period as written
+    TimelineQuery(String),
+    // "this week vs last week"
+    ComparePeriods,
+    // Anything that needs the LLM to reason over the summaries
+    FreeformQuery,
+}
+
+// Decides how a query should be answered using keyword matching. Only
+// freeform questions need the LLM; everything else is a database lookup.
+pub struct QueryIntentClassifier;
+
+impl QueryIntentClassifier {
+    pub fn classify(query: &str) -> QueryIntent {
+        let query = query.to_lowercase();
+        let padded = format!(" {} ", query);
+
+        if DIFF_TRIGGERS.iter().any(|trigger| padded.contains(trigger)) {
+            return QueryIntent::ComparePeriods;
+        }
+
+        if LIST_APPS_TRIGGERS.iter().any(|trigger| query.contains(trigger)) {
+            return QueryIntent::ListApps;
+        }
+
+        if AGGREGATE_TRIGGERS.iter().any(|trigger| query.contains(trigger)) {
+            return QueryIntent::GetStats;
+        }
+
+        let words: Vec<&str> = query
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let normalized = format!(" {} ", words.join(" "));
+
+        if FREEFORM_TRIGGERS
+            .iter()
+            .any(|trigger| normalized.contains(&format!(" {} ", trigger)))
+        {
+            return QueryIntent::FreeformQuery;
+        }
+
+        // Clock times like "9am" belong to the period, not the search
+        let terms: Vec<String> = words
+            .iter()
+            .filter(|word| !QUESTION_WORDS.contains(word) && !word.chars().any(|c| c.is_ascii_digit()))
+            .map(|word| word.to_string())
+            .collect();
+
+        match (Self::period(&query), terms.is_empty()) {
+            (_, false) => QueryIntent::SearchActivity(terms),
+            (Some(period), true) => QueryIntent::TimelineQuery(period),
+            (None, true) => QueryIntent::FreeformQuery,
+        }
+    }
+
+    fn period(query: &str) -> Option<String> {
+        if let Some(range) = HourRange::parse(query) {
+            return Some(range.description());
+        }
+
+        if timeframe::is_recent(query) {
+            return Some("just now".to_string());
+        }
+
+        if let Some(description) = timeframe::describe_timeframe(query) {
+            return Some(description);
+        }
+
+        PERIOD_WORDS
+            .iter()
+            .find(|period| query.contains(*period))
+            .map(|period| period.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Share of the labelled queries that must be classified as labelled
+    const MIN_ACCURACY: f64 = 0.95;
+
+    #[derive(Deserialize)]
+    struct LabelledQuery {
+        query: String,
+        intent: QueryIntent,
+    }
+
+    #[test]
+    fn labelled_queries_are_classified_accurately() {
+        let cases: Vec<LabelledQuery> = serde_json::from_str(include_str!("../tests/fixtures/intents.json")).unwrap();
+        assert_eq!(cases.len(), 50);
+
+        let wrong: Vec<String> = cases
+            .iter()
+            .filter_map(|case| {
+                let intent = QueryIntentClassifier::classify(&case.query);
+                (intent != case.intent).then(|| format!("{:?}: {:?}, labelled {:?}", case.query, intent, case.intent))
+            })
+            .collect();
+        let accuracy = 1.0 - wrong.len() as f64 / cases.len() as f64;
+
+        assert!(
+            accuracy >= MIN_ACCURACY,
+            "accuracy {:.2}, misclassified:\n{}",
+            accuracy,
+            wrong.join("\n")
+        );
+    }
+}
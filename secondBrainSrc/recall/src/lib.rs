@@ -0,0 +1,3 @@
+pub mod cache;
+pub mod calendar_export;
+pub mod timeframe;
@@ -0,0 +1,114 @@
+use activity_tracker_common::config::Config;
+use rand::seq::SliceRandom;
+use std::error::Error;
+use std::path::Path;
+
+// How the recall service frames its answers
+#[derive(Debug, Clone)]
+pub struct Persona {
+    pub name: String,
+    pub intro_messages: Vec<String>,
+    pub no_data_messages: Vec<String>,
+    // Prepended to LLM prompts when recall asks the model to phrase an answer
+    pub system_prompt_prefix: String,
+    // Sent when a query takes longer than RECALL_QUERY_TIMEOUT_SECS
+    pub timeout_message: String,
+    // Sent instead of a freeform answer while the health checker reports the LLM down
+    pub llm_unavailable_message: String,
+    // Sent when too many queries are already waiting for an answer
+    pub busy_message: String,
+}
+
+impl Persona {
+    pub fn fishy() -> Self {
+        Self {
+            name: "fishy".to_string(),
+            intro_messages: vec!["Fishy says:".to_string()],
+            no_data_messages: vec!["Fishy says: I don't remember anything matching that query.".to_string()],
+            system_prompt_prefix:
+                "You are Fishy, a friendly fish who remembers everything the user did on their computer.".to_string(),
+            timeout_message: "🐟 Fishy got confused... took too long to think. Try again?".to_string(),
+            llm_unavailable_message: "🐟 Fishy can't reach his brain! Ollama seems to be down.".to_string(),
+            busy_message: "🐟 Fishy is too busy right now, try again in a moment.".to_string(),
+        }
+    }
+
+    pub fn professional() -> Self {
+        Self {
+            name: "professional".to_string(),
+            intro_messages: vec!["Here is a summary of the matching activity:".to_string()],
+            no_data_messages: vec!["No recorded activity matches that query.".to_string()],
+            system_prompt_prefix: "You are a concise, professional assistant that answers questions about the user's recorded computer activity.".to_string(),
+            timeout_message: "The query took too long to answer. Please try again.".to_string(),
+            llm_unavailable_message: "The language model is currently unavailable. Please try again later.".to_string(),
+            busy_message: "Too many queries are in progress. Please try again in a moment.".to_string(),
+        }
+    }
+
+    pub fn minimal() -> Self {
+        Self {
+            name: "minimal".to_string(),
+            intro_messages: Vec::new(),
+            no_data_messages: vec!["No results.".to_string()],
+            system_prompt_prefix: String::new(),
+            timeout_message: "Timed out.".to_string(),
+            llm_unavailable_message: "LLM unavailable.".to_string(),
+            busy_message: "Busy.".to_string(),
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "fishy" => Some(Self::fishy()),
+            "professional" => Some(Self::professional()),
+            "minimal" => Some(Self::minimal()),
+            _ => None,
+        }
+    }
+
+    // Loads the persona from the [persona] section, defaulting to Fishy
+    pub fn load(config_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let config = Config::load(config_path)?.persona;
+
+        let mut persona = match &config.name {
+            Some(name) => Self::builtin(name).unwrap_or_else(|| Self {
+                name: name.clone(),
+                ..Self::fishy()
+            }),
+            None => Self::fishy(),
+        };
+
+        if let Some(intro_messages) = config.intro_messages {
+            persona.intro_messages = intro_messages;
+        }
+        if let Some(no_data_messages) = config.no_data_messages {
+            persona.no_data_messages = no_data_messages;
+        }
+        if let Some(system_prompt_prefix) = config.system_prompt_prefix {
+            persona.system_prompt_prefix = system_prompt_prefix;
+        }
+        if let Some(timeout_message) = config.timeout_message {
+            persona.timeout_message = timeout_message;
+        }
+        if let Some(llm_unavailable_message) = config.llm_unavailable_message {
+            persona.llm_unavailable_message = llm_unavailable_message;
+        }
+        if let Some(busy_message) = config.busy_message {
+            persona.busy_message = busy_message;
+        }
+
+        Ok(persona)
+    }
+
+    pub fn random_intro(&self) -> String {
+        Self::pick(&self.intro_messages)
+    }
+
+    pub fn random_no_data(&self) -> String {
+        Self::pick(&self.no_data_messages)
+    }
+
+    fn pick(messages: &[String]) -> String {
+        messages.choose(&mut rand::thread_rng()).cloned().unwrap_or_default()
+    }
+}
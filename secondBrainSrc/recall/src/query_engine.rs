@@ -1,67 +1,1185 @@
+use crate::app_matcher::SimilarityMatcher;
+use crate::calendar::{self, CalendarEvent};
+use crate::intent::{QueryIntent, QueryIntentClassifier, DIFF_TRIGGERS};
+use crate::ranking::{self, ScoredSummary};
+use crate::router::RecallRouter;
+use crate::server::Answerer;
 use activity_tracker_common::{
-    ActivitySummary,
-    db::{GeneralDbClient, SummaryStore}
+    db::{
+        self, EventStore, GeneralDbClient, SummaryStore, TimescaleClient, APP_CORRELATIONS_QUERY,
+        APP_DWELL_TIMES_QUERY, DAILY_SUMMARY_QUERY, EVENTS_BY_APP_QUERY, EVENTS_IN_TIMEFRAME_QUERY,
+        SUMMARIES_IN_TIMEFRAME_QUERY, TASKS_IN_TIMEFRAME_QUERY, TOP_APP_NAMES_QUERY, WAYPOINTS_QUERY,
+        WINDOW_TITLE_SEARCH_QUERY,
+    },
+    memory,
+    privacy::{self, AuditEntry},
+    ActivitySummary, AppCorrelation, Suggestion, Task, UserEvent, WaypointEvent, WindowTitleHit,
 };
-use chrono::{DateTime, Duration, Utc};
+use activity_tracker_recall::timeframe::{self, HourRange};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::error::Error;
+use std::path::PathBuf;
+use std::pin::pin;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tokio::time::{timeout_at, Instant};
+
+// Phrases that suggest the user is asking about something they had on screen
+const WINDOW_TITLE_TRIGGERS: [&str; 7] = [
+    "looking at",
+    "opened",
+    "open",
+    "browsed to",
+    "browsing",
+    "visited",
+    "reading",
+];
+// Phrases that are followed by an application name
+const APP_NAME_TRIGGERS: [&str; 3] = ["using", "used", "in"];
+// Longest app name, in words, looked for after an app trigger, e.g. "visual studio code"
+const MAX_APP_NAME_WORDS: usize = 3;
+// App names mentioned in a query are matched against the most used apps in this period
+const KNOWN_APPS_LOOKBACK_DAYS: i64 = 30;
+const KNOWN_APPS_LIMIT: i64 = 50;
+// Words that carry no search meaning
+const FILLER_WORDS: [&str; 19] = [
+    "the",
+    "a",
+    "an",
+    "any",
+    "my",
+    "some",
+    "i",
+    "was",
+    "did",
+    "today",
+    "yesterday",
+    "this",
+    "last",
+    "week",
+    "month",
+    "hour",
+    "on",
+    "at",
+    "to",
+];
+
+// Batched queries about different periods looked up at once
+pub const MAX_CONCURRENT_QUERIES: usize = 4;
+
+// Gaps longer than this between events count as idle time
+const IDLE_GAP_MINUTES: i64 = 5;
+
+// The learner answers from memory, so anything slower means it's stuck
+const SHORT_TERM_MEMORY_TIMEOUT_MS: u64 = 500;
+
+// Searched for when a query has no usable terms
+const FTS_FALLBACK_QUERY: &str = "user activity";
+
+// Words that mean the user is asking about detected tasks
+const TASK_WORDS: [&str; 2] = ["task", "tasks"];
+
+// Phrases asking which apps go with another, e.g. "what do I use with VSCode".
+// Matched against the query padded with spaces; the app follows the phrase.
+const CORRELATION_TRIGGERS: [&str; 5] = [
+    " use with ",
+    " used with ",
+    " alongside ",
+    " together with ",
+    " along with ",
+];
+
+// Most apps listed as usually used with another
+const CORRELATIONS_LIMIT: i64 = 10;
+
+// Words asking about how sessions felt, with the summary tag the Thinker
+// gives such sessions. Matched against the query padded with spaces, so
+// " frustrat" covers frustrated, frustrating and frustration.
+const SENTIMENT_WORDS: [(&str, &str); 4] = [
+    (" frustrat", "sentiment:frustrated"),
+    (" focused ", "sentiment:focused"),
+    (" flow ", "sentiment:flow"),
+    (" in the zone ", "sentiment:flow"),
+];
+
+// Words that mean the user is asking about a particular monitor
+const MONITOR_WORDS: [&str; 5] = ["monitor", "screen", "display", "monitors", "screens"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SearchIntent {
+    WindowTitle(String),
+    AppName(String),
+    Monitor(MonitorRef),
+    // A summary tag such as "sentiment:frustrated"
+    Sentiment(String),
+    General,
+}
+
+// Which monitor a query refers to. Monitors are numbered left to right.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MonitorRef {
+    Index(u8),
+    Leftmost,
+    Rightmost,
+}
+
+pub use activity_tracker_recall::timeframe::Timeframe;
+
+// Per-app event counts and estimated active minutes over a period
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppStats {
+    pub events: HashMap<String, u64>,
+    pub minutes: HashMap<String, f64>,
+}
+
+impl AppStats {
+    pub fn total_events(&self) -> u64 {
+        self.events.values().sum()
+    }
+
+    pub fn total_minutes(&self) -> f64 {
+        self.minutes.values().sum()
+    }
+}
+
+// Serialized with a "type" tag, e.g. {"type": "summaries", "data": [...]}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum QueryResult {
+    Summaries(Vec<ActivitySummary>),
+    WindowTitles(Vec<WindowTitleHit>),
+    // Computed directly from events without involving the LLM
+    Statistics {
+        stats: HashMap<String, serde_json::Value>,
+        timeframe: Timeframe,
+        query: String,
+    },
+    // Tasks detected by the thinker, oldest first
+    Tasks {
+        tasks: Vec<Task>,
+        timeframe: Timeframe,
+    },
+    // Two periods side by side; delta is period A minus period B in minutes
+    Diff {
+        period_a: Timeframe,
+        period_b: Timeframe,
+        stats_a: AppStats,
+        stats_b: AppStats,
+        delta: HashMap<String, f64>,
+    },
+    // Apps usually used in the same hours as `app`, strongest first
+    Correlations {
+        app: String,
+        correlations: Vec<AppCorrelation>,
+    },
+}
+
+impl QueryResult {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            QueryResult::Summaries(_) => "summaries",
+            QueryResult::WindowTitles(_) => "window_titles",
+            QueryResult::Statistics { .. } => "statistics",
+            QueryResult::Tasks { .. } => "tasks",
+            QueryResult::Diff { .. } => "diff",
+            QueryResult::Correlations { .. } => "correlations",
+        }
+    }
+}
+
+// A query's result and, when it's a list of summaries, how many there are
+// in all. Recent summaries come a page at a time, so there can be more than
+// the result holds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryAnswer {
+    #[serde(flatten)]
+    pub result: QueryResult,
+    pub total_count: Option<u64>,
+}
+
+// Every summary there is, for results that aren't one page of many
+impl From<QueryResult> for QueryAnswer {
+    fn from(result: QueryResult) -> Self {
+        let total_count = match &result {
+            QueryResult::Summaries(summaries) => Some(summaries.len() as u64),
+            _ => None,
+        };
+
+        Self { result, total_count }
+    }
+}
+
+// Where the answer to a query comes from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DataSource {
+    // Summaries looked up by time
+    SummaryDb,
+    // Raw events, e.g. for statistics and window titles
+    EventDb,
+    // Summaries matched by text
+    FtsSearch,
+}
+
+// How a query was interpreted, for users puzzled by an answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryExplanation {
+    pub intent: QueryIntent,
+    pub timeframe: Timeframe,
+    pub app_filter: Option<String>,
+    pub data_source: DataSource,
+    // SQL that answering the query runs, with placeholders instead of values
+    pub sql_queries: Vec<String>,
+    // A default period was assumed, or a search found nothing and a broader one was used
+    pub fallback_used: bool,
+}
+
+// A period's summaries, fetched by the first query in a batch that needs them
+pub(crate) type SharedSummaries = OnceCell<Vec<ActivitySummary>>;
+
+// Batched queries about the same period to the minute share a fetch, since
+// "today" parsed twice a few milliseconds apart ends at a different instant
+fn timeframe_key(timeframe: Timeframe) -> (i64, i64) {
+    (timeframe.0.timestamp() / 60, timeframe.1.timestamp() / 60)
+}
 
 #[derive(Clone)]
 pub struct QueryEngine {
-    db_client: GeneralDbClient
+    db_client: GeneralDbClient,
+    events_db: TimescaleClient,
+    app_matcher: Arc<SimilarityMatcher>,
+    calendar: Arc<Vec<CalendarEvent>>,
+    // Where the learner shares the events it hasn't had summarized yet
+    short_term_socket: PathBuf,
+    router: Arc<RecallRouter>,
+    // Puts batch results into words, as the server does for single queries
+    answerer: Option<Answerer>,
 }
 
 impl QueryEngine {
-    pub fn new(db_client: GeneralDbClient) -> Self {
-        Self { db_client }
+    pub fn new(db_client: GeneralDbClient, events_db: TimescaleClient) -> Self {
+        let app_matcher = Arc::new(SimilarityMatcher::default());
+        let calendar = Arc::new(calendar::load_calendar());
+
+        Self {
+            db_client,
+            events_db,
+            router: Arc::new(RecallRouter::new(app_matcher.clone(), calendar.clone())),
+            app_matcher,
+            calendar,
+            short_term_socket: memory::socket_path(),
+            answerer: None,
+        }
     }
 
-    pub async fn process_query(&self, query: &str) -> Result<Vec<ActivitySummary>, Box<dyn Error>> {
+    pub fn with_answerer(mut self, answerer: Answerer) -> Self {
+        self.answerer = Some(answerer);
+        self
+    }
+
+    // Suggestions the thinker queued that haven't been shown, marked as shown
+    pub async fn take_pending_suggestions(&self) -> Result<Vec<Suggestion>, Box<dyn Error>> {
+        self.db_client.take_unshown_suggestions().await
+    }
+
+    pub async fn process_query(&self, query: &str) -> Result<QueryAnswer, Box<dyn Error>> {
+        self.process_query_sharing(query, None).await
+    }
+
+    // Answers the queries in the order given, each in words. The batch as a
+    // whole gets the answerer's batch timeout, and each query the query
+    // timeout within that; a query left without time gets the timeout message.
+    pub async fn process_batch(&self, queries: &[String]) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let answerer = self
+            .answerer
+            .as_ref()
+            .ok_or("No answerer to put batch results into words")?;
+        let deadline = Instant::now() + answerer.batch_timeout;
+
+        let Ok(results) = timeout_at(deadline, self.resolve_batch(queries)).await else {
+            eprintln!(
+                "⚠️ Batch of {} queries timed out before any was answered",
+                queries.len()
+            );
+            return Ok(queries
+                .iter()
+                .map(|query| (query.clone(), answerer.persona.timeout_message.clone()))
+                .collect());
+        };
+
+        let mut answers = Vec::with_capacity(results.len());
+        for (query, result) in results {
+            let response = answerer.answer(&query, result, self, deadline).await;
+            answers.push((query, response));
+        }
+
+        Ok(answers)
+    }
+
+    // Queries about the same period share one fetch of its summaries, and up
+    // to MAX_CONCURRENT_QUERIES periods are looked up at once. Each query
+    // fails on its own.
+    async fn resolve_batch(&self, queries: &[String]) -> Vec<(String, Result<QueryAnswer, String>)> {
+        // Indexes into `queries`, one group per period. Queries without a
+        // period have nothing to share and get a group each.
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut group_by_timeframe: HashMap<(i64, i64), usize> = HashMap::new();
+        for (i, query) in queries.iter().enumerate() {
+            match self.parse_time_query(query).map(timeframe_key) {
+                Some(key) => {
+                    let group = *group_by_timeframe.entry(key).or_insert_with(|| {
+                        groups.push(Vec::new());
+                        groups.len() - 1
+                    });
+                    groups[group].push(i);
+                }
+                None => groups.push(vec![i]),
+            }
+        }
+
+        let mut answered = futures::stream::iter(groups)
+            .map(|group| async move {
+                let shared = SharedSummaries::new();
+                let mut results = Vec::new();
+                for i in group {
+                    // Box<dyn Error> isn't Send, so drop it before any further awaits
+                    let result = self
+                        .process_query_sharing(&queries[i], Some(&shared))
+                        .await
+                        .map_err(|e| e.to_string());
+                    results.push((i, result));
+                }
+                results
+            })
+            .buffer_unordered(MAX_CONCURRENT_QUERIES);
+
+        let mut results: Vec<Option<Result<QueryAnswer, String>>> = vec![None; queries.len()];
+        while let Some(group_results) = answered.next().await {
+            for (i, result) in group_results {
+                results[i] = Some(result);
+            }
+        }
+
+        queries
+            .iter()
+            .cloned()
+            .zip(
+                results
+                    .into_iter()
+                    .map(|result| result.unwrap_or_else(|| Err("query was not answered".to_string()))),
+            )
+            .collect()
+    }
+
+    async fn process_query_sharing(
+        &self,
+        query: &str,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        let answer = self.resolve_query(query, shared).await?;
+
+        privacy::record(AuditEntry::QueryProcessed {
+            query_text_hash: privacy::hash_query(query),
+            timeframe: self.parse_time_query(query),
+            result_type: answer.result.kind().to_string(),
+        });
+
+        Ok(answer)
+    }
+
+    // Only freeform questions end up needing the LLM; every other kind of
+    // query is answered straight from the databases
+    async fn resolve_query(
+        &self,
+        query: &str,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        self.router.route(query).handle(query, self, shared).await
+    }
+
+    // Apps usually used in the same hours as the one named
+    pub(crate) async fn correlations(&self, app_name: &str) -> Result<QueryResult, Box<dyn Error>> {
+        let app = self.resolve_app_name(app_name, None).await?;
+
+        Ok(QueryResult::Correlations {
+            correlations: self
+                .events_db
+                .get_app_correlations(Some(&app), CORRELATIONS_LIMIT)
+                .await?,
+            app,
+        })
+    }
+
+    pub async fn mark_waypoint(&self, waypoint: &WaypointEvent) -> Result<(), Box<dyn Error>> {
+        self.events_db.store_event(UserEvent::waypoint(waypoint)).await
+    }
+
+    // The summaries with the waypoints marked in the range added as
+    // summaries of their own, in time order
+    pub(crate) async fn with_waypoints(
+        &self,
+        mut summaries: Vec<ActivitySummary>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ActivitySummary>, Box<dyn Error>> {
+        for (timestamp, waypoint) in self.events_db.get_waypoints(start, end).await? {
+            let mut description = format!("You marked '{}' at {}", waypoint.title, timestamp.format("%H:%M"));
+            if let Some(note) = &waypoint.note {
+                description.push_str(&format!(": {}", note));
+            }
+
+            summaries.push(ActivitySummary {
+                start_time: timestamp,
+                end_time: timestamp,
+                description,
+                events: Vec::new(),
+                tags: waypoint
+                    .category
+                    .iter()
+                    .map(|category| format!("waypoint:{}", category))
+                    .collect(),
+            });
+        }
+
+        summaries.sort_by_key(|summary| summary.start_time);
+        Ok(summaries)
+    }
+
+    pub(crate) async fn tasks(&self, timeframe: Timeframe) -> Result<QueryResult, Box<dyn Error>> {
+        Ok(QueryResult::Tasks {
+            tasks: self.db_client.get_tasks_in_timeframe(timeframe.0, timeframe.1).await?,
+            timeframe,
+        })
+    }
+
+    // The day's rollup as a single summary spanning the day, if it has been
+    // rolled up with a description
+    pub(crate) async fn daily_summary(&self, day: NaiveDate) -> Result<Option<ActivitySummary>, Box<dyn Error>> {
+        let Some(daily) = self.events_db.get_daily_summary(day).await? else {
+            return Ok(None);
+        };
+        let Some((start_time, end_time)) = timeframe::day_timeframe(day) else {
+            return Ok(None);
+        };
+        if daily.description.is_empty() {
+            return Ok(None);
+        }
+
+        let mut description = daily.description;
+        if let Some(highlight) = daily.highlight {
+            description.push_str(&format!(" Highlight: {}", highlight));
+        }
+        if !daily.top_apps.is_empty() {
+            description.push_str(&format!(" Most used: {}.", daily.top_apps.join(", ")));
+        }
+
+        Ok(Some(ActivitySummary {
+            start_time,
+            end_time,
+            description,
+            events: Vec::new(),
+            tags: Vec::new(),
+        }))
+    }
+
+    // The learner's events in the range as a single summary, when the range
+    // is recent enough for its short-term memory to hold all of it. None when
+    // the learner isn't running or has nothing, so the databases are asked.
+    pub(crate) async fn short_term_summary(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Option<ActivitySummary> {
+        if start < Utc::now() - Duration::minutes(timeframe::RECENT_MINUTES) {
+            return None;
+        }
+
+        let fetch = memory::fetch_recent_events(&self.short_term_socket, start);
+        let timeout = std::time::Duration::from_millis(SHORT_TERM_MEMORY_TIMEOUT_MS);
+        let events = tokio::time::timeout(timeout, fetch).await.ok()?.ok()?;
+
+        unsummarized_summary(events.into_iter().filter(|event| event.timestamp <= end).collect())
+    }
+
+    pub(crate) async fn search_activity(
+        &self,
+        query: &str,
+        terms: &[String],
+        time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        match self.extract_search_intent(query) {
+            SearchIntent::WindowTitle(pattern) => {
+                // Default to the last day when no time period was mentioned
+                let (start, end) = time_range.unwrap_or_else(|| {
+                    let now = Utc::now();
+                    (now - Duration::days(1), now)
+                });
+
+                let hits = self.events_db.search_window_titles(&pattern, start, end).await?;
+                if !hits.is_empty() {
+                    return Ok(QueryResult::WindowTitles(hits).into());
+                }
+            }
+            SearchIntent::AppName(app_name) => {
+                return Ok(self.app_activity(query, &app_name, time_range, shared).await?.into());
+            }
+            SearchIntent::Monitor(monitor) => {
+                let (start, end) = time_range.unwrap_or_else(|| {
+                    let now = Utc::now();
+                    (now - Duration::days(1), now)
+                });
+                let summaries = self.summaries_in_timeframe(start, end, shared).await?;
+
+                return Ok(QueryResult::Summaries(Self::filter_by_monitor(summaries, &monitor)).into());
+            }
+            SearchIntent::Sentiment(tag) => {
+                // "were there any frustrating sessions" is usually about today
+                let (start, end) = time_range.unwrap_or_else(|| {
+                    let now = Utc::now();
+                    (now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc(), now)
+                });
+                let summaries = self.summaries_in_timeframe(start, end, shared).await?;
+
+                return Ok(
+                    QueryResult::Summaries(summaries.into_iter().filter(|s| s.tags.contains(&tag)).collect()).into(),
+                );
+            }
+            SearchIntent::General => {}
+        }
+
+        // Search the terms themselves rather than the whole question
+        let (summaries, total) = match time_range {
+            Some(_) => self.search_summaries_counted(query, time_range, shared).await?,
+            None => self.db_client.search_summaries_counted(&terms.join(" ")).await?,
+        };
+
+        Ok(QueryAnswer {
+            result: QueryResult::Summaries(summaries),
+            total_count: Some(total),
+        })
+    }
+
+    // Summaries matching the query that have events in the named app
+    pub(crate) async fn app_activity(
+        &self,
+        query: &str,
+        app_name: &str,
+        time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<QueryResult, Box<dyn Error>> {
+        let summaries = self.search_summaries(query, time_range, shared).await?;
+        let app_name = self.resolve_app_name(app_name, time_range).await?.to_lowercase();
+
+        // With a period, the event database finds when the app was used
+        // rather than every summary's events being checked
+        if let Some((start, end)) = time_range {
+            let used_at: Vec<DateTime<Utc>> = self
+                .events_db
+                .get_events_by_app_name(start, end, &app_name)
+                .await?
+                .into_iter()
+                .map(|event| event.timestamp)
+                .collect();
+
+            return Ok(QueryResult::Summaries(
+                summaries
+                    .into_iter()
+                    .filter(|s| {
+                        let first_after_start = used_at.partition_point(|t| *t < s.start_time);
+                        used_at.get(first_after_start).is_some_and(|t| *t <= s.end_time)
+                    })
+                    .collect(),
+            ));
+        }
+
+        Ok(QueryResult::Summaries(
+            summaries
+                .into_iter()
+                .filter(|s| {
+                    s.events
+                        .iter()
+                        .any(|e| e.app_context.app_name.to_lowercase().contains(&app_name))
+                })
+                .collect(),
+        ))
+    }
+
+    // Follows the same path as resolve_query without fetching the answer
+    pub async fn explain_query(&self, query: &str) -> Result<QueryExplanation, Box<dyn Error>> {
+        let time_range = self.parse_time_query(query);
+        let intent = QueryIntentClassifier::classify(query);
+        let now = Utc::now();
+        let today = (now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc(), now);
+        let last_day = (now - Duration::days(1), now);
+
+        let mut explanation = QueryExplanation {
+            intent: intent.clone(),
+            timeframe: time_range.unwrap_or(today),
+            app_filter: None,
+            data_source: DataSource::EventDb,
+            sql_queries: Vec::new(),
+            fallback_used: time_range.is_none(),
+        };
+        let app_stats_sql = vec![EVENTS_IN_TIMEFRAME_QUERY.to_string(), APP_DWELL_TIMES_QUERY.to_string()];
+
+        if intent == QueryIntent::ComparePeriods {
+            let (text_a, text_b) = Self::split_comparison(&query.to_lowercase());
+            let (period_a, period_b) = self.comparison_periods(query);
+
+            explanation.timeframe = (period_a.0.min(period_b.0), period_a.1.max(period_b.1));
+            explanation.sql_queries = app_stats_sql;
+            explanation.fallback_used =
+                self.parse_comparison_period(&text_a).is_none() || self.parse_comparison_period(&text_b).is_none();
+            return Ok(explanation);
+        }
+
+        if let Some(app_name) = Self::extract_correlation_app(query) {
+            explanation.app_filter = Some(self.resolve_app_name(&app_name, None).await?);
+            explanation.sql_queries = vec![TOP_APP_NAMES_QUERY.to_string(), APP_CORRELATIONS_QUERY.to_string()];
+            explanation.fallback_used = false;
+            return Ok(explanation);
+        }
+
+        if Self::is_task_query(query) {
+            explanation.data_source = DataSource::SummaryDb;
+            explanation.sql_queries = vec![
+                TASKS_IN_TIMEFRAME_QUERY.to_string(),
+                SUMMARIES_IN_TIMEFRAME_QUERY.to_string(),
+            ];
+            return Ok(explanation);
+        }
+
+        match &intent {
+            QueryIntent::ListApps | QueryIntent::GetStats => {
+                explanation.sql_queries = app_stats_sql;
+                return Ok(explanation);
+            }
+            QueryIntent::TimelineQuery(_) => {
+                if let Some(day) = timeframe::parse_day(query) {
+                    if self.daily_summary(day).await?.is_some() {
+                        explanation.timeframe = timeframe::day_timeframe(day).unwrap_or(last_day);
+                        explanation.sql_queries = vec![DAILY_SUMMARY_QUERY.to_string(), WAYPOINTS_QUERY.to_string()];
+                        return Ok(explanation);
+                    }
+                }
+
+                explanation.timeframe = time_range.unwrap_or(last_day);
+                explanation.data_source = DataSource::SummaryDb;
+                explanation.sql_queries = vec![SUMMARIES_IN_TIMEFRAME_QUERY.to_string(), WAYPOINTS_QUERY.to_string()];
+                return Ok(explanation);
+            }
+            QueryIntent::SearchActivity(_) => match self.extract_search_intent(query) {
+                SearchIntent::WindowTitle(pattern) => {
+                    let (start, end) = time_range.unwrap_or(last_day);
+                    explanation.sql_queries.push(WINDOW_TITLE_SEARCH_QUERY.to_string());
+
+                    if !self
+                        .events_db
+                        .search_window_titles(&pattern, start, end)
+                        .await?
+                        .is_empty()
+                    {
+                        explanation.timeframe = (start, end);
+                        explanation.fallback_used = false;
+                        return Ok(explanation);
+                    }
+                    // No matching titles, so the summaries are searched instead
+                    explanation.fallback_used = true;
+                }
+                SearchIntent::AppName(app_name) => {
+                    explanation.sql_queries.push(TOP_APP_NAMES_QUERY.to_string());
+                    if time_range.is_some() {
+                        explanation.sql_queries.push(EVENTS_BY_APP_QUERY.to_string());
+                    }
+                    explanation.app_filter = Some(self.resolve_app_name(&app_name, time_range).await?);
+                }
+                SearchIntent::Monitor(_) => {
+                    explanation.timeframe = time_range.unwrap_or(last_day);
+                    explanation.data_source = DataSource::SummaryDb;
+                    explanation.sql_queries = vec![SUMMARIES_IN_TIMEFRAME_QUERY.to_string()];
+                    return Ok(explanation);
+                }
+                SearchIntent::Sentiment(_) => {
+                    explanation.data_source = DataSource::SummaryDb;
+                    explanation.sql_queries = vec![SUMMARIES_IN_TIMEFRAME_QUERY.to_string()];
+                    return Ok(explanation);
+                }
+                SearchIntent::General => {}
+            },
+            QueryIntent::ComparePeriods | QueryIntent::FreeformQuery => {}
+        }
+
+        // Everything else ends in a summary lookup: by time if a period was
+        // given, otherwise by text
+        match time_range {
+            Some(time_range) => {
+                explanation.timeframe = time_range;
+                explanation.data_source = DataSource::SummaryDb;
+                explanation.sql_queries.push(SUMMARIES_IN_TIMEFRAME_QUERY.to_string());
+                explanation.fallback_used = false;
+            }
+            None => {
+                let search = match (&intent, &explanation.app_filter) {
+                    (QueryIntent::SearchActivity(terms), None) => terms.join(" "),
+                    _ => self.sanitize_query_for_fts(query),
+                };
+
+                explanation.timeframe = (DateTime::<Utc>::UNIX_EPOCH, now);
+                explanation.data_source = DataSource::FtsSearch;
+                explanation.sql_queries.push(db::summary_search_sql(&search));
+                explanation.fallback_used |= search == FTS_FALLBACK_QUERY;
+            }
+        }
+
+        Ok(explanation)
+    }
+
+    pub(crate) async fn search_summaries(
+        &self,
+        query: &str,
+        time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<Vec<ActivitySummary>, Box<dyn Error>> {
+        Ok(self.search_summaries_counted(query, time_range, shared).await?.0)
+    }
+
+    // search_summaries along with how many summaries match in all, which is
+    // more than it returns when only the recent ones are
+    pub(crate) async fn search_summaries_counted(
+        &self,
+        query: &str,
+        time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<(Vec<ActivitySummary>, u64), Box<dyn Error>> {
+        let all = |summaries: Vec<ActivitySummary>| {
+            let total = summaries.len() as u64;
+            (summaries, total)
+        };
+
         // If it's a time-based query, handle it directly
-        if let Some(time_range) = self.parse_time_query(query) {
-            return self.db_client.get_summaries_in_timeframe(time_range.0, time_range.1).await;
+        if let Some(time_range) = time_range {
+            return Ok(all(self
+                .summaries_in_timeframe(time_range.0, time_range.1, shared)
+                .await?));
         }
-        
-        // Otherwise, sanitize the query and perform a search
+
+        // Otherwise, sanitize the query and perform a search. With more than
+        // one term, the best matches come first rather than the newest.
         let clean_query = self.sanitize_query_for_fts(query);
-        self.db_client.search_summaries(&clean_query).await
+        if clean_query.split_whitespace().count() >= 2 {
+            let ranked = self.ranked_search(query).await?;
+            return Ok(all(ranked.into_iter().map(|scored| scored.summary).collect()));
+        }
+
+        self.db_client.search_summaries_counted(&clean_query).await
+    }
+
+    // Summaries in the period. In a batch, the first query about the period
+    // fetches them and the rest reuse that.
+    pub(crate) async fn summaries_in_timeframe(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<Vec<ActivitySummary>, Box<dyn Error>> {
+        match shared {
+            Some(shared) => shared
+                .get_or_try_init(|| self.db_client.get_summaries_in_timeframe(start, end))
+                .await
+                .cloned(),
+            None => self.db_client.get_summaries_in_timeframe(start, end).await,
+        }
+    }
+
+    // Summaries matching any of the query's terms, best match first
+    pub async fn ranked_search(&self, query: &str) -> Result<Vec<ScoredSummary>, Box<dyn Error>> {
+        let clean_query = self.sanitize_query_for_fts(query).to_lowercase();
+        let summaries = self.db_client.search_summaries(&clean_query).await?;
+
+        let terms: Vec<&str> = clean_query.split_whitespace().collect();
+        Ok(ranking::rank(summaries, &terms))
+    }
+
+    pub fn is_task_query(query: &str) -> bool {
+        query
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| TASK_WORDS.contains(&word))
+    }
+
+    // Per-app event counts and active time, streamed from the event database.
+    // Time between two events is credited to the app of the first one unless
+    // the gap is long enough to count as idle.
+    async fn compute_app_stats(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<AppStats, Box<dyn Error>> {
+        let mut stats = AppStats::default();
+        let mut previous: Option<(DateTime<Utc>, String)> = None;
+
+        let mut events = pin!(self.events_db.stream_events_in_timeframe(start, end));
+        while let Some(event) = events.next().await {
+            let event = event?;
+            let app_name = event.app_context.app_name;
+
+            *stats.events.entry(app_name.clone()).or_insert(0) += 1;
+
+            if let Some((previous_time, previous_app)) = previous {
+                let gap = event.timestamp - previous_time;
+                if gap <= Duration::minutes(IDLE_GAP_MINUTES) {
+                    *stats.minutes.entry(previous_app).or_insert(0.0) += gap.num_seconds() as f64 / 60.0;
+                }
+            }
+
+            previous = Some((event.timestamp, app_name));
+        }
+
+        // Recorded dwell times beat estimates from event gaps, which miss
+        // time spent reading or pasting. The app currently in focus has no
+        // dwell time yet and keeps its estimate.
+        for (app_name, dwell_time_ms) in self.events_db.get_app_dwell_times(start, end).await? {
+            stats.events.entry(app_name.clone()).or_insert(0);
+            stats.minutes.insert(app_name, dwell_time_ms as f64 / 60_000.0);
+        }
+
+        Ok(stats)
+    }
+
+    pub(crate) async fn compute_statistics(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<HashMap<String, serde_json::Value>, Box<dyn Error>> {
+        let app_stats = self.compute_app_stats(start, end).await?;
+
+        let most_used_app = app_stats
+            .minutes
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(app, _)| app.clone())
+            .or_else(|| {
+                app_stats
+                    .events
+                    .iter()
+                    .max_by_key(|(_, count)| **count)
+                    .map(|(app, _)| app.clone())
+            });
+
+        let apps: serde_json::Map<String, serde_json::Value> = app_stats
+            .events
+            .iter()
+            .map(|(app, count)| {
+                let minutes = app_stats.minutes.get(app).copied().unwrap_or(0.0);
+                (
+                    app.clone(),
+                    serde_json::json!({ "events": count, "active_minutes": minutes }),
+                )
+            })
+            .collect();
+
+        let mut stats = HashMap::new();
+        stats.insert("total_events".to_string(), serde_json::json!(app_stats.total_events()));
+        stats.insert(
+            "active_hours".to_string(),
+            serde_json::json!(app_stats.total_minutes() / 60.0),
+        );
+        stats.insert("most_used_app".to_string(), serde_json::json!(most_used_app));
+        stats.insert("apps".to_string(), serde_json::Value::Object(apps));
+
+        Ok(stats)
+    }
+
+    // Splits "this week compared to last week" into the text describing each period
+    fn split_comparison(query_lower: &str) -> (String, String) {
+        if let Some(rest) = query_lower.split_once("difference between").map(|(_, rest)| rest) {
+            if let Some((a, b)) = rest.split_once(" and ") {
+                return (a.to_string(), b.to_string());
+            }
+        }
+
+        let padded = format!(" {} ", query_lower);
+        DIFF_TRIGGERS
+            .iter()
+            .find_map(|trigger| padded.split_once(trigger))
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .unwrap_or_else(|| (query_lower.to_string(), String::new()))
+    }
+
+    fn comparison_periods(&self, query: &str) -> (Timeframe, Timeframe) {
+        let (text_a, text_b) = Self::split_comparison(&query.to_lowercase());
+        let now = Utc::now();
+
+        let period_a = self
+            .parse_comparison_period(&text_a)
+            .unwrap_or((now - Duration::days(7), now));
+        // "this week vs last" or an unrecognized period means the period just before
+        let period_b = self.parse_comparison_period(&text_b).unwrap_or_else(|| {
+            let length = period_a.1 - period_a.0;
+            (period_a.0 - length, period_a.0)
+        });
+
+        (period_a, period_b)
+    }
+
+    pub(crate) async fn compute_diff(&self, query: &str) -> Result<QueryResult, Box<dyn Error>> {
+        let (period_a, period_b) = self.comparison_periods(query);
+
+        let stats_a = self.compute_app_stats(period_a.0, period_a.1).await?;
+        let stats_b = self.compute_app_stats(period_b.0, period_b.1).await?;
+
+        let mut delta: HashMap<String, f64> = HashMap::new();
+        for app in stats_a.events.keys().chain(stats_b.events.keys()) {
+            let minutes_a = stats_a.minutes.get(app).copied().unwrap_or(0.0);
+            let minutes_b = stats_b.minutes.get(app).copied().unwrap_or(0.0);
+            delta.insert(app.clone(), minutes_a - minutes_b);
+        }
+        delta.insert("total".to_string(), stats_a.total_minutes() - stats_b.total_minutes());
+
+        Ok(QueryResult::Diff {
+            period_a,
+            period_b,
+            stats_a,
+            stats_b,
+            delta,
+        })
+    }
+
+    // Keep only summaries with activity on the given monitor, trimmed to that monitor's events
+    fn filter_by_monitor(summaries: Vec<ActivitySummary>, monitor: &MonitorRef) -> Vec<ActivitySummary> {
+        let screen_indexes = summaries
+            .iter()
+            .flat_map(|s| s.events.iter())
+            .filter_map(|e| e.app_context.screen_index);
+
+        let target = match monitor {
+            MonitorRef::Index(i) => Some(*i),
+            MonitorRef::Leftmost => Some(0),
+            MonitorRef::Rightmost => screen_indexes.max(),
+        };
+
+        let Some(target) = target else {
+            return Vec::new();
+        };
+
+        summaries
+            .into_iter()
+            .filter_map(|mut s| {
+                s.events.retain(|e| e.app_context.screen_index == Some(target));
+                (!s.events.is_empty()).then_some(s)
+            })
+            .collect()
+    }
+
+    // Decide whether the query is about window titles, a specific app, a monitor, or general activity
+    pub fn extract_search_intent(&self, query: &str) -> SearchIntent {
+        Self::search_intent(query, &self.app_matcher)
+    }
+
+    pub(crate) fn search_intent(query: &str, app_matcher: &SimilarityMatcher) -> SearchIntent {
+        let query_lower = query
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '.')
+            .collect::<String>();
+
+        if let Some(monitor) = Self::extract_monitor(&query_lower) {
+            return SearchIntent::Monitor(monitor);
+        }
+
+        if let Some(tag) = Self::extract_sentiment(&query_lower) {
+            return SearchIntent::Sentiment(tag.to_string());
+        }
+
+        for trigger in WINDOW_TITLE_TRIGGERS {
+            if let Some(rest) = Self::text_after_phrase(&query_lower, trigger) {
+                let terms = Self::meaningful_words(rest);
+                if !terms.is_empty() {
+                    // Join with % so ILIKE matches the terms in order with anything between
+                    return SearchIntent::WindowTitle(terms.join("%"));
+                }
+            }
+        }
+
+        for trigger in APP_NAME_TRIGGERS {
+            if let Some(rest) = Self::text_after_phrase(&query_lower, trigger) {
+                let words = Self::meaningful_words(rest);
+
+                // Prefer the longest run of words that names a known alias,
+                // so "vs code" isn't cut down to "vs"
+                let longest = words.len().min(MAX_APP_NAME_WORDS);
+                let alias = (1..=longest)
+                    .rev()
+                    .find_map(|n| app_matcher.match_app(&words[..n].join(" "), &[]).map(|(app, _)| app));
+
+                if let Some(app_name) = alias.or_else(|| words.first().map(|w| w.to_string())) {
+                    return SearchIntent::AppName(app_name);
+                }
+            }
+        }
+
+        SearchIntent::General
+    }
+
+    // The name an app mentioned in a query was recorded under, or the
+    // mention itself when nothing recorded is close enough
+    async fn resolve_app_name(&self, app_name: &str, time_range: Option<Timeframe>) -> Result<String, Box<dyn Error>> {
+        let (start, end) = time_range.unwrap_or_else(|| {
+            let now = Utc::now();
+            (now - Duration::days(KNOWN_APPS_LOOKBACK_DAYS), now)
+        });
+        let known_apps = self.events_db.get_top_app_names(start, end, KNOWN_APPS_LIMIT).await?;
+
+        Ok(match self.app_matcher.match_app(app_name, &known_apps) {
+            Some((app, _)) => app,
+            None => app_name.to_string(),
+        })
+    }
+
+    // The app named after a phrase like "use with", as written
+    pub(crate) fn extract_correlation_app(query: &str) -> Option<String> {
+        let padded = format!(" {} ", query.to_lowercase());
+        let (_, rest) = CORRELATION_TRIGGERS
+            .iter()
+            .find_map(|trigger| padded.split_once(trigger))?;
+
+        let app = rest.trim().trim_end_matches(|c: char| !c.is_alphanumeric()).trim();
+        (!app.is_empty()).then(|| app.to_string())
+    }
+
+    fn extract_sentiment(query_lower: &str) -> Option<&'static str> {
+        let padded = format!(" {} ", query_lower);
+
+        SENTIMENT_WORDS
+            .iter()
+            .find(|(phrase, _)| padded.contains(phrase))
+            .map(|(_, tag)| *tag)
+    }
+
+    fn extract_monitor(query_lower: &str) -> Option<MonitorRef> {
+        let words: Vec<&str> = query_lower.split_whitespace().collect();
+        let position = words.iter().position(|w| MONITOR_WORDS.contains(w))?;
+
+        // The word right before "monitor" says which one, e.g. "left monitor".
+        // Events only record where a monitor is, not whether it's the primary
+        // one, so "primary", "main" and "secondary" can't be answered.
+        match *words.get(position.checked_sub(1)?)? {
+            "left" | "leftmost" => Some(MonitorRef::Leftmost),
+            "right" | "rightmost" => Some(MonitorRef::Rightmost),
+            "first" | "1st" => Some(MonitorRef::Index(0)),
+            "second" | "2nd" | "other" => Some(MonitorRef::Index(1)),
+            "third" | "3rd" => Some(MonitorRef::Index(2)),
+            _ => None,
+        }
+    }
+
+    // Returns the text following `phrase` when it appears as whole words
+    fn text_after_phrase<'a>(text: &'a str, phrase: &str) -> Option<&'a str> {
+        let padded = format!(" {} ", phrase);
+        let haystack = format!(" {} ", text);
+
+        haystack.find(&padded).map(|i| {
+            let offset = (i + padded.len()).saturating_sub(1).min(text.len());
+            &text[offset..]
+        })
+    }
+
+    fn meaningful_words(text: &str) -> Vec<&str> {
+        text.split_whitespace()
+            .map(|word| word.trim_matches('.'))
+            .filter(|word| !word.is_empty() && !FILLER_WORDS.contains(word))
+            .collect()
     }
 
     // Add this new method to sanitize queries
     fn sanitize_query_for_fts(&self, query: &str) -> String {
         // Remove question marks and other special characters
-        let clean_query = query.chars()
+        let clean_query = query
+            .chars()
             .filter(|c| c.is_alphanumeric() || c.is_whitespace())
             .collect::<String>();
-    
+
         // Extract key terms (simplified approach - split by spaces and take words 3+ chars)
-        let terms = clean_query.split_whitespace()
+        let terms = clean_query
+            .split_whitespace()
             .filter(|word| word.len() >= 3)
             .collect::<Vec<_>>();
-    
+
         if terms.is_empty() {
-            "user activity".to_string() // Fallback search term
+            FTS_FALLBACK_QUERY.to_string()
         } else {
             terms.join(" ") // Join with OR for more permissive matching
         }
     }
 
-    fn parse_time_query(&self, query: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    // Human-readable version of parse_time_query's result
+    pub fn describe_time_query(&self, query: &str) -> Option<String> {
+        if let Some(range) = HourRange::parse(query) {
+            return Some(range.description());
+        }
+
+        if let Some(description) = timeframe::describe_timeframe(query) {
+            return Some(description);
+        }
+
+        self.parse_time_query(query)
+            .map(|(start, end)| format!("{} to {}", start.format("%Y-%m-%d %H:%M"), end.format("%Y-%m-%d %H:%M")))
+    }
+
+    // Unlike parse_time_query, "last week" here means the week before this one
+    // so that "this week vs last week" compares two different periods
+    fn parse_comparison_period(&self, text: &str) -> Option<Timeframe> {
         let now = Utc::now();
+        let midnight = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
 
-        if query.contains("last week") {
-            let end = now;
-            let start = now - Duration::days(7);
-            Some((start, end))
-        } else if query.contains("yesterday") {
-            let end = now;
-            let start = now - Duration::days(1);
-            Some((start, end))
-        } else if query.contains("today") {
-            let end = now;
-            let start = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
-            Some((start, end))
+        if text.contains("yesterday") {
+            Some((midnight - Duration::days(1), midnight))
+        } else if text.contains("today") {
+            Some((midnight, now))
+        } else if text.contains("last week") {
+            Some((now - Duration::days(14), now - Duration::days(7)))
+        } else if text.contains("this week") {
+            Some((now - Duration::days(7), now))
+        } else if text.contains("last month") {
+            Some((now - Duration::days(60), now - Duration::days(30)))
+        } else if text.contains("this month") {
+            Some((now - Duration::days(30), now))
         } else {
             None
         }
     }
+
+    // Calendar events are only looked up when no period is named outright
+    pub fn parse_time_query(&self, query: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        timeframe::parse_timeframe(query).or_else(|| calendar::find_calendar_event(query, &self.calendar))
+    }
+}
+
+// Describes events the thinker hasn't summarized by the apps they were in,
+// busiest first. Window titles are left out, as they haven't been through
+// the thinker's redaction.
+fn unsummarized_summary(events: Vec<UserEvent>) -> Option<ActivitySummary> {
+    let start_time = events.first()?.timestamp;
+    let end_time = events.last()?.timestamp;
+    let latest_app = events.last()?.app_context.app_name.clone();
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for event in &events {
+        *counts.entry(event.app_context.app_name.as_str()).or_insert(0) += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let apps: Vec<String> = counts
+        .iter()
+        .map(|(app, count)| format!("{} ({} events)", app, count))
+        .collect();
+    let description = format!(
+        "Recent activity, not summarized yet. Most recently in {}. Apps used: {}.",
+        latest_app,
+        apps.join(", ")
+    );
+
+    Some(ActivitySummary {
+        start_time,
+        end_time,
+        description,
+        events,
+        tags: Vec::new(),
+    })
 }
@@ -0,0 +1,165 @@
+use activity_tracker_common::ActivitySummary;
+use chrono::Utc;
+
+// How much each signal counts towards a summary's score
+const DESCRIPTION_WEIGHT: f32 = 0.4;
+const TAGS_WEIGHT: f32 = 0.3;
+const RECENCY_WEIGHT: f32 = 0.2;
+const EXACT_TAG_WEIGHT: f32 = 0.1;
+
+// A summary's recency counts for 1/e as much after this many days
+const RECENCY_DECAY_DAYS: f32 = 30.0;
+
+// A search result and how well it matched
+#[derive(Debug, Clone)]
+pub struct ScoredSummary {
+    pub summary: ActivitySummary,
+    pub score: f32,
+}
+
+// Scores how well a summary matches the lowercase query terms, between 0 and 1
+pub fn score_summary(summary: &ActivitySummary, query_terms: &[&str]) -> f32 {
+    if query_terms.is_empty() {
+        return 0.0;
+    }
+
+    let description_words: Vec<String> = words(&summary.description).collect();
+    let tags: Vec<String> = summary.tags.iter().map(|tag| tag.to_lowercase()).collect();
+
+    let mut description_score = 0.0;
+    let mut tags_score = 0.0;
+    for term in query_terms {
+        // Each further mention counts for less, so a summary that's all
+        // about a term beats one that mentions it once
+        let mentions = description_words.iter().filter(|word| word.as_str() == *term).count() as f32;
+        description_score += mentions / (mentions + 1.0);
+
+        if tags.iter().any(|tag| tag.contains(term)) {
+            tags_score += 1.0;
+        }
+    }
+    description_score /= query_terms.len() as f32;
+    tags_score /= query_terms.len() as f32;
+
+    let age_days = (Utc::now() - summary.end_time).num_seconds().max(0) as f32 / 86400.0;
+    let recency_score = (-age_days / RECENCY_DECAY_DAYS).exp();
+
+    let exact_tag_score = if tags.iter().any(|tag| query_terms.contains(&tag.as_str())) {
+        1.0
+    } else {
+        0.0
+    };
+
+    DESCRIPTION_WEIGHT * description_score
+        + TAGS_WEIGHT * tags_score
+        + RECENCY_WEIGHT * recency_score
+        + EXACT_TAG_WEIGHT * exact_tag_score
+}
+
+// Scores every summary and sorts them best match first
+pub fn rank(summaries: Vec<ActivitySummary>, query_terms: &[&str]) -> Vec<ScoredSummary> {
+    let mut ranked: Vec<ScoredSummary> = summaries
+        .into_iter()
+        .map(|summary| {
+            let score = score_summary(&summary, query_terms);
+            ScoredSummary { summary, score }
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    ranked
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn summary(days_ago: i64, description: &str, tags: &[&str]) -> ActivitySummary {
+        let end_time = Utc::now() - Duration::days(days_ago);
+
+        ActivitySummary {
+            start_time: end_time - Duration::minutes(5),
+            end_time,
+            description: description.to_string(),
+            events: Vec::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn a_highly_relevant_older_result_outranks_a_weakly_relevant_newer_one() {
+        let older = summary(
+            20,
+            "Rust programming all afternoon: wrote Rust, read about Rust programming patterns",
+            &["rust", "programming"],
+        );
+        let newer = summary(0, "Answered email, one of which mentioned Rust", &["email"]);
+        let terms = ["rust", "programming"];
+
+        assert!(score_summary(&older, &terms) > score_summary(&newer, &terms));
+        let ranked = rank(vec![newer, older], &terms);
+        assert!(ranked[0]
+            .summary
+            .description
+            .starts_with("Rust programming all afternoon"));
+    }
+
+    #[test]
+    fn recency_breaks_ties_between_equally_relevant_results() {
+        let terms = ["parser"];
+        let ranked = rank(
+            vec![
+                summary(30, "Fixed the parser", &["parser"]),
+                summary(1, "Fixed the parser", &["parser"]),
+                summary(10, "Fixed the parser", &["parser"]),
+            ],
+            &terms,
+        );
+
+        let ages: Vec<i64> = ranked
+            .iter()
+            .map(|r| (Utc::now() - r.summary.end_time).num_days())
+            .collect();
+        assert_eq!(ages, [1, 10, 30]);
+    }
+
+    #[test]
+    fn further_mentions_count_for_less() {
+        let terms = ["rust"];
+        let once = score_summary(&summary(5, "rust", &[]), &terms);
+        let twice = score_summary(&summary(5, "rust and rust", &[]), &terms);
+        let thrice = score_summary(&summary(5, "rust, rust and rust", &[]), &terms);
+
+        assert!(twice > once);
+        assert!(thrice > twice);
+        assert!(thrice - twice < twice - once);
+    }
+
+    #[test]
+    fn an_exact_tag_beats_a_partial_one() {
+        let terms = ["rust"];
+        let exact = score_summary(&summary(5, "Worked on the crate", &["rust"]), &terms);
+        let partial = score_summary(&summary(5, "Worked on the crate", &["rustfmt"]), &terms);
+        let none = score_summary(&summary(5, "Worked on the crate", &["email"]), &terms);
+
+        assert!((exact - partial - EXACT_TAG_WEIGHT).abs() < 1e-6);
+        assert!((partial - none - TAGS_WEIGHT).abs() < 1e-6);
+    }
+
+    #[test]
+    fn scores_stay_between_zero_and_one() {
+        let best = summary(0, "rust rust rust rust rust rust rust rust rust rust", &["rust"]);
+        let worst = summary(365, "Read the news", &[]);
+
+        assert!(score_summary(&best, &["rust"]) <= 1.0);
+        assert!(score_summary(&worst, &["rust"]) >= 0.0);
+        assert_eq!(score_summary(&best, &[]), 0.0);
+    }
+}
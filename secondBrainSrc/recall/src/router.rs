@@ -0,0 +1,577 @@
+use crate::app_matcher::SimilarityMatcher;
+use crate::calendar::{self, CalendarEvent};
+use crate::intent::{QueryIntent, QueryIntentClassifier};
+use crate::query_engine::{QueryAnswer, QueryEngine, QueryResult, SearchIntent, SharedSummaries};
+use activity_tracker_recall::timeframe::{self, Timeframe};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use std::error::Error;
+use std::sync::Arc;
+
+// How sure each handler is when a query is its kind. The order matches the
+// order queries used to be checked in, so "how many tasks" counts tasks
+// rather than events and "what apps do I use with VSCode" isn't answered
+// with every app used today.
+const COMPARISON_CONFIDENCE: f32 = 1.0;
+const CORRELATION_CONFIDENCE: f32 = 0.95;
+const TASK_CONFIDENCE: f32 = 0.9;
+const CALENDAR_CONFIDENCE: f32 = 0.85;
+const STATS_CONFIDENCE: f32 = 0.8;
+const TIMEFRAME_CONFIDENCE: f32 = 0.8;
+const APP_SEARCH_CONFIDENCE: f32 = 0.6;
+const SEARCH_CONFIDENCE: f32 = 0.5;
+// Anything nothing else claims is searched for
+const FALLBACK_CONFIDENCE: f32 = 0.2;
+
+// One kind of query and how to answer it
+#[async_trait]
+pub trait QueryHandler: Send + Sync {
+    // How sure the handler is that the query is its kind, 0.0 to 1.0
+    fn can_handle(&self, query: &str) -> f32;
+
+    // The answer as data rather than words. The server puts it into words
+    // according to how it was asked: as text, as JSON for RAW: and JSON:,
+    // piece by piece when streamed, or through the Answerer for a batch.
+    // Queries in a batch pass the summaries they share, so each period is
+    // fetched once.
+    async fn handle(
+        &self,
+        query: &str,
+        engine: &QueryEngine,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>>;
+}
+
+// Hands each query to the handler most confident it can answer it. A new
+// kind of query only needs a handler added to the list.
+pub struct RecallRouter {
+    handlers: Vec<Box<dyn QueryHandler>>,
+}
+
+impl RecallRouter {
+    pub fn new(app_matcher: Arc<SimilarityMatcher>, calendar: Arc<Vec<CalendarEvent>>) -> Self {
+        Self {
+            handlers: vec![
+                Box::new(ComparisonHandler),
+                Box::new(AppSpecificHandler { app_matcher }),
+                Box::new(TaskHandler),
+                Box::new(CalendarHandler { calendar }),
+                Box::new(StatsHandler),
+                Box::new(TimeframeHandler),
+                Box::new(SearchHandler),
+            ],
+        }
+    }
+
+    // Ties go to the handler listed first
+    pub fn route(&self, query: &str) -> &dyn QueryHandler {
+        let mut best: (&dyn QueryHandler, f32) = (self.handlers[0].as_ref(), self.handlers[0].can_handle(query));
+        for handler in &self.handlers[1..] {
+            let confidence = handler.can_handle(query);
+            if confidence > best.1 {
+                best = (handler.as_ref(), confidence);
+            }
+        }
+        best.0
+    }
+}
+
+// Aggregate questions are usually about today
+fn today() -> Timeframe {
+    let now = Utc::now();
+    (now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc(), now)
+}
+
+// "this week vs last week"
+pub struct ComparisonHandler;
+
+#[async_trait]
+impl QueryHandler for ComparisonHandler {
+    fn can_handle(&self, query: &str) -> f32 {
+        match QueryIntentClassifier::classify(query) {
+            QueryIntent::ComparePeriods => COMPARISON_CONFIDENCE,
+            _ => 0.0,
+        }
+    }
+
+    async fn handle(
+        &self,
+        query: &str,
+        engine: &QueryEngine,
+        _: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        Ok(engine.compute_diff(query).await?.into())
+    }
+}
+
+// "what do I use with VSCode" and "when was I in Figma"
+pub struct AppSpecificHandler {
+    app_matcher: Arc<SimilarityMatcher>,
+}
+
+#[async_trait]
+impl QueryHandler for AppSpecificHandler {
+    fn can_handle(&self, query: &str) -> f32 {
+        if QueryEngine::extract_correlation_app(query).is_some() {
+            return CORRELATION_CONFIDENCE;
+        }
+
+        match (
+            QueryIntentClassifier::classify(query),
+            QueryEngine::search_intent(query, &self.app_matcher),
+        ) {
+            (QueryIntent::SearchActivity(_), SearchIntent::AppName(_)) => APP_SEARCH_CONFIDENCE,
+            _ => 0.0,
+        }
+    }
+
+    async fn handle(
+        &self,
+        query: &str,
+        engine: &QueryEngine,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        if let Some(app_name) = QueryEngine::extract_correlation_app(query) {
+            return Ok(engine.correlations(&app_name).await?.into());
+        }
+
+        let time_range = engine.parse_time_query(query);
+        match QueryEngine::search_intent(query, &self.app_matcher) {
+            SearchIntent::AppName(app_name) => {
+                Ok(engine.app_activity(query, &app_name, time_range, shared).await?.into())
+            }
+            _ => {
+                let (summaries, total) = engine.search_summaries_counted(query, time_range, shared).await?;
+                Ok(QueryAnswer {
+                    result: QueryResult::Summaries(summaries),
+                    total_count: Some(total),
+                })
+            }
+        }
+    }
+}
+
+// "what tasks did I work on today"
+pub struct TaskHandler;
+
+#[async_trait]
+impl QueryHandler for TaskHandler {
+    fn can_handle(&self, query: &str) -> f32 {
+        if QueryEngine::is_task_query(query) {
+            TASK_CONFIDENCE
+        } else {
+            0.0
+        }
+    }
+
+    async fn handle(
+        &self,
+        query: &str,
+        engine: &QueryEngine,
+        _: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        // Task questions are usually about today
+        Ok(engine
+            .tasks(engine.parse_time_query(query).unwrap_or_else(today))
+            .await?
+            .into())
+    }
+}
+
+// "what was I doing during the standup", where the calendar event only names
+// the period and there's nothing left to search for
+pub struct CalendarHandler {
+    calendar: Arc<Vec<CalendarEvent>>,
+}
+
+#[async_trait]
+impl QueryHandler for CalendarHandler {
+    fn can_handle(&self, query: &str) -> f32 {
+        let from_calendar = timeframe::parse_timeframe(query).is_none()
+            && calendar::find_calendar_event(query, &self.calendar).is_some();
+
+        match QueryIntentClassifier::classify(query) {
+            QueryIntent::SearchActivity(_) if from_calendar => CALENDAR_CONFIDENCE,
+            _ => 0.0,
+        }
+    }
+
+    async fn handle(
+        &self,
+        query: &str,
+        engine: &QueryEngine,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        let (start, end) = engine.parse_time_query(query).unwrap_or_default();
+        Ok(QueryResult::Summaries(engine.summaries_in_timeframe(start, end, shared).await?).into())
+    }
+}
+
+// "how much time did I spend in Slack" and "what apps did I use"
+pub struct StatsHandler;
+
+#[async_trait]
+impl QueryHandler for StatsHandler {
+    fn can_handle(&self, query: &str) -> f32 {
+        match QueryIntentClassifier::classify(query) {
+            QueryIntent::ListApps | QueryIntent::GetStats => STATS_CONFIDENCE,
+            _ => 0.0,
+        }
+    }
+
+    async fn handle(
+        &self,
+        query: &str,
+        engine: &QueryEngine,
+        _: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        let timeframe = engine.parse_time_query(query).unwrap_or_else(today);
+
+        Ok(QueryResult::Statistics {
+            stats: engine.compute_statistics(timeframe.0, timeframe.1).await?,
+            timeframe,
+            query: query.to_string(),
+        }
+        .into())
+    }
+}
+
+// "what did I do yesterday"
+pub struct TimeframeHandler;
+
+#[async_trait]
+impl QueryHandler for TimeframeHandler {
+    fn can_handle(&self, query: &str) -> f32 {
+        match QueryIntentClassifier::classify(query) {
+            QueryIntent::TimelineQuery(_) => TIMEFRAME_CONFIDENCE,
+            _ => 0.0,
+        }
+    }
+
+    async fn handle(
+        &self,
+        query: &str,
+        engine: &QueryEngine,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        let time_range = engine.parse_time_query(query);
+
+        // The last few minutes usually haven't been summarized yet, so they're
+        // answered from the learner's memory when it's running
+        if let Some((start, end)) = time_range {
+            if let Some(summary) = engine.short_term_summary(start, end).await {
+                return Ok(QueryResult::Summaries(engine.with_waypoints(vec![summary], start, end).await?).into());
+            }
+        }
+
+        // "what did I do last wednesday" is answered from the day's rollup
+        if let Some(day) = timeframe::parse_day(query) {
+            let daily = engine.daily_summary(day).await?;
+            if let Some(daily) = daily {
+                let (start, end) = (daily.start_time, daily.end_time);
+                return Ok(QueryResult::Summaries(engine.with_waypoints(vec![daily], start, end).await?).into());
+            }
+        }
+
+        let period = match QueryIntentClassifier::classify(query) {
+            QueryIntent::TimelineQuery(period) => period,
+            _ => String::new(),
+        };
+        let (start, end) = time_range
+            .or_else(|| engine.parse_time_query(&period))
+            .unwrap_or_else(|| {
+                let now = Utc::now();
+                (now - Duration::days(1), now)
+            });
+
+        let summaries = engine.summaries_in_timeframe(start, end, shared).await?;
+        Ok(QueryResult::Summaries(engine.with_waypoints(summaries, start, end).await?).into())
+    }
+}
+
+// "when did I work on the parser", and anything no other handler claims
+pub struct SearchHandler;
+
+#[async_trait]
+impl QueryHandler for SearchHandler {
+    fn can_handle(&self, query: &str) -> f32 {
+        match QueryIntentClassifier::classify(query) {
+            QueryIntent::SearchActivity(_) => SEARCH_CONFIDENCE,
+            _ => FALLBACK_CONFIDENCE,
+        }
+    }
+
+    async fn handle(
+        &self,
+        query: &str,
+        engine: &QueryEngine,
+        shared: Option<&SharedSummaries>,
+    ) -> Result<QueryAnswer, Box<dyn Error>> {
+        let time_range = engine.parse_time_query(query);
+
+        if let QueryIntent::SearchActivity(terms) = QueryIntentClassifier::classify(query) {
+            return engine.search_activity(query, &terms, time_range, shared).await;
+        }
+
+        if let Some((start, end)) = time_range {
+            if let Some(summary) = engine.short_term_summary(start, end).await {
+                return Ok(QueryResult::Summaries(vec![summary]).into());
+            }
+        }
+
+        let (summaries, total) = engine.search_summaries_counted(query, time_range, shared).await?;
+        Ok(QueryAnswer {
+            result: QueryResult::Summaries(summaries),
+            total_count: Some(total),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> RecallRouter {
+        RecallRouter::new(Arc::new(SimilarityMatcher::default()), Arc::new(Vec::new()))
+    }
+
+    // Aggregate questions, and whether they name their own period rather
+    // than falling back to today
+    const AGGREGATE_QUERIES: [(&str, bool); 20] = [
+        ("how many hours did I code today", true),
+        ("how much time did I spend in Slack", false),
+        ("how much time did I spend in Firefox yesterday", true),
+        ("total time in VSCode this week", true),
+        ("total keystrokes last week", true),
+        ("count my events today", true),
+        ("how many events did I record yesterday", true),
+        ("how many windows did I open on monday", true),
+        ("hours in the terminal", false),
+        ("how many hours was I at the computer 3 days ago", true),
+        ("what's my most used app this week", true),
+        ("what's my most-used app", false),
+        ("how long was I in Slack today", true),
+        ("how long did I spend in email last friday", true),
+        ("total hours this week", true),
+        ("How many keystrokes today?", true),
+        ("count of app switches yesterday", true),
+        ("how much time in meetings on march 3", true),
+        ("how long have I been coding", false),
+        ("how many hours did I work last week", true),
+    ];
+
+    #[test]
+    fn aggregate_queries_take_the_statistics_path() {
+        let router = router();
+
+        for (query, _) in AGGREGATE_QUERIES {
+            assert_eq!(
+                QueryIntentClassifier::classify(query),
+                QueryIntent::GetStats,
+                "{}",
+                query
+            );
+            // Only StatsHandler answers a GetStats query with this confidence
+            assert_eq!(router.route(query).can_handle(query), STATS_CONFIDENCE, "{}", query);
+            assert_eq!(StatsHandler.can_handle(query), STATS_CONFIDENCE, "{}", query);
+        }
+    }
+
+    #[test]
+    fn aggregate_queries_parse_their_period() {
+        for (query, names_period) in AGGREGATE_QUERIES {
+            assert_eq!(timeframe::parse_timeframe(query).is_some(), names_period, "{}", query);
+        }
+    }
+
+    #[test]
+    fn other_queries_skip_the_statistics_path() {
+        for query in [
+            "what did I do yesterday",
+            "when did I work on the parser",
+            "why was I so distracted",
+        ] {
+            assert_eq!(StatsHandler.can_handle(query), 0.0, "{}", query);
+        }
+    }
+
+    // A standup and a design review, to find periods by
+    fn calendar() -> Arc<Vec<CalendarEvent>> {
+        Arc::new(calendar::parse_ics(
+            "BEGIN:VCALENDAR\r\n\
+             BEGIN:VEVENT\r\nSUMMARY:Daily standup\r\nDTSTART:20240101T090000Z\r\nDTEND:20240101T091500Z\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nSUMMARY:Design review\r\nDTSTART:20240102T140000Z\r\nDTEND:20240102T150000Z\r\nEND:VEVENT\r\n\
+             END:VCALENDAR\r\n",
+        ))
+    }
+
+    fn assert_confidences(handler: &dyn QueryHandler, cases: &[(&str, f32)]) {
+        for (query, confidence) in cases {
+            assert_eq!(handler.can_handle(query), *confidence, "{}", query);
+        }
+    }
+
+    #[test]
+    fn comparison_handler_claims_two_periods_side_by_side() {
+        assert_confidences(
+            &ComparisonHandler,
+            &[
+                ("this week vs last week", COMPARISON_CONFIDENCE),
+                ("how did this week compare to last week", COMPARISON_CONFIDENCE),
+                ("monday versus tuesday", COMPARISON_CONFIDENCE),
+                ("today vs yesterday", COMPARISON_CONFIDENCE),
+                ("yesterday compared to the day before", COMPARISON_CONFIDENCE),
+                ("last month vs this month", COMPARISON_CONFIDENCE),
+                ("what did I do yesterday", 0.0),
+                ("how much time did I spend in Slack", 0.0),
+                ("what tasks did I work on today", 0.0),
+                ("when did I work on the parser", 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn app_specific_handler_claims_questions_about_one_app() {
+        let handler = AppSpecificHandler {
+            app_matcher: Arc::new(SimilarityMatcher::default()),
+        };
+
+        assert_confidences(
+            &handler,
+            &[
+                ("what apps do I use with VSCode", CORRELATION_CONFIDENCE),
+                ("what do I use alongside Slack", CORRELATION_CONFIDENCE),
+                ("which apps do I use together with Firefox", CORRELATION_CONFIDENCE),
+                ("what do I use with Slack", CORRELATION_CONFIDENCE),
+                ("when was I in Figma", APP_SEARCH_CONFIDENCE),
+                ("what did I do in Slack yesterday", APP_SEARCH_CONFIDENCE),
+                ("show me my time in vscode", APP_SEARCH_CONFIDENCE),
+                ("what was I doing in chrome this morning", APP_SEARCH_CONFIDENCE),
+                ("what did I do in Firefox", APP_SEARCH_CONFIDENCE),
+                ("when did I work on the parser", 0.0),
+                ("what did I do yesterday", 0.0),
+                ("how much time did I spend in Slack", 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn task_handler_claims_questions_about_tasks() {
+        assert_confidences(
+            &TaskHandler,
+            &[
+                ("what tasks did I work on today", TASK_CONFIDENCE),
+                ("what tasks did I finish yesterday", TASK_CONFIDENCE),
+                ("list my tasks this week", TASK_CONFIDENCE),
+                ("which task took longest", TASK_CONFIDENCE),
+                ("how many tasks did I do", TASK_CONFIDENCE),
+                ("show tasks", TASK_CONFIDENCE),
+                ("what were my tasks last week", TASK_CONFIDENCE),
+                ("what was I working on", 0.0),
+                ("what did I do yesterday", 0.0),
+                ("this week vs last week", 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn calendar_handler_claims_periods_named_by_an_event() {
+        let handler = CalendarHandler { calendar: calendar() };
+
+        assert_confidences(
+            &handler,
+            &[
+                ("what was I doing during the standup", CALENDAR_CONFIDENCE),
+                ("what did I do before the design review", CALENDAR_CONFIDENCE),
+                ("what happened after the daily standup", CALENDAR_CONFIDENCE),
+                ("notes from the design review meeting", CALENDAR_CONFIDENCE),
+                ("what happened during the standup", CALENDAR_CONFIDENCE),
+                ("what did I do after the standup", CALENDAR_CONFIDENCE),
+                // No event by that name
+                ("what was I doing during lunch", 0.0),
+                // A period named outright wins over the calendar
+                ("what did I do during the design review yesterday", 0.0),
+                ("when did I work on the parser", 0.0),
+                ("what did I do yesterday", 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn stats_handler_claims_aggregate_questions() {
+        assert_confidences(
+            &StatsHandler,
+            &[
+                ("how much time did I spend in Slack", STATS_CONFIDENCE),
+                ("what apps did I use today", STATS_CONFIDENCE),
+                ("what is my most used app", STATS_CONFIDENCE),
+                ("total keystrokes today", STATS_CONFIDENCE),
+                ("how long was I in Slack yesterday", STATS_CONFIDENCE),
+                ("how much time did I spend in meetings", STATS_CONFIDENCE),
+                ("what apps do I use with VSCode", STATS_CONFIDENCE),
+                ("what did I do yesterday", 0.0),
+                ("when did I work on the parser", 0.0),
+                ("why was I so distracted", 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn timeframe_handler_claims_questions_about_a_period() {
+        assert_confidences(
+            &TimeframeHandler,
+            &[
+                ("what did I do yesterday", TIMEFRAME_CONFIDENCE),
+                ("show me last week", TIMEFRAME_CONFIDENCE),
+                ("yesterday", TIMEFRAME_CONFIDENCE),
+                ("what did I do 3 days ago", TIMEFRAME_CONFIDENCE),
+                ("what did I do on tuesday", TIMEFRAME_CONFIDENCE),
+                ("show me today", TIMEFRAME_CONFIDENCE),
+                ("what was I doing at 3pm yesterday", TIMEFRAME_CONFIDENCE),
+                ("what did I do between 9am and 11am", TIMEFRAME_CONFIDENCE),
+                ("how was my day yesterday", TIMEFRAME_CONFIDENCE),
+                ("what did I do in Slack yesterday", 0.0),
+                ("how much time did I spend in Slack", 0.0),
+                ("when did I work on the parser", 0.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn search_handler_claims_searches_and_anything_left_over() {
+        assert_confidences(
+            &SearchHandler,
+            &[
+                ("when did I work on the parser", SEARCH_CONFIDENCE),
+                ("rust", SEARCH_CONFIDENCE),
+                ("debugging the login bug", SEARCH_CONFIDENCE),
+                ("firefox", SEARCH_CONFIDENCE),
+                ("when did I use the terminal", SEARCH_CONFIDENCE),
+                ("why was I so distracted", FALLBACK_CONFIDENCE),
+                ("summarize my week", FALLBACK_CONFIDENCE),
+                ("tell me about my morning", FALLBACK_CONFIDENCE),
+                ("this week vs last week", FALLBACK_CONFIDENCE),
+                ("what did I do yesterday", FALLBACK_CONFIDENCE),
+            ],
+        );
+    }
+
+    #[test]
+    fn the_most_confident_handler_answers() {
+        let router = RecallRouter::new(Arc::new(SimilarityMatcher::default()), calendar());
+
+        for (query, confidence) in [
+            // Correlations outrank the app statistics the question also asks for
+            ("what apps do I use with VSCode", CORRELATION_CONFIDENCE),
+            // Tasks are counted rather than events
+            ("how many tasks did I do", TASK_CONFIDENCE),
+            ("what tasks did I work on in VSCode", TASK_CONFIDENCE),
+            ("what was I doing during the standup", CALENDAR_CONFIDENCE),
+            ("what did I do in Slack yesterday", APP_SEARCH_CONFIDENCE),
+            ("when did I work on the parser", SEARCH_CONFIDENCE),
+            ("why was I so distracted", FALLBACK_CONFIDENCE),
+        ] {
+            assert_eq!(router.route(query).can_handle(query), confidence, "{}", query);
+        }
+    }
+}
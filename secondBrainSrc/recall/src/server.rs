@@ -0,0 +1,1209 @@
+use crate::format::{
+    format_correlations, format_diff, format_showing, format_statistics, format_summaries, format_tasks,
+    format_window_titles,
+};
+use crate::fuzzy_finder::FuzzyFinder;
+use crate::health::SharedHealth;
+use crate::intent::{QueryIntent, QueryIntentClassifier};
+use crate::persona::Persona;
+use crate::query_engine::{QueryAnswer, QueryEngine, QueryResult, SearchIntent, Timeframe};
+use activity_tracker_common::{
+    llm::{ContextWindowManager, LlmChain, LlmClient},
+    ActivitySummary, WaypointEvent,
+};
+use activity_tracker_recall::cache::DiskCache;
+use futures::StreamExt;
+use second_brain_client::{
+    read_framed, write_framed, BatchAnswer, RecallResponse, BATCH_PREFIX, EXPLAIN_PREFIX, JSON_PREFIX, RAW_PREFIX,
+    STREAM_PREFIX, WAYPOINT_PREFIX,
+};
+use std::env;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::{timeout, timeout_at, Duration, Instant};
+
+pub const DEFAULT_BIND_ADDR: &str = "127.0.0.1:8080";
+const DEFAULT_QUERY_TIMEOUT_SECS: u64 = 120;
+// Ollama usually handles only one or two requests at a time
+const DEFAULT_MAX_CONCURRENT_QUERIES: usize = 3;
+// Queries waiting for a slot beyond this are turned away
+const MAX_QUEUED_QUERIES: usize = 10;
+// Connections open longer than this are closed no matter what they're doing
+const CONNECTION_WATCHDOG_SECS: u64 = 300;
+// Leaves time to write a batch's answers before the watchdog closes its connection
+const BATCH_TIMEOUT_SECS: u64 = CONNECTION_WATCHDOG_SECS - 30;
+// Longest request read from one connection, which only batches come near
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+pub struct RecallConfig {
+    pub bind_addr: String,
+    // A hung LLM would otherwise hold the connection open forever
+    pub query_timeout: Duration,
+    // Queries in a batch share one deadline, so slow ones can't add up past the watchdog
+    pub batch_timeout: Duration,
+    pub max_concurrent_queries: usize,
+}
+
+impl RecallConfig {
+    pub fn from_env() -> Self {
+        Self {
+            bind_addr: DEFAULT_BIND_ADDR.to_string(),
+            query_timeout: Duration::from_secs(
+                env::var("RECALL_QUERY_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .unwrap_or(DEFAULT_QUERY_TIMEOUT_SECS),
+            ),
+            batch_timeout: Duration::from_secs(BATCH_TIMEOUT_SECS),
+            max_concurrent_queries: env::var("RECALL_MAX_CONCURRENT")
+                .ok()
+                .and_then(|s| s.parse::<usize>().ok())
+                .filter(|&max| max > 0)
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_QUERIES),
+        }
+    }
+}
+
+// Answers recall queries, one per connection. Connections are any byte
+// stream, so the server can be driven without a real socket.
+#[derive(Clone)]
+pub struct RecallServer {
+    config: Arc<RecallConfig>,
+    query_engine: QueryEngine,
+    fuzzy_finder: FuzzyFinder,
+    persona: Arc<Persona>,
+    llm: LlmService,
+    // Bounds the queries answered at once, and so the load on the LLM
+    query_slots: Arc<Semaphore>,
+    queued_queries: Arc<AtomicUsize>,
+}
+
+// Counts a query as queued until it gets a slot or its connection closes
+struct QueuedQuery<'a>(&'a AtomicUsize);
+
+impl Drop for QueuedQuery<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl RecallServer {
+    pub fn new(
+        config: RecallConfig,
+        query_engine: QueryEngine,
+        fuzzy_finder: FuzzyFinder,
+        persona: Arc<Persona>,
+        llm: LlmService,
+    ) -> Self {
+        let answerer = Answerer {
+            persona: persona.clone(),
+            llm: llm.clone(),
+            query_timeout: config.query_timeout,
+            batch_timeout: config.batch_timeout,
+        };
+
+        Self {
+            query_slots: Arc::new(Semaphore::new(config.max_concurrent_queries)),
+            queued_queries: Arc::new(AtomicUsize::new(0)),
+            config: Arc::new(config),
+            query_engine: query_engine.with_answerer(answerer),
+            fuzzy_finder,
+            persona,
+            llm,
+        }
+    }
+
+    // Waits for a query slot, or returns None at once if the queue is full
+    async fn acquire_query_slot(&self) -> Option<SemaphorePermit<'_>> {
+        if let Ok(permit) = self.query_slots.try_acquire() {
+            return Some(permit);
+        }
+
+        if self.queued_queries.fetch_add(1, Ordering::SeqCst) >= MAX_QUEUED_QUERIES {
+            self.queued_queries.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        let _queued = QueuedQuery(&self.queued_queries);
+
+        self.query_slots.acquire().await.ok()
+    }
+
+    // Answers a batch in one query slot, so it puts no more load on the LLM
+    // than a single query. Freeform questions are still answered one by one.
+    async fn answer_batch(&self, queries: &[String]) -> Vec<BatchAnswer> {
+        let Some(_slot) = self.acquire_query_slot().await else {
+            eprintln!(
+                "⚠️ Turned a batch of {} queries away, {} already waiting",
+                queries.len(),
+                MAX_QUEUED_QUERIES
+            );
+            return queries
+                .iter()
+                .map(|query| BatchAnswer {
+                    query: query.clone(),
+                    response: self.persona.busy_message.clone(),
+                })
+                .collect();
+        };
+
+        // Box<dyn Error> isn't Send, so drop it before returning
+        let answers = self
+            .query_engine
+            .process_batch(queries)
+            .await
+            .map_err(|e| e.to_string());
+        match answers {
+            Ok(answers) => answers
+                .into_iter()
+                .map(|(query, response)| BatchAnswer { query, response })
+                .collect(),
+            Err(e) => queries
+                .iter()
+                .map(|query| BatchAnswer {
+                    query: query.clone(),
+                    response: format!("Error: {}", e),
+                })
+                .collect(),
+        }
+    }
+
+    // Accepts connections until binding or accepting fails
+    pub async fn run(&self) -> Result<(), Box<dyn Error>> {
+        let listener = TcpListener::bind(&self.config.bind_addr).await?;
+        println!("🚀 Recall thread started. Listening on {}", self.config.bind_addr);
+        println!(
+            "🚦 Answering up to {} queries at once",
+            self.config.max_concurrent_queries
+        );
+
+        loop {
+            let (socket, _) = listener.accept().await?;
+            let server = self.clone();
+
+            // Process a client request in a new task
+            tokio::spawn(async move {
+                // Dropping the handler closes the socket
+                let handler = server.handle_connection(socket);
+                match timeout(Duration::from_secs(CONNECTION_WATCHDOG_SECS), handler).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => println!("Error handling recall connection: {}", e),
+                    Err(_) => {
+                        eprintln!(
+                            "⚠️ Closed a recall connection open for more than {}s",
+                            CONNECTION_WATCHDOG_SECS
+                        )
+                    }
+                }
+            });
+        }
+    }
+
+    // Reads one query and writes its answer. A request starting with a zero
+    // byte is framed, as second_brain_client::write_framed sends it, and is
+    // answered the same way. Anything else is plain text, e.g. from `nc`,
+    // answered with plain text and the connection closed.
+    pub async fn handle_connection<S>(&self, mut socket: S) -> Result<(), Box<dyn Error>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut buffer = [0; 1024];
+        let n = socket.read(&mut buffer).await?;
+
+        // No text query starts with a zero byte, but the length header of
+        // any request under 16 MB does
+        let framed = n > 0 && buffer[0] == 0;
+        let request = if framed {
+            let mut reader = (&buffer[..n]).chain(&mut socket);
+            read_framed(&mut reader, MAX_REQUEST_BYTES)
+                .await
+                .map_err(|e| e as Box<dyn Error>)?
+        } else {
+            let mut request = buffer[..n].to_vec();
+            // Batches and waypoint notes don't always fit in one read; their
+            // client closes its end once everything is sent
+            if request.starts_with(BATCH_PREFIX.as_bytes()) || request.starts_with(WAYPOINT_PREFIX.as_bytes()) {
+                (&mut socket)
+                    .take(MAX_REQUEST_BYTES as u64)
+                    .read_to_end(&mut request)
+                    .await?;
+            }
+            request
+        };
+
+        let query = String::from_utf8_lossy(&request).to_string();
+
+        if let Some(query) = query.strip_prefix(STREAM_PREFIX) {
+            return self.stream_response(query, &mut socket, framed).await;
+        }
+
+        let response = self.respond(query).await;
+
+        if framed {
+            write_framed(&mut socket, response.as_bytes())
+                .await
+                .map_err(|e| e as Box<dyn Error>)?;
+        } else {
+            socket.write_all(response.as_bytes()).await?;
+        }
+
+        Ok(())
+    }
+
+    // Writes the answer as plain text a piece at a time, each piece a frame
+    // when the request was framed, ending with an empty frame. Only freeform
+    // answers are written as the LLM generates them; anything else, or an
+    // answer from the cache, is written in one piece.
+    async fn stream_response<S>(&self, query: &str, socket: &mut S, framed: bool) -> Result<(), Box<dyn Error>>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        let freeform = QueryIntentClassifier::classify(query) == QueryIntent::FreeformQuery;
+        let llm = self.llm.available().filter(|_| freeform);
+
+        let streamed = match llm {
+            Some(llm) => match self.acquire_query_slot().await {
+                Some(_slot) => {
+                    let answer = stream_freeform(query, &self.query_engine, &self.persona, llm, socket, framed);
+                    match timeout(self.config.query_timeout, answer).await {
+                        Ok(streamed) => streamed.map_err(|e| e as Box<dyn Error>)?,
+                        Err(_) => {
+                            eprintln!(
+                                "⚠️ Query timed out after {}s: {}",
+                                self.config.query_timeout.as_secs(),
+                                query
+                            );
+                            let text = format!("\n\n{}", self.persona.timeout_message);
+                            write_piece(socket, framed, text.as_bytes())
+                                .await
+                                .map_err(|e| e as Box<dyn Error>)?;
+                            true
+                        }
+                    }
+                }
+                None => false,
+            },
+            None => false,
+        };
+
+        if streamed {
+            let suggestions = self
+                .query_engine
+                .take_pending_suggestions()
+                .await
+                .map_err(|e| e.to_string());
+            match suggestions {
+                Ok(suggestions) => {
+                    for suggestion in suggestions {
+                        let text = format!("\n\n💡 {}", suggestion.message);
+                        write_piece(socket, framed, text.as_bytes())
+                            .await
+                            .map_err(|e| e as Box<dyn Error>)?;
+                    }
+                }
+                Err(e) => eprintln!("⚠️ Failed to fetch suggestions: {}", e),
+            }
+        } else {
+            let response = self.respond(query.to_string()).await;
+            write_piece(socket, framed, response.as_bytes())
+                .await
+                .map_err(|e| e as Box<dyn Error>)?;
+        }
+
+        if framed {
+            write_framed(socket, b"").await.map_err(|e| e as Box<dyn Error>)?;
+        }
+
+        Ok(())
+    }
+
+    // The response to one request, whatever its prefix
+    async fn respond(&self, query: String) -> String {
+        if let Some(batch) = query.strip_prefix(BATCH_PREFIX) {
+            let queries: Vec<String> = batch
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+
+            let answers = self.answer_batch(&queries).await;
+            let response = serde_json::to_string(&answers).unwrap_or_else(|e| format!("{{\"error\": \"{}\"}}", e));
+
+            return response;
+        }
+
+        // Raw results skip formatting entirely, for piping into other tools
+        if let Some(query) = query.strip_prefix(RAW_PREFIX) {
+            let query_timeout = self.config.query_timeout;
+            let response = match timeout(query_timeout, self.query_engine.process_query(query)).await {
+                Ok(Ok(result)) => serde_json::to_string(&result),
+                Ok(Err(e)) => serde_json::to_string(&serde_json::json!({ "error": e.to_string() })),
+                Err(_) => serde_json::to_string(&serde_json::json!({ "error": "query timed out" })),
+            }
+            .unwrap_or_else(|e| format!("{{\"error\": \"{}\"}}", e));
+
+            return response;
+        }
+
+        if let Some(waypoint) = query.strip_prefix(WAYPOINT_PREFIX) {
+            let response = match serde_json::from_str::<WaypointEvent>(waypoint) {
+                Ok(waypoint) => match self.query_engine.mark_waypoint(&waypoint).await {
+                    Ok(()) => serde_json::json!({ "ok": true }),
+                    Err(e) => serde_json::json!({ "error": e.to_string() }),
+                },
+                Err(e) => serde_json::json!({ "error": format!("invalid waypoint: {}", e) }),
+            };
+
+            return response.to_string();
+        }
+
+        if let Some(query) = query.strip_prefix(EXPLAIN_PREFIX) {
+            let query_timeout = self.config.query_timeout;
+            let response = match timeout(query_timeout, self.query_engine.explain_query(query)).await {
+                Ok(Ok(explanation)) => serde_json::to_string(&explanation),
+                Ok(Err(e)) => serde_json::to_string(&serde_json::json!({ "error": e.to_string() })),
+                Err(_) => serde_json::to_string(&serde_json::json!({ "error": "query timed out" })),
+            }
+            .unwrap_or_else(|e| format!("{{\"error\": \"{}\"}}", e));
+
+            return response;
+        }
+
+        // Programmatic clients ask for a structured response
+        let (query, as_json) = match query.strip_prefix(JSON_PREFIX) {
+            Some(query) => (query.to_string(), true),
+            None => (query, false),
+        };
+
+        // Canned replies for when the query can't be answered normally
+        let unanswered = |formatted_text: &str, query_type: &str| RecallResponse {
+            formatted_text: formatted_text.to_string(),
+            query_type: query_type.to_string(),
+            timeframe_description: self
+                .query_engine
+                .describe_time_query(&query)
+                .unwrap_or_else(|| "all time".to_string()),
+            app_filter: None,
+            suggestions: Vec::new(),
+        };
+
+        // Freeform questions need the LLM, so there's no point waiting out a
+        // timeout when it's known to be down. Everything else is answered without it.
+        let needs_llm = QueryIntentClassifier::classify(&query) == QueryIntent::FreeformQuery;
+
+        let mut response = if needs_llm && !self.llm.is_healthy() {
+            unanswered(&self.persona.llm_unavailable_message, "llm_unavailable")
+        } else if let Some(_slot) = self.acquire_query_slot().await {
+            let answer = answer_query(
+                &query,
+                &self.query_engine,
+                &self.fuzzy_finder,
+                &self.persona,
+                self.llm.available(),
+            );
+            match timeout(self.config.query_timeout, answer).await {
+                Ok(response) => response,
+                Err(_) => {
+                    eprintln!(
+                        "⚠️ Query timed out after {}s: {}",
+                        self.config.query_timeout.as_secs(),
+                        query
+                    );
+                    unanswered(&self.persona.timeout_message, "timeout")
+                }
+            }
+        } else {
+            eprintln!(
+                "⚠️ Turned a query away, {} already waiting: {}",
+                MAX_QUEUED_QUERIES, query
+            );
+            unanswered(&self.persona.busy_message, "busy")
+        };
+
+        // Reminders the thinker queued are shown with the next answer
+        match self.query_engine.take_pending_suggestions().await {
+            Ok(suggestions) => response.suggestions = suggestions.into_iter().map(|s| s.message).collect(),
+            Err(e) => eprintln!("⚠️ Failed to fetch suggestions: {}", e),
+        }
+
+        if as_json {
+            serde_json::to_string(&response).unwrap_or_else(|e| format!("Error encoding response: {}", e))
+        } else {
+            let mut text = response.formatted_text;
+            for suggestion in &response.suggestions {
+                text.push_str(&format!("\n\n💡 {}", suggestion));
+            }
+            text
+        }
+    }
+}
+
+// Puts results into words the way the server answers single queries, for
+// QueryEngine::process_batch
+#[derive(Clone)]
+pub struct Answerer {
+    pub persona: Arc<Persona>,
+    pub llm: LlmService,
+    pub query_timeout: Duration,
+    pub batch_timeout: Duration,
+}
+
+impl Answerer {
+    // The result in words, or the timeout message when that takes longer
+    // than the query timeout or runs past the deadline
+    pub(crate) async fn answer(
+        &self,
+        query: &str,
+        result: Result<QueryAnswer, String>,
+        query_engine: &QueryEngine,
+        deadline: Instant,
+    ) -> String {
+        let answer = answer_result(query, result, query_engine, &self.persona, self.llm.available());
+        match timeout_at(deadline.min(Instant::now() + self.query_timeout), answer).await {
+            Ok(response) => response.formatted_text,
+            Err(_) => {
+                eprintln!("⚠️ Query in a batch timed out: {}", query);
+                self.persona.timeout_message.clone()
+            }
+        }
+    }
+}
+
+// Everything a connection needs to reach the LLM, shared across connections
+#[derive(Clone)]
+pub struct LlmService {
+    pub client: Option<Arc<LlmChain>>,
+    pub cache: Option<DiskCache>,
+    pub health: SharedHealth,
+}
+
+impl LlmService {
+    fn is_healthy(&self) -> bool {
+        self.health.read().map(|status| status.llm.healthy).unwrap_or(true)
+    }
+
+    // The LLM, unless there isn't one or the health checker reports it down
+    fn available(&self) -> Option<Llm<'_>> {
+        self.client.as_deref().filter(|_| self.is_healthy()).map(|client| Llm {
+            client,
+            cache: self.cache.as_ref(),
+        })
+    }
+}
+
+// The LLM along with the cache its answers go through
+#[derive(Clone, Copy)]
+struct Llm<'a> {
+    client: &'a LlmChain,
+    cache: Option<&'a DiskCache>,
+}
+
+impl Llm<'_> {
+    async fn generate(
+        &self,
+        query: &str,
+        timeframe: Option<Timeframe>,
+        generate: impl std::future::Future<Output = Option<String>>,
+    ) -> Option<String> {
+        match self.cache {
+            Some(cache) => cache.get_or_generate(query, timeframe, generate).await,
+            None => generate.await,
+        }
+    }
+}
+
+async fn answer_query(
+    query: &str,
+    query_engine: &QueryEngine,
+    fuzzy_finder: &FuzzyFinder,
+    persona: &Persona,
+    llm: Option<Llm<'_>>,
+) -> RecallResponse {
+    if let Some(search_term) = query.strip_prefix("Fuzzy:") {
+        let text = match fuzzy_finder.search(search_term).await {
+            Ok(summaries) => format_summaries(summaries, query, persona),
+            Err(e) => format!("Error in fuzzy search: {}", e),
+        };
+        return RecallResponse {
+            formatted_text: text,
+            query_type: "fuzzy".to_string(),
+            timeframe_description: "all time".to_string(),
+            app_filter: None,
+            suggestions: Vec::new(),
+        };
+    }
+
+    // Box<dyn Error> isn't Send, so drop it before any further awaits
+    let result = query_engine.process_query(query).await.map_err(|e| e.to_string());
+
+    answer_result(query, result, query_engine, persona, llm).await
+}
+
+// Puts a query's result into words, asking the LLM when it's a freeform question
+async fn answer_result(
+    query: &str,
+    result: Result<QueryAnswer, String>,
+    query_engine: &QueryEngine,
+    persona: &Persona,
+    llm: Option<Llm<'_>>,
+) -> RecallResponse {
+    let describe = |timeframe: Timeframe| {
+        format!(
+            "{} to {}",
+            timeframe.0.format("%Y-%m-%d %H:%M"),
+            timeframe.1.format("%Y-%m-%d %H:%M")
+        )
+    };
+    let response = |formatted_text: String, query_type: &str, timeframe_description: String| RecallResponse {
+        formatted_text,
+        query_type: query_type.to_string(),
+        timeframe_description,
+        app_filter: None,
+        suggestions: Vec::new(),
+    };
+
+    let timeframe_description = query_engine
+        .describe_time_query(query)
+        .unwrap_or_else(|| "all time".to_string());
+
+    let (result, total_count) = match result {
+        Ok(answer) => (Ok(answer.result), answer.total_count),
+        Err(e) => (Err(e), None),
+    };
+
+    match result {
+        Ok(QueryResult::Summaries(summaries)) => {
+            // Lookups are answered from the data alone; only freeform questions
+            // are worth the wait for the LLM
+            let freeform = QueryIntentClassifier::classify(query) == QueryIntent::FreeformQuery;
+            if let Some(llm) = llm.filter(|_| freeform && !summaries.is_empty()) {
+                let timeframe = query_engine.parse_time_query(query);
+                let answer = llm.generate(
+                    query,
+                    timeframe,
+                    answer_freeform(llm.client, persona, query, &summaries),
+                );
+                if let Some(answer) = answer.await {
+                    let text = format!("{}\n\n{}", answer, format_summaries(summaries, query, persona));
+                    return response(text, "freeform", timeframe_description);
+                }
+            }
+
+            let showing = format_showing(summaries.len(), total_count);
+            let mut text = format_summaries(summaries, query, persona);
+            if let Some(showing) = showing {
+                text.push_str(&format!("\n\n{}", showing));
+            }
+
+            RecallResponse {
+                app_filter: match query_engine.extract_search_intent(query) {
+                    SearchIntent::AppName(app_name) => Some(app_name),
+                    _ => None,
+                },
+                ..response(text, "summaries", timeframe_description)
+            }
+        }
+        Ok(QueryResult::WindowTitles(hits)) => response(
+            format_window_titles(hits, persona),
+            "window_titles",
+            timeframe_description,
+        ),
+        Ok(QueryResult::Statistics {
+            stats,
+            timeframe,
+            query,
+        }) => response(
+            format_statistics(&stats, timeframe, &query, persona),
+            "statistics",
+            describe(timeframe),
+        ),
+        Ok(QueryResult::Tasks { tasks, timeframe }) => {
+            response(format_tasks(&tasks, timeframe, persona), "tasks", describe(timeframe))
+        }
+        Ok(QueryResult::Diff {
+            period_a,
+            period_b,
+            stats_a,
+            stats_b,
+            delta,
+        }) => {
+            let mut text = format_diff(period_a, period_b, &stats_a, &stats_b, &delta);
+            if let Some(llm) = llm {
+                let span = (period_a.0.min(period_b.0), period_a.1.max(period_b.1));
+                let narrative = llm.generate(query, Some(span), narrate_diff(llm.client, persona, query, &text));
+                if let Some(narrative) = narrative.await {
+                    text.push('\n');
+                    text.push_str(&narrative);
+                }
+            }
+            response(
+                text,
+                "diff",
+                format!("{} vs {}", describe(period_a), describe(period_b)),
+            )
+        }
+        Ok(QueryResult::Correlations { app, correlations }) => RecallResponse {
+            app_filter: Some(app.clone()),
+            ..response(
+                format_correlations(&app, &correlations, persona),
+                "correlations",
+                "the last 30 days".to_string(),
+            )
+        },
+        Err(e) => response(format!("Error in query: {}", e), "error", timeframe_description),
+    }
+}
+
+// Ask the LLM to put the numbers into words; the numbers themselves come from SQL
+async fn narrate_diff(llm_client: &LlmChain, persona: &Persona, query: &str, table: &str) -> Option<String> {
+    let prompt = ContextWindowManager::from_env().fit_prompt(
+        &format!(
+            "{}\n\nThe user asked: \"{}\"\n\nActive time per app in the two periods:",
+            persona.system_prompt_prefix,
+            query.trim()
+        ),
+        table,
+        "In one or two sentences, describe the most important differences, \
+         e.g. \"You spent 2 more hours coding this week\". Only use the numbers in the table.",
+    );
+
+    match llm_client.generate_text(&prompt).await {
+        Ok(text) => Some(text.trim().to_string()),
+        Err(e) => {
+            eprintln!("⚠️ Failed to narrate comparison: {}", e);
+            None
+        }
+    }
+}
+
+// Answer an open-ended question from the summaries that matched it
+async fn answer_freeform(
+    llm_client: &LlmChain,
+    persona: &Persona,
+    query: &str,
+    summaries: &[ActivitySummary],
+) -> Option<String> {
+    match llm_client
+        .generate_text(&freeform_prompt(persona, query, summaries))
+        .await
+    {
+        Ok(text) => Some(text.trim().to_string()),
+        Err(e) => {
+            eprintln!("⚠️ Failed to answer question: {}", e);
+            None
+        }
+    }
+}
+
+// Writes an open-ended answer as the LLM generates it, then the summaries it
+// came from. Returns false, having written nothing, if the question isn't
+// answered from summaries.
+async fn stream_freeform<S>(
+    query: &str,
+    query_engine: &QueryEngine,
+    persona: &Persona,
+    llm: Llm<'_>,
+    socket: &mut S,
+    framed: bool,
+) -> Result<bool, Box<dyn Error + Send + Sync>>
+where
+    S: AsyncWrite + Unpin,
+{
+    let summaries = match query_engine.process_query(query).await.map_err(|e| e.to_string()) {
+        Ok(QueryAnswer {
+            result: QueryResult::Summaries(summaries),
+            ..
+        }) if !summaries.is_empty() => summaries,
+        _ => return Ok(false),
+    };
+
+    let prompt = freeform_prompt(persona, query, &summaries);
+    let mut written = false;
+    let mut write_result = Ok(());
+    let generate = async {
+        let pieces = llm
+            .client
+            .generate_text_stream(&prompt)
+            .await
+            .map_err(|e| e.to_string());
+        let mut pieces = match pieces {
+            Ok(pieces) => pieces,
+            Err(e) => {
+                eprintln!("⚠️ Failed to answer question: {}", e);
+                return None;
+            }
+        };
+
+        let mut answer = String::new();
+        loop {
+            // Box<dyn Error> isn't Send, so drop it before writing the piece
+            let piece = pieces.next().await.map(|piece| piece.map_err(|e| e.to_string()));
+            // An answer cut short isn't cached
+            let piece = match piece {
+                Some(Ok(piece)) => piece,
+                None => break,
+                Some(Err(e)) => {
+                    eprintln!("⚠️ Failed to finish answering question: {}", e);
+                    return None;
+                }
+            };
+            if let Err(e) = write_piece(socket, framed, piece.as_bytes()).await {
+                write_result = Err(e);
+                return None;
+            }
+            written = true;
+            answer.push_str(&piece);
+        }
+
+        Some(answer.trim().to_string())
+    };
+    let answer = llm
+        .generate(query, query_engine.parse_time_query(query), generate)
+        .await;
+    write_result?;
+
+    let summaries = format_summaries(summaries, query, persona);
+    let text = match (answer, written) {
+        (_, true) => format!("\n\n{}", summaries),
+        // From the cache, so none of it has been written
+        (Some(answer), false) => format!("{}\n\n{}", answer, summaries),
+        (None, false) => summaries,
+    };
+    write_piece(socket, framed, text.as_bytes()).await?;
+
+    Ok(true)
+}
+
+// A frame when the request was framed, else the text as it is
+async fn write_piece<S>(socket: &mut S, framed: bool, piece: &[u8]) -> Result<(), Box<dyn Error + Send + Sync>>
+where
+    S: AsyncWrite + Unpin,
+{
+    if framed {
+        write_framed(socket, piece).await
+    } else {
+        socket.write_all(piece).await?;
+        Ok(socket.flush().await?)
+    }
+}
+
+fn freeform_prompt(persona: &Persona, query: &str, summaries: &[ActivitySummary]) -> String {
+    let data = summaries
+        .iter()
+        .map(|s| {
+            format!(
+                "- {} – {}: {}",
+                s.start_time.format("%Y-%m-%d %H:%M"),
+                s.end_time.format("%H:%M"),
+                s.description
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let question = format!(
+        "The user asked: \"{}\"\nAnswer in a few sentences using only the activity above.",
+        query.trim()
+    );
+    ContextWindowManager::from_env().fit_prompt(&persona.system_prompt_prefix, &data, &question)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::{ComponentHealth, HealthStatus};
+    use activity_tracker_common::{GeneralDbClient, SummaryStore, TimescaleClient};
+    use async_trait::async_trait;
+    use chrono::{Duration as ChronoDuration, Utc};
+    use std::sync::RwLock;
+    use tokio::io::{duplex, DuplexStream};
+
+    // Answers every prompt with the same text, after a delay
+    struct ScriptedLlm {
+        reply: String,
+        delay: Duration,
+        load: Arc<LlmLoad>,
+    }
+
+    // Requests the LLM is answering now, and the most it answered at once
+    #[derive(Default)]
+    struct LlmLoad {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmClient for ScriptedLlm {
+        async fn generate_text(&self, _prompt: &str) -> Result<String, Box<dyn Error>> {
+            let in_flight = self.load.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.load.peak.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.load.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(self.reply.clone())
+        }
+
+        async fn extract_tags(&self, _text: &str) -> Result<Vec<String>, Box<dyn Error>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn healthy() -> ComponentHealth {
+        ComponentHealth {
+            healthy: true,
+            error: None,
+        }
+    }
+
+    // A server over a scratch summary database holding one summary from half
+    // an hour ago. Needs an events database from TEST_DATABASE_URL.
+    async fn server(dir: &tempfile::TempDir, llm: impl LlmClient + Send + Sync + 'static) -> Option<RecallServer> {
+        server_with_config(dir, config(Duration::from_secs(5)), llm).await
+    }
+
+    fn config(query_timeout: Duration) -> RecallConfig {
+        RecallConfig {
+            bind_addr: DEFAULT_BIND_ADDR.to_string(),
+            query_timeout,
+            batch_timeout: Duration::from_secs(BATCH_TIMEOUT_SECS),
+            max_concurrent_queries: DEFAULT_MAX_CONCURRENT_QUERIES,
+        }
+    }
+
+    async fn server_with_config(
+        dir: &tempfile::TempDir,
+        config: RecallConfig,
+        llm: impl LlmClient + Send + Sync + 'static,
+    ) -> Option<RecallServer> {
+        let Ok(events_db_url) = env::var("TEST_DATABASE_URL") else {
+            eprintln!("⚠️ SKIPPED: TEST_DATABASE_URL isn't set");
+            return None;
+        };
+        let events_db = TimescaleClient::new(&events_db_url).await.unwrap();
+        let summary_db_url = format!("sqlite:{}?mode=rwc", dir.path().join("summaries.db").display());
+        let summary_db = GeneralDbClient::new(&summary_db_url).await.unwrap();
+
+        let start_time = Utc::now() - ChronoDuration::minutes(30);
+        summary_db
+            .store_summary(&ActivitySummary {
+                start_time,
+                end_time: start_time + ChronoDuration::minutes(5),
+                description: DESCRIPTION.to_string(),
+                events: Vec::new(),
+                tags: vec!["rust".to_string()],
+            })
+            .await
+            .unwrap();
+
+        let mut chain = LlmChain::new(std::time::Duration::from_secs(60), std::time::Duration::from_secs(60));
+        chain.add_backend("scripted", Box::new(llm));
+        let health = HealthStatus {
+            postgres: healthy(),
+            sqlite: healthy(),
+            llm: healthy(),
+            checked_at: Utc::now(),
+        };
+        let llm = LlmService {
+            client: Some(Arc::new(chain)),
+            cache: None,
+            health: Arc::new(RwLock::new(health)),
+        };
+
+        Some(RecallServer::new(
+            config,
+            QueryEngine::new(summary_db.clone(), events_db),
+            FuzzyFinder::new(summary_db),
+            Arc::new(Persona::fishy()),
+            llm,
+        ))
+    }
+
+    fn scripted(reply: &str) -> ScriptedLlm {
+        slow(reply, Duration::ZERO).0
+    }
+
+    fn slow(reply: &str, delay: Duration) -> (ScriptedLlm, Arc<LlmLoad>) {
+        let load = Arc::new(LlmLoad::default());
+        let llm = ScriptedLlm {
+            reply: reply.to_string(),
+            delay,
+            load: load.clone(),
+        };
+        (llm, load)
+    }
+
+    // The client's end of a connection the server is handling
+    fn connect(server: &RecallServer) -> DuplexStream {
+        let (client, socket) = duplex(64 * 1024);
+        let server = server.clone();
+        tokio::spawn(async move { server.handle_connection(socket).await.map_err(|e| e.to_string()) });
+        client
+    }
+
+    async fn ask(server: &RecallServer, request: &str) -> String {
+        let mut client = connect(server);
+        client.write_all(request.as_bytes()).await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    async fn ask_framed(server: &RecallServer, request: &str) -> String {
+        let mut client = connect(server);
+        write_framed(&mut client, request.as_bytes()).await.unwrap();
+
+        String::from_utf8(read_framed(&mut client, MAX_REQUEST_BYTES).await.unwrap()).unwrap()
+    }
+
+    const DESCRIPTION: &str = "Reviewed the recall server in the editor";
+    const ANSWER: &str = "You spent the last hour on the recall server.";
+
+    #[tokio::test]
+    async fn a_plain_text_query_is_answered_before_the_connection_closes() {
+        let dir = tempfile::tempdir().unwrap();
+        let Some(server) = server(&dir, scripted(ANSWER)).await else {
+            return;
+        };
+
+        let response = ask(&server, "recall server").await;
+        assert!(response.contains(DESCRIPTION), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn a_framed_query_is_answered_with_a_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let Some(server) = server(&dir, scripted(ANSWER)).await else {
+            return;
+        };
+
+        let response: RecallResponse = serde_json::from_str(&ask_framed(&server, "JSON:recall server").await).unwrap();
+        assert_eq!(response.query_type, "summaries");
+        assert_eq!(response.timeframe_description, "all time");
+        assert!(
+            response.formatted_text.contains(DESCRIPTION),
+            "{}",
+            response.formatted_text
+        );
+    }
+
+    #[tokio::test]
+    async fn a_freeform_question_is_answered_by_the_llm() {
+        let dir = tempfile::tempdir().unwrap();
+        let Some(server) = server(&dir, scripted(ANSWER)).await else {
+            return;
+        };
+
+        let response = ask(&server, "what was I doing in the last hour").await;
+        assert!(response.starts_with(ANSWER), "{}", response);
+        assert!(response.contains(DESCRIPTION), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn a_streamed_answer_ends_with_an_empty_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let Some(server) = server(&dir, scripted(ANSWER)).await else {
+            return;
+        };
+        let mut client = connect(&server);
+        let request = format!("{}what was I doing in the last hour", STREAM_PREFIX);
+        write_framed(&mut client, request.as_bytes()).await.unwrap();
+
+        let mut pieces = Vec::new();
+        loop {
+            let piece = read_framed(&mut client, MAX_REQUEST_BYTES).await.unwrap();
+            if piece.is_empty() {
+                break;
+            }
+            pieces.push(String::from_utf8(piece).unwrap());
+        }
+
+        assert_eq!(pieces[0], ANSWER);
+        assert!(pieces.concat().contains(DESCRIPTION), "{:?}", pieces);
+    }
+
+    #[tokio::test]
+    async fn a_raw_query_is_answered_with_the_result_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let Some(server) = server(&dir, scripted(ANSWER)).await else {
+            return;
+        };
+
+        let response = ask(&server, "RAW:recall server").await;
+        let result: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(result["type"], "summaries");
+        assert_eq!(result["data"][0]["description"], DESCRIPTION);
+        assert_eq!(result["total_count"], 1);
+
+        let answer: QueryAnswer = serde_json::from_str(&response).unwrap();
+        assert!(matches!(answer.result, QueryResult::Summaries(summaries) if summaries.len() == 1));
+    }
+
+    #[test]
+    fn only_a_page_of_summaries_says_how_many_there_are() {
+        assert_eq!(
+            format_showing(10, Some(157)).as_deref(),
+            Some("Showing 1–10 of 157 results")
+        );
+        assert_eq!(format_showing(10, Some(10)), None);
+        assert_eq!(format_showing(3, None), None);
+    }
+
+    #[tokio::test]
+    async fn a_batch_is_answered_in_words_in_the_order_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let Some(server) = server(&dir, scripted(ANSWER)).await else {
+            return;
+        };
+        let queries = ["recall server".to_string(), "what did I do with the editor".to_string()];
+
+        let answers = server.query_engine.process_batch(&queries).await.unwrap();
+
+        assert_eq!(answers.len(), 2);
+        for ((query, response), asked) in answers.iter().zip(&queries) {
+            assert_eq!(query, asked);
+            assert!(response.contains(DESCRIPTION), "{}", response);
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_questions_in_a_batch_share_one_deadline() {
+        let dir = tempfile::tempdir().unwrap();
+        let (llm, _) = slow(ANSWER, Duration::from_secs(10));
+        let config = RecallConfig {
+            batch_timeout: Duration::from_millis(500),
+            ..config(Duration::from_secs(5))
+        };
+        let Some(server) = server_with_config(&dir, config, llm).await else {
+            return;
+        };
+        let request = format!(
+            "{}{}",
+            BATCH_PREFIX,
+            ["what was I doing in the last hour"; 3].join("\n")
+        );
+
+        let started = tokio::time::Instant::now();
+        let answers: Vec<BatchAnswer> = serde_json::from_str(&ask(&server, &request).await).unwrap();
+
+        // Each query alone would have had five seconds
+        assert!(
+            started.elapsed() < Duration::from_secs(3),
+            "took {:?}",
+            started.elapsed()
+        );
+        assert_eq!(answers.len(), 3);
+        for answer in answers {
+            assert_eq!(answer.response, Persona::fishy().timeout_message);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_burst_of_queries_never_runs_more_llm_requests_than_the_limit() {
+        const QUERIES: usize = 50;
+        let dir = tempfile::tempdir().unwrap();
+        let (llm, load) = slow(ANSWER, Duration::from_millis(50));
+        let query_timeout = Duration::from_secs(5);
+        let Some(server) = server_with_config(&dir, config(query_timeout), llm).await else {
+            return;
+        };
+
+        let queries = (0..QUERIES).map(|_| {
+            let server = server.clone();
+            tokio::spawn(async move {
+                let started = tokio::time::Instant::now();
+                let response = ask(&server, "what was I doing in the last hour").await;
+                (response, started.elapsed())
+            })
+        });
+        let responses: Vec<(String, Duration)> = futures::future::try_join_all(queries).await.unwrap();
+
+        let mut answered = 0;
+        for (response, elapsed) in &responses {
+            assert!(*elapsed <= query_timeout * QUERIES as u32, "took {:?}", elapsed);
+            if response.starts_with(ANSWER) {
+                answered += 1;
+            } else {
+                assert_eq!(response, &Persona::fishy().busy_message);
+            }
+        }
+        assert!(
+            answered >= DEFAULT_MAX_CONCURRENT_QUERIES + MAX_QUEUED_QUERIES,
+            "{} answered",
+            answered
+        );
+        assert!(load.peak.load(Ordering::SeqCst) <= DEFAULT_MAX_CONCURRENT_QUERIES);
+        assert_eq!(load.in_flight.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn a_slow_llm_is_cut_off_with_the_timeout_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let (llm, _) = slow(ANSWER, Duration::from_secs(10));
+        let Some(server) = server_with_config(&dir, config(Duration::from_millis(200)), llm).await else {
+            return;
+        };
+
+        let started = tokio::time::Instant::now();
+        let response = ask(&server, "what was I doing in the last hour").await;
+
+        assert_eq!(response, Persona::fishy().timeout_message);
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "took {:?}",
+            started.elapsed()
+        );
+    }
+
+    #[tokio::test]
+    async fn a_timed_out_query_says_so_in_its_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let (llm, _) = slow(ANSWER, Duration::from_secs(10));
+        let Some(server) = server_with_config(&dir, config(Duration::from_millis(200)), llm).await else {
+            return;
+        };
+
+        let response = ask_framed(&server, "JSON:what was I doing in the last hour").await;
+        let response: RecallResponse = serde_json::from_str(&response).unwrap();
+
+        assert_eq!(response.query_type, "timeout");
+        assert_eq!(response.formatted_text, Persona::fishy().timeout_message);
+    }
+
+    #[tokio::test]
+    async fn a_slow_stream_ends_with_the_timeout_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let (llm, _) = slow(ANSWER, Duration::from_secs(10));
+        let Some(server) = server_with_config(&dir, config(Duration::from_millis(200)), llm).await else {
+            return;
+        };
+        let mut client = connect(&server);
+        let request = format!("{}what was I doing in the last hour", STREAM_PREFIX);
+        write_framed(&mut client, request.as_bytes()).await.unwrap();
+
+        let piece = read_framed(&mut client, MAX_REQUEST_BYTES).await.unwrap();
+        assert_eq!(
+            String::from_utf8(piece).unwrap().trim(),
+            Persona::fishy().timeout_message
+        );
+        assert!(read_framed(&mut client, MAX_REQUEST_BYTES).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn queries_that_need_no_llm_are_answered_while_it_hangs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (llm, load) = slow(ANSWER, Duration::from_secs(10));
+        let Some(server) = server_with_config(&dir, config(Duration::from_millis(200)), llm).await else {
+            return;
+        };
+
+        let response = ask(&server, "recall server").await;
+
+        assert!(response.contains(DESCRIPTION), "{}", response);
+        assert_eq!(load.peak.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,523 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+// "between 9am and 11am", "from 14:30 to 16:00", "between 10pm and 2am", ...
+fn hour_range_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"(?:between|from)\s+(\d{1,2})(?::(\d{2}))?\s*(am|pm)?\s+(?:and|to|until|-)\s+(\d{1,2})(?::(\d{2}))?\s*(am|pm)?",
+        )
+        .unwrap()
+    })
+}
+
+// "3 days ago", "2 weeks ago", ...
+fn ago_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\b(\d+)\s*(hour|day|week|month)s?\s+ago\b").unwrap())
+}
+
+// "march 15", "sept 3rd", "dec. 24", ...
+fn month_day_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"\b(jan(?:uary)?|feb(?:ruary)?|mar(?:ch)?|apr(?:il)?|may|june?|july?|aug(?:ust)?|sept?(?:ember)?|oct(?:ober)?|nov(?:ember)?|dec(?:ember)?)\.?\s+(\d{1,2})(?:st|nd|rd|th)?\b",
+        )
+        .unwrap()
+    })
+}
+
+pub type Timeframe = (DateTime<Utc>, DateTime<Utc>);
+
+// How far back "what was I just doing" looks
+pub const RECENT_MINUTES: i64 = 10;
+
+// Phrases asking about the last few minutes, matched as whole words
+const RECENT_PHRASES: [&str; 5] = ["just", "right now", "moment ago", "minute ago", "few minutes"];
+
+// Anything further back than this many units is before any recorded activity
+const MAX_AGO: i64 = 10_000;
+
+// Matched on their first three letters
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("monday", Weekday::Mon),
+    ("tuesday", Weekday::Tue),
+    ("wednesday", Weekday::Wed),
+    ("thursday", Weekday::Thu),
+    ("friday", Weekday::Fri),
+    ("saturday", Weekday::Sat),
+    ("sunday", Weekday::Sun),
+];
+
+// The period a query asks about, e.g. "yesterday" or "between 9am and 11am".
+// Queries come straight off the socket, so any input must give None or a
+// range that starts no later than it ends.
+pub fn parse_timeframe(query: &str) -> Option<Timeframe> {
+    // Specific clock times take precedence over the day they fall on
+    if let Some(timeframe) = HourRange::parse(query).and_then(|range| range.to_timeframe()) {
+        return Some(timeframe);
+    }
+
+    let now = Utc::now();
+
+    if is_recent(query) {
+        return Some((now - Duration::minutes(RECENT_MINUTES), now));
+    }
+
+    if let Some(ago) = Ago::parse(query) {
+        return ago.to_timeframe();
+    }
+
+    if let Some(day) = parse_month_day(query) {
+        return day_timeframe(day);
+    }
+
+    let query = query.to_lowercase();
+    if query.contains("last week") || query.contains("this week") {
+        let end = now;
+        let start = now - Duration::days(7);
+        Some((start, end))
+    } else if let Some(day) = parse_weekday(&query) {
+        day_timeframe(day)
+    } else if query.contains("yesterday") {
+        let end = now;
+        let start = now - Duration::days(1);
+        Some((start, end))
+    } else if query.contains("today") {
+        let end = now;
+        let start = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+// Whether the query asks about the last RECENT_MINUTES, e.g. "what was I just doing"
+pub fn is_recent(query: &str) -> bool {
+    let query = query.to_lowercase();
+    let words: Vec<&str> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let normalized = format!(" {} ", words.join(" "));
+
+    RECENT_PHRASES
+        .iter()
+        .any(|phrase| normalized.contains(&format!(" {} ", phrase)))
+}
+
+// How parse_timeframe describes a period counted back from now or named by
+// its date, e.g. "3 days ago (2024-11-10)" or "March 15 (2024-03-15)"
+pub fn describe_timeframe(query: &str) -> Option<String> {
+    if is_recent(query) {
+        return None;
+    }
+
+    if let Some(ago) = Ago::parse(query) {
+        return ago.description();
+    }
+
+    parse_month_day(query).map(|day| format!("{} ({})", day.format("%B %-d"), day))
+}
+
+// The single past local day a query names, e.g. "yesterday", "last
+// wednesday", "3 days ago" or "march 15"
+pub fn parse_day(query: &str) -> Option<NaiveDate> {
+    if query.to_lowercase().contains("yesterday") {
+        return Some(Local::now().date_naive() - Duration::days(1));
+    }
+
+    parse_weekday(query)
+        .or_else(|| Ago::parse(query).and_then(|ago| ago.day()))
+        .or_else(|| parse_month_day(query))
+}
+
+// The most recent day with that month and day of the month, which is last
+// year's when this year's hasn't happened yet
+fn parse_month_day(query: &str) -> Option<NaiveDate> {
+    let query = query.to_lowercase();
+    let captures = month_day_regex().captures(&query)?;
+
+    let month = MONTHS.iter().position(|month| captures[1].starts_with(month))? as u32 + 1;
+    let day: u32 = captures[2].parse().ok()?;
+
+    let today = Local::now().date_naive();
+    NaiveDate::from_ymd_opt(today.year(), month, day)
+        .filter(|date| *date <= today)
+        .or_else(|| NaiveDate::from_ymd_opt(today.year() - 1, month, day))
+}
+
+// The most recent past day with the weekday the query names, so on a
+// Wednesday "wednesday" means a week ago
+fn parse_weekday(query: &str) -> Option<NaiveDate> {
+    let query = query.to_lowercase();
+    let (_, weekday) = WEEKDAYS.iter().find(|(name, _)| query.contains(name))?;
+
+    let today = Local::now().date_naive();
+    let days_back = match (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7 {
+        0 => 7,
+        days => days,
+    };
+
+    Some(today - Duration::days(days_back as i64))
+}
+
+// From the local midnight starting the day to the one ending it
+pub fn day_timeframe(day: NaiveDate) -> Option<Timeframe> {
+    let start = Local.from_local_datetime(&day.and_hms_opt(0, 0, 0)?).earliest()?;
+    let end = Local
+        .from_local_datetime(&(day + Duration::days(1)).and_hms_opt(0, 0, 0)?)
+        .earliest()?;
+
+    Some((start.with_timezone(&Utc), end.with_timezone(&Utc)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AgoUnit {
+    Hour,
+    Day,
+    Week,
+    Month,
+}
+
+// A period counted back from now, e.g. "3 days ago"
+#[derive(Debug, Clone, PartialEq)]
+struct Ago {
+    count: i64,
+    unit: AgoUnit,
+}
+
+impl Ago {
+    fn parse(query: &str) -> Option<Self> {
+        let query = query.to_lowercase();
+        let captures = ago_regex().captures(&query)?;
+
+        let count = captures[1].parse().ok().filter(|count| (1..=MAX_AGO).contains(count))?;
+        let unit = match &captures[2] {
+            "hour" => AgoUnit::Hour,
+            "day" => AgoUnit::Day,
+            "week" => AgoUnit::Week,
+            _ => AgoUnit::Month,
+        };
+
+        Some(Self { count, unit })
+    }
+
+    // The local day for "3 days ago"; other units don't name a single day
+    fn day(&self) -> Option<NaiveDate> {
+        match self.unit {
+            AgoUnit::Day => Some(Local::now().date_naive() - Duration::days(self.count)),
+            _ => None,
+        }
+    }
+
+    // "3 days ago" is that whole local day. Other units run for one unit
+    // from then, so "2 hours ago" is the hour after that time, and a month
+    // counts as 30 days.
+    fn to_timeframe(&self) -> Option<Timeframe> {
+        if let Some(day) = self.day() {
+            return day_timeframe(day);
+        }
+
+        let unit = self.unit_duration();
+        let now = Utc::now();
+        Some((now - unit * self.count as i32, now - unit * (self.count - 1) as i32))
+    }
+
+    // e.g. "3 days ago (2024-11-10)" or "2 hours ago (13:05 to 14:05)"
+    fn description(&self) -> Option<String> {
+        let unit = match self.unit {
+            AgoUnit::Hour => "hour",
+            AgoUnit::Day => "day",
+            AgoUnit::Week => "week",
+            AgoUnit::Month => "month",
+        };
+        let ago = format!("{} {}{} ago", self.count, unit, if self.count == 1 { "" } else { "s" });
+
+        if let Some(day) = self.day() {
+            return Some(format!("{} ({})", ago, day));
+        }
+
+        let (start, end) = self.to_timeframe()?;
+        let (start, end) = (start.with_timezone(&Local), end.with_timezone(&Local));
+        let format = if self.unit == AgoUnit::Hour {
+            "%H:%M"
+        } else {
+            "%Y-%m-%d"
+        };
+        Some(format!("{} ({} to {})", ago, start.format(format), end.format(format)))
+    }
+
+    fn unit_duration(&self) -> Duration {
+        match self.unit {
+            AgoUnit::Hour => Duration::hours(1),
+            AgoUnit::Day => Duration::days(1),
+            AgoUnit::Week => Duration::weeks(1),
+            AgoUnit::Month => Duration::days(30),
+        }
+    }
+}
+
+// A clock-time range on a single day, in the user's local time zone
+#[derive(Debug, Clone, PartialEq)]
+pub struct HourRange {
+    // 0 for today, 1 for yesterday
+    pub days_ago: i64,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl HourRange {
+    pub fn parse(query: &str) -> Option<Self> {
+        let query = query.to_lowercase();
+        let captures = hour_range_regex().captures(&query)?;
+
+        let start_meridiem = captures.get(3).map(|m| m.as_str());
+        let end_meridiem = captures.get(6).map(|m| m.as_str());
+
+        let (start_hour, start_minute) = clock_parts(&captures, 1, 2)?;
+        let (end_hour, end_minute) = clock_parts(&captures, 4, 5)?;
+
+        let end = to_time(end_hour, end_minute, end_meridiem)?;
+        let start = match (start_meridiem, end_meridiem) {
+            // "between 9 and 11am": the start shares the end's am/pm unless
+            // that would put it after the end, as in "between 11 and 2pm"
+            (None, Some(meridiem)) => {
+                let shared = to_time(start_hour, start_minute, Some(meridiem))?;
+                if shared <= end {
+                    shared
+                } else {
+                    to_time(
+                        start_hour,
+                        start_minute,
+                        Some(if meridiem == "pm" { "am" } else { "pm" }),
+                    )?
+                }
+            }
+            _ => to_time(start_hour, start_minute, start_meridiem)?,
+        };
+
+        let days_ago = if query.contains("yesterday") { 1 } else { 0 };
+
+        Some(Self { days_ago, start, end })
+    }
+
+    // Ranges that end before they start cross midnight into the next day
+    pub fn to_timeframe(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let day = Local::now().date_naive() - Duration::days(self.days_ago);
+        let start = Local.from_local_datetime(&day.and_time(self.start)).earliest()?;
+
+        let end_day = if self.end <= self.start {
+            day + Duration::days(1)
+        } else {
+            day
+        };
+        let end = Local.from_local_datetime(&end_day.and_time(self.end)).earliest()?;
+
+        Some((start.with_timezone(&Utc), end.with_timezone(&Utc)))
+    }
+
+    // e.g. "today between 09:00 and 11:00"
+    pub fn description(&self) -> String {
+        format!(
+            "{} between {} and {}",
+            if self.days_ago == 1 { "yesterday" } else { "today" },
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+fn clock_parts(captures: &Captures, hour: usize, minute: usize) -> Option<(u32, u32)> {
+    let hour = captures.get(hour)?.as_str().parse().ok()?;
+    let minute = match captures.get(minute) {
+        Some(m) => m.as_str().parse().ok()?,
+        None => 0,
+    };
+    Some((hour, minute))
+}
+
+// Converts a 12h (with am/pm) or 24h clock reading, rejecting invalid times
+fn to_time(hour: u32, minute: u32, meridiem: Option<&str>) -> Option<NaiveTime> {
+    let hour = match meridiem {
+        Some(_) if hour == 0 || hour > 12 => return None,
+        Some("am") => hour % 12,
+        Some(_) => hour % 12 + 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn hours(query: &str) -> (i64, NaiveTime, NaiveTime) {
+        let range = HourRange::parse(query).unwrap_or_else(|| panic!("{} didn't parse", query));
+        (range.days_ago, range.start, range.end)
+    }
+
+    #[test]
+    fn twelve_hour_ranges() {
+        assert_eq!(
+            hours("what did I do between 9am and 11am"),
+            (0, time(9, 0), time(11, 0))
+        );
+        assert_eq!(hours("between 1:30pm and 3pm"), (0, time(13, 30), time(15, 0)));
+    }
+
+    #[test]
+    fn twenty_four_hour_ranges() {
+        assert_eq!(hours("from 14:30 to 16:00"), (0, time(14, 30), time(16, 0)));
+        assert_eq!(hours("from 8 until 17"), (0, time(8, 0), time(17, 0)));
+    }
+
+    #[test]
+    fn the_start_shares_the_end_meridiem() {
+        assert_eq!(hours("between 9 and 11am"), (0, time(9, 0), time(11, 0)));
+        assert_eq!(hours("between 2 and 4pm"), (0, time(14, 0), time(16, 0)));
+    }
+
+    #[test]
+    fn a_shared_meridiem_never_puts_the_start_after_the_end() {
+        assert_eq!(hours("between 11 and 2pm"), (0, time(11, 0), time(14, 0)));
+    }
+
+    #[test]
+    fn midnight_and_noon() {
+        assert_eq!(hours("between 12am and 1am"), (0, time(0, 0), time(1, 0)));
+        assert_eq!(hours("between 12pm and 1pm"), (0, time(12, 0), time(13, 0)));
+    }
+
+    #[test]
+    fn ranges_can_cross_midnight() {
+        assert_eq!(hours("between 10pm and 2am"), (0, time(22, 0), time(2, 0)));
+
+        let (start, end) = HourRange::parse("between 10pm and 2am")
+            .unwrap()
+            .to_timeframe()
+            .unwrap();
+        assert_eq!(end - start, Duration::hours(4));
+    }
+
+    #[test]
+    fn yesterdays_ranges() {
+        let range = HourRange::parse("yesterday between 9am and 11am").unwrap();
+
+        assert_eq!(range.days_ago, 1);
+        assert_eq!(range.description(), "yesterday between 09:00 and 11:00");
+        assert_eq!(
+            HourRange::parse("between 9am and 11am").unwrap().description(),
+            "today between 09:00 and 11:00"
+        );
+    }
+
+    #[test]
+    fn invalid_clock_times_are_rejected() {
+        for query in [
+            "between 13pm and 2pm",
+            "between 0am and 2am",
+            "from 25:00 to 26:00",
+            "from 9:75 to 10:00",
+        ] {
+            assert_eq!(HourRange::parse(query), None, "{}", query);
+            assert_eq!(parse_timeframe(query), None, "{}", query);
+        }
+    }
+
+    #[test]
+    fn days_ago_are_whole_local_days() {
+        let day = Local::now().date_naive() - Duration::days(3);
+
+        assert_eq!(parse_timeframe("what did I do 3 days ago"), day_timeframe(day));
+        assert_eq!(parse_day("3 days ago"), Some(day));
+        assert_eq!(describe_timeframe("3 days ago"), Some(format!("3 days ago ({})", day)));
+    }
+
+    #[test]
+    fn hours_ago_last_an_hour() {
+        let (start, end) = parse_timeframe("2 hours ago").unwrap();
+
+        assert_eq!(end - start, Duration::hours(1));
+        assert!(end <= Utc::now() - Duration::hours(1));
+    }
+
+    #[test]
+    fn counts_out_of_range_are_ignored() {
+        assert_eq!(parse_timeframe("0 days ago"), None);
+        assert_eq!(parse_timeframe("99999 weeks ago"), None);
+    }
+
+    #[test]
+    fn recent_questions_cover_the_last_few_minutes() {
+        let (start, end) = parse_timeframe("what was I just doing").unwrap();
+
+        assert_eq!(end - start, Duration::minutes(RECENT_MINUTES));
+        assert_eq!(describe_timeframe("what was I just doing"), None);
+    }
+
+    #[test]
+    fn named_periods() {
+        let (start, end) = parse_timeframe("this week").unwrap();
+        assert_eq!(end - start, Duration::days(7));
+
+        let (start, end) = parse_timeframe("yesterday").unwrap();
+        assert_eq!(end - start, Duration::days(1));
+
+        let (start, _) = parse_timeframe("today").unwrap();
+        assert_eq!(start, Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+
+    #[test]
+    fn named_periods_ignore_case() {
+        assert!(parse_timeframe("What did I do Today?").is_some());
+        assert!(parse_timeframe("LAST WEEK").is_some());
+        assert!(parse_timeframe("Yesterday").is_some());
+    }
+
+    #[test]
+    fn weekdays_are_the_most_recent_past_one() {
+        let today = Local::now().date_naive();
+
+        for (name, weekday) in WEEKDAYS {
+            let day = parse_day(&format!("what did I do last {}", name)).unwrap();
+            assert_eq!(day.weekday(), weekday);
+            assert!((1..=7).contains(&(today - day).num_days()), "{} is {}", name, day);
+        }
+    }
+
+    #[test]
+    fn month_days_are_never_in_the_future() {
+        let day = parse_day("march 15").unwrap();
+
+        assert_eq!((day.month(), day.day()), (3, 15));
+        assert!(day <= Local::now().date_naive());
+        assert_eq!(parse_day("Sept 3rd").map(|day| (day.month(), day.day())), Some((9, 3)));
+        assert_eq!(describe_timeframe("march 15"), Some(format!("March 15 ({})", day)));
+    }
+
+    #[test]
+    fn impossible_dates_are_rejected() {
+        assert_eq!(parse_day("feb 30"), None);
+        assert_eq!(parse_timeframe("april 31"), None);
+    }
+
+    #[test]
+    fn queries_without_a_period_give_none() {
+        for query in ["", "when did I work on the parser", "between lunch and dinner", "ago"] {
+            assert_eq!(parse_timeframe(query), None, "{:?}", query);
+            assert_eq!(parse_day(query), None, "{:?}", query);
+        }
+    }
+}
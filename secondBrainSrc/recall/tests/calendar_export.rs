@@ -0,0 +1,130 @@
+// Exported calendars are read back with an independent iCalendar parser
+#[path = "../../common/tests/support/summaries.rs"]
+#[allow(dead_code)]
+mod support;
+
+use activity_tracker_recall::calendar_export::export_summaries_to_ics;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use ical::parser::ical::component::{IcalCalendar, IcalEvent};
+use ical::IcalParser;
+use support::{at, summary};
+
+fn parse(ics: &str) -> IcalCalendar {
+    let mut calendars: Vec<IcalCalendar> = IcalParser::new(ics.as_bytes()).map(Result::unwrap).collect();
+    assert_eq!(calendars.len(), 1);
+    calendars.remove(0)
+}
+
+fn property<'a>(properties: &'a [ical::property::Property], name: &str) -> &'a str {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .and_then(|property| property.value.as_deref())
+        .unwrap_or_else(|| panic!("no {}", name))
+}
+
+fn time(event: &IcalEvent, name: &str) -> DateTime<Utc> {
+    let value = property(&event.properties, name);
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+        .unwrap()
+        .and_utc()
+}
+
+// Text values as written, before iCalendar escaping
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+#[test]
+fn every_span_of_activity_is_read_back_as_one_event() {
+    let summaries = [
+        summary(at(14, 0), 30, "Wrote the exporter", &["rust"]),
+        summary(at(9, 0), 5, "Reviewed the calendar export", &["review", "rust"]),
+        summary(at(9, 8), 5, "Fixed line folding", &["rust", "ics"]),
+        summary(at(9, 10), 20, "Read RFC 5545", &["reading"]),
+    ];
+
+    let calendar = parse(&export_summaries_to_ics(&summaries));
+
+    assert_eq!(
+        property(&calendar.properties, "PRODID"),
+        "-//SecondBrain//ActivityTracker//EN"
+    );
+    assert_eq!(property(&calendar.properties, "VERSION"), "2.0");
+    assert_eq!(calendar.events.len(), 2);
+
+    let morning = &calendar.events[0];
+    assert_eq!(
+        (time(morning, "DTSTART"), time(morning, "DTEND")),
+        (at(9, 0), at(9, 30))
+    );
+    assert_eq!(
+        unescape(property(&morning.properties, "DESCRIPTION")),
+        "Reviewed the calendar export\nFixed line folding\nRead RFC 5545"
+    );
+    assert_eq!(property(&morning.properties, "CATEGORIES"), "review,rust,ics,reading");
+
+    let afternoon = &calendar.events[1];
+    assert_eq!(
+        (time(afternoon, "DTSTART"), time(afternoon, "DTEND")),
+        (at(14, 0), at(14, 30))
+    );
+    assert_eq!(property(&afternoon.properties, "SUMMARY"), "Wrote the exporter");
+    assert_ne!(
+        property(&morning.properties, "UID"),
+        property(&afternoon.properties, "UID")
+    );
+}
+
+#[test]
+fn summaries_further_apart_than_the_gap_stay_separate() {
+    let summaries = [
+        summary(at(9, 0), 5, "Answered email", &[]),
+        summary(at(9, 11), 5, "Planned the sprint", &[]),
+    ];
+
+    let calendar = parse(&export_summaries_to_ics(&summaries));
+
+    let spans: Vec<_> = calendar
+        .events
+        .iter()
+        .map(|event| (time(event, "DTSTART"), time(event, "DTEND")))
+        .collect();
+    assert_eq!(spans, [(at(9, 0), at(9, 5)), (at(9, 11), at(9, 16))]);
+    assert!(calendar
+        .events
+        .iter()
+        .all(|event| event.properties.iter().all(|p| p.name != "CATEGORIES")));
+}
+
+#[test]
+fn long_text_survives_escaping_and_line_folding() {
+    let description = "Débogué le parseur; puis lu la RFC, encore et encore \\ sans fin. ".repeat(4);
+    let summaries = [summary(at(10, 0), 5, description.trim(), &["ünïcode", "a,b"])];
+
+    let ics = export_summaries_to_ics(&summaries);
+    assert!(ics.split("\r\n").all(|line| line.len() <= 75), "{}", ics);
+    let calendar = parse(&ics);
+
+    let event = &calendar.events[0];
+    assert_eq!(unescape(property(&event.properties, "DESCRIPTION")), description.trim());
+    let title = unescape(property(&event.properties, "SUMMARY"));
+    assert_eq!(title.chars().count(), 80);
+    assert!(description.starts_with(&title));
+    assert_eq!(property(&event.properties, "CATEGORIES"), "ünïcode,a\\,b");
+}
+
+#[test]
+fn no_summaries_is_an_empty_calendar() {
+    let calendar = parse(&export_summaries_to_ics(&[]));
+
+    assert!(calendar.events.is_empty());
+    assert_eq!(
+        property(&calendar.properties, "PRODID"),
+        "-//SecondBrain//ActivityTracker//EN"
+    );
+}
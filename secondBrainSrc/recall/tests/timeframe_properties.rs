@@ -0,0 +1,92 @@
+// Whatever arrives on the socket, timeframes parse into a forward range or nothing
+use activity_tracker_recall::timeframe::parse_timeframe;
+use chrono::{Duration, Utc};
+use proptest::prelude::*;
+
+// Enough slack for the clock to move between two parses of the same query
+fn close(a: chrono::DateTime<Utc>, b: chrono::DateTime<Utc>) -> bool {
+    (a - b).num_seconds().abs() <= 5
+}
+
+// Text around a phrase that can't itself be read as a timeframe
+fn filler() -> impl Strategy<Value = String> {
+    "[a-z ]{0,20}".prop_filter("no timeframe", |text| parse_timeframe(text).is_none())
+}
+
+fn units() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec!["hour", "day", "week", "month"])
+}
+
+fn phrase() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just("today".to_string()),
+        Just("yesterday".to_string()),
+        Just("last week".to_string()),
+        Just("just now".to_string()),
+        Just("last friday".to_string()),
+        (1..=10_000i64, units()).prop_map(|(n, unit)| format!("{} {}s ago", n, unit)),
+        (0..24u32, 0..24u32).prop_map(|(from, to)| format!("between {}:00 and {}:30", from, to)),
+        (1..=12u32, 1..=12u32, prop::sample::select(vec!["am", "pm"]))
+            .prop_map(|(from, to, m)| format!("from {} to {}{}", from, to, m)),
+        (prop::sample::select(vec!["jan", "march", "sept", "dec."]), 1..=28u32)
+            .prop_map(|(month, day)| format!("{} {}", month, day)),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn any_query_gives_nothing_or_a_forward_range(query in ".*") {
+        if let Some((start, end)) = parse_timeframe(&query) {
+            prop_assert!(start <= end, "{:?} gave {} to {}", query, start, end);
+        }
+    }
+
+    #[test]
+    fn timeframe_phrases_are_found_anywhere_in_a_query(before in filler(), phrase in phrase(), after in filler()) {
+        let query = format!("{} {} {}", before, phrase, after);
+
+        let timeframe = parse_timeframe(&query);
+        prop_assert!(timeframe.is_some(), "{:?} gave nothing", query);
+        let (start, end) = timeframe.unwrap();
+        prop_assert!(start <= end, "{:?} gave {} to {}", query, start, end);
+    }
+
+    #[test]
+    fn case_doesnt_change_the_timeframe(before in filler(), phrase in phrase(), after in filler()) {
+        let query = format!("{} {} {}", before, phrase, after);
+
+        match (parse_timeframe(&query), parse_timeframe(&query.to_uppercase())) {
+            (Some((start, end)), Some((upper_start, upper_end))) => {
+                prop_assert!(close(start, upper_start) && close(end, upper_end), "{:?}", query);
+            }
+            (lower, upper) => prop_assert_eq!(lower.is_none(), upper.is_none(), "{:?}", query),
+        }
+    }
+
+    #[test]
+    fn days_ago_is_one_whole_day_before_the_next(days in 1..=9_999i64) {
+        let (start, end) = parse_timeframe(&format!("what did I do {} days ago", days)).unwrap();
+        let (later_start, _) = parse_timeframe(&format!("what did I do {} days ago", days - 1))
+            .filter(|_| days > 1)
+            .unwrap_or((end, end));
+
+        // Local days are 23 to 25 hours long around daylight saving changes
+        prop_assert!(end - start >= Duration::hours(23) && end - start <= Duration::hours(25));
+        prop_assert_eq!(end, later_start);
+        prop_assert!(end <= Utc::now() - Duration::hours(23) * (days - 1) as i32);
+    }
+
+    #[test]
+    fn hours_ago_is_the_hour_after_that_time(hours in 1..=10_000i64) {
+        let (start, end) = parse_timeframe(&format!("{} hours ago", hours)).unwrap();
+
+        prop_assert_eq!(end - start, Duration::hours(1));
+        prop_assert!(close(end, Utc::now() - Duration::hours(hours - 1)));
+    }
+
+    #[test]
+    fn counts_beyond_any_recorded_activity_give_nothing(count in 10_001..u64::MAX, unit in units()) {
+        let query = format!("{} {}s ago", count, unit);
+        prop_assert!(parse_timeframe(&query).is_none(), "{:?}", query);
+    }
+}
@@ -0,0 +1,148 @@
+use activity_tracker_common::config::{config_path, Config};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::path::Path;
+use std::process::Command;
+use toml_edit::{DocumentMut, Item, Value};
+
+const TEMPLATE: &str = include_str!("templates/config.toml");
+
+// Editors tried when $EDITOR isn't set
+const FALLBACK_EDITORS: [&str; 2] = ["nano", "vi"];
+
+// Opens the config file in $EDITOR until it's valid or the user gives up
+pub fn edit() -> Result<(), Box<dyn Error>> {
+    let path = config_path();
+
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, TEMPLATE)?;
+        println!("📝 Created {}", path.display());
+    }
+
+    loop {
+        open_editor(&path)?;
+
+        match Config::load(&path) {
+            Ok(_) => {
+                println!("✅ {} is valid", path.display());
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                if !confirm("retry? [Y/n] ")? {
+                    return Err(format!("{} is not valid", path.display()).into());
+                }
+            }
+        }
+    }
+}
+
+// Prints the config the services would use, env var fallbacks included
+pub fn show() -> Result<(), Box<dyn Error>> {
+    let path = config_path();
+    let config = match Config::load(&path) {
+        Ok(config) => config.resolved()?,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return Err(format!("{} is not valid", path.display()).into());
+        }
+    };
+
+    println!("# {}", path.display());
+    print!("{}", toml::to_string_pretty(&config)?);
+
+    Ok(())
+}
+
+// Sets one dotted key, e.g. "persona.name", keeping the file's comments.
+// The file is only written if the result is still a valid config.
+pub fn set(key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    let path = config_path();
+    let contents = if path.exists() {
+        fs::read_to_string(&path)?
+    } else {
+        TEMPLATE.to_string()
+    };
+    let mut document: DocumentMut = match contents.parse() {
+        Ok(document) => document,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return Err(format!("{} is not valid", path.display()).into());
+        }
+    };
+
+    let mut sections: Vec<&str> = key.split('.').collect();
+    let field = sections.pop().filter(|field| !field.is_empty());
+    let Some(field) = field.filter(|_| sections.iter().all(|s| !s.is_empty())) else {
+        return Err(format!("'{}' is not a valid key", key).into());
+    };
+
+    let mut table = document.as_table_mut() as &mut dyn toml_edit::TableLike;
+    for section in sections {
+        table = table
+            .entry(section)
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .ok_or_else(|| format!("'{}' is not a section", section))?;
+    }
+
+    // Anything that isn't a TOML value, like a bare word, is set as a string
+    let value = value.parse::<Value>().unwrap_or_else(|_| Value::from(value));
+    table.insert(field, Item::Value(value));
+
+    let contents = document.to_string();
+    if let Err(e) = Config::from_toml(&contents) {
+        eprintln!("❌ {}", e);
+        return Err(format!("{} was not changed", path.display()).into());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, contents)?;
+    println!("✅ Set {} in {}", key, path.display());
+
+    Ok(())
+}
+
+fn open_editor(path: &Path) -> Result<(), Box<dyn Error>> {
+    let editors = match env::var("EDITOR") {
+        Ok(editor) if !editor.trim().is_empty() => vec![editor],
+        _ => FALLBACK_EDITORS.iter().map(|e| e.to_string()).collect(),
+    };
+
+    for editor in &editors {
+        // $EDITOR may carry arguments, like "code --wait"
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else {
+            continue;
+        };
+
+        match Command::new(program).args(words).arg(path).status() {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => return Err(format!("{} exited with {}", editor, status).into()),
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(format!("No editor found (tried {}), set $EDITOR", editors.join(", ")).into())
+}
+
+// Yes unless the answer starts with "n"; no if stdin is closed
+fn confirm(prompt: &str) -> Result<bool, Box<dyn Error>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        return Ok(false);
+    }
+
+    Ok(!answer.trim().to_lowercase().starts_with('n'))
+}
@@ -0,0 +1,197 @@
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use second_brain_client::SecondBrainClient;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PROMPT: &str = "🐟 ";
+// Shown while a query continued with a trailing backslash is read
+const CONTINUATION_PROMPT: &str = ">>> ";
+
+const TIME_EXPRESSIONS: [&str; 4] = ["today", "yesterday", "this week", "last week"];
+
+// Asked of the recall service at startup for the app names to complete
+const APPS_QUERY: &str = "what apps did I use this week";
+
+// ~/.local/share/second-brain/repl_history.txt
+pub fn default_history_path() -> PathBuf {
+    let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".local/share/second-brain/repl_history.txt")
+}
+
+// Completes app names after "in" and time expressions anywhere else, from
+// the word being typed
+pub struct QueryCompleter {
+    app_names: Vec<String>,
+}
+
+impl QueryCompleter {
+    // Candidates for the end of `line`, and where the text they replace starts
+    fn candidates(&self, line: &str) -> (usize, Vec<String>) {
+        let word_start = line.rfind(' ').map_or(0, |i| i + 1);
+        let word = line[word_start..].to_lowercase();
+        let before = line[..word_start].trim_end().to_lowercase();
+
+        if before == "in" || before.ends_with(" in") {
+            let apps = self
+                .app_names
+                .iter()
+                .filter(|app| app.to_lowercase().starts_with(&word))
+                .cloned()
+                .collect();
+            return (word_start, apps);
+        }
+
+        if !word.starts_with(|c: char| c.is_alphabetic()) {
+            return (word_start, Vec::new());
+        }
+
+        // "this w" completes to "this week", so the word before counts too
+        let previous_start = line[..word_start].trim_end().rfind(' ').map_or(0, |i| i + 1);
+        for start in [previous_start, word_start] {
+            let typed = line[start..].to_lowercase();
+            let matches: Vec<String> = TIME_EXPRESSIONS
+                .iter()
+                .filter(|expression| expression.starts_with(&typed))
+                .map(|expression| expression.to_string())
+                .collect();
+
+            if !matches.is_empty() {
+                return (start, matches);
+            }
+        }
+
+        (word_start, Vec::new())
+    }
+}
+
+impl Completer for QueryCompleter {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, candidates) = self.candidates(&line[..pos]);
+
+        Ok((
+            start,
+            candidates
+                .into_iter()
+                .map(|candidate| Pair {
+                    display: candidate.clone(),
+                    replacement: candidate,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for QueryCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for QueryCompleter {}
+
+impl Validator for QueryCompleter {}
+
+impl Helper for QueryCompleter {}
+
+// Asks the recall service each query typed until "exit", "quit" or Ctrl+D.
+// Ctrl+C clears the line being typed.
+pub fn run(recall_addr: String, history_file: &Path) -> Result<(), Box<dyn Error>> {
+    let client = SecondBrainClient::new(recall_addr, "");
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    let app_names = runtime.block_on(recent_app_names(&client));
+
+    let mut editor: Editor<QueryCompleter, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(QueryCompleter { app_names }));
+
+    // There's no history the first time
+    let _ = editor.load_history(history_file);
+
+    println!("Ask a question, or type exit to quit. End a line with \\ to continue it.");
+
+    loop {
+        let query = match read_query(&mut editor) {
+            Ok(query) => query,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        let query = query.trim();
+        if query.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(query)?;
+
+        if query == "exit" || query == "quit" {
+            break;
+        }
+
+        match runtime.block_on(client.query(query)) {
+            Ok(response) => {
+                println!("{}\n", response.formatted_text.trim_end());
+                for suggestion in &response.suggestions {
+                    println!("💡 {}\n", suggestion);
+                }
+            }
+            Err(e) => eprintln!("❌ {}", e),
+        }
+    }
+
+    if let Some(parent) = history_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    editor.save_history(history_file)?;
+
+    Ok(())
+}
+
+// Lines ending in a backslash are joined with the next one
+fn read_query(editor: &mut Editor<QueryCompleter, DefaultHistory>) -> Result<String, ReadlineError> {
+    let mut query = String::new();
+    let mut prompt = PROMPT;
+
+    loop {
+        let line = editor.readline(prompt)?;
+
+        match line.strip_suffix('\\') {
+            Some(line) => {
+                query.push_str(line.trim_end());
+                query.push(' ');
+                prompt = CONTINUATION_PROMPT;
+            }
+            None => {
+                query.push_str(&line);
+                return Ok(query);
+            }
+        }
+    }
+}
+
+// Apps used in the last week, most used first. Nothing to complete if the
+// recall service can't be reached yet.
+async fn recent_app_names(client: &SecondBrainClient) -> Vec<String> {
+    let Ok(result) = client.query_raw(APPS_QUERY).await else {
+        return Vec::new();
+    };
+
+    let Some(apps) = result["data"]["stats"]["apps"].as_object() else {
+        return Vec::new();
+    };
+
+    let mut apps: Vec<(&String, u64)> = apps
+        .iter()
+        .map(|(app, stats)| (app, stats["events"].as_u64().unwrap_or(0)))
+        .collect();
+    apps.sort_by_key(|(_, events)| std::cmp::Reverse(*events));
+
+    apps.into_iter().map(|(app, _)| app.clone()).collect()
+}
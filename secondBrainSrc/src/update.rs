@@ -0,0 +1,282 @@
+use activity_tracker_common::config::{self, Config, DEFAULT_OLLAMA_MODEL};
+use activity_tracker_common::llm::{LlmClient, OllamaClient};
+use activity_tracker_common::privacy::{self, AuditEntry};
+use reqwest::Client;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::error::Error;
+use tokio::process::Command;
+
+const DEFAULT_OLLAMA_HOST: &str = "http://localhost:11434";
+const DEFAULT_REGISTRY: &str = "registry.ollama.ai";
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.docker.distribution.manifest.v2+json";
+const SMOKE_TEST_PROMPT: &str = "Reply with the single word: ready";
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    models: Vec<InstalledModel>,
+}
+
+#[derive(Deserialize)]
+struct InstalledModel {
+    name: String,
+    digest: String,
+    size: u64,
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    config: Layer,
+    layers: Vec<Layer>,
+}
+
+#[derive(Deserialize)]
+struct Layer {
+    size: u64,
+}
+
+// What the registry has for a model
+struct RegistryModel {
+    digest: String,
+    size: u64,
+}
+
+// A model name like "llama3.2:3b", "library/llama3.2" or "registry.example.com/team/model:tag"
+struct ModelRef {
+    registry: String,
+    namespace: String,
+    repository: String,
+    tag: String,
+}
+
+impl ModelRef {
+    fn parse(model: &str) -> Result<Self, Box<dyn Error>> {
+        let (path, tag) = match model.rsplit_once(':') {
+            Some((path, tag)) if !tag.contains('/') => (path, tag),
+            _ => (model, "latest"),
+        };
+
+        let mut parts: Vec<&str> = path.split('/').collect();
+        let repository = parts
+            .pop()
+            .filter(|r| !r.is_empty())
+            .ok_or(format!("'{}' is not a model name", model))?;
+        // Only a host has a dot in it
+        let registry = match parts.first() {
+            Some(first) if first.contains('.') => parts.remove(0),
+            _ => DEFAULT_REGISTRY,
+        };
+        let namespace = match parts.as_slice() {
+            [] => "library".to_string(),
+            parts => parts.join("/"),
+        };
+
+        Ok(Self {
+            registry: registry.to_string(),
+            namespace,
+            repository: repository.to_string(),
+            tag: tag.to_string(),
+        })
+    }
+
+    fn manifest_url(&self) -> String {
+        format!(
+            "https://{}/v2/{}/{}/manifests/{}",
+            self.registry, self.namespace, self.repository, self.tag
+        )
+    }
+
+    // Where the model is copied before pulling, e.g. "llama3.2:3b-previous",
+    // or "llama3.2:previous" for the latest tag
+    fn previous_name(&self, model: &str) -> String {
+        let path = model
+            .rsplit_once(':')
+            .filter(|(_, tag)| !tag.contains('/'))
+            .map_or(model, |(path, _)| path);
+        if self.tag == "latest" {
+            format!("{}:previous", path)
+        } else {
+            format!("{}:{}-previous", path, self.tag)
+        }
+    }
+}
+
+// Pulls a newer build of the Ollama model, if the registry has one. The
+// installed build is kept as a "-previous" tag to roll back to, and the new
+// one has to answer a prompt before the update counts as done.
+pub async fn update(model: Option<String>, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    let (model, base_url) = configured_model(model)?;
+    let model_ref = ModelRef::parse(&model)?;
+    let client = Client::new();
+
+    println!("🔍 Checking {} against {}", model, model_ref.registry);
+    let installed = installed_model(&client, &base_url, &model).await?;
+    let latest = registry_model(&client, &model_ref).await?;
+
+    if let Some(installed) = &installed {
+        if installed.digest == latest.digest {
+            println!("✅ {} is up to date ({})", model, short_digest(&installed.digest));
+            return Ok(());
+        }
+    }
+
+    let old_digest = installed.as_ref().map(|m| m.digest.clone());
+    let old_size = installed.as_ref().map_or(0, |m| m.size);
+    println!(
+        "⬆️ {} {} → {}",
+        model,
+        old_digest.as_deref().map_or("not installed".to_string(), short_digest),
+        short_digest(&latest.digest)
+    );
+
+    if dry_run {
+        println!(
+            "Would pull about {} (dry run, nothing changed)",
+            size_change(old_size, latest.size)
+        );
+        return Ok(());
+    }
+
+    let previous = model_ref.previous_name(&model);
+    if installed.is_some() {
+        copy_model(&client, &base_url, &model, &previous).await?;
+        println!("💾 Kept the installed build as {}", previous);
+    }
+
+    let status = Command::new("ollama")
+        .args(["pull", &model])
+        .env("OLLAMA_HOST", &base_url)
+        .status()
+        .await
+        .map_err(|e| format!("Could not run `ollama pull`, is Ollama installed? {}", e))?;
+    if !status.success() {
+        return Err(format!("`ollama pull {}` failed with {}", model, status).into());
+    }
+
+    let rollback_hint = if installed.is_some() {
+        format!(" Roll back with `ollama cp {} {}`.", previous, model)
+    } else {
+        String::new()
+    };
+
+    println!("🧪 Checking the new build answers...");
+    let response = OllamaClient::with_base_url(&model, &base_url)?
+        .generate_text(SMOKE_TEST_PROMPT)
+        .await
+        .map_err(|e| format!("The updated model failed to answer: {}.{}", e, rollback_hint))?;
+    if response.is_empty() {
+        return Err(format!("The updated model gave an empty answer.{}", rollback_hint).into());
+    }
+
+    let updated = installed_model(&client, &base_url, &model)
+        .await?
+        .ok_or(format!("{} is missing after pulling it", model))?;
+
+    privacy::record(AuditEntry::ModelUpdated {
+        model: model.clone(),
+        old_digest,
+        new_digest: updated.digest.clone(),
+    });
+
+    println!(
+        "✅ Updated {} to {}, {}",
+        model,
+        short_digest(&updated.digest),
+        size_change(old_size, updated.size)
+    );
+
+    Ok(())
+}
+
+// --model, else the first Ollama backend in the config, with its server
+fn configured_model(model: Option<String>) -> Result<(String, String), Box<dyn Error>> {
+    let config = Config::load(&config::config_path())?.resolved()?;
+    let backend = config.llm.backends.into_iter().find(|backend| backend.kind == "ollama");
+
+    let base_url = backend
+        .as_ref()
+        .and_then(|backend| backend.base_url.clone())
+        .or_else(|| env::var("OLLAMA_HOST").ok())
+        .unwrap_or_else(|| DEFAULT_OLLAMA_HOST.to_string());
+    let model = model
+        .or_else(|| backend.map(|backend| backend.model))
+        .unwrap_or_else(|| DEFAULT_OLLAMA_MODEL.to_string());
+
+    Ok((model, base_url.trim_end_matches('/').to_string()))
+}
+
+// Ollama lists untagged models as "name:latest"
+async fn installed_model(
+    client: &Client,
+    base_url: &str,
+    model: &str,
+) -> Result<Option<InstalledModel>, Box<dyn Error>> {
+    let tags: TagsResponse = client
+        .get(format!("{}/api/tags", base_url))
+        .send()
+        .await
+        .map_err(|e| format!("Ollama is unreachable at {}: {}", base_url, e))?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(tags
+        .models
+        .into_iter()
+        .find(|m| m.name == model || m.name == format!("{}:latest", model)))
+}
+
+// Ollama identifies a model by the SHA-256 of its manifest, so hashing the
+// registry's copy gives the digest a fresh pull would have
+async fn registry_model(client: &Client, model_ref: &ModelRef) -> Result<RegistryModel, Box<dyn Error>> {
+    let body = client
+        .get(model_ref.manifest_url())
+        .header("Accept", MANIFEST_MEDIA_TYPE)
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|e| {
+            format!(
+                "The registry doesn't have {}:{}: {}",
+                model_ref.repository, model_ref.tag, e
+            )
+        })?
+        .bytes()
+        .await?;
+
+    let manifest: Manifest = serde_json::from_slice(&body)?;
+    let digest = Sha256::digest(&body).iter().map(|b| format!("{:02x}", b)).collect();
+
+    Ok(RegistryModel {
+        digest,
+        size: manifest.config.size + manifest.layers.iter().map(|layer| layer.size).sum::<u64>(),
+    })
+}
+
+async fn copy_model(client: &Client, base_url: &str, source: &str, destination: &str) -> Result<(), Box<dyn Error>> {
+    client
+        .post(format!("{}/api/copy", base_url))
+        .json(&serde_json::json!({"source": source, "destination": destination}))
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|e| format!("Could not copy {} to {}: {}", source, destination, e))?;
+
+    Ok(())
+}
+
+fn short_digest(digest: &str) -> String {
+    digest.trim_start_matches("sha256:").chars().take(12).collect()
+}
+
+// e.g. "2019.3 MB (+12.4 MB)"
+fn size_change(old: u64, new: u64) -> String {
+    let new_mb = new as f64 / BYTES_PER_MB;
+    if old == 0 {
+        return format!("{:.1} MB", new_mb);
+    }
+
+    format!("{:.1} MB ({:+.1} MB)", new_mb, new_mb - old as f64 / BYTES_PER_MB)
+}
@@ -0,0 +1,88 @@
+use activity_tracker_common::{db::TimescaleClient, AppCorrelation};
+use chrono::{Duration, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+// Focus sessions this far back are used when the correlations are refreshed
+pub const CORRELATION_LOOKBACK_DAYS: i64 = 30;
+// How long stored correlations are used before they're recomputed
+pub const CORRELATION_REFRESH_DAYS: i64 = 7;
+
+// Pairs seen together in fewer hours than this are coincidence, not a pattern
+const MIN_CO_OCCURRENCES: u64 = 2;
+
+const SECONDS_PER_HOUR: i64 = 3600;
+
+// Which apps are used in the same hours, from the focus sessions over the
+// last `window`, highest co-occurrence rate first
+pub async fn compute_app_correlations(
+    db: &TimescaleClient,
+    window: Duration,
+) -> Result<Vec<AppCorrelation>, Box<dyn Error>> {
+    let end = Utc::now();
+    let sessions = db.get_focus_sessions(end - window, end).await?;
+
+    // Apps used in each clock hour, keyed by hours since the epoch
+    let mut hours: HashMap<i64, BTreeSet<&str>> = HashMap::new();
+    for session in &sessions {
+        let first = session.start_time.timestamp().div_euclid(SECONDS_PER_HOUR);
+        let last = session.end_time.timestamp().div_euclid(SECONDS_PER_HOUR);
+
+        for hour in first..=last {
+            hours.entry(hour).or_default().insert(session.app_name.as_str());
+        }
+    }
+
+    let mut app_hours: HashMap<&str, u64> = HashMap::new();
+    let mut pair_hours: HashMap<(&str, &str), u64> = HashMap::new();
+    for apps in hours.values() {
+        let apps: Vec<&str> = apps.iter().copied().collect();
+
+        for (i, app_a) in apps.iter().enumerate() {
+            *app_hours.entry(app_a).or_insert(0) += 1;
+
+            // BTreeSet order keeps each pair as (earlier name, later name)
+            for app_b in &apps[i + 1..] {
+                *pair_hours.entry((app_a, app_b)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut correlations: Vec<AppCorrelation> = pair_hours
+        .into_iter()
+        .filter(|(_, count)| *count >= MIN_CO_OCCURRENCES)
+        .map(|((app_a, app_b), count)| {
+            let either = app_hours[app_a] + app_hours[app_b] - count;
+            AppCorrelation {
+                app_a: app_a.to_string(),
+                app_b: app_b.to_string(),
+                co_occurrence_count: count,
+                co_occurrence_rate: count as f32 / either as f32,
+            }
+        })
+        .collect();
+
+    correlations.sort_by(|a, b| {
+        b.co_occurrence_rate
+            .total_cmp(&a.co_occurrence_rate)
+            .then_with(|| b.co_occurrence_count.cmp(&a.co_occurrence_count))
+            .then_with(|| (&a.app_a, &a.app_b).cmp(&(&b.app_a, &b.app_b)))
+    });
+
+    Ok(correlations)
+}
+
+// Recomputes and stores the correlations if they're missing or older than
+// CORRELATION_REFRESH_DAYS. Returns the number stored, or None if the stored
+// ones were still fresh.
+pub async fn refresh_app_correlations(db: &TimescaleClient) -> Result<Option<usize>, Box<dyn Error>> {
+    let computed_at = db.get_app_correlations_computed_at().await?;
+    if computed_at.is_some_and(|at| Utc::now() - at < Duration::days(CORRELATION_REFRESH_DAYS)) {
+        return Ok(None);
+    }
+
+    let correlations = compute_app_correlations(db, Duration::days(CORRELATION_LOOKBACK_DAYS)).await?;
+    db.replace_app_correlations(&correlations).await?;
+
+    Ok(Some(correlations.len()))
+}
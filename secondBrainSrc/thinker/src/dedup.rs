@@ -0,0 +1,78 @@
+use activity_tracker_common::{db::GeneralDbClient, ActivitySummary};
+use chrono::{DateTime, Utc};
+use std::error::Error;
+
+// A summary is a duplicate when an existing one covers most of its time
+// range and describes it in nearly the same words
+const MIN_TIME_OVERLAP: f64 = 0.8;
+const MIN_DESCRIPTION_SIMILARITY: f64 = 0.85;
+
+// Fraction of `candidate`'s time range that `existing` also covers
+fn time_overlap(existing: &ActivitySummary, candidate: &ActivitySummary) -> f64 {
+    let duration = (candidate.end_time - candidate.start_time).num_seconds();
+    if duration <= 0 {
+        return if existing.start_time <= candidate.start_time && existing.end_time >= candidate.end_time {
+            1.0
+        } else {
+            0.0
+        };
+    }
+
+    let overlap_start = existing.start_time.max(candidate.start_time);
+    let overlap_end = existing.end_time.min(candidate.end_time);
+    let overlap = (overlap_end - overlap_start).num_seconds().max(0);
+
+    overlap as f64 / duration as f64
+}
+
+pub fn is_duplicate(existing: &ActivitySummary, candidate: &ActivitySummary) -> bool {
+    time_overlap(existing, candidate) > MIN_TIME_OVERLAP
+        && strsim::jaro_winkler(&existing.description, &candidate.description) > MIN_DESCRIPTION_SIMILARITY
+}
+
+// Pairs of (kept id, duplicate id) among stored summaries overlapping the
+// range. The oldest summary of each group of duplicates is the one kept.
+pub async fn find_duplicate_summaries(
+    summary_db: &GeneralDbClient,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<(i64, i64)>, Box<dyn Error>> {
+    let summaries = summary_db.get_summaries_with_ids_in_timeframe(start, end).await?;
+
+    Ok(duplicate_pairs(&summaries))
+}
+
+// Deletes duplicates in the range, returning (removed, retained) counts
+pub async fn deduplicate(
+    summary_db: &GeneralDbClient,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<(u64, u64), Box<dyn Error>> {
+    let summaries = summary_db.get_summaries_with_ids_in_timeframe(start, end).await?;
+
+    let duplicate_ids: Vec<i64> = duplicate_pairs(&summaries)
+        .into_iter()
+        .map(|(_, duplicate)| duplicate)
+        .collect();
+
+    let removed = summary_db.delete_summaries_by_id(&duplicate_ids).await?;
+
+    Ok((removed, summaries.len() as u64 - removed))
+}
+
+// Expects summaries ordered oldest first
+fn duplicate_pairs(summaries: &[(i64, ActivitySummary)]) -> Vec<(i64, i64)> {
+    let mut retained: Vec<&(i64, ActivitySummary)> = Vec::new();
+    let mut duplicates = Vec::new();
+
+    for entry in summaries {
+        let (id, summary) = entry;
+
+        match retained.iter().find(|(_, existing)| is_duplicate(existing, summary)) {
+            Some((kept_id, _)) => duplicates.push((*kept_id, *id)),
+            None => retained.push(entry),
+        }
+    }
+
+    duplicates
+}
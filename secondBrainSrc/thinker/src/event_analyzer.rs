@@ -1,98 +1,417 @@
-use activity_tracker_common::{ActivitySummary, UserEvent, llm::LlmClient};
-use chrono::{DateTime, Utc};
+use crate::quality::score_summary;
+use crate::sentiment::analyze_typing_sentiment;
+use activity_tracker_common::{
+    llm::{ContextWindowManager, LlmClient},
+    privacy::RedactionPolicy,
+    ActivitySummary, EventType, FocusSession, IdleEvent, UserEvent,
+};
+use chrono::{DateTime, Duration, Utc};
+use futures::{Stream, StreamExt};
+use std::borrow::Cow;
 use std::error::Error;
+use std::pin::pin;
+use tracing::{debug, warn, Span};
+
+// Cap how much OCR text each screen capture contributes to the description
+const MAX_OCR_CHARS: usize = 500;
+
+// How many times to retry a low-quality description before using the fallback
+const MAX_REGENERATIONS: usize = 2;
+
+// Copying and pasting at least this often per keystroke, and at least
+// HEAVY_CLIPBOARD_MIN_EVENTS times, suggests reading and reusing sources
+// rather than writing from scratch
+const HEAVY_CLIPBOARD_RATIO: f64 = 0.05;
+const HEAVY_CLIPBOARD_MIN_EVENTS: usize = 3;
+
+// Events quoted in the fallback description
+const SAMPLE_EVENTS: usize = 3;
+
+// Windows the user was away for more than this share of aren't described
+const MAX_IDLE_SHARE: f64 = 0.5;
+
+// Windows with fewer events than this aren't described. One means every
+// window with any events is.
+pub const DEFAULT_MIN_EVENTS: usize = 1;
 
 pub struct EventAnalyzer<T: LlmClient> {
     llm_client: T,
+    context_window: ContextWindowManager,
+    // None when events are described to the LLM as recorded
+    redaction: Option<RedactionPolicy>,
+    min_events: usize,
 }
 
 impl<T: LlmClient> EventAnalyzer<T> {
     pub fn new(llm_client: T) -> Self {
-        Self { llm_client }
+        let redaction = RedactionPolicy::from_env(llm_client.is_remote());
+
+        Self {
+            llm_client,
+            context_window: ContextWindowManager::from_env(),
+            redaction,
+            min_events: DEFAULT_MIN_EVENTS,
+        }
+    }
+
+    // Windows with fewer events are skipped rather than described
+    pub fn with_min_events(mut self, min_events: usize) -> Self {
+        self.min_events = min_events;
+        self
     }
 
+    pub fn min_events(&self) -> usize {
+        self.min_events
+    }
+
+    pub fn llm_client(&self) -> &T {
+        &self.llm_client
+    }
+
+    #[tracing::instrument(
+        skip(self, events, focus_sessions),
+        fields(event_count = tracing::field::Empty, start_time = %start_time)
+    )]
     pub async fn analyze_events(
         &self,
-        events: Vec<UserEvent>,
+        events: impl Stream<Item = Result<UserEvent, Box<dyn Error>>>,
+        focus_sessions: &[FocusSession],
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
-    ) -> Result<ActivitySummary, Box<dyn Error>> {
+    ) -> Result<Option<ActivitySummary>, Box<dyn Error>> {
         // Extract key information from events for better analysis
         let mut app_count = std::collections::HashMap::new();
         let mut key_count = std::collections::HashMap::new();
-        
-        for event in &events {
+        let mut screen_text = Vec::new();
+        let mut typed_chars = 0usize;
+        let mut git_activity = Vec::new();
+        let mut keystrokes = 0usize;
+        let mut mouse_clicks = 0usize;
+        let mut clipboard_actions = std::collections::BTreeMap::new();
+        let mut clipboard_chars = 0u64;
+        // The events as recorded, stored with the summary
+        let mut recorded = Vec::new();
+        let mut samples = Vec::new();
+        let mut redacted = 0usize;
+        // Time the user was away, from the learner's idle_start and idle_end
+        // markers, and the start of an idle stretch still open
+        let mut idle_time = Duration::zero();
+        let mut idle_from = None;
+
+        // Statistics are built up one event at a time, so only the recorded
+        // events are held in memory, never a redacted copy of them all
+        let mut events = pin!(events);
+        while let Some(recorded_event) = events.next().await {
+            let recorded_event = recorded_event?;
+            let event = match &self.redaction {
+                Some(policy) => {
+                    let mut event = recorded_event.clone();
+                    if event.redact(policy) {
+                        redacted += 1;
+                    }
+                    Cow::Owned(event)
+                }
+                None => Cow::Borrowed(&recorded_event),
+            };
+
+            // Being away isn't activity, so the markers only count towards idle time
+            if event.is_idle_marker() {
+                let idle_since = serde_json::from_str::<IdleEvent>(&event.data)
+                    .map_or(event.timestamp, |idle| idle.idle_since)
+                    .max(start_time);
+                if event.event == EventType::IdleStart.as_str() {
+                    idle_from = Some(idle_since);
+                } else {
+                    // A window can open part way through an idle stretch
+                    let from = idle_from.take().unwrap_or(idle_since);
+                    idle_time += (event.timestamp.min(end_time) - from).max(Duration::zero());
+                }
+                recorded.push(recorded_event);
+                continue;
+            }
+
+            if samples.len() < SAMPLE_EVENTS && event.event != EventType::ScreenCapture.as_str() {
+                samples.push(format!("{:?}", event));
+            }
+
             // Count app usage
             *app_count.entry(event.app_context.app_name.clone()).or_insert(0) += 1;
-            
+
             // Extract and count keys from event data
             if let Ok(data) = serde_json::from_str::<serde_json::Value>(&event.data) {
                 if let Some(key) = data.get("key").and_then(|k| k.as_str()) {
                     *key_count.entry(key.to_string()).or_insert(0) += 1;
                 }
+
+                // Commit messages say far more about the work than keystrokes do
+                if event.event == EventType::GitCommit.as_str() {
+                    git_activity.push(format!(
+                        "Committed \"{}\" to {} ({}, {} files)",
+                        data.get("message").and_then(|m| m.as_str()).unwrap_or_default(),
+                        data.get("repo").and_then(|r| r.as_str()).unwrap_or_default(),
+                        data.get("branch").and_then(|b| b.as_str()).unwrap_or_default(),
+                        data.get("files_changed").and_then(|f| f.as_u64()).unwrap_or(0)
+                    ));
+                } else if event.event == EventType::GitCheckout.as_str() {
+                    git_activity.push(format!(
+                        "Switched {} from {} to {}",
+                        data.get("repo").and_then(|r| r.as_str()).unwrap_or_default(),
+                        data.get("from").and_then(|f| f.as_str()).unwrap_or_default(),
+                        data.get("to").and_then(|t| t.as_str()).unwrap_or_default()
+                    ));
+                }
+
+                if event.event == EventType::Keystroke.as_str() {
+                    keystrokes += 1;
+                } else if event.event == EventType::MouseClick.as_str() {
+                    mouse_clicks += 1;
+                } else if event.event == EventType::Clipboard.as_str() {
+                    let action = data.get("action").and_then(|a| a.as_str()).unwrap_or("copy");
+                    *clipboard_actions.entry(action.to_string()).or_insert(0usize) += 1;
+                    clipboard_chars += data.get("content_length").and_then(|l| l.as_u64()).unwrap_or(0);
+                }
+
+                // Only keys that produced a character count towards typing speed
+                if data.get("logical_char").is_some_and(|c| !c.is_null()) {
+                    typed_chars += 1;
+                }
+
+                // Screen captures carry OCR text that says what was on screen
+                if let Some(text) = data.get("ocr_text").and_then(|t| t.as_str()) {
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if !text.is_empty() {
+                        screen_text.push(text.chars().take(MAX_OCR_CHARS).collect::<String>());
+                    }
+                }
             }
+
+            recorded.push(recorded_event);
+        }
+
+        Span::current().record("event_count", recorded.len());
+        if recorded.len() < self.min_events {
+            return Ok(None);
         }
-        
+        if let Some(from) = idle_from {
+            idle_time += end_time - from;
+        }
+        let window_secs = (end_time - start_time).num_seconds().max(1);
+        if idle_time.num_seconds() as f64 / window_secs as f64 > MAX_IDLE_SHARE {
+            debug!(
+                idle_secs = idle_time.num_seconds(),
+                window_secs, "💤 Window mostly idle"
+            );
+            return Ok(None);
+        }
+        if self.redaction.is_some() {
+            debug!(redacted, total = recorded.len(), "🙈 Redacted events before LLM call");
+        }
+
+        // Time in focus says more than event counts, so rank apps by it when
+        // the learner recorded focus sessions
+        let mut focus_seconds = std::collections::HashMap::new();
+        for session in focus_sessions {
+            let seconds = session.seconds_within(start_time, end_time);
+            if seconds > 0 {
+                *focus_seconds.entry(session.app_name.clone()).or_insert(0) += seconds;
+            }
+        }
+
         // Find most used apps and keys
-        let mut app_vec: Vec<_> = app_count.into_iter().collect();
-        app_vec.sort_by(|a, b| b.1.cmp(&a.1));
-        let top_apps = app_vec.into_iter().take(3).map(|(app, count)| format!("{} ({})", app, count)).collect::<Vec<_>>();
-        
+        let (mut app_vec, unit): (Vec<(String, i64)>, _) = if focus_seconds.is_empty() {
+            (app_count.into_iter().collect(), "events")
+        } else {
+            (
+                focus_seconds
+                    .into_iter()
+                    .map(|(app, secs)| (app, (secs + 30) / 60))
+                    .collect(),
+                "minutes",
+            )
+        };
+        app_vec.sort_by_key(|b| std::cmp::Reverse(b.1));
+        let top_apps = app_vec
+            .iter()
+            .take(3)
+            .map(|(app, count)| format!("{}: {} {}", app, count, unit))
+            .collect::<Vec<_>>();
+        let top_app_names = app_vec.iter().take(3).map(|(app, _)| app.as_str()).collect::<Vec<_>>();
+
         let mut key_vec: Vec<_> = key_count.into_iter().collect();
-        key_vec.sort_by(|a, b| b.1.cmp(&a.1));
-        let top_keys = key_vec.into_iter().take(5).map(|(key, count)| format!("{} ({})", key, count)).collect::<Vec<_>>();
-        
+        key_vec.sort_by_key(|b| std::cmp::Reverse(b.1));
+        let top_keys = key_vec
+            .into_iter()
+            .take(5)
+            .map(|(key, count)| format!("{} ({})", key, count))
+            .collect::<Vec<_>>();
+
+        // Words per minute, using the standard 5 characters per word
+        let minutes = (end_time - start_time).num_seconds().max(1) as f64 / 60.0;
+        let wpm = typed_chars as f64 / 5.0 / minutes;
+
         // Create a rich data summary containing stats for different query types
-        let stats_summary = format!(
+        let mut stats_summary = format!(
             "Session statistics:\n\
              - Time period: {} to {}\n\
              - Total events: {}\n\
              - Top applications: {}\n\
-             - Most used keys: {}\n",
+             - Most used keys: {}\n\
+             - Typing speed: {:.0} WPM\n",
             start_time.format("%H:%M"),
             end_time.format("%H:%M"),
-            events.len(),
+            recorded.len(),
             top_apps.join(", "),
-            top_keys.join(", ")
+            top_keys.join(", "),
+            wpm
         );
 
-        // Create a description that can be used to answer different query types
-        let description = format!(
+        // Simple description used when the LLM can't produce a good one
+        let mut fallback_description = format!(
             "During this session ({} to {}), the user was active with {} events.\n\
              Most used keys: {}\n\
              Top applications: {}\n\
+             Typing speed: {:.0} WPM\n\
              Sample events: {}",
             start_time.format("%H:%M"),
             end_time.format("%H:%M"),
-            events.len(),
+            recorded.len(),
             top_keys.join(", "),
             top_apps.join(", "),
-            events.iter().take(3).map(|e| format!("{:?}", e)).collect::<Vec<_>>().join("\n")
+            wpm,
+            samples.join("\n")
         );
 
-        // Extract tags from the activity data
-        let tags = self.extract_tags(&description).await?;
+        // Supplement with the most recent screen text when screen capture is enabled
+        if !screen_text.is_empty() {
+            let recent = &screen_text[screen_text.len().saturating_sub(3)..];
+            fallback_description.push_str(&format!("\nScreen text: {}", recent.join("\n")));
+            stats_summary.push_str(&format!(" - Screen text: {}\n", recent.join("\n")));
+        }
+
+        // Lots of clicking and little typing is browsing or reviewing rather than writing
+        if mouse_clicks > 0 {
+            stats_summary.push_str(&format!(
+                " - Mouse clicks: {} (keystrokes: {})\n",
+                mouse_clicks, keystrokes
+            ));
+        }
+
+        let clipboard_events: usize = clipboard_actions.values().sum();
+        if clipboard_events > 0 {
+            let actions = clipboard_actions
+                .iter()
+                .map(|(action, count)| format!("{} {}", count, action))
+                .collect::<Vec<_>>()
+                .join(", ");
+            stats_summary.push_str(&format!(" - Clipboard: {} ({} characters)\n", actions, clipboard_chars));
+
+            let ratio = clipboard_events as f64 / keystrokes.max(1) as f64;
+            if clipboard_events >= HEAVY_CLIPBOARD_MIN_EVENTS && ratio >= HEAVY_CLIPBOARD_RATIO {
+                fallback_description.push_str("\nMode: heavy research / reference");
+                stats_summary.push_str(" - Mode: heavy research / reference, copying and pasting often\n");
+            }
+        }
+
+        // Only the scores leave this function, so the unredacted keys are used
+        let sentiment = analyze_typing_sentiment(&recorded);
+        if let Some(hint) = sentiment.hint() {
+            stats_summary.push_str(&format!(" - {}\n", hint));
+        }
+
+        if !git_activity.is_empty() {
+            fallback_description.push_str(&format!("\nGit activity: {}", git_activity.join("; ")));
+            stats_summary.push_str(&format!(" - Git activity: {}\n", git_activity.join("; ")));
+        }
 
-        Ok(ActivitySummary {
+        let mut summary = ActivitySummary {
             start_time,
             end_time,
-            description,
-            events,
-            tags,
-        })
+            description: String::new(),
+            events: recorded,
+            tags: Vec::new(),
+        };
+        self.describe(&mut summary, &stats_summary, &top_app_names, fallback_description)
+            .await;
+
+        // Extract tags from the activity data
+        summary.tags = self.extract_tags(&summary.description).await?;
+        summary.tags.extend(sentiment.tags());
+        debug!(
+            frustration = sentiment.frustration_score,
+            focus = sentiment.focus_score,
+            flow = sentiment.flow_score,
+            "🌡️ Typing sentiment"
+        );
+
+        Ok(Some(summary))
+    }
+
+    // Ask the LLM to describe the session, retrying with a more directive prompt
+    // when the result scores poorly. Falls back to the simple description.
+    async fn describe(
+        &self,
+        summary: &mut ActivitySummary,
+        stats_summary: &str,
+        top_app_names: &[&str],
+        fallback_description: String,
+    ) {
+        for attempt in 0..=MAX_REGENERATIONS {
+            let instruction = if attempt == 0 {
+                "Describe what the user was doing in 1-3 sentences. \
+                 If there is git activity, say what was worked on based on the commit messages."
+                    .to_string()
+            } else {
+                format!(
+                    "Describe what the user was doing in 1-3 sentences. \
+                     Mention these applications by name: {}. \
+                     Do not mention any application that is not listed in the data.",
+                    top_app_names.join(", ")
+                )
+            };
+
+            let prompt = self.context_window.fit_prompt(
+                "You summarize a user's computer activity from recorded statistics.",
+                stats_summary,
+                &instruction,
+            );
+
+            summary.description = match self.llm_client.generate_text(&prompt).await {
+                Ok(text) => text.trim().to_string(),
+                Err(e) => {
+                    warn!(error = %e, "⚠️ Failed to generate description");
+                    break;
+                }
+            };
+
+            let quality = score_summary(summary);
+
+            debug!(
+                attempt = attempt + 1,
+                completeness = quality.completeness_score,
+                hallucination = quality.hallucination_score,
+                conciseness = quality.conciseness_score,
+                overall = quality.overall(),
+                "🔬 Summary quality"
+            );
+
+            if quality.is_acceptable() {
+                return;
+            }
+        }
+
+        warn!("⚠️ LLM description rejected, using fallback description");
+        summary.description = fallback_description;
     }
 
     async fn extract_tags(&self, description: &str) -> Result<Vec<String>, Box<dyn Error>> {
-        let prompt = format!(
-            "Extract 3-5 tags or topics from this activity description: \n\n{}",
-            description
+        let prompt = self.context_window.fit_prompt(
+            "Extract 3-5 tags or topics from this activity description:",
+            description,
+            "",
         );
 
         let tags_text = self.llm_client.generate_text(&prompt).await?;
-        let tags = tags_text
-            .split('\n')
-            .map(|s| s.trim().to_string())
-            .collect();
+        let tags = tags_text.split('\n').map(|s| s.trim().to_string()).collect();
 
         Ok(tags)
     }
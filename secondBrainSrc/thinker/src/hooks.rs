@@ -0,0 +1,211 @@
+use activity_tracker_common::config::{Config, HookConfig};
+use activity_tracker_common::ActivitySummary;
+use async_trait::async_trait;
+use futures::future::join_all;
+use reqwest::Client;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+const HOOK_TIMEOUT_SECS: u64 = 10;
+
+// Called after a summary has been stored successfully
+#[async_trait]
+pub trait PostProcessHook {
+    fn name(&self) -> String;
+    async fn on_summary_created(&self, summary: &ActivitySummary) -> Result<(), Box<dyn Error>>;
+}
+
+pub type Hooks = Vec<Box<dyn PostProcessHook + Send + Sync>>;
+
+// POSTs the summary as JSON to a configurable URL
+pub struct WebhookHook {
+    client: Client,
+    url: String,
+}
+
+impl WebhookHook {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl PostProcessHook for WebhookHook {
+    fn name(&self) -> String {
+        format!("webhook ({})", self.url)
+    }
+
+    async fn on_summary_created(&self, summary: &ActivitySummary) -> Result<(), Box<dyn Error>> {
+        let response = self.client.post(&self.url).json(summary).send().await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Webhook returned status {}", response.status()).into());
+        }
+
+        Ok(())
+    }
+}
+
+// Runs a shell command with the summary exposed through environment variables
+pub struct ShellHook {
+    command: String,
+}
+
+impl ShellHook {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl PostProcessHook for ShellHook {
+    fn name(&self) -> String {
+        format!("shell ({})", self.command)
+    }
+
+    async fn on_summary_created(&self, summary: &ActivitySummary) -> Result<(), Box<dyn Error>> {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("SUMMARY_START_TIME", summary.start_time.to_rfc3339())
+            .env("SUMMARY_END_TIME", summary.end_time.to_rfc3339())
+            .env("SUMMARY_DESCRIPTION", &summary.description)
+            .env("SUMMARY_TAGS", summary.tags.join(","))
+            .env("SUMMARY_EVENT_COUNT", summary.events.len().to_string())
+            .kill_on_drop(true)
+            .status()
+            .await?;
+
+        if !status.success() {
+            return Err(format!("Command exited with {}", status).into());
+        }
+
+        Ok(())
+    }
+}
+
+// Appends each summary as a line of JSON to a file
+pub struct FileHook {
+    path: PathBuf,
+}
+
+impl FileHook {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+}
+
+#[async_trait]
+impl PostProcessHook for FileHook {
+    fn name(&self) -> String {
+        format!("file ({})", self.path.display())
+    }
+
+    async fn on_summary_created(&self, summary: &ActivitySummary) -> Result<(), Box<dyn Error>> {
+        let mut line = serde_json::to_string(summary)?;
+        line.push('\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+
+        Ok(())
+    }
+}
+
+// Loads the [[hooks]] sections from the config file, if there is one
+pub fn load_hooks(config_path: &Path) -> Result<Hooks, Box<dyn Error>> {
+    let hooks = Config::load(config_path)?
+        .hooks
+        .into_iter()
+        .map(|config| -> Box<dyn PostProcessHook + Send + Sync> {
+            match config {
+                HookConfig::Webhook { url } => Box::new(WebhookHook::new(&url)),
+                HookConfig::Shell { command } => Box::new(ShellHook::new(&command)),
+                HookConfig::File { path } => Box::new(FileHook::new(&path)),
+            }
+        })
+        .collect();
+
+    Ok(hooks)
+}
+
+// Runs every hook concurrently; failures are logged and never propagated
+pub async fn run_hooks(hooks: &Hooks, summary: &ActivitySummary) {
+    let runs = hooks.iter().map(|hook| async move {
+        match timeout(Duration::from_secs(HOOK_TIMEOUT_SECS), hook.on_summary_created(summary)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::error!(hook = %hook.name(), error = %e, "❌ Hook failed"),
+            Err(_) => tracing::warn!(
+                hook = %hook.name(),
+                timeout_secs = HOOK_TIMEOUT_SECS,
+                "⏱️ Hook timed out"
+            ),
+        }
+    });
+
+    join_all(runs).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use mockito::Matcher;
+
+    fn summary() -> ActivitySummary {
+        let start_time = Utc.with_ymd_and_hms(2025, 4, 1, 9, 0, 0).unwrap();
+
+        ActivitySummary {
+            start_time,
+            end_time: start_time + chrono::Duration::minutes(5),
+            description: "Reviewed a pull request in Firefox".to_string(),
+            events: Vec::new(),
+            tags: vec!["review".to_string()],
+        }
+    }
+
+    #[tokio::test]
+    async fn webhook_posts_the_summary_as_json() {
+        let mut server = mockito::Server::new_async().await;
+        let summary = summary();
+        let mock = server
+            .mock("POST", "/summaries")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(serde_json::to_value(&summary).unwrap()))
+            .with_status(204)
+            .create_async()
+            .await;
+
+        let hook = WebhookHook::new(&format!("{}/summaries", server.url()));
+        hook.on_summary_created(&summary).await.unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn webhook_fails_on_an_error_status() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/summaries").with_status(500).create_async().await;
+
+        let hook = WebhookHook::new(&format!("{}/summaries", server.url()));
+        let error = hook.on_summary_created(&summary()).await.unwrap_err();
+
+        assert_eq!(error.to_string(), "Webhook returned status 500 Internal Server Error");
+        mock.assert_async().await;
+    }
+}
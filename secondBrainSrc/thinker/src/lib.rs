@@ -0,0 +1,15 @@
+// thinker/src/lib.rs
+pub mod correlation;
+pub mod dedup;
+pub mod event_analyzer;
+pub mod hooks;
+pub mod logging;
+pub mod merger;
+pub mod notifications;
+pub mod quality;
+pub mod report_writer;
+pub mod rollup;
+pub mod sentiment;
+pub mod suggestions;
+pub mod task_detector;
+pub mod thinker;
@@ -0,0 +1,22 @@
+use std::env;
+use tracing_subscriber::EnvFilter;
+
+// Sets up the global subscriber. RUST_LOG picks the level (info by default)
+// and LOG_FORMAT=json switches to one JSON object per line for log shippers.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let json = env::var("LOG_FORMAT")
+        .map(|v| v.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = if json {
+        builder.json().try_init()
+    } else {
+        builder.try_init()
+    };
+
+    if let Err(e) = result {
+        eprintln!("⚠️ Failed to set up logging: {}", e);
+    }
+}
@@ -0,0 +1,242 @@
+use activity_tracker_common::{
+    db::GeneralDbClient,
+    llm::{ContextWindowManager, LlmClient},
+    ActivitySummary,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::error::Error;
+use tracing::warn;
+
+// Summaries further apart than this are never merged
+pub const MERGE_GAP_MINUTES: i64 = 10;
+// Jaro-Winkler similarity above which neighbouring descriptions are the same topic
+pub const MERGE_SIMILARITY: f32 = 0.7;
+
+// Combines runs of neighbouring summaries about the same topic, e.g. 24
+// five-minute summaries of one coding session, into one summary each.
+// Summaries that aren't merged are returned unchanged.
+pub async fn merge_adjacent_summaries<T: LlmClient>(
+    llm_client: &T,
+    summaries: &[ActivitySummary],
+    gap_threshold: Duration,
+    similarity_threshold: f32,
+) -> Vec<ActivitySummary> {
+    let mut ordered: Vec<&ActivitySummary> = summaries.iter().collect();
+    ordered.sort_by_key(|s| s.start_time);
+
+    let mut merged = Vec::new();
+    for group in group_adjacent(&ordered, gap_threshold, similarity_threshold) {
+        match group.as_slice() {
+            [single] => merged.push((*single).clone()),
+            group => merged.push(merge_group(llm_client, group).await),
+        }
+    }
+
+    merged
+}
+
+// Merges the stored summaries in the range, flagging the originals as
+// merged so they drop out of queries. Summaries that are themselves the
+// result of an earlier merge are left alone, so running this twice over the
+// same range changes nothing. Returns the number of merged summaries stored.
+pub async fn merge_stored_summaries<T: LlmClient>(
+    summary_db: &GeneralDbClient,
+    llm_client: &T,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<u64, Box<dyn Error>> {
+    let already_merged = summary_db.get_merged_summaries_in_timeframe(start, end).await?;
+
+    let mut candidates: Vec<(i64, ActivitySummary)> = summary_db
+        .get_summaries_with_ids_in_timeframe(start, end)
+        .await?
+        .into_iter()
+        .filter(|(_, s)| s.start_time >= start && s.end_time <= end)
+        .filter(|(_, s)| {
+            !already_merged
+                .iter()
+                .any(|child| child.start_time >= s.start_time && child.end_time <= s.end_time)
+        })
+        .collect();
+    candidates.sort_by_key(|(_, s)| s.start_time);
+
+    let summaries: Vec<&ActivitySummary> = candidates.iter().map(|(_, s)| s).collect();
+    let groups = group_indices(&summaries, Duration::minutes(MERGE_GAP_MINUTES), MERGE_SIMILARITY);
+
+    let mut stored = 0;
+    for group in groups.into_iter().filter(|group| group.len() > 1) {
+        let children: Vec<&ActivitySummary> = group.iter().map(|&i| summaries[i]).collect();
+        let child_ids: Vec<i64> = group.iter().map(|&i| candidates[i].0).collect();
+
+        let merged = merge_group(llm_client, &children).await;
+        summary_db.store_merged_summary(&merged, &child_ids).await?;
+        stored += 1;
+    }
+
+    Ok(stored)
+}
+
+fn group_adjacent<'a>(
+    summaries: &[&'a ActivitySummary],
+    gap_threshold: Duration,
+    similarity_threshold: f32,
+) -> Vec<Vec<&'a ActivitySummary>> {
+    group_indices(summaries, gap_threshold, similarity_threshold)
+        .into_iter()
+        .map(|group| group.into_iter().map(|i| summaries[i]).collect())
+        .collect()
+}
+
+// Indices of time-ordered summaries, split wherever the gap reaches the
+// threshold or the topic changes from the previous summary
+fn group_indices(
+    summaries: &[&ActivitySummary],
+    gap_threshold: Duration,
+    similarity_threshold: f32,
+) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+
+    for (i, summary) in summaries.iter().enumerate() {
+        let continues = groups.last().and_then(|group| group.last()).is_some_and(|&previous| {
+            let previous = summaries[previous];
+            summary.start_time - previous.end_time < gap_threshold
+                && strsim::jaro_winkler(&previous.description, &summary.description) > similarity_threshold as f64
+        });
+
+        match groups.last_mut() {
+            Some(group) if continues => group.push(i),
+            _ => groups.push(vec![i]),
+        }
+    }
+
+    groups
+}
+
+async fn merge_group<T: LlmClient>(llm_client: &T, group: &[&ActivitySummary]) -> ActivitySummary {
+    let descriptions = group
+        .iter()
+        .map(|s| format!("- {}", s.description))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = ContextWindowManager::from_env().fit_prompt(
+        "These consecutive summaries describe one stretch of the user's computer activity:",
+        &descriptions,
+        "Rewrite them as a single summary of one or two sentences.",
+    );
+
+    let description = match llm_client.generate_text(&prompt).await {
+        Ok(text) if !text.trim().is_empty() => text.trim().to_string(),
+        Ok(_) => longest_description(group),
+        Err(e) => {
+            warn!(error = %e, "⚠️ Failed to write merged summary, keeping the longest description");
+            longest_description(group)
+        }
+    };
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in group.iter().flat_map(|s| &s.tags) {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+
+    ActivitySummary {
+        start_time: group.iter().map(|s| s.start_time).min().unwrap_or_default(),
+        end_time: group.iter().map(|s| s.end_time).max().unwrap_or_default(),
+        description,
+        events: group.iter().flat_map(|s| s.events.iter().cloned()).collect(),
+        tags,
+    }
+}
+
+fn longest_description(group: &[&ActivitySummary]) -> String {
+    group
+        .iter()
+        .map(|s| s.description.as_str())
+        .max_by_key(|d| d.len())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use activity_tracker_common::SummaryStore;
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Writes the same merged description every time, counting the calls
+    #[derive(Default)]
+    struct CountingLlm {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmClient for CountingLlm {
+        async fn generate_text(&self, _prompt: &str) -> Result<String, Box<dyn Error>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok("Wrote the summary merger in the editor".to_string())
+        }
+
+        async fn extract_tags(&self, _text: &str) -> Result<Vec<String>, Box<dyn Error>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn summary(start_minute: i64, description: &str) -> ActivitySummary {
+        let start_time = Utc.with_ymd_and_hms(2025, 4, 1, 9, 0, 0).unwrap() + Duration::minutes(start_minute);
+
+        ActivitySummary {
+            start_time,
+            end_time: start_time + Duration::minutes(5),
+            description: description.to_string(),
+            events: Vec::new(),
+            tags: vec!["rust".to_string()],
+        }
+    }
+
+    fn spans(summaries: &[ActivitySummary]) -> Vec<(DateTime<Utc>, DateTime<Utc>, String)> {
+        summaries
+            .iter()
+            .map(|s| (s.start_time, s.end_time, s.description.clone()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn merging_the_same_summaries_twice_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_url = format!("sqlite:{}?mode=rwc", dir.path().join("summaries.db").display());
+        let db = GeneralDbClient::new(&db_url).await.unwrap();
+        let llm = CountingLlm::default();
+        let start = Utc.with_ymd_and_hms(2025, 4, 1, 0, 0, 0).unwrap();
+        let end = start + Duration::days(1);
+
+        for minute in [0, 5, 10] {
+            db.store_summary(&summary(minute, "Wrote rust code for the summary merger in the editor"))
+                .await
+                .unwrap();
+        }
+        db.store_summary(&summary(60, "Read the news in Firefox"))
+            .await
+            .unwrap();
+
+        assert_eq!(merge_stored_summaries(&db, &llm, start, end).await.unwrap(), 1);
+        let after_first = spans(&db.get_summaries_in_timeframe(start, end).await.unwrap());
+        let merged_first = spans(&db.get_merged_summaries_in_timeframe(start, end).await.unwrap());
+        assert_eq!(after_first.len(), 2);
+        assert_eq!(merged_first.len(), 3);
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+
+        assert_eq!(merge_stored_summaries(&db, &llm, start, end).await.unwrap(), 0);
+        assert_eq!(
+            spans(&db.get_summaries_in_timeframe(start, end).await.unwrap()),
+            after_first
+        );
+        assert_eq!(
+            spans(&db.get_merged_summaries_in_timeframe(start, end).await.unwrap()),
+            merged_first
+        );
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+    }
+}
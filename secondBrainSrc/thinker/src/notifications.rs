@@ -0,0 +1,156 @@
+use crate::report_writer::local_midnight;
+use activity_tracker_common::{
+    db::{GeneralDbClient, SummaryStore},
+    llm::{ContextWindowManager, LlmClient},
+};
+use chrono::{Duration, Local, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use tracing::warn;
+
+const INSIGHT_TITLE: &str = "🐟 Fishy's Daily Insight";
+const DEFAULT_NOTIFICATION_HOUR: u32 = 18;
+
+pub struct NotificationSettings {
+    pub enabled: bool,
+    // Local time after which the day's insight is sent
+    pub time: NaiveTime,
+}
+
+impl NotificationSettings {
+    pub fn from_env() -> Self {
+        let default_time = NaiveTime::from_hms_opt(DEFAULT_NOTIFICATION_HOUR, 0, 0).unwrap_or_default();
+
+        Self {
+            enabled: env::var("NOTIFICATIONS_ENABLED")
+                .map(|v| !(v.eq_ignore_ascii_case("false") || v == "0"))
+                .unwrap_or(true),
+            time: env::var("NOTIFICATION_TIME")
+                .ok()
+                .and_then(|s| NaiveTime::parse_from_str(&s, "%H:%M").ok())
+                .unwrap_or(default_time),
+        }
+    }
+}
+
+// The last insight sent, so restarts after the notification time don't
+// generate and send it again
+#[derive(Serialize, Deserialize)]
+struct CachedInsight {
+    day: NaiveDate,
+    insight: String,
+}
+
+// ~/.cache/second-brain/daily_insight.json
+fn insight_cache_path() -> Result<PathBuf, Box<dyn Error>> {
+    let home = env::var("HOME")?;
+    Ok(PathBuf::from(home).join(".cache/second-brain/daily_insight.json"))
+}
+
+fn cached_insight(day: NaiveDate) -> Option<String> {
+    let contents = std::fs::read_to_string(insight_cache_path().ok()?).ok()?;
+    let cached: CachedInsight = serde_json::from_str(&contents).ok()?;
+    (cached.day == day).then_some(cached.insight)
+}
+
+fn cache_insight(day: NaiveDate, insight: &str) -> Result<(), Box<dyn Error>> {
+    let path = insight_cache_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let cached = CachedInsight {
+        day,
+        insight: insight.to_string(),
+    };
+    std::fs::write(path, serde_json::to_string(&cached)?)?;
+
+    Ok(())
+}
+
+// Sends the day's insight unless it already went out, returning whether it
+// was sent. Days without summaries have nothing to say.
+pub async fn deliver_daily_insight<T: LlmClient>(
+    summary_db: &GeneralDbClient,
+    llm_client: &T,
+    day: NaiveDate,
+) -> Result<bool, Box<dyn Error>> {
+    if cached_insight(day).is_some() {
+        return Ok(false);
+    }
+
+    let Some(insight) = generate_daily_insight(summary_db, llm_client, day).await? else {
+        return Ok(false);
+    };
+
+    cache_insight(day, &insight)?;
+    send_daily_insight(&insight).await?;
+
+    Ok(true)
+}
+
+// Two sentences on the most interesting thing done that day
+async fn generate_daily_insight<T: LlmClient>(
+    summary_db: &GeneralDbClient,
+    llm_client: &T,
+    day: NaiveDate,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let summaries = summary_db
+        .get_summaries_in_timeframe(local_midnight(day)?, local_midnight(day + Duration::days(1))?)
+        .await?;
+    if summaries.is_empty() {
+        return Ok(None);
+    }
+
+    let data = summaries
+        .iter()
+        .map(|s| {
+            let start = s.start_time.with_timezone(&Local);
+            format!("- {}: {}", start.format("%H:%M"), s.description)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let prompt = ContextWindowManager::from_env().fit_prompt(
+        "You are writing a short end-of-day note about the user's computer activity. \
+         Below are summaries of what they did today, in order.",
+        &data,
+        "In exactly two sentences, tell the user the most interesting thing they accomplished today. \
+         Address the user as \"you\".",
+    );
+
+    match llm_client.generate_text(&prompt).await {
+        Ok(text) if !text.trim().is_empty() => Ok(Some(text.trim().to_string())),
+        Ok(_) => Ok(None),
+        Err(e) => {
+            warn!(error = %e, "⚠️ Failed to write the daily insight");
+            Ok(None)
+        }
+    }
+}
+
+// Shows the insight as a desktop notification
+pub async fn send_daily_insight(summary: &str) -> Result<(), Box<dyn Error>> {
+    notify(INSIGHT_TITLE, summary).await
+}
+
+// Notification Center on macOS, libnotify over D-Bus on Linux
+pub async fn notify(title: &str, body: &str) -> Result<(), Box<dyn Error>> {
+    let title = title.to_string();
+    let body = body.to_string();
+
+    // Showing a notification blocks on the notification daemon
+    tokio::task::spawn_blocking(move || {
+        notify_rust::Notification::new()
+            .summary(&title)
+            .body(&body)
+            .show()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+    .await??;
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use activity_tracker_common::ActivitySummary;
+use std::collections::{HashMap, HashSet};
+
+// Summaries scoring below this are regenerated
+pub const QUALITY_THRESHOLD: f32 = 0.5;
+
+const MIN_DESCRIPTION_CHARS: usize = 50;
+const MAX_DESCRIPTION_CHARS: usize = 500;
+
+// How many of the most used apps a complete description should mention
+const TOP_APPS_TO_CHECK: usize = 3;
+
+// Well-known apps the LLM likes to mention. If the description names one of
+// these but it never appears in the events, the model made it up.
+const KNOWN_APPS: [&str; 24] = [
+    "chrome",
+    "firefox",
+    "safari",
+    "edge",
+    "zen",
+    "slack",
+    "discord",
+    "zoom",
+    "teams",
+    "outlook",
+    "mail",
+    "excel",
+    "word",
+    "powerpoint",
+    "notion",
+    "obsidian",
+    "figma",
+    "spotify",
+    "vscode",
+    "xcode",
+    "intellij",
+    "terminal",
+    "iterm",
+    "ghostty",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummaryQuality {
+    // Fraction of the top apps the description mentions (higher is better)
+    pub completeness_score: f32,
+    // Fraction of mentioned apps that aren't in the events (lower is better)
+    pub hallucination_score: f32,
+    // 1.0 when the description is a reasonable length (higher is better)
+    pub conciseness_score: f32,
+}
+
+impl SummaryQuality {
+    pub fn overall(&self) -> f32 {
+        (self.completeness_score + (1.0 - self.hallucination_score) + self.conciseness_score) / 3.0
+    }
+
+    pub fn is_acceptable(&self) -> bool {
+        self.overall() >= QUALITY_THRESHOLD
+    }
+}
+
+pub fn score_summary(summary: &ActivitySummary) -> SummaryQuality {
+    let description = summary.description.to_lowercase();
+
+    // Count events per app so we know which ones matter most
+    let mut app_counts: HashMap<String, usize> = HashMap::new();
+    for event in &summary.events {
+        *app_counts.entry(event.app_context.app_name.to_lowercase()).or_insert(0) += 1;
+    }
+
+    let mut top_apps: Vec<_> = app_counts.iter().collect();
+    top_apps.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+    let top_apps: Vec<&String> = top_apps
+        .into_iter()
+        .map(|(app, _)| app)
+        .filter(|app| app.as_str() != "unknown")
+        .take(TOP_APPS_TO_CHECK)
+        .collect();
+
+    let completeness_score = if top_apps.is_empty() {
+        1.0
+    } else {
+        let mentioned = top_apps.iter().filter(|app| description.contains(app.as_str())).count();
+        mentioned as f32 / top_apps.len() as f32
+    };
+
+    let event_apps: HashSet<&String> = app_counts.keys().collect();
+    let mentioned_known: Vec<&str> = KNOWN_APPS
+        .iter()
+        .copied()
+        .filter(|app| contains_word(&description, app))
+        .collect();
+
+    let hallucination_score = if mentioned_known.is_empty() {
+        0.0
+    } else {
+        let invented = mentioned_known
+            .iter()
+            .filter(|known| !event_apps.iter().any(|app| app.contains(*known)))
+            .count();
+        invented as f32 / mentioned_known.len() as f32
+    };
+
+    let length = summary.description.trim().chars().count();
+    let conciseness_score = if length < MIN_DESCRIPTION_CHARS {
+        length as f32 / MIN_DESCRIPTION_CHARS as f32
+    } else if length > MAX_DESCRIPTION_CHARS {
+        MAX_DESCRIPTION_CHARS as f32 / length as f32
+    } else {
+        1.0
+    };
+
+    SummaryQuality {
+        completeness_score,
+        hallucination_score,
+        conciseness_score,
+    }
+}
+
+// Whole-word match so "word" doesn't match "password"
+fn contains_word(text: &str, word: &str) -> bool {
+    text.split(|c: char| !c.is_alphanumeric()).any(|token| token == word)
+}
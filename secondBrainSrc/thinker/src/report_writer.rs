@@ -0,0 +1,440 @@
+use activity_tracker_common::{
+    db::{EventStore, GeneralDbClient, SummaryStore, TimescaleClient},
+    llm::{ContextWindowManager, LlmClient},
+    ActivitySummary, DailySummary,
+};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike, Utc, Weekday};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::pin::pin;
+use tracing::{info, warn};
+
+// Gaps between events longer than this count as time away, not time in an app
+const IDLE_GAP_MINUTES: i64 = 5;
+const TOP_APPS: usize = 10;
+const NOTABLE_TITLES: usize = 10;
+// Darkest last; each cell is scaled against the busiest hour of the week
+const HEATMAP_SHADES: &[char] = &[' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+// Active time and events for one local day
+#[derive(Default)]
+struct DayStats {
+    minutes: f64,
+    events: u64,
+    apps: HashMap<String, f64>,
+}
+
+// Everything the report needs, collected in a single pass over the week's events
+#[derive(Default)]
+struct WeekStats {
+    days: HashMap<NaiveDate, DayStats>,
+    apps: HashMap<String, f64>,
+    // Minutes by [weekday][hour], in local time
+    heatmap: [[f64; 24]; 7],
+    // Minutes by (app, window title)
+    titles: HashMap<(String, String), f64>,
+}
+
+impl WeekStats {
+    fn total_minutes(&self) -> f64 {
+        self.days.values().map(|day| day.minutes).sum()
+    }
+
+    // Time between two events is credited to the first one, unless the gap
+    // is long enough to count as idle
+    fn credit(&mut self, at: DateTime<Utc>, app: &str, title: &str, minutes: f64) {
+        let local = at.with_timezone(&Local);
+
+        let day = self.days.entry(local.date_naive()).or_default();
+        day.minutes += minutes;
+        *day.apps.entry(app.to_string()).or_insert(0.0) += minutes;
+
+        *self.apps.entry(app.to_string()).or_insert(0.0) += minutes;
+        self.heatmap[local.weekday().num_days_from_monday() as usize][local.hour() as usize] += minutes;
+
+        if !title.trim().is_empty() {
+            *self.titles.entry((app.to_string(), title.to_string())).or_insert(0.0) += minutes;
+        }
+    }
+}
+
+// Builds a markdown report of the activity between start and end
+pub async fn generate_weekly_report<T: LlmClient>(
+    events_db: &TimescaleClient,
+    summary_db: &GeneralDbClient,
+    llm_client: &T,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<String, Box<dyn Error>> {
+    let stats = collect_stats(events_db, start, end).await?;
+
+    let mut summaries = summary_db.get_summaries_in_timeframe(start, end).await?;
+    summaries.sort_by_key(|s| s.start_time);
+
+    let local_start = start.with_timezone(&Local).date_naive();
+    let local_end = (end - Duration::seconds(1)).with_timezone(&Local).date_naive();
+    let iso_week = local_start.iso_week();
+
+    let daily = events_db.get_daily_summaries(local_start, local_end).await?;
+
+    let overview = match meta_summarize(llm_client, &summaries, &daily).await {
+        Some(overview) => overview,
+        None => fallback_overview(&stats),
+    };
+
+    let mut report = format!(
+        "# Week {}-{:02}: {} to {}\n\n## Overview\n\n{}\n\n",
+        iso_week.year(),
+        iso_week.week(),
+        local_start.format("%a %-d %b"),
+        local_end.format("%a %-d %b %Y"),
+        overview
+    );
+
+    report.push_str(&format_timeline(&stats, &summaries, local_start, local_end));
+    report.push_str(&format_top_apps(&stats));
+    report.push_str(&format_heatmap(&stats));
+    report.push_str(&format_window_titles(&stats));
+
+    Ok(report)
+}
+
+// Generates the report for the week starting on the given Monday, overwriting
+// any earlier copy, and returns where it was saved
+pub async fn write_weekly_report<T: LlmClient>(
+    events_db: &TimescaleClient,
+    summary_db: &GeneralDbClient,
+    llm_client: &T,
+    monday: NaiveDate,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let (start, end) = week_bounds(monday)?;
+    let report = generate_weekly_report(events_db, summary_db, llm_client, start, end).await?;
+
+    let path = report_path(monday)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, report)?;
+
+    info!(path = %path.display(), "📝 Saved weekly report");
+    notify_saved(&path).await;
+
+    Ok(path)
+}
+
+// Parses an ISO week like "2024-07" into the Monday it starts on
+pub fn parse_week(week: &str) -> Result<NaiveDate, Box<dyn Error>> {
+    let (year, number) = week
+        .split_once('-')
+        .ok_or_else(|| format!("Invalid week '{}', expected YYYY-WW", week))?;
+
+    let year: i32 = year.trim().parse()?;
+    let number: u32 = number.trim().trim_start_matches(['W', 'w']).parse()?;
+
+    NaiveDate::from_isoywd_opt(year, number, Weekday::Mon)
+        .ok_or_else(|| format!("{} has no week {}", year, number).into())
+}
+
+// The Monday starting the week before the one containing `today`
+pub fn previous_week(today: NaiveDate) -> NaiveDate {
+    today.week(Weekday::Mon).first_day() - Duration::weeks(1)
+}
+
+// SECOND_BRAIN_REPORTS_DIR, defaulting to ~/Documents/SecondBrain/reports,
+// with one file per ISO week
+pub fn report_path(monday: NaiveDate) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = match env::var("SECOND_BRAIN_REPORTS_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => PathBuf::from(env::var("HOME")?).join("Documents/SecondBrain/reports"),
+    };
+
+    let iso_week = monday.iso_week();
+    Ok(dir.join(format!("week-{}-{:02}.md", iso_week.year(), iso_week.week())))
+}
+
+// Local midnight on the Monday to local midnight a week later
+fn week_bounds(monday: NaiveDate) -> Result<(DateTime<Utc>, DateTime<Utc>), Box<dyn Error>> {
+    Ok((local_midnight(monday)?, local_midnight(monday + Duration::weeks(1))?))
+}
+
+// The instant a local calendar day starts
+pub fn local_midnight(day: NaiveDate) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    Local
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| format!("No local midnight on {}", day).into())
+}
+
+async fn collect_stats(
+    events_db: &TimescaleClient,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<WeekStats, Box<dyn Error>> {
+    let mut stats = WeekStats::default();
+    let mut previous: Option<(DateTime<Utc>, String, String)> = None;
+
+    let mut events = pin!(events_db.stream_events_in_timeframe(start, end));
+    while let Some(event) = events.next().await {
+        let event = event?;
+
+        if let Some((previous_time, app, title)) = &previous {
+            let gap = event.timestamp - *previous_time;
+            if gap <= Duration::minutes(IDLE_GAP_MINUTES) {
+                stats.credit(*previous_time, app, title, gap.num_seconds() as f64 / 60.0);
+            }
+        }
+
+        let day = event.timestamp.with_timezone(&Local).date_naive();
+        stats.days.entry(day).or_default().events += 1;
+
+        previous = Some((
+            event.timestamp,
+            event.app_context.app_name,
+            event.app_context.window_title,
+        ));
+    }
+
+    // Recorded dwell times beat estimates from event gaps, which miss time
+    // spent reading. Only the weekly totals are corrected; per-day figures
+    // stay estimates.
+    for (app_name, dwell_time_ms) in events_db.get_app_dwell_times(start, end).await? {
+        stats.apps.insert(app_name, dwell_time_ms as f64 / 60_000.0);
+    }
+
+    Ok(stats)
+}
+
+// A paragraph about the whole week, written by the LLM from the days' rollups
+// where there are some and that week's summaries otherwise
+async fn meta_summarize<T: LlmClient>(
+    llm_client: &T,
+    summaries: &[ActivitySummary],
+    daily: &[DailySummary],
+) -> Option<String> {
+    let daily: HashMap<NaiveDate, &DailySummary> = daily
+        .iter()
+        .filter(|d| !d.description.is_empty())
+        .map(|d| (d.date, d))
+        .collect();
+
+    let mut lines = Vec::new();
+    let mut rolled_up = Vec::new();
+    for s in summaries {
+        let start = s.start_time.with_timezone(&Local);
+
+        match daily.get(&start.date_naive()) {
+            Some(day) if !rolled_up.contains(&day.date) => {
+                rolled_up.push(day.date);
+                lines.push(format!("- {}: {}", start.format("%a"), day.description));
+            }
+            Some(_) => {}
+            None => lines.push(format!("- {}: {}", start.format("%a %H:%M"), s.description)),
+        }
+    }
+    if lines.is_empty() {
+        return None;
+    }
+
+    let data = lines.join("\n");
+
+    let prompt = ContextWindowManager::from_env().fit_prompt(
+        "You are writing the overview of a weekly report about the user's computer activity. \
+         Below are summaries of what they did, in order.",
+        &data,
+        "In one paragraph of three to five sentences, describe what the user focused on this week \
+         and how their time was split. Address the user as \"you\".",
+    );
+
+    match llm_client.generate_text(&prompt).await {
+        Ok(text) if !text.trim().is_empty() => Some(text.trim().to_string()),
+        Ok(_) => None,
+        Err(e) => {
+            warn!(error = %e, "⚠️ Failed to write weekly overview");
+            None
+        }
+    }
+}
+
+fn fallback_overview(stats: &WeekStats) -> String {
+    let top_app = stats
+        .apps
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(app, _)| app.as_str());
+
+    let active_days = stats.days.values().filter(|day| day.minutes > 0.0).count();
+
+    match top_app {
+        Some(app) => format!(
+            "You were active for {} across {} day{}, mostly in {}.",
+            format_minutes(stats.total_minutes()),
+            active_days,
+            if active_days == 1 { "" } else { "s" },
+            app
+        ),
+        None => "No activity was recorded this week.".to_string(),
+    }
+}
+
+fn format_timeline(
+    stats: &WeekStats,
+    summaries: &[ActivitySummary],
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+) -> String {
+    let mut summary_counts: HashMap<NaiveDate, usize> = HashMap::new();
+    for summary in summaries {
+        *summary_counts
+            .entry(summary.start_time.with_timezone(&Local).date_naive())
+            .or_insert(0) += 1;
+    }
+
+    let mut result = String::from(
+        "## Daily timeline\n\n| Day | Active time | Events | Summaries | Top app |\n|---|---|---|---|---|\n",
+    );
+
+    for day in first_day.iter_days().take_while(|day| *day <= last_day) {
+        let stats = stats.days.get(&day);
+        let top_app = stats
+            .and_then(|s| s.apps.iter().max_by(|a, b| a.1.total_cmp(b.1)))
+            .map(|(app, _)| app.as_str())
+            .unwrap_or("-");
+
+        result.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            day.format("%a %-d %b"),
+            format_minutes(stats.map(|s| s.minutes).unwrap_or(0.0)),
+            stats.map(|s| s.events).unwrap_or(0),
+            summary_counts.get(&day).copied().unwrap_or(0),
+            top_app
+        ));
+    }
+
+    result.push('\n');
+    result
+}
+
+fn format_top_apps(stats: &WeekStats) -> String {
+    let mut apps: Vec<(&String, &f64)> = stats.apps.iter().filter(|(_, minutes)| **minutes > 0.0).collect();
+    apps.sort_by(|a, b| b.1.total_cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    if apps.is_empty() {
+        return "## Top apps\n\nNo app usage was recorded.\n\n".to_string();
+    }
+
+    let total: f64 = apps.iter().map(|(_, minutes)| **minutes).sum();
+    let mut result = String::from("## Top apps\n\n| # | App | Time | Share |\n|---|---|---|---|\n");
+
+    for (rank, (app, minutes)) in apps.into_iter().take(TOP_APPS).enumerate() {
+        result.push_str(&format!(
+            "| {} | {} | {} | {:.0}% |\n",
+            rank + 1,
+            app,
+            format_minutes(*minutes),
+            minutes / total * 100.0
+        ));
+    }
+
+    result.push('\n');
+    result
+}
+
+fn format_heatmap(stats: &WeekStats) -> String {
+    let busiest = stats.heatmap.iter().flatten().copied().fold(0.0, f64::max);
+    if busiest <= 0.0 {
+        return String::new();
+    }
+
+    let mut result = String::from("## Activity by hour\n\n```\n     ");
+    for hour in (0..24).step_by(3) {
+        result.push_str(&format!("{:<3}", hour));
+    }
+    result.push('\n');
+
+    for (weekday, hours) in stats.heatmap.iter().enumerate() {
+        result.push_str(&format!("{}  ", DAY_NAMES[weekday]));
+        for minutes in hours {
+            let shade = (minutes / busiest * (HEATMAP_SHADES.len() - 1) as f64).ceil() as usize;
+            result.push(HEATMAP_SHADES[shade.min(HEATMAP_SHADES.len() - 1)]);
+        }
+        result.push('\n');
+    }
+    result.push_str("```\n\n");
+
+    let day_totals: Vec<f64> = stats.heatmap.iter().map(|hours| hours.iter().sum()).collect();
+    let hour_totals: Vec<f64> = (0..24)
+        .map(|hour| stats.heatmap.iter().map(|hours| hours[hour]).sum())
+        .collect();
+    let busiest_index = |totals: &[f64]| {
+        totals
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    };
+
+    let day = busiest_index(&day_totals);
+    let hour = busiest_index(&hour_totals);
+    result.push_str(&format!(
+        "Most active day: **{}** ({}). Most active hour: **{:02}:00** ({} over the week).\n\n",
+        DAY_NAMES[day],
+        format_minutes(day_totals[day]),
+        hour,
+        format_minutes(hour_totals[hour])
+    ));
+
+    result
+}
+
+fn format_window_titles(stats: &WeekStats) -> String {
+    let mut titles: Vec<(&(String, String), &f64)> = stats.titles.iter().collect();
+    titles.sort_by(|a, b| b.1.total_cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    if titles.is_empty() {
+        return String::new();
+    }
+
+    let mut result = String::from("## Notable windows\n\n");
+    for ((app, title), minutes) in titles.into_iter().take(NOTABLE_TITLES) {
+        result.push_str(&format!("- **{}**: {} ({})\n", app, title, format_minutes(*minutes)));
+    }
+
+    result
+}
+
+fn format_minutes(minutes: f64) -> String {
+    let minutes = minutes.round() as i64;
+    if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+// Desktop notifications are a nicety, so failures are only logged
+async fn notify_saved(path: &std::path::Path) {
+    let body = format!("Your weekly report is ready: {}", path.display());
+
+    // Showing a notification blocks on the notification daemon
+    let shown = tokio::task::spawn_blocking(move || {
+        notify_rust::Notification::new()
+            .summary("Second Brain")
+            .body(&body)
+            .show()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|shown| shown);
+
+    if let Err(e) = shown {
+        warn!(error = %e, "⚠️ Failed to show report notification");
+    }
+}
@@ -0,0 +1,91 @@
+use crate::report_writer::local_midnight;
+use activity_tracker_common::{
+    db::{EventStore, GeneralDbClient, SummaryStore, TimescaleClient},
+    llm::{ContextWindowManager, LlmClient},
+    ActivitySummary, DailySummary,
+};
+use chrono::{Duration, Local, NaiveDate};
+use std::error::Error;
+use tracing::warn;
+
+const TOP_APPS: i64 = 3;
+
+// Rolls the local day's summaries up into one daily summary and stores it,
+// replacing any earlier rollup. Days without summaries are skipped.
+pub async fn rollup_summaries_to_daily<T: LlmClient>(
+    events_db: &TimescaleClient,
+    summary_db: &GeneralDbClient,
+    llm_client: &T,
+    day: NaiveDate,
+) -> Result<Option<DailySummary>, Box<dyn Error>> {
+    let start = local_midnight(day)?;
+    let end = local_midnight(day + Duration::days(1))?;
+
+    let mut summaries = summary_db.get_summaries_in_timeframe(start, end).await?;
+    if summaries.is_empty() {
+        return Ok(None);
+    }
+    summaries.sort_by_key(|s| s.start_time);
+
+    let data = summaries
+        .iter()
+        .map(|s| {
+            let start = s.start_time.with_timezone(&Local);
+            format!("- {}: {}", start.format("%H:%M"), s.description)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let description = generate(
+        llm_client,
+        &data,
+        "In three or four sentences, describe what the user worked on during the day and in what order.",
+    )
+    .await
+    .unwrap_or_else(|| longest_description(&summaries));
+
+    let highlight = generate(
+        llm_client,
+        &data,
+        "In one sentence, name the most interesting thing the user accomplished that day.",
+    )
+    .await;
+
+    let daily = DailySummary {
+        date: day,
+        description,
+        event_count: events_db.count_events_in_timeframe(start, end).await?,
+        top_apps: events_db.get_top_app_names(start, end, TOP_APPS).await?,
+        highlight,
+    };
+    events_db.store_daily_summary(&daily).await?;
+
+    Ok(Some(daily))
+}
+
+async fn generate<T: LlmClient>(llm_client: &T, data: &str, instruction: &str) -> Option<String> {
+    let prompt = ContextWindowManager::from_env().fit_prompt(
+        "You are rolling up a day of the user's computer activity. \
+         Below are summaries of what they did that day, in order.",
+        data,
+        instruction,
+    );
+
+    match llm_client.generate_text(&prompt).await {
+        Ok(text) if !text.trim().is_empty() => Some(text.trim().to_string()),
+        Ok(_) => None,
+        Err(e) => {
+            warn!(error = %e, "⚠️ Failed to write the daily rollup");
+            None
+        }
+    }
+}
+
+fn longest_description(summaries: &[ActivitySummary]) -> String {
+    summaries
+        .iter()
+        .map(|s| s.description.as_str())
+        .max_by_key(|d| d.len())
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,266 @@
+use activity_tracker_common::{EventType, UserEvent};
+
+// Keys that undo typing
+const CORRECTION_KEYS: [&str; 2] = ["Backspace", "Delete"];
+
+// Gaps between keystrokes longer than this are pauses, not typing rhythm
+const MAX_TYPING_GAP_MS: i64 = 5_000;
+
+// Too few keystrokes say nothing about how the session felt
+const MIN_KEYSTROKES: usize = 20;
+
+// Scores at or past these thresholds become summary tags
+const FRUSTRATED_THRESHOLD: f32 = 0.15;
+const FOCUSED_THRESHOLD: f32 = 0.75;
+const FLOW_THRESHOLD: f32 = 0.6;
+
+// How a session felt, judged from typing behaviour. Each score is 0.0 to 1.0.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TypingSentiment {
+    // Share of keystrokes that were Backspace or Delete
+    pub frustration_score: f32,
+    // 1 with no app switches, falling as switches per minute rise
+    pub focus_score: f32,
+    // How evenly keystrokes were spaced, ignoring pauses
+    pub flow_score: f32,
+    pub keystrokes: usize,
+}
+
+impl TypingSentiment {
+    // "sentiment:frustrated", "sentiment:focused" and "sentiment:flow", for
+    // the scores past their thresholds
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = Vec::new();
+        if self.keystrokes < MIN_KEYSTROKES {
+            return tags;
+        }
+
+        if self.frustration_score >= FRUSTRATED_THRESHOLD {
+            tags.push("sentiment:frustrated".to_string());
+        }
+        if self.focus_score >= FOCUSED_THRESHOLD {
+            tags.push("sentiment:focused".to_string());
+        }
+        if self.flow_score >= FLOW_THRESHOLD {
+            tags.push("sentiment:flow".to_string());
+        }
+
+        tags
+    }
+
+    // A sentence for the LLM prompt, when the session stood out
+    pub fn hint(&self) -> Option<&'static str> {
+        let tags = self.tags();
+        let has = |tag: &str| tags.iter().any(|t| t == tag);
+
+        if has("sentiment:frustrated") {
+            Some("Note: the user appeared frustrated during this session.")
+        } else if has("sentiment:flow") {
+            Some("Note: the user appeared to be in a steady flow during this session.")
+        } else if has("sentiment:focused") {
+            Some("Note: the user appeared focused during this session.")
+        } else {
+            None
+        }
+    }
+}
+
+// Scores time-ordered events. App switches are counted from consecutive
+// events in different apps, so events recorded before app_switch events
+// existed count too.
+pub fn analyze_typing_sentiment(events: &[UserEvent]) -> TypingSentiment {
+    if events.is_empty() {
+        return TypingSentiment::default();
+    }
+
+    let keystrokes: Vec<&UserEvent> = events
+        .iter()
+        .filter(|e| e.event == EventType::Keystroke.as_str())
+        .collect();
+    let corrections = keystrokes
+        .iter()
+        .filter(|e| {
+            serde_json::from_str::<serde_json::Value>(&e.data)
+                .is_ok_and(|data| data["key"].as_str().is_some_and(|key| CORRECTION_KEYS.contains(&key)))
+        })
+        .count();
+    let frustration_score = corrections as f32 / keystrokes.len().max(1) as f32;
+
+    let switches = events
+        .windows(2)
+        .filter(|pair| pair[0].app_context.app_name != pair[1].app_context.app_name)
+        .count();
+    let minutes = events
+        .last()
+        .zip(events.first())
+        .map(|(last, first)| (last.timestamp - first.timestamp).num_seconds() as f32 / 60.0)
+        .unwrap_or(0.0)
+        .max(1.0);
+    let focus_score = 1.0 / (1.0 + switches as f32 / minutes);
+
+    TypingSentiment {
+        frustration_score,
+        focus_score,
+        flow_score: flow_score(&keystrokes),
+        keystrokes: keystrokes.len(),
+    }
+}
+
+// 1 / (1 + coefficient of variation) of the gaps between keystrokes, so
+// perfectly even typing scores 1
+fn flow_score(keystrokes: &[&UserEvent]) -> f32 {
+    if keystrokes.len() < MIN_KEYSTROKES {
+        return 0.0;
+    }
+
+    let gaps: Vec<f64> = keystrokes
+        .windows(2)
+        .map(|pair| (pair[1].timestamp - pair[0].timestamp).num_milliseconds())
+        .filter(|gap| (0..=MAX_TYPING_GAP_MS).contains(gap))
+        .map(|gap| gap as f64)
+        .collect();
+    if gaps.len() < MIN_KEYSTROKES - 1 {
+        return 0.0;
+    }
+
+    let mean = gaps.iter().sum::<f64>() / gaps.len() as f64;
+    if mean <= 0.0 {
+        return 0.0;
+    }
+    let variance = gaps.iter().map(|gap| (gap - mean).powi(2)).sum::<f64>() / gaps.len() as f64;
+
+    (1.0 / (1.0 + variance.sqrt() / mean)) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use activity_tracker_common::UserEventBuilder;
+    use chrono::{Duration, TimeZone, Utc};
+
+    // Keystrokes in one app, each `gaps_ms[i % len]` after the last
+    fn typing(keys: &[&str], gaps_ms: &[i64]) -> Vec<UserEvent> {
+        let mut at = Utc.with_ymd_and_hms(2025, 4, 1, 9, 0, 0).unwrap();
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| {
+                at += Duration::milliseconds(gaps_ms[i % gaps_ms.len()]);
+                UserEventBuilder::keystroke(key).app("Code").at(at).build()
+            })
+            .collect()
+    }
+
+    // `total` keys of which the first `corrections` are Backspace
+    fn keys(total: usize, corrections: usize) -> Vec<&'static str> {
+        (0..total)
+            .map(|i| if i < corrections { "Backspace" } else { "KeyA" })
+            .collect()
+    }
+
+    // Alternating short and long gaps, far from a steady rhythm
+    const UNEVEN_GAPS: [i64; 2] = [50, 3_000];
+
+    #[test]
+    fn no_events_score_nothing() {
+        let sentiment = analyze_typing_sentiment(&[]);
+
+        assert_eq!(sentiment, TypingSentiment::default());
+        assert!(sentiment.tags().is_empty());
+        assert_eq!(sentiment.hint(), None);
+    }
+
+    #[test]
+    fn too_few_keystrokes_are_never_tagged() {
+        let sentiment = analyze_typing_sentiment(&typing(&keys(MIN_KEYSTROKES - 1, MIN_KEYSTROKES - 1), &[200]));
+
+        assert_eq!(sentiment.frustration_score, 1.0);
+        assert_eq!(sentiment.flow_score, 0.0);
+        assert!(sentiment.tags().is_empty());
+        assert_eq!(sentiment.hint(), None);
+    }
+
+    #[test]
+    fn many_corrections_are_frustrated() {
+        let sentiment = analyze_typing_sentiment(&typing(&keys(40, 8), &UNEVEN_GAPS));
+
+        assert!(sentiment.frustration_score >= FRUSTRATED_THRESHOLD);
+        assert!(sentiment.tags().contains(&"sentiment:frustrated".to_string()));
+        assert_eq!(
+            sentiment.hint(),
+            Some("Note: the user appeared frustrated during this session.")
+        );
+    }
+
+    #[test]
+    fn corrections_below_the_threshold_are_not_frustrated() {
+        let sentiment = analyze_typing_sentiment(&typing(&keys(100, 14), &UNEVEN_GAPS));
+
+        assert!(sentiment.frustration_score < FRUSTRATED_THRESHOLD);
+        assert!(!sentiment.tags().contains(&"sentiment:frustrated".to_string()));
+    }
+
+    #[test]
+    fn staying_in_one_app_is_focused() {
+        let sentiment = analyze_typing_sentiment(&typing(&keys(30, 0), &UNEVEN_GAPS));
+
+        assert_eq!(sentiment.focus_score, 1.0);
+        assert_eq!(sentiment.tags(), vec!["sentiment:focused".to_string()]);
+        assert_eq!(
+            sentiment.hint(),
+            Some("Note: the user appeared focused during this session.")
+        );
+    }
+
+    #[test]
+    fn switching_apps_constantly_is_not_focused() {
+        let mut events = typing(&keys(30, 0), &UNEVEN_GAPS);
+        for (i, event) in events.iter_mut().enumerate() {
+            event.app_context.app_name = if i % 2 == 0 { "Code" } else { "Slack" }.to_string();
+        }
+
+        let sentiment = analyze_typing_sentiment(&events);
+
+        assert!(sentiment.focus_score < FOCUSED_THRESHOLD);
+        assert!(!sentiment.tags().contains(&"sentiment:focused".to_string()));
+    }
+
+    #[test]
+    fn evenly_spaced_typing_is_flow() {
+        let sentiment = analyze_typing_sentiment(&typing(&keys(30, 0), &[200]));
+
+        assert!(sentiment.flow_score >= FLOW_THRESHOLD);
+        assert!(sentiment.tags().contains(&"sentiment:flow".to_string()));
+        assert_eq!(
+            sentiment.hint(),
+            Some("Note: the user appeared to be in a steady flow during this session.")
+        );
+    }
+
+    #[test]
+    fn uneven_typing_is_not_flow() {
+        let sentiment = analyze_typing_sentiment(&typing(&keys(30, 0), &UNEVEN_GAPS));
+
+        assert!(sentiment.flow_score < FLOW_THRESHOLD);
+        assert!(!sentiment.tags().contains(&"sentiment:flow".to_string()));
+    }
+
+    #[test]
+    fn pauses_do_not_break_flow() {
+        // A ten second break every ten keys, otherwise a steady rhythm
+        let gaps: Vec<i64> = (0..10).map(|i| if i == 9 { 10_000 } else { 200 }).collect();
+        let sentiment = analyze_typing_sentiment(&typing(&keys(40, 0), &gaps));
+
+        assert!(sentiment.flow_score >= FLOW_THRESHOLD);
+    }
+
+    #[test]
+    fn frustration_outranks_flow_in_the_hint() {
+        let sentiment = analyze_typing_sentiment(&typing(&keys(40, 10), &[200]));
+
+        assert!(sentiment.tags().contains(&"sentiment:flow".to_string()));
+        assert_eq!(
+            sentiment.hint(),
+            Some("Note: the user appeared frustrated during this session.")
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use crate::notifications;
+use crate::report_writer::local_midnight;
+use activity_tracker_common::{
+    config::{Config, WeeklyGoal},
+    db::{GeneralDbClient, TimescaleClient},
+    FocusSession, Suggestion, Urgency,
+};
+use chrono::{DateTime, Duration, Local, Utc, Weekday};
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use tracing::warn;
+
+const SUGGESTION_TITLE: &str = "🐟 Fishy noticed something";
+
+// Time in one app before it's pointed out
+const PROLONGED_USE_MINUTES: i64 = 120;
+// Time at the computer before a break is suggested
+const MISSING_BREAK_MINUTES: i64 = 90;
+// Gaps between focus sessions shorter than this don't count as a break
+const BREAK_GAP_MINUTES: i64 = 5;
+// Goals aren't checked before this much of the week has gone
+const GOAL_GRACE_DAYS: f64 = 2.0;
+// A goal is drifting once it's below this share of where it should be by now
+const GOAL_DRIFT_RATIO: f64 = 0.5;
+
+// How far back the break and app-use rules look
+const LOOKBACK_HOURS: i64 = 4;
+
+// Which rules run, and the goals they check against
+pub struct SuggestionRules {
+    pub goals: Vec<WeeklyGoal>,
+}
+
+impl SuggestionRules {
+    // None unless ENABLE_PROACTIVE_SUGGESTIONS is set. Goals come from the
+    // config file's [[goals]].
+    pub fn load(config_path: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let enabled = env::var("ENABLE_PROACTIVE_SUGGESTIONS")
+            .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            goals: Config::load(config_path)?.goals,
+        }))
+    }
+}
+
+// Runs every rule against the activity up to `now` and stores what they
+// raise, skipping reminders raised too recently. High-urgency ones are sent
+// as desktop notifications; the rest wait for the next recall answer.
+pub async fn raise_suggestions(
+    events_db: &TimescaleClient,
+    summary_db: &GeneralDbClient,
+    rules: &SuggestionRules,
+    now: DateTime<Utc>,
+) -> Result<Vec<Suggestion>, Box<dyn Error>> {
+    let mut sessions = events_db
+        .get_focus_sessions(now - Duration::hours(LOOKBACK_HOURS), now)
+        .await?;
+    sessions.sort_by_key(|s| s.start_time);
+
+    let mut candidates = Vec::new();
+    candidates.extend(check_prolonged_app_use(&sessions, now));
+    candidates.extend(check_missing_break(&sessions, now));
+
+    if !rules.goals.is_empty() {
+        let today = now.with_timezone(&Local).date_naive();
+        let week_start = local_midnight(today.week(Weekday::Mon).first_day())?;
+
+        let mut app_hours: HashMap<String, f64> = HashMap::new();
+        for session in events_db.get_focus_sessions(week_start, now).await? {
+            let hours = session.seconds_within(week_start, now) as f64 / 3600.0;
+            *app_hours.entry(session.app_name.to_lowercase()).or_insert(0.0) += hours;
+        }
+
+        let days_elapsed = (now - week_start).num_seconds() as f64 / 86400.0;
+        candidates.extend(check_goal_drift(&app_hours, &rules.goals, days_elapsed, now));
+    }
+
+    let mut raised = Vec::new();
+    for suggestion in candidates {
+        let last_raised = summary_db.get_latest_suggestion_time(&suggestion.kind).await?;
+        if last_raised.is_some_and(|at| now - at < cooldown(&suggestion)) {
+            continue;
+        }
+
+        let mut shown = false;
+        if suggestion.urgency == Urgency::High {
+            match notifications::notify(SUGGESTION_TITLE, &suggestion.message).await {
+                Ok(()) => shown = true,
+                Err(e) => warn!(error = %e, "⚠️ Failed to show suggestion, keeping it for the next query"),
+            }
+        }
+
+        summary_db.store_suggestion(&suggestion, shown).await?;
+        raised.push(suggestion);
+    }
+
+    Ok(raised)
+}
+
+// Goals are about the week, so once a day is plenty
+fn cooldown(suggestion: &Suggestion) -> Duration {
+    if suggestion.kind.starts_with("goal_drift:") {
+        Duration::days(1)
+    } else {
+        Duration::hours(1)
+    }
+}
+
+// The app in focus right now, if it's been in focus for PROLONGED_USE_MINUTES
+// with no break. Sessions must be sorted by start time.
+pub fn check_prolonged_app_use(sessions: &[FocusSession], now: DateTime<Utc>) -> Option<Suggestion> {
+    let current = sessions.last()?;
+    let mut start = current.start_time;
+    let mut next_start = current.start_time;
+
+    for session in sessions.iter().rev().skip(1) {
+        if session.app_name != current.app_name || next_start - session.end_time > break_gap() {
+            break;
+        }
+        start = session.start_time;
+        next_start = session.start_time;
+    }
+
+    let minutes = (current.end_time - start).num_minutes();
+    if now - current.end_time > break_gap() || minutes < PROLONGED_USE_MINUTES {
+        return None;
+    }
+
+    Some(Suggestion {
+        kind: format!("prolonged_use:{}", current.app_name),
+        message: format!(
+            "You've been in {} for {} straight. Is it still what you meant to be doing?",
+            current.app_name,
+            format_minutes(minutes)
+        ),
+        urgency: Urgency::High,
+        created_at: now,
+    })
+}
+
+// Goals the week's app use is well behind on, given how much of the week has
+// gone. `app_hours` is keyed by lowercase app name.
+pub fn check_goal_drift(
+    app_hours: &HashMap<String, f64>,
+    goals: &[WeeklyGoal],
+    days_elapsed: f64,
+    now: DateTime<Utc>,
+) -> Vec<Suggestion> {
+    if days_elapsed < GOAL_GRACE_DAYS {
+        return Vec::new();
+    }
+
+    goals
+        .iter()
+        .filter(|goal| goal.hours > 0.0)
+        .filter_map(|goal| {
+            let hours = app_hours.get(&goal.app.to_lowercase()).copied().unwrap_or(0.0);
+            let expected = goal.hours * (days_elapsed / 7.0).min(1.0);
+            if hours >= expected * GOAL_DRIFT_RATIO {
+                return None;
+            }
+
+            Some(Suggestion {
+                kind: format!("goal_drift:{}", goal.app),
+                message: format!(
+                    "You've spent {:.1} of your {} hours in {} this week.",
+                    hours, goal.hours, goal.app
+                ),
+                urgency: Urgency::Low,
+                created_at: now,
+            })
+        })
+        .collect()
+}
+
+// How long the user has been at the computer, in any app, if it's been
+// MISSING_BREAK_MINUTES without a break. Sessions must be sorted by start time.
+pub fn check_missing_break(sessions: &[FocusSession], now: DateTime<Utc>) -> Option<Suggestion> {
+    let mut start = sessions.last()?.start_time;
+    let mut end = sessions.last()?.end_time;
+
+    for session in sessions.iter().rev().skip(1) {
+        if start - session.end_time > break_gap() {
+            break;
+        }
+        start = start.min(session.start_time);
+        end = end.max(session.end_time);
+    }
+
+    let minutes = (end - start).num_minutes();
+    if now - end > break_gap() || minutes < MISSING_BREAK_MINUTES {
+        return None;
+    }
+
+    Some(Suggestion {
+        kind: "missing_break".to_string(),
+        message: format!(
+            "You've been at the computer for {} without a break. Time to stretch?",
+            format_minutes(minutes)
+        ),
+        urgency: Urgency::Medium,
+        created_at: now,
+    })
+}
+
+fn break_gap() -> Duration {
+    Duration::minutes(BREAK_GAP_MINUTES)
+}
+
+// "2 hours", "1h 35m"
+fn format_minutes(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (1, 0) => "an hour".to_string(),
+        (hours, 0) => format!("{} hours", hours),
+        (0, minutes) => format!("{} minutes", minutes),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
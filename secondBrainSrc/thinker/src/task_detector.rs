@@ -0,0 +1,140 @@
+use activity_tracker_common::{
+    llm::{ContextWindowManager, LlmClient},
+    ActivitySummary, AppCorrelation, Task,
+};
+use std::collections::{HashMap, HashSet};
+
+// A new task starts when the dominant app changes and the description moves
+// on to another topic, i.e. Jaro-Winkler distance above this
+const MIN_TOPIC_DISTANCE: f64 = 0.4;
+
+// A task made of a single summary has nothing to be consistent with
+const SINGLE_SUMMARY_CONFIDENCE: f32 = 0.5;
+
+// Apps used together at least this often are one cluster, so moving between
+// them (VSCode and Terminal, say) doesn't count as changing app
+const APP_CLUSTER_MIN_RATE: f32 = 0.5;
+
+// Groups consecutive summaries into tasks and names them with the LLM
+pub struct TaskDetector<'a, T: LlmClient> {
+    llm_client: &'a T,
+    // Pairs of apps in the same cluster, as (earlier name, later name)
+    app_clusters: HashSet<(String, String)>,
+}
+
+impl<'a, T: LlmClient> TaskDetector<'a, T> {
+    pub fn new(llm_client: &'a T) -> Self {
+        Self {
+            llm_client,
+            app_clusters: HashSet::new(),
+        }
+    }
+
+    // Treats apps that are usually used together as one when splitting tasks
+    pub fn with_app_correlations(mut self, correlations: &[AppCorrelation]) -> Self {
+        self.app_clusters = correlations
+            .iter()
+            .filter(|c| c.co_occurrence_rate >= APP_CLUSTER_MIN_RATE)
+            .map(|c| (c.app_a.clone(), c.app_b.clone()))
+            .collect();
+        self
+    }
+
+    // Splits time-ordered summaries into groups that belong to the same task
+    pub fn group(&self, summaries: Vec<ActivitySummary>) -> Vec<Vec<ActivitySummary>> {
+        let mut groups: Vec<Vec<ActivitySummary>> = Vec::new();
+
+        for summary in summaries {
+            let continues = groups
+                .last()
+                .and_then(|group| group.last())
+                .is_some_and(|previous| !self.is_task_switch(previous, &summary));
+
+            match groups.last_mut() {
+                Some(group) if continues => group.push(summary),
+                _ => groups.push(vec![summary]),
+            }
+        }
+
+        groups
+    }
+
+    fn is_task_switch(&self, previous: &ActivitySummary, next: &ActivitySummary) -> bool {
+        let app_changed = match (dominant_app(previous), dominant_app(next)) {
+            (Some(a), Some(b)) if a != b => !self.in_same_cluster(a, b),
+            (a, b) => a != b,
+        };
+        let topic_distance = 1.0 - strsim::jaro_winkler(&previous.description, &next.description);
+
+        app_changed && topic_distance > MIN_TOPIC_DISTANCE
+    }
+
+    fn in_same_cluster(&self, a: &str, b: &str) -> bool {
+        let pair = if a < b { (a, b) } else { (b, a) };
+        self.app_clusters.contains(&(pair.0.to_string(), pair.1.to_string()))
+    }
+
+    // Builds a titled task from a group. The id is assigned when it's stored.
+    pub async fn build_task(&self, summaries: Vec<ActivitySummary>) -> Task {
+        let descriptions = summaries
+            .iter()
+            .map(|s| format!("- {}", s.description))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = ContextWindowManager::from_env().fit_prompt(
+            "What is the single most likely task name given these summaries:",
+            &descriptions,
+            "Answer with one concise phrase, e.g. \"Implementing the export command\".",
+        );
+
+        let title = match self.llm_client.generate_text(&prompt).await {
+            Ok(title) => title.trim().trim_matches('"').to_string(),
+            Err(e) => {
+                tracing::warn!(error = %e, "⚠️ Failed to name task");
+                String::new()
+            }
+        };
+        let title = if title.is_empty() {
+            format!("Working in {}", dominant_app(&summaries[0]).unwrap_or("unknown apps"))
+        } else {
+            title
+        };
+
+        Task {
+            id: 0,
+            title,
+            start_time: summaries.iter().map(|s| s.start_time).min().unwrap_or_default(),
+            end_time: summaries.iter().map(|s| s.end_time).max().unwrap_or_default(),
+            confidence: confidence(&summaries),
+            summaries,
+        }
+    }
+}
+
+// The app with the most events in the summary
+fn dominant_app(summary: &ActivitySummary) -> Option<&str> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for event in &summary.events {
+        *counts.entry(event.app_context.app_name.as_str()).or_insert(0) += 1;
+    }
+
+    counts
+        .into_iter()
+        // Break ties by name so the result doesn't depend on hash order
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(app, _)| app)
+}
+
+// Average similarity of consecutive descriptions
+fn confidence(summaries: &[ActivitySummary]) -> f32 {
+    if summaries.len() < 2 {
+        return SINGLE_SUMMARY_CONFIDENCE;
+    }
+
+    let total: f64 = summaries
+        .windows(2)
+        .map(|pair| strsim::jaro_winkler(&pair[0].description, &pair[1].description))
+        .sum();
+
+    (total / (summaries.len() - 1) as f64) as f32
+}
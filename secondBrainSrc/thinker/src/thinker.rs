@@ -0,0 +1,427 @@
+use crate::correlation;
+use crate::dedup;
+use crate::event_analyzer::EventAnalyzer;
+use crate::hooks::{self, Hooks};
+use crate::merger;
+use crate::notifications;
+use crate::report_writer;
+use crate::rollup;
+use crate::suggestions::{self, SuggestionRules};
+use crate::task_detector::TaskDetector;
+use activity_tracker_common::{
+    db::{EventStore, GeneralDbClient, SummaryStore, TimescaleClient},
+    llm::LlmClient,
+    ActivitySummary, UserEvent,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures::{Stream, StreamExt};
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use tracing::{debug, info, warn, Span};
+
+// Size of each analysis window, unless THINKER_WINDOW_SECS says otherwise
+pub const DEFAULT_WINDOW_SECS: i64 = 300;
+
+// What process_window did with a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowOutcome {
+    NoEvents,
+    // Fewer events than the analyzer's minimum, so nothing was summarized
+    TooFewEvents(usize),
+    // The user was away for most of the window, so nothing was summarized
+    Idle,
+    Summarized(usize),
+}
+
+// Window size from THINKER_WINDOW_SECS
+pub fn window_from_env() -> Duration {
+    let secs = env::var("THINKER_WINDOW_SECS")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_WINDOW_SECS);
+
+    Duration::seconds(secs)
+}
+
+pub struct Thinker<T: LlmClient> {
+    events_db: TimescaleClient,
+    summary_db: GeneralDbClient,
+    analyzer: EventAnalyzer<T>,
+    hooks: Hooks,
+    // None when proactive suggestions are turned off
+    suggestion_rules: Option<SuggestionRules>,
+    processing_interval: Duration,
+    window: Duration,
+}
+
+impl<T: LlmClient> Thinker<T> {
+    pub fn new(
+        events_db: TimescaleClient,
+        summary_db: GeneralDbClient,
+        analyzer: EventAnalyzer<T>,
+        hooks: Hooks,
+        suggestion_rules: Option<SuggestionRules>,
+        processing_interval: Duration,
+        window: Duration,
+    ) -> Self {
+        Self {
+            events_db,
+            summary_db,
+            analyzer,
+            hooks,
+            suggestion_rules,
+            processing_interval,
+            window,
+        }
+    }
+
+    // Analyze and store a single window
+    #[tracing::instrument(
+        skip(self),
+        fields(window_start = %start_time, window_end = %end_time, events_found)
+    )]
+    pub async fn process_window(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<WindowOutcome, Box<dyn Error>> {
+        // Counted first, so no events are read from a window that won't be summarized
+        let events_found = self.events_db.count_events_in_timeframe(start_time, end_time).await? as usize;
+        Span::current().record("events_found", events_found);
+
+        if events_found == 0 {
+            info!("⚠️ No events found in the specified time period");
+            return Ok(WindowOutcome::NoEvents);
+        }
+
+        let outcome = if events_found < self.analyzer.min_events() {
+            debug!("⏭️ Too few events to summarize");
+            WindowOutcome::TooFewEvents(events_found)
+        } else {
+            info!("📊 Analyzing events");
+            let events = self.events_db.stream_events_in_timeframe(start_time, end_time);
+            self.summarize(events, start_time, end_time).await?
+        };
+
+        // Only live windows, since a backfilled one says nothing about now
+        if let Some(rules) = &self.suggestion_rules {
+            match suggestions::raise_suggestions(&self.events_db, &self.summary_db, rules, end_time).await {
+                Ok(raised) => {
+                    for suggestion in raised {
+                        info!(kind = %suggestion.kind, urgency = suggestion.urgency.as_str(), "💡 Raised a suggestion");
+                    }
+                }
+                Err(e) => warn!(error = %e, "⚠️ Checking for suggestions failed"),
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    // Catch up on windows missed while the thinker wasn't running.
+    // Returns the number of windows processed.
+    pub async fn backfill(&self, max_backfill_hours: u32) -> Result<u64, Box<dyn Error>> {
+        let now = Utc::now();
+        let backfill_end = now - self.processing_interval;
+        let earliest = now - Duration::hours(max_backfill_hours as i64);
+
+        let backfill_start = match self.summary_db.get_latest_summary_end_time().await? {
+            Some(latest) => latest.max(earliest),
+            None => earliest,
+        };
+
+        if backfill_start >= backfill_end {
+            return Ok(0);
+        }
+
+        // Hours of events aren't fetched when there aren't enough to summarize
+        let events_found = self
+            .events_db
+            .count_events_in_timeframe(backfill_start, backfill_end)
+            .await?;
+        if events_found < self.analyzer.min_events() as u64 {
+            return Ok(0);
+        }
+
+        let events = self
+            .events_db
+            .get_events_in_timeframe(backfill_start, backfill_end)
+            .await?;
+        let windows = group_into_windows(events, backfill_start, backfill_end, self.window);
+        let total = windows.len();
+
+        for (i, (window_start, window_end, window_events)) in windows.into_iter().enumerate() {
+            info!(
+                window = i + 1,
+                total,
+                window_start = %window_start,
+                window_end = %window_end,
+                "⏪ Backfilling window"
+            );
+
+            self.summarize(futures::stream::iter(window_events).map(Ok), window_start, window_end)
+                .await?;
+        }
+
+        Ok(total as u64)
+    }
+
+    // Pairs of (kept id, duplicate id) among stored summaries in the range
+    pub async fn find_duplicate_summaries(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(i64, i64)>, Box<dyn Error>> {
+        dedup::find_duplicate_summaries(&self.summary_db, start, end).await
+    }
+
+    // Deletes duplicate summaries in the range, returning (removed, retained)
+    pub async fn deduplicate(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<(u64, u64), Box<dyn Error>> {
+        dedup::deduplicate(&self.summary_db, start, end).await
+    }
+
+    // Groups summaries since the last stored task into tasks. The newest group
+    // may still be in progress, so it's left for the next run. Returns the
+    // number of tasks stored.
+    pub async fn detect_tasks(&self, max_lookback_hours: u32) -> Result<u64, Box<dyn Error>> {
+        let now = Utc::now();
+        let earliest = now - Duration::hours(max_lookback_hours as i64);
+        let since = match self.summary_db.get_latest_task_end_time().await? {
+            Some(latest) => latest.max(earliest),
+            None => earliest,
+        };
+
+        let mut summaries: Vec<_> = self
+            .summary_db
+            .get_summaries_in_timeframe(since, now)
+            .await?
+            .into_iter()
+            .filter(|s| s.start_time >= since)
+            .collect();
+        summaries.sort_by_key(|s| s.start_time);
+
+        // Correlations only sharpen the grouping, so tasks are still detected without them
+        let correlations = self
+            .events_db
+            .get_app_correlations(None, i64::MAX)
+            .await
+            .unwrap_or_default();
+        let detector = TaskDetector::new(self.analyzer.llm_client()).with_app_correlations(&correlations);
+        let mut groups = detector.group(summaries);
+        groups.pop();
+
+        let mut stored = 0;
+        for group in groups {
+            let task = detector.build_task(group).await;
+            info!(title = %task.title, "🗂️ Storing task");
+            self.summary_db.store_task(&task).await?;
+            stored += 1;
+        }
+
+        Ok(stored)
+    }
+
+    // Recomputes which apps are used together once the stored results are a
+    // week old, returning how many pairs were stored if they were
+    pub async fn refresh_app_correlations(&self) -> Result<Option<usize>, Box<dyn Error>> {
+        correlation::refresh_app_correlations(&self.events_db).await
+    }
+
+    // Merges runs of summaries about the same topic within a local day,
+    // returning the number of merged summaries stored
+    pub async fn merge_summaries(&self, day: NaiveDate) -> Result<u64, Box<dyn Error>> {
+        let start = report_writer::local_midnight(day)?;
+        let end = report_writer::local_midnight(day + Duration::days(1))?;
+
+        merger::merge_stored_summaries(&self.summary_db, self.analyzer.llm_client(), start, end).await
+    }
+
+    // Rolls the local day up into its daily summary unless that's already
+    // been done. The pg_cron job only fills in the counts, so its rows are
+    // rolled up again.
+    pub async fn rollup_day(&self, day: NaiveDate) -> Result<bool, Box<dyn Error>> {
+        let existing = self.events_db.get_daily_summary(day).await?;
+        if existing.is_some_and(|daily| !daily.description.is_empty()) {
+            return Ok(false);
+        }
+
+        let daily =
+            rollup::rollup_summaries_to_daily(&self.events_db, &self.summary_db, self.analyzer.llm_client(), day)
+                .await?;
+
+        Ok(daily.is_some())
+    }
+
+    // Writes the markdown report for the week starting on the given Monday
+    pub async fn write_weekly_report(&self, monday: NaiveDate) -> Result<PathBuf, Box<dyn Error>> {
+        report_writer::write_weekly_report(&self.events_db, &self.summary_db, self.analyzer.llm_client(), monday).await
+    }
+
+    // Sends the day's highlights as a desktop notification, once per day
+    pub async fn send_daily_insight(&self, day: NaiveDate) -> Result<bool, Box<dyn Error>> {
+        notifications::deliver_daily_insight(&self.summary_db, self.analyzer.llm_client(), day).await
+    }
+
+    async fn summarize(
+        &self,
+        events: impl Stream<Item = Result<UserEvent, Box<dyn Error>>>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<WindowOutcome, Box<dyn Error>> {
+        let focus_sessions = self.events_db.get_focus_sessions(start_time, end_time).await?;
+        let mut events_found = 0;
+        let summary = self
+            .analyzer
+            .analyze_events(
+                events.inspect(|_| events_found += 1),
+                &focus_sessions,
+                start_time,
+                end_time,
+            )
+            .await?;
+        let Some(summary) = summary else {
+            return Ok(if events_found < self.analyzer.min_events() {
+                WindowOutcome::TooFewEvents(events_found)
+            } else {
+                WindowOutcome::Idle
+            });
+        };
+
+        // Late or overlapping windows can reproduce a summary we already have
+        let existing = self.summary_db.get_summaries_in_timeframe(start_time, end_time).await?;
+        if existing.iter().any(|e| dedup::is_duplicate(e, &summary)) {
+            debug!(description = %summary.description, "🔁 Skipping duplicate summary");
+            return Ok(WindowOutcome::Summarized(summary.events.len()));
+        }
+
+        self.store(&summary).await?;
+        hooks::run_hooks(&self.hooks, &summary).await;
+
+        Ok(WindowOutcome::Summarized(summary.events.len()))
+    }
+
+    #[tracing::instrument(skip_all, fields(summary_id))]
+    async fn store(&self, summary: &ActivitySummary) -> Result<(), Box<dyn Error>> {
+        let summary_id = self.summary_db.store_summary(summary).await?;
+        Span::current().record("summary_id", summary_id);
+        info!(description = %summary.description, "💾 Stored summary");
+
+        Ok(())
+    }
+}
+
+// Splits time-ordered events into consecutive fixed-size windows, dropping empty ones
+pub fn group_into_windows(
+    events: Vec<UserEvent>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    window: Duration,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>, Vec<UserEvent>)> {
+    let mut windows = Vec::new();
+    let mut events = events.into_iter().peekable();
+    let mut window_start = start;
+
+    while window_start < end {
+        let window_end = (window_start + window).min(end);
+        let mut window_events = Vec::new();
+
+        while let Some(event) = events.next_if(|e| e.timestamp < window_end) {
+            window_events.push(event);
+        }
+
+        if !window_events.is_empty() {
+            windows.push((window_start, window_end, window_events));
+        }
+
+        window_start = window_end;
+    }
+
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use activity_tracker_common::UserEventBuilder;
+    use async_trait::async_trait;
+    use tracing_test::traced_test;
+
+    // Windows never get as far as the LLM in these tests
+    struct UnusedLlm;
+
+    #[async_trait]
+    impl LlmClient for UnusedLlm {
+        async fn generate_text(&self, _prompt: &str) -> Result<String, Box<dyn Error>> {
+            Err("the LLM shouldn't be called".into())
+        }
+
+        async fn extract_tags(&self, _text: &str) -> Result<Vec<String>, Box<dyn Error>> {
+            Err("the LLM shouldn't be called".into())
+        }
+    }
+
+    // Needs an events database that can be written to, from TEST_DATABASE_URL
+    async fn thinker() -> Option<Thinker<UnusedLlm>> {
+        let Ok(db_url) = env::var("TEST_DATABASE_URL") else {
+            eprintln!("⚠️ SKIPPED: TEST_DATABASE_URL isn't set");
+            return None;
+        };
+        let events_db = TimescaleClient::new(&db_url).await.unwrap();
+        let summary_db = GeneralDbClient::new("sqlite::memory:").await.unwrap();
+        let analyzer = EventAnalyzer::new(UnusedLlm).with_min_events(10);
+
+        Some(Thinker::new(
+            events_db,
+            summary_db,
+            analyzer,
+            Vec::new(),
+            None,
+            Duration::minutes(5),
+            Duration::minutes(5),
+        ))
+    }
+
+    // A window of its own, far enough back that nothing else writes to it
+    fn unused_window() -> (DateTime<Utc>, DateTime<Utc>) {
+        let start = DateTime::<Utc>::from_timestamp(0, 0).unwrap()
+            + Duration::minutes((Utc::now().timestamp_subsec_nanos() % 1_000_000) as i64 * 5);
+        (start, start + Duration::minutes(5))
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn an_empty_window_records_that_no_events_were_found() {
+        let Some(thinker) = thinker().await else { return };
+        let (start, end) = unused_window();
+
+        assert_eq!(
+            thinker.process_window(start, end).await.unwrap(),
+            WindowOutcome::NoEvents
+        );
+        assert!(logs_contain("process_window{"));
+        assert!(logs_contain("events_found=0"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn the_window_span_records_how_many_events_were_found() {
+        let Some(thinker) = thinker().await else { return };
+        let (start, end) = unused_window();
+        for (i, key) in ["c", "a", "r"].iter().enumerate() {
+            let event = UserEventBuilder::keystroke(key)
+                .app("Terminal")
+                .at(start + Duration::seconds(i as i64))
+                .build();
+            thinker.events_db.store_event(event).await.unwrap();
+        }
+
+        assert_eq!(
+            thinker.process_window(start, end).await.unwrap(),
+            WindowOutcome::TooFewEvents(3)
+        );
+        assert!(logs_contain("events_found=3"));
+        assert!(logs_contain("Too few events to summarize"));
+    }
+}